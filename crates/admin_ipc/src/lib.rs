@@ -1,3 +1,5 @@
+use std::future::Future;
+
 use anyhow::Result;
 
 #[cfg(not(unix))]
@@ -12,6 +14,12 @@ pub enum AdminRequest {
     Status,
     Pause,
     Resume,
+    KillSwitch {
+        flatten: bool,
+    },
+    ResetKillSwitch,
+    /// Take a verified database backup now.
+    Backup,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,10 +28,31 @@ pub struct AdminStatus {
     pub risk_state: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KillSwitchOutcome {
+    pub confirmed: bool,
+    pub attempts: u32,
+    pub flatten_orders: usize,
+    pub residual_open_orders: usize,
+    pub residual_positions: usize,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupOutcome {
+    pub path: String,
+    pub bytes: u64,
+    pub elapsed_ms: u64,
+    /// Older backups removed by rotation.
+    pub rotated: Vec<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "type", content = "payload")]
 pub enum AdminResponse {
     Status(AdminStatus),
+    KillSwitch(KillSwitchOutcome),
+    Backup(BackupOutcome),
     Ack,
     Error(String),
 }
@@ -39,6 +68,15 @@ mod unix {
     pub async fn run_server<F>(socket_path: &str, handler: F) -> Result<()>
     where
         F: Fn(AdminRequest) -> Result<AdminResponse> + Send + Sync + 'static,
+    {
+        run_server_async(socket_path, move |req| std::future::ready(handler(req))).await
+    }
+
+    /// Like `run_server`, for handlers that need to await (e.g. the kill switch).
+    pub async fn run_server_async<F, Fut>(socket_path: &str, handler: F) -> Result<()>
+    where
+        F: Fn(AdminRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<AdminResponse>> + Send,
     {
         let _ = std::fs::remove_file(socket_path);
         let listener = UnixListener::bind(socket_path)?;
@@ -55,9 +93,10 @@ mod unix {
         }
     }
 
-    async fn handle_stream<F>(stream: UnixStream, handler: std::sync::Arc<F>) -> Result<()>
+    async fn handle_stream<F, Fut>(stream: UnixStream, handler: std::sync::Arc<F>) -> Result<()>
     where
-        F: Fn(AdminRequest) -> Result<AdminResponse> + Send + Sync + 'static,
+        F: Fn(AdminRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<AdminResponse>> + Send,
     {
         let (read_half, mut write_half): (OwnedReadHalf, OwnedWriteHalf) = stream.into_split();
         let mut reader = BufReader::new(read_half);
@@ -67,7 +106,7 @@ mod unix {
             return Ok(());
         }
         let req: AdminRequest = serde_json::from_str(buf.trim())?;
-        let resp = handler(req)?;
+        let resp = handler(req).await?;
         let line = serde_json::to_string(&resp)? + "\n";
         write_half.write_all(line.as_bytes()).await?;
         Ok(())
@@ -97,9 +136,17 @@ mod unix {
         bail!("admin ipc server is only supported on unix platforms");
     }
 
+    pub async fn run_server_async<F, Fut>(_socket_path: &str, _handler: F) -> Result<()>
+    where
+        F: Fn(AdminRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<AdminResponse>> + Send,
+    {
+        bail!("admin ipc server is only supported on unix platforms");
+    }
+
     pub async fn send_request(_socket_path: &str, _req: &AdminRequest) -> Result<AdminResponse> {
         bail!("admin ipc client is only supported on unix platforms");
     }
 }
 
-pub use unix::{run_server, send_request};
+pub use unix::{run_server, run_server_async, send_request};
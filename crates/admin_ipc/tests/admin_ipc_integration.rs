@@ -18,26 +18,31 @@ async fn status_pause_resume_flow() {
     let risk_state = Arc::new(Mutex::new(String::from("running")));
     let handler_state = Arc::clone(&risk_state);
 
-    let server_task = tokio::spawn(admin_ipc::run_server(&socket_str, move |req| {
-        let mut state = handler_state
-            .lock()
-            .map_err(|_| anyhow!("state poisoned"))?;
+    let server_socket = socket_str.clone();
+    let server_task = tokio::spawn(async move {
+        admin_ipc::run_server(&server_socket, move |req| {
+            let mut state = handler_state
+                .lock()
+                .map_err(|_| anyhow!("state poisoned"))?;
 
-        match req {
-            AdminRequest::Status => Ok(AdminResponse::Status(AdminStatus {
-                run_id: "run-123".to_string(),
-                risk_state: state.clone(),
-            })),
-            AdminRequest::Pause => {
-                *state = "paused".to_string();
-                Ok(AdminResponse::Ack)
+            match req {
+                AdminRequest::Status => Ok(AdminResponse::Status(AdminStatus {
+                    run_id: "run-123".to_string(),
+                    risk_state: state.clone(),
+                })),
+                AdminRequest::Pause => {
+                    *state = "paused".to_string();
+                    Ok(AdminResponse::Ack)
+                }
+                AdminRequest::Resume => {
+                    *state = "running".to_string();
+                    Ok(AdminResponse::Ack)
+                }
+                other => Ok(AdminResponse::Error(format!("unsupported: {other:?}"))),
             }
-            AdminRequest::Resume => {
-                *state = "running".to_string();
-                Ok(AdminResponse::Ack)
-            }
-        }
-    }));
+        })
+        .await
+    });
 
     // Allow the server task to start listening.
     sleep(Duration::from_millis(50)).await;
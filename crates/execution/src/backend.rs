@@ -0,0 +1,224 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::Result;
+use strategies::Token;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::live::PolymarketVenue;
+use crate::order_manager::UserUpdate;
+use crate::paper::PaperVenue;
+use crate::venue::{
+    OrderAck, OrderRequest, TopOfBook, Venue, VenueOrder, VenuePosition, VenueTrade,
+};
+
+/// `orders.venue` / `fills.venue` for live Polymarket orders.
+pub const LIVE_VENUE: &str = "polymarket";
+/// `orders.venue` / `fills.venue` for simulated orders.
+pub const PAPER_VENUE: &str = "polymarket-paper";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutionMode {
+    #[default]
+    Paper,
+    Live,
+}
+
+impl ExecutionMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExecutionMode::Paper => "paper",
+            ExecutionMode::Live => "live",
+        }
+    }
+}
+
+impl fmt::Display for ExecutionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ExecutionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "paper" | "dry-run" => Ok(ExecutionMode::Paper),
+            "live" => Ok(ExecutionMode::Live),
+            other => Err(format!("unknown execution mode {other} (paper|live)")),
+        }
+    }
+}
+
+/// The venue traderd trades against, chosen at startup.
+///
+/// The paper variant holds no client or key, so a paper run has no way to
+/// sign or submit a real order.
+#[derive(Clone)]
+pub enum ExecutionBackend {
+    Paper(PaperVenue),
+    Live(PolymarketVenue),
+}
+
+impl ExecutionBackend {
+    pub fn paper() -> (Self, UnboundedReceiver<UserUpdate>) {
+        let (venue, updates) = PaperVenue::new();
+        (ExecutionBackend::Paper(venue), updates)
+    }
+
+    pub fn live(venue: PolymarketVenue) -> Self {
+        ExecutionBackend::Live(venue)
+    }
+
+    pub fn mode(&self) -> ExecutionMode {
+        match self {
+            ExecutionBackend::Paper(_) => ExecutionMode::Paper,
+            ExecutionBackend::Live(_) => ExecutionMode::Live,
+        }
+    }
+
+    /// Venue tag written to `orders` and `fills`.
+    pub fn venue_name(&self) -> &'static str {
+        match self {
+            ExecutionBackend::Paper(_) => PAPER_VENUE,
+            ExecutionBackend::Live(_) => LIVE_VENUE,
+        }
+    }
+
+    /// The simulator, for feeding it book and trade updates.
+    pub fn paper_venue(&self) -> Option<&PaperVenue> {
+        match self {
+            ExecutionBackend::Paper(venue) => Some(venue),
+            ExecutionBackend::Live(_) => None,
+        }
+    }
+}
+
+impl Venue for ExecutionBackend {
+    async fn place_order(&self, order: &OrderRequest) -> Result<OrderAck> {
+        match self {
+            ExecutionBackend::Paper(v) => v.place_order(order).await,
+            ExecutionBackend::Live(v) => v.place_order(order).await,
+        }
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> Result<()> {
+        match self {
+            ExecutionBackend::Paper(v) => v.cancel_order(order_id).await,
+            ExecutionBackend::Live(v) => v.cancel_order(order_id).await,
+        }
+    }
+
+    async fn cancel_all(&self) -> Result<()> {
+        match self {
+            ExecutionBackend::Paper(v) => v.cancel_all().await,
+            ExecutionBackend::Live(v) => v.cancel_all().await,
+        }
+    }
+
+    async fn open_orders(&self) -> Result<Vec<VenueOrder>> {
+        match self {
+            ExecutionBackend::Paper(v) => v.open_orders().await,
+            ExecutionBackend::Live(v) => v.open_orders().await,
+        }
+    }
+
+    async fn recent_trades(&self, since_ms: i64) -> Result<Vec<VenueTrade>> {
+        match self {
+            ExecutionBackend::Paper(v) => v.recent_trades(since_ms).await,
+            ExecutionBackend::Live(v) => v.recent_trades(since_ms).await,
+        }
+    }
+
+    async fn positions(&self) -> Result<Vec<VenuePosition>> {
+        match self {
+            ExecutionBackend::Paper(v) => v.positions().await,
+            ExecutionBackend::Live(v) => v.positions().await,
+        }
+    }
+
+    fn supports_gtd(&self) -> bool {
+        match self {
+            ExecutionBackend::Paper(v) => v.supports_gtd(),
+            ExecutionBackend::Live(v) => v.supports_gtd(),
+        }
+    }
+
+    async fn top_of_book(&self, market_id: i64, token: Token) -> Result<TopOfBook> {
+        match self {
+            ExecutionBackend::Paper(v) => v.top_of_book(market_id, token).await,
+            ExecutionBackend::Live(v) => v.top_of_book(market_id, token).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order_manager::{NewOrder, OrderManager, OrderStatus};
+    use crate::venue::{BookLevel, TimeInForce};
+    use strategies::Side;
+
+    #[test]
+    fn parses_modes() {
+        assert_eq!("paper".parse(), Ok(ExecutionMode::Paper));
+        assert_eq!("LIVE".parse(), Ok(ExecutionMode::Live));
+        assert!("prod".parse::<ExecutionMode>().is_err());
+        assert_eq!(ExecutionMode::default(), ExecutionMode::Paper);
+    }
+
+    #[tokio::test]
+    async fn paper_orders_and_fills_are_tagged_with_the_paper_venue() {
+        let store = storage::init_sqlite("sqlite::memory:").await.unwrap();
+        store.insert_run("run-paper", None).await.unwrap();
+        let (backend, mut updates) = ExecutionBackend::paper();
+        assert_eq!(backend.mode(), ExecutionMode::Paper);
+
+        backend.paper_venue().unwrap().on_book(
+            3,
+            Token::Yes,
+            vec![],
+            vec![BookLevel {
+                price: 0.52,
+                qty: 50.0,
+            }],
+        );
+        let manager = OrderManager::new(
+            backend.clone(),
+            backend.venue_name(),
+            "run-paper",
+            store.clone(),
+        );
+        manager
+            .submit(NewOrder {
+                client_order_id: "c-1".into(),
+                approved_id: None,
+                intent_id: None,
+                strategy: "mm".into(),
+                market_id: 3,
+                side: Side::BuyYes,
+                price: 0.52,
+                qty: 5.0,
+                tif: TimeInForce::Gtc,
+                post_only: false,
+                expires_at_ms: None,
+                reduce_only: false,
+            })
+            .await
+            .unwrap();
+        while let Ok(update) = updates.try_recv() {
+            manager.apply_update(update).await;
+        }
+
+        assert_eq!(
+            manager.get("c-1").await.unwrap().status,
+            OrderStatus::Filled
+        );
+        let row = store.fetch_order("c-1").await.unwrap().unwrap();
+        assert_eq!(row.venue, PAPER_VENUE);
+        let fills = store.fills_for_run("run-paper").await.unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].venue, PAPER_VENUE);
+    }
+}
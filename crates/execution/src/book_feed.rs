@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use risk::StalenessGuard;
+use strategies::Token;
+use tokio::task::JoinSet;
+use tokio::time;
+use tracing::warn;
+use venue_polymarket::{BookClient, ClobBook, ClobLevel, TokenMap};
+
+use crate::order_manager::now_ms;
+use crate::paper::PaperVenue;
+use crate::venue::BookLevel;
+
+/// Feed name the book poller reports to the [`StalenessGuard`].
+pub const BOOK_FEED: &str = "clob_book";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookFeedConfig {
+    /// Time between polls of every mapped market; keep it well under the
+    /// staleness guard's max book age.
+    pub interval: Duration,
+}
+
+impl Default for BookFeedConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Polls the public CLOB book of every market in the token map and hands
+/// each update to the staleness guard, so orders are only placed on
+/// markets whose book is fresh, and to the paper venue, which fills its
+/// resting orders against it. The REST book has no trade prints, so
+/// [`PaperVenue::on_trade`] waits for a market websocket feed.
+pub struct BookFeed {
+    client: BookClient,
+    tokens: TokenMap,
+    config: BookFeedConfig,
+    staleness: Option<StalenessGuard>,
+    paper: Option<PaperVenue>,
+}
+
+impl BookFeed {
+    pub fn new(client: BookClient, tokens: TokenMap, config: BookFeedConfig) -> Self {
+        Self {
+            client,
+            tokens,
+            config,
+            staleness: None,
+            paper: None,
+        }
+    }
+
+    pub fn with_staleness_guard(mut self, guard: StalenessGuard) -> Self {
+        self.staleness = Some(guard);
+        self
+    }
+
+    pub fn with_paper_venue(mut self, venue: PaperVenue) -> Self {
+        self.paper = Some(venue);
+        self
+    }
+
+    pub async fn run(self) {
+        let mut ticker = time::interval(self.config.interval);
+        ticker.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            self.poll().await;
+        }
+    }
+
+    /// Fetch every mapped book once, concurrently.
+    pub async fn poll(&self) {
+        let mut fetches = JoinSet::new();
+        for (market_id, yes, token_id) in self.tokens.all() {
+            let client = self.client.clone();
+            let token_id = token_id.to_string();
+            let token = if yes { Token::Yes } else { Token::No };
+            fetches.spawn(async move { (market_id, token, client.book(&token_id).await) });
+        }
+        while let Some(joined) = fetches.join_next().await {
+            match joined {
+                Ok((market_id, token, Ok(book))) => self.apply(market_id, token, book, now_ms()),
+                Ok((market_id, token, Err(err))) => {
+                    warn!(error = ?err, market_id, ?token, "book poll failed")
+                }
+                Err(err) => warn!(error = ?err, "book poll task failed"),
+            }
+        }
+    }
+
+    /// Record a book of `market_id` received at `ts_ms`.
+    pub fn apply(&self, market_id: i64, token: Token, book: ClobBook, ts_ms: i64) {
+        if let Some(paper) = &self.paper {
+            paper.on_book(market_id, token, levels(book.bids), levels(book.asks));
+        }
+        if let Some(guard) = &self.staleness {
+            guard.record_book_update(market_id, ts_ms);
+            guard.record_feed_message(BOOK_FEED, ts_ms, None);
+        }
+    }
+}
+
+fn levels(levels: Vec<ClobLevel>) -> Vec<BookLevel> {
+    levels
+        .into_iter()
+        .map(|l| BookLevel {
+            price: l.price,
+            qty: l.size,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use risk::StalenessLimits;
+
+    use crate::venue::Venue;
+
+    #[tokio::test]
+    async fn polled_books_reach_the_paper_venue_and_the_guard() {
+        let (paper, _updates) = PaperVenue::new();
+        let (guard, _episodes) = StalenessGuard::new(StalenessLimits::default());
+        let feed = BookFeed::new(
+            BookClient::new("http://127.0.0.1:1").unwrap(),
+            TokenMap::default(),
+            BookFeedConfig::default(),
+        )
+        .with_staleness_guard(guard.clone())
+        .with_paper_venue(paper.clone());
+
+        let ts = now_ms();
+        assert!(guard.check(7, ts, ts).is_err());
+        let book = ClobBook {
+            bids: vec![ClobLevel {
+                price: 0.40,
+                size: 25.0,
+            }],
+            asks: vec![ClobLevel {
+                price: 0.42,
+                size: 10.0,
+            }],
+        };
+        feed.apply(7, Token::Yes, book, ts);
+
+        assert!(guard.check(7, ts, ts).is_ok());
+        let top = paper.top_of_book(7, Token::Yes).await.unwrap();
+        assert_eq!(top.bid.map(|l| (l.price, l.qty)), Some((0.40, 25.0)));
+        assert_eq!(top.ask.map(|l| (l.price, l.qty)), Some((0.42, 10.0)));
+    }
+}
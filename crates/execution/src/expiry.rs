@@ -0,0 +1,364 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use strategies::{Side, Urgency};
+use tracing::{info, warn};
+
+use crate::order_manager::{now_ms, OrderManager};
+use crate::venue::{TimeInForce, TopOfBook, Venue};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UrgencyPolicy {
+    /// Order type for Taker intents: [`TimeInForce::Fak`] or
+    /// [`TimeInForce::Fok`].
+    pub taker_tif: TimeInForce,
+    /// TTLs shorter than this use only the local timer; the venue's GTD
+    /// clock has second granularity.
+    pub gtd_min_ttl_ms: i64,
+    /// Whether the venue expires GTD orders itself ([`Venue::supports_gtd`]).
+    pub venue_gtd: bool,
+}
+
+impl Default for UrgencyPolicy {
+    fn default() -> Self {
+        Self {
+            taker_tif: TimeInForce::Fak,
+            gtd_min_ttl_ms: 5_000,
+            venue_gtd: false,
+        }
+    }
+}
+
+/// Price and order type for an intent, before sizing and ids.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrderPlan {
+    pub price: f64,
+    pub tif: TimeInForce,
+    pub post_only: bool,
+    pub expires_at_ms: Option<i64>,
+}
+
+impl UrgencyPolicy {
+    /// Map an intent's urgency and TTL onto an order.
+    ///
+    /// Maker rests post-only at the strategy's limit. Neutral joins the
+    /// touch on its own side (never beyond the limit) as a plain limit
+    /// order. Taker crosses up to the limit with `taker_tif` and never
+    /// rests, so it has no expiry. A resting order with a TTL is sent as
+    /// GTD when the venue supports it and the TTL is long enough; otherwise
+    /// it goes out GTC and the [`ExpiryTimer`] cancels it.
+    pub fn plan(
+        &self,
+        urgency: Urgency,
+        side: Side,
+        limit_price: f64,
+        ttl_ms: i64,
+        top: &TopOfBook,
+        now_ms: i64,
+    ) -> OrderPlan {
+        if urgency == Urgency::Taker {
+            return OrderPlan {
+                price: limit_price,
+                tif: self.taker_tif,
+                post_only: false,
+                expires_at_ms: None,
+            };
+        }
+        let price = match urgency {
+            Urgency::Neutral if side.is_buy() => top
+                .bid
+                .map(|b| b.price.min(limit_price))
+                .unwrap_or(limit_price),
+            Urgency::Neutral => top
+                .ask
+                .map(|a| a.price.max(limit_price))
+                .unwrap_or(limit_price),
+            _ => limit_price,
+        };
+        let expires_at_ms = (ttl_ms > 0).then(|| now_ms + ttl_ms);
+        let tif = if expires_at_ms.is_some() && self.venue_gtd && ttl_ms >= self.gtd_min_ttl_ms {
+            TimeInForce::Gtd
+        } else {
+            TimeInForce::Gtc
+        };
+        OrderPlan {
+            price,
+            tif,
+            post_only: urgency == Urgency::Maker,
+            expires_at_ms,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpiryConfig {
+    pub interval: Duration,
+    /// Extra time given to the venue to expire a GTD order before the local
+    /// timer cancels it anyway.
+    pub gtd_grace: Duration,
+}
+
+impl Default for ExpiryConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(250),
+            gtd_grace: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Cancels live orders whose TTL has lapsed. The expiry and who enforced
+/// it end up in `orders.notes` when the cancel is confirmed.
+pub struct ExpiryTimer<V> {
+    manager: Arc<OrderManager<V>>,
+    config: ExpiryConfig,
+}
+
+impl<V: Venue> ExpiryTimer<V> {
+    pub fn new(manager: Arc<OrderManager<V>>, config: ExpiryConfig) -> Self {
+        Self { manager, config }
+    }
+
+    /// Cancel every live order due at `now_ms`; returns the client ids.
+    pub async fn sweep(&self, now_ms: i64) -> Vec<String> {
+        let grace_ms = self.config.gtd_grace.as_millis() as i64;
+        let mut expired = Vec::new();
+        for order in self.manager.live_orders().await {
+            let Some(expires_at) = order.expires_at_ms else {
+                continue;
+            };
+            let due = if order.tif == TimeInForce::Gtd {
+                expires_at + grace_ms
+            } else {
+                expires_at
+            };
+            if now_ms < due || order.cancel_in_flight() || order.order_id.is_none() {
+                continue;
+            }
+            match self.manager.cancel(&order.client_order_id).await {
+                Ok(()) => expired.push(order.client_order_id),
+                Err(err) => {
+                    warn!(error = ?err, client_order_id = %order.client_order_id, "expiry cancel failed")
+                }
+            }
+        }
+        expired
+    }
+
+    pub async fn run(&self) {
+        let mut ticker = tokio::time::interval(self.config.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let expired = self.sweep(now_ms()).await;
+            if !expired.is_empty() {
+                info!(count = expired.len(), "expired orders canceled");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockVenue;
+    use crate::order_manager::{NewOrder, OrderStatus, UserUpdate, UserUpdateKind};
+    use crate::venue::BookLevel;
+    use strategies::{Intent, IntentKind, Token};
+
+    const NOW: i64 = 1_700_000_000_000;
+
+    fn top() -> TopOfBook {
+        TopOfBook {
+            bid: Some(BookLevel {
+                price: 0.44,
+                qty: 100.0,
+            }),
+            ask: Some(BookLevel {
+                price: 0.46,
+                qty: 100.0,
+            }),
+        }
+    }
+
+    #[test]
+    fn urgency_maps_to_order_type() {
+        let gtd = UrgencyPolicy {
+            venue_gtd: true,
+            ..UrgencyPolicy::default()
+        };
+        let maker = gtd.plan(Urgency::Maker, Side::BuyYes, 0.43, 0, &top(), NOW);
+        assert!(maker.post_only);
+        assert_eq!(
+            (maker.price, maker.tif, maker.expires_at_ms),
+            (0.43, TimeInForce::Gtc, None)
+        );
+
+        let neutral = gtd.plan(Urgency::Neutral, Side::BuyYes, 0.50, 10_000, &top(), NOW);
+        assert!(!neutral.post_only);
+        assert_eq!(neutral.price, 0.44);
+        assert_eq!(neutral.tif, TimeInForce::Gtd);
+        assert_eq!(neutral.expires_at_ms, Some(NOW + 10_000));
+
+        let sell = gtd.plan(Urgency::Neutral, Side::SellYes, 0.48, 0, &top(), NOW);
+        assert_eq!(sell.price, 0.48);
+
+        let taker = gtd.plan(Urgency::Taker, Side::BuyYes, 0.47, 10_000, &top(), NOW);
+        assert_eq!(taker.tif, TimeInForce::Fak);
+        assert_eq!(taker.expires_at_ms, None);
+    }
+
+    #[test]
+    fn short_ttl_or_no_venue_gtd_falls_back_to_local_timer() {
+        let local = UrgencyPolicy::default();
+        let gtd = UrgencyPolicy {
+            venue_gtd: true,
+            ..UrgencyPolicy::default()
+        };
+        let short = gtd.plan(Urgency::Maker, Side::BuyNo, 0.3, 1_000, &top(), NOW);
+        assert_eq!(short.tif, TimeInForce::Gtc);
+        assert_eq!(short.expires_at_ms, Some(NOW + 1_000));
+        let no_gtd = local.plan(Urgency::Maker, Side::BuyNo, 0.3, 60_000, &top(), NOW);
+        assert_eq!(no_gtd.tif, TimeInForce::Gtc);
+    }
+
+    #[tokio::test]
+    async fn lapsed_orders_are_canceled_with_a_note() {
+        let store = storage::init_sqlite("sqlite::memory:").await.unwrap();
+        store.insert_run("run-ttl", None).await.unwrap();
+        let om = Arc::new(OrderManager::new(
+            MockVenue::new(),
+            "polymarket",
+            "run-ttl",
+            store.clone(),
+        ));
+        let order = |id: &str, tif, expires_at_ms| NewOrder {
+            client_order_id: id.into(),
+            approved_id: None,
+            intent_id: None,
+            strategy: "mm".into(),
+            market_id: 2,
+            side: Side::BuyYes,
+            price: 0.40,
+            qty: 5.0,
+            tif,
+            post_only: true,
+            expires_at_ms,
+            reduce_only: false,
+        };
+        let now = now_ms();
+        om.submit(order("local", TimeInForce::Gtc, Some(now - 1)))
+            .await
+            .unwrap();
+        om.submit(order("gtd", TimeInForce::Gtd, Some(now - 1)))
+            .await
+            .unwrap();
+        let gtd_oid = om.get("gtd").await.unwrap().order_id.unwrap();
+        om.submit(order("later", TimeInForce::Gtc, Some(now + 60_000)))
+            .await
+            .unwrap();
+        om.submit(order("forever", TimeInForce::Gtc, None))
+            .await
+            .unwrap();
+
+        let timer = ExpiryTimer::new(om.clone(), ExpiryConfig::default());
+        assert_eq!(timer.sweep(now).await, vec!["local".to_string()]);
+        let row = store.fetch_order("local").await.unwrap().unwrap();
+        assert_eq!(row.status, "Acked");
+        assert!(om.get("local").await.unwrap().cancel_in_flight());
+        for update in om.venue().take_user_updates() {
+            om.apply_update(update).await;
+        }
+        let row = store.fetch_order("local").await.unwrap().unwrap();
+        assert_eq!(row.status, "Canceled");
+        let notes = row.notes.unwrap();
+        assert!(notes.starts_with("expired: ttl"), "{notes}");
+        assert!(notes.contains("(local timer)"));
+
+        // the venue expires the GTD order itself
+        om.apply_update(UserUpdate {
+            order_id: gtd_oid,
+            client_order_id: None,
+            ts_ms: now_ms(),
+            kind: UserUpdateKind::Canceled,
+        })
+        .await;
+        let gtd = om.get("gtd").await.unwrap();
+        assert_eq!(gtd.status, OrderStatus::Canceled);
+        assert!(gtd.notes.unwrap().contains("(venue GTD)"));
+
+        assert!(timer.sweep(now + 10_000).await.is_empty());
+        assert_eq!(timer.sweep(now + 61_000).await, vec!["later".to_string()]);
+        for update in om.venue().take_user_updates() {
+            om.apply_update(update).await;
+        }
+        assert_eq!(om.live_orders().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn intents_are_placed_per_urgency_and_missing_lapsed_gtd_is_noted() {
+        let store = storage::init_sqlite("sqlite::memory:").await.unwrap();
+        store.insert_run("run-ttl", None).await.unwrap();
+        let venue = MockVenue::new();
+        venue.set_book(2, Token::Yes, top().bid, top().ask);
+        let om = OrderManager::new(venue, "polymarket", "run-ttl", store.clone())
+            .with_urgency_policy(UrgencyPolicy {
+                venue_gtd: true,
+                ..UrgencyPolicy::default()
+            });
+        let intent = |urgency, ttl_ms| Intent {
+            intent_id: "i-1".into(),
+            strategy: "mm".into(),
+            market_id: 2,
+            kind: IntentKind::PlaceOrder,
+            side: Some(Side::BuyYes),
+            price: Some(0.45),
+            size: Some(5.0),
+            urgency,
+            ttl_ms,
+            expected_value: 0.0,
+        };
+
+        let before = now_ms();
+        let maker = om
+            .submit_intent(&intent(Urgency::Maker, 10_000), "maker", None, None)
+            .await
+            .unwrap();
+        assert_eq!(maker.tif, TimeInForce::Gtd);
+        assert!(maker.expires_at_ms.unwrap() >= before + 10_000);
+        let neutral = om
+            .submit_intent(&intent(Urgency::Neutral, 0), "neutral", None, None)
+            .await
+            .unwrap();
+        assert_eq!(neutral.limit_price, 0.44);
+        let placed = om.venue().placed();
+        assert!(placed[0].post_only);
+        assert!(!placed[1].post_only);
+
+        // the venue expired it but the update never arrived
+        om.submit(NewOrder {
+            client_order_id: "gtd".into(),
+            approved_id: None,
+            intent_id: None,
+            strategy: "mm".into(),
+            market_id: 2,
+            side: Side::BuyYes,
+            price: 0.40,
+            qty: 5.0,
+            tif: TimeInForce::Gtd,
+            post_only: true,
+            expires_at_ms: Some(before - 1),
+            reduce_only: false,
+        })
+        .await
+        .unwrap();
+        let closed = om
+            .close_missing("gtd", "not open on venue at reconcile")
+            .await
+            .unwrap();
+        assert_eq!(closed.status, OrderStatus::Canceled);
+        let notes = closed.notes.unwrap();
+        assert!(notes.contains("(venue GTD)"), "{notes}");
+        assert!(notes.ends_with("not open on venue at reconcile"));
+    }
+}
@@ -0,0 +1,427 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use risk::RiskGate;
+use serde::{Deserialize, Serialize};
+use strategies::Side;
+use tracing::{error, info, warn};
+
+use crate::order_manager::{NewOrder, OrderManager};
+use crate::venue::{TimeInForce, Venue, VenuePosition};
+
+/// Incident kind recorded for each kill-switch run.
+pub const KILL_SWITCH_INCIDENT: &str = "KILL_SWITCH";
+
+/// `orders.strategy` of the kill switch's flatten orders.
+pub const FLATTEN_STRATEGY: &str = "kill_switch";
+
+const QTY_EPSILON: f64 = 1e-9;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KillSwitchConfig {
+    /// Send marketable orders to close every position after canceling.
+    pub flatten: bool,
+    /// Worst price we accept relative to the touch when flattening.
+    pub max_slippage: f64,
+    pub tick: f64,
+    /// Cancel/flatten/verify rounds before giving up.
+    pub max_attempts: u32,
+    pub retry_delay: Duration,
+}
+
+impl Default for KillSwitchConfig {
+    fn default() -> Self {
+        Self {
+            flatten: false,
+            max_slippage: 0.05,
+            tick: 0.01,
+            max_attempts: 10,
+            retry_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KillSwitchReport {
+    pub started_ms: i64,
+    pub finished_ms: i64,
+    pub attempts: u32,
+    pub flatten_orders: usize,
+    pub residual_open_orders: usize,
+    pub residual_positions: Vec<VenuePosition>,
+    /// True once the venue reports no open orders (and no positions when
+    /// flattening was requested).
+    pub confirmed: bool,
+    pub errors: Vec<String>,
+}
+
+impl KillSwitchReport {
+    pub fn summary(&self) -> String {
+        format!(
+            "kill switch {} after {} attempt(s): flatten_orders={} open_orders={} positions={} errors={}",
+            if self.confirmed { "confirmed" } else { "NOT confirmed" },
+            self.attempts,
+            self.flatten_orders,
+            self.residual_open_orders,
+            self.residual_positions.len(),
+            self.errors.len()
+        )
+    }
+}
+
+/// Engage the kill switch: set the gate to KillSwitched, so `manager` refuses
+/// everything but reduce-only orders, cancel everything on the venue,
+/// optionally flatten, and retry until the venue confirms that no exposure
+/// is left or `max_attempts` runs out.
+///
+/// `venue` is the bare venue rather than the breaker-wrapped one `manager`
+/// trades through, so an open circuit breaker can't stop the kill switch.
+/// Flatten orders still go through `manager` and land in `orders`, and
+/// orders the cancel removed from the venue are closed there.
+pub async fn engage<V: Venue, W: Venue>(
+    manager: &OrderManager<V>,
+    venue: &W,
+    gate: &RiskGate,
+    config: &KillSwitchConfig,
+    reason: &str,
+) -> KillSwitchReport {
+    gate.kill_switch(reason);
+
+    let mut report = KillSwitchReport {
+        started_ms: now_ms(),
+        ..KillSwitchReport::default()
+    };
+    warn!(%reason, flatten = config.flatten, "kill switch engaged");
+
+    while report.attempts < config.max_attempts.max(1) {
+        if report.attempts > 0 {
+            tokio::time::sleep(config.retry_delay).await;
+        }
+        report.attempts += 1;
+
+        if let Err(err) = venue.cancel_all().await {
+            report.errors.push(format!("cancel_all: {err}"));
+        }
+        if let Err(err) = close_canceled(manager, venue).await {
+            report.errors.push(format!("open_orders: {err}"));
+        }
+
+        if config.flatten {
+            match venue.positions().await {
+                Ok(positions) => {
+                    for position in positions.iter().filter(|p| p.qty.abs() > QTY_EPSILON) {
+                        flatten_position(manager, venue, config, &mut report, position).await;
+                    }
+                }
+                Err(err) => report.errors.push(format!("positions: {err}")),
+            }
+        }
+
+        match verify(venue, config).await {
+            Ok((open_orders, positions)) => {
+                report.residual_open_orders = open_orders;
+                report.residual_positions = positions;
+                if open_orders == 0 && report.residual_positions.is_empty() {
+                    report.confirmed = true;
+                    break;
+                }
+            }
+            Err(err) => report.errors.push(format!("verify: {err}")),
+        }
+    }
+
+    report.finished_ms = now_ms();
+    if report.confirmed {
+        info!(summary = %report.summary(), "kill switch complete");
+    } else {
+        error!(summary = %report.summary(), "kill switch could not confirm zero exposure");
+    }
+    report
+}
+
+/// Close the manager's live orders that are no longer open on the venue.
+async fn close_canceled<V: Venue, W: Venue>(
+    manager: &OrderManager<V>,
+    venue: &W,
+) -> anyhow::Result<()> {
+    let open: Vec<String> = venue
+        .open_orders()
+        .await?
+        .into_iter()
+        .map(|o| o.order_id)
+        .collect();
+    for order in manager.live_orders().await {
+        let gone = order.order_id.as_ref().is_some_and(|id| !open.contains(id));
+        if gone {
+            manager.backfill_fills(&order.client_order_id).await;
+            manager
+                .close_missing(&order.client_order_id, "canceled by kill switch")
+                .await;
+        }
+    }
+    Ok(())
+}
+
+async fn flatten_position<V: Venue, W: Venue>(
+    manager: &OrderManager<V>,
+    venue: &W,
+    config: &KillSwitchConfig,
+    report: &mut KillSwitchReport,
+    position: &VenuePosition,
+) {
+    let book = match venue.top_of_book(position.market_id, position.token).await {
+        Ok(book) => book,
+        Err(err) => {
+            report.errors.push(format!("top_of_book: {err}"));
+            return;
+        }
+    };
+
+    // Marketable limit order, but never worse than `max_slippage` past the touch.
+    let (side, price) = if position.qty > 0.0 {
+        let Some(bid) = book.bid else {
+            report.errors.push(format!(
+                "no bid to flatten market {} {:?}",
+                position.market_id, position.token
+            ));
+            return;
+        };
+        (
+            Side::sell(position.token),
+            (bid.price - config.max_slippage).max(config.tick),
+        )
+    } else {
+        let Some(ask) = book.ask else {
+            report.errors.push(format!(
+                "no ask to flatten market {} {:?}",
+                position.market_id, position.token
+            ));
+            return;
+        };
+        (
+            Side::buy(position.token),
+            (ask.price + config.max_slippage).min(1.0 - config.tick),
+        )
+    };
+
+    let client_order_id = format!(
+        "ks-{}-{}-{}-{:?}",
+        report.started_ms, report.attempts, position.market_id, position.token
+    );
+    let order = NewOrder {
+        client_order_id: client_order_id.clone(),
+        approved_id: None,
+        intent_id: None,
+        strategy: FLATTEN_STRATEGY.to_string(),
+        market_id: position.market_id,
+        side,
+        price,
+        qty: position.qty.abs(),
+        tif: TimeInForce::Fak,
+        post_only: false,
+        expires_at_ms: None,
+        reduce_only: true,
+    };
+    match manager.submit_via(order, venue).await {
+        Ok(order) if order.status.is_final() && order.filled_qty <= QTY_EPSILON => {
+            report.errors.push(format!(
+                "flatten {client_order_id}: {} {}",
+                order.status.as_str(),
+                order.notes.unwrap_or_default()
+            ))
+        }
+        Ok(_) => report.flatten_orders += 1,
+        Err(err) => report
+            .errors
+            .push(format!("flatten {client_order_id}: {err}")),
+    }
+}
+
+async fn verify<V: Venue>(
+    venue: &V,
+    config: &KillSwitchConfig,
+) -> anyhow::Result<(usize, Vec<VenuePosition>)> {
+    let open_orders = venue.open_orders().await?.len();
+    let positions = if config.flatten {
+        venue
+            .positions()
+            .await?
+            .into_iter()
+            .filter(|p| p.qty.abs() > QTY_EPSILON)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Ok((open_orders, positions))
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockVenue;
+    use crate::order_manager::OrderStatus;
+    use crate::resilience::{BreakerConfig, Endpoint, ResilientVenue, RetryPolicy};
+    use crate::venue::{BookLevel, OrderRequest};
+    use risk::RiskState;
+    use strategies::Token;
+
+    fn config(flatten: bool) -> KillSwitchConfig {
+        KillSwitchConfig {
+            flatten,
+            max_attempts: 3,
+            retry_delay: Duration::from_millis(1),
+            ..KillSwitchConfig::default()
+        }
+    }
+
+    fn resting(client_order_id: &str) -> OrderRequest {
+        OrderRequest {
+            client_order_id: client_order_id.into(),
+            market_id: 1,
+            side: Side::BuyYes,
+            price: 0.30,
+            qty: 5.0,
+            tif: TimeInForce::Gtc,
+            post_only: false,
+            expires_at_ms: None,
+        }
+    }
+
+    async fn manager(venue: &MockVenue) -> OrderManager<MockVenue> {
+        let store = storage::init_sqlite("sqlite::memory:").await.unwrap();
+        store.insert_run("run-ks", None).await.unwrap();
+        OrderManager::new(venue.clone(), "polymarket", "run-ks", store)
+    }
+
+    #[tokio::test]
+    async fn cancels_resting_orders_and_blocks_gate() {
+        let venue = MockVenue::new();
+        venue.place_order(&resting("a")).await.unwrap();
+        let gate = RiskGate::new();
+        let om = manager(&venue).await.with_risk_gate(gate.clone());
+        om.submit(NewOrder {
+            client_order_id: "b".into(),
+            approved_id: None,
+            intent_id: None,
+            strategy: "mm".into(),
+            market_id: 1,
+            side: Side::BuyYes,
+            price: 0.30,
+            qty: 5.0,
+            tif: TimeInForce::Gtc,
+            post_only: false,
+            expires_at_ms: None,
+            reduce_only: false,
+        })
+        .await
+        .unwrap();
+
+        let report = engage(&om, &venue, &gate, &config(false), "test").await;
+
+        assert!(report.confirmed);
+        assert_eq!(report.attempts, 1);
+        assert_eq!(gate.status(), RiskState::KillSwitched);
+        assert!(!gate.allows_place("mm", false));
+        assert!(venue.open_orders().await.unwrap().is_empty());
+        let closed = om.get("b").await.unwrap();
+        assert_eq!(closed.status, OrderStatus::Canceled);
+        assert_eq!(closed.notes.as_deref(), Some("canceled by kill switch"));
+    }
+
+    #[tokio::test]
+    async fn retries_until_flat_within_price_band() {
+        let venue = MockVenue::new();
+        venue.set_position(1, Token::No, 8.0);
+        // only 5 on the bid: the first flatten round leaves 3 behind
+        venue.set_book(
+            1,
+            Token::No,
+            Some(BookLevel {
+                price: 0.55,
+                qty: 5.0,
+            }),
+            None,
+        );
+        let gate = RiskGate::new();
+        let om = manager(&venue).await.with_risk_gate(gate.clone());
+
+        let first = engage(&om, &venue, &gate, &config(true), "test").await;
+        assert!(!first.confirmed);
+        assert_eq!(first.attempts, 3);
+        assert_eq!(first.residual_positions.len(), 1);
+        assert!((first.residual_positions[0].qty - 3.0).abs() < 1e-9);
+
+        let placed = venue.placed();
+        assert!(placed.iter().all(|o| o.side == Side::SellNo));
+        assert!(placed.iter().all(|o| (o.price - 0.50).abs() < 1e-9));
+
+        venue.set_book(
+            1,
+            Token::No,
+            Some(BookLevel {
+                price: 0.54,
+                qty: 10.0,
+            }),
+            None,
+        );
+        let second = engage(&om, &venue, &gate, &config(true), "test").await;
+        assert!(second.confirmed);
+        assert!(second.residual_positions.is_empty());
+
+        // every flatten order is tracked like any other
+        let flattens = placed.len() + 1;
+        assert_eq!(venue.placed().len(), flattens);
+        for order in venue.placed() {
+            let tracked = om.get(&order.client_order_id).await.unwrap();
+            assert_eq!(tracked.strategy, FLATTEN_STRATEGY);
+        }
+    }
+
+    #[tokio::test]
+    async fn venue_errors_are_reported_and_retried() {
+        let venue = MockVenue::new();
+        venue.place_order(&resting("a")).await.unwrap();
+        venue.fail_next("cancel_all", 2);
+        let gate = RiskGate::new();
+        let om = manager(&venue).await;
+
+        let report = engage(&om, &venue, &gate, &config(false), "test").await;
+
+        assert!(report.confirmed);
+        assert_eq!(report.attempts, 3);
+        assert_eq!(report.errors.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn open_breakers_do_not_stop_the_kill_switch() {
+        let mock = MockVenue::new();
+        mock.place_order(&resting("a")).await.unwrap();
+        let retry = RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        };
+        let breaker = BreakerConfig {
+            consecutive_errors: 1,
+            ..BreakerConfig::default()
+        };
+        let (venue, _events) = ResilientVenue::new(mock.clone(), retry, breaker);
+        mock.fail_next("cancel_all", 1);
+        assert!(venue.cancel_all().await.is_err());
+        assert_eq!(venue.tripped(), vec![Endpoint::CancelAll]);
+
+        let store = storage::init_sqlite("sqlite::memory:").await.unwrap();
+        store.insert_run("run-ks", None).await.unwrap();
+        let gate = RiskGate::new();
+        let om = OrderManager::new(venue, "polymarket", "run-ks", store);
+        let report = engage(&om, om.venue().inner(), &gate, &config(false), "test").await;
+
+        assert!(report.confirmed);
+        assert!(report.errors.is_empty());
+    }
+}
@@ -0,0 +1,638 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use storage::{PnlEntry, Store};
+use strategies::{Side, Token};
+use tokio::time::{sleep, Instant};
+use tracing::warn;
+
+use crate::order_manager::{now_ms, ManagedOrder, NewOrder, OrderManager};
+use crate::venue::{TimeInForce, Venue};
+
+/// Incident kind recorded when a leg group is left unhedged.
+pub const LEGGING_INCIDENT: &str = "LEGGING";
+
+/// `pnl_ledger.kind` for the planned edge of a leg group.
+pub const BOX_ARB_PNL_KIND: &str = "BOX_ARB";
+/// `pnl_ledger.kind` for the cost of unwinding a legged group.
+pub const LEGGING_LOSS_PNL_KIND: &str = "LEGGING_LOSS";
+
+const QTY_EPSILON: f64 = 1e-9;
+
+/// What to do when one leg filled and the other did not by the deadline.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum UnwindPolicy {
+    /// Buy the missing leg, paying at most `max_price`.
+    Chase { max_price: f64 },
+    /// Sell the excess of the filled leg, accepting no less than `min_price`.
+    SellBack { min_price: f64 },
+    /// Keep the unhedged position and flag it.
+    Hold,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegGroupConfig {
+    /// How long both legs may work before the group is considered legged.
+    pub deadline: Duration,
+    /// How long to wait for the venue to confirm the deadline cancels.
+    pub cancel_timeout: Duration,
+    /// How long an unwind order may take to fill.
+    pub unwind_timeout: Duration,
+    pub poll_interval: Duration,
+    pub policy: UnwindPolicy,
+}
+
+impl Default for LegGroupConfig {
+    fn default() -> Self {
+        Self {
+            deadline: Duration::from_secs(2),
+            cancel_timeout: Duration::from_secs(1),
+            unwind_timeout: Duration::from_secs(1),
+            poll_interval: Duration::from_millis(25),
+            policy: UnwindPolicy::Hold,
+        }
+    }
+}
+
+/// A YES and a NO buy of equal size, e.g. a box arb.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LegGroup {
+    pub group_id: String,
+    pub strategy: String,
+    pub market_id: i64,
+    pub qty: f64,
+    pub yes_price: f64,
+    pub no_price: f64,
+    pub approved_id: Option<i64>,
+    pub intent_id: Option<i64>,
+}
+
+impl LegGroup {
+    fn price(&self, token: Token) -> f64 {
+        match token {
+            Token::Yes => self.yes_price,
+            Token::No => self.no_price,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LegGroupStatus {
+    /// Both legs filled the same quantity.
+    Complete,
+    /// Neither leg filled.
+    Missed,
+    /// The missing leg was bought at a worse price.
+    Chased,
+    /// The excess of the filled leg was sold.
+    SoldBack,
+    /// An unhedged position remains.
+    Held,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LegGroupOutcome {
+    pub group_id: String,
+    pub strategy: String,
+    pub market_id: i64,
+    pub status: LegGroupStatus,
+    /// Quantity held on both sides, redeemable for 1 each.
+    pub matched_qty: f64,
+    pub unhedged_token: Option<Token>,
+    pub unhedged_qty: f64,
+    /// Box payout plus unwind proceeds, less the cost of everything but the
+    /// unhedged inventory. Includes the legging loss.
+    pub realized_pnl_usd: f64,
+    /// Extra cost of chasing plus the loss on selling back.
+    pub legging_loss_usd: f64,
+    pub risk_flag: bool,
+    pub client_order_ids: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct TokenFlow {
+    bought_qty: f64,
+    bought_cost: f64,
+    sold_qty: f64,
+    sold_proceeds: f64,
+}
+
+impl TokenFlow {
+    fn add(&mut self, order: &ManagedOrder) {
+        if order.side.is_buy() {
+            self.bought_qty += order.filled_qty;
+            self.bought_cost += order.filled_notional;
+        } else {
+            self.sold_qty += order.filled_qty;
+            self.sold_proceeds += order.filled_notional;
+        }
+    }
+
+    fn net_qty(&self) -> f64 {
+        self.bought_qty - self.sold_qty
+    }
+
+    fn avg_buy(&self) -> f64 {
+        if self.bought_qty > QTY_EPSILON {
+            self.bought_cost / self.bought_qty
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Submits linked YES/NO legs and unwinds per policy when they leg.
+///
+/// Library-only for now: traderd runs no strategy loop, so nothing in the
+/// daemon produces leg groups. A strategy runner that emits box arbs should
+/// share the daemon's `OrderManager` and execute them through this.
+pub struct LegGroupExecutor<V> {
+    manager: Arc<OrderManager<V>>,
+    store: Store,
+    run_id: String,
+    config: LegGroupConfig,
+}
+
+impl<V: Venue> LegGroupExecutor<V> {
+    pub fn new(
+        manager: Arc<OrderManager<V>>,
+        store: Store,
+        run_id: &str,
+        config: LegGroupConfig,
+    ) -> Self {
+        Self {
+            manager,
+            store,
+            run_id: run_id.to_string(),
+            config,
+        }
+    }
+
+    pub async fn execute(&self, group: &LegGroup) -> Result<LegGroupOutcome> {
+        let yes_id = format!("{}-yes", group.group_id);
+        let no_id = format!("{}-no", group.group_id);
+        for (client_order_id, token) in [(&yes_id, Token::Yes), (&no_id, Token::No)] {
+            self.manager
+                .submit(self.order(
+                    group,
+                    client_order_id,
+                    Side::buy(token),
+                    group.price(token),
+                    group.qty,
+                    TimeInForce::Gtc,
+                ))
+                .await?;
+        }
+
+        let legs = [yes_id.clone(), no_id.clone()];
+        let deadline = Instant::now() + self.config.deadline;
+        self.wait_until(&legs, deadline, |orders| {
+            orders
+                .iter()
+                .all(|o| o.status.is_final() || o.remaining_qty() <= QTY_EPSILON)
+        })
+        .await;
+
+        for id in &legs {
+            if let Some(order) = self.manager.get(id).await {
+                if !order.status.is_final() {
+                    if let Err(err) = self.manager.cancel(id).await {
+                        warn!(error = ?err, client_order_id = %id, "failed to cancel leg");
+                    }
+                }
+            }
+        }
+
+        // a leg can still fill between the deadline and its cancel landing;
+        // size the unwind only once both legs are final and any trades the
+        // user channel has not delivered yet are folded in
+        let timeout = Instant::now() + self.config.cancel_timeout;
+        let settled = self
+            .wait_until(&legs, timeout, |orders| {
+                orders.iter().all(|o| o.status.is_final())
+            })
+            .await;
+        if settled.iter().any(|o| !o.status.is_final()) {
+            warn!(group_id = %group.group_id, "leg cancel unconfirmed; unwinding the fills seen so far");
+        }
+        for id in &legs {
+            self.manager.backfill_fills(id).await;
+        }
+
+        let mut client_order_ids = legs.to_vec();
+        let (yes, no) = self.flows(&client_order_ids).await;
+        let imbalance = yes.net_qty() - no.net_qty();
+        let mut status = if yes.bought_qty <= QTY_EPSILON && no.bought_qty <= QTY_EPSILON {
+            LegGroupStatus::Missed
+        } else {
+            LegGroupStatus::Complete
+        };
+        let mut legging_loss_usd = 0.0;
+
+        if imbalance.abs() > QTY_EPSILON {
+            let (filled, missing) = if imbalance > 0.0 {
+                (Token::Yes, Token::No)
+            } else {
+                (Token::No, Token::Yes)
+            };
+            let excess = imbalance.abs();
+            let unwind = match self.config.policy {
+                UnwindPolicy::Chase { max_price } => Some((
+                    format!("{}-chase", group.group_id),
+                    Side::buy(missing),
+                    max_price,
+                    LegGroupStatus::Chased,
+                )),
+                UnwindPolicy::SellBack { min_price } => Some((
+                    format!("{}-sellback", group.group_id),
+                    Side::sell(filled),
+                    min_price,
+                    LegGroupStatus::SoldBack,
+                )),
+                UnwindPolicy::Hold => None,
+            };
+
+            if let Some((client_order_id, side, price, unwound)) = unwind {
+                // both unwinds close the unhedged leg's exposure, so the risk
+                // gate lets them through; price bands and the staleness guard
+                // still apply, and a rejected unwind leaves the group Held
+                let order = self
+                    .manager
+                    .submit(NewOrder {
+                        reduce_only: true,
+                        ..self.order(
+                            group,
+                            &client_order_id,
+                            side,
+                            price,
+                            excess,
+                            TimeInForce::Fak,
+                        )
+                    })
+                    .await?;
+                let timeout = Instant::now() + self.config.unwind_timeout;
+                let ids = [client_order_id.clone()];
+                let order = self
+                    .wait_until(&ids, timeout, |o| {
+                        o[0].status.is_final() || o[0].remaining_qty() <= QTY_EPSILON
+                    })
+                    .await
+                    .pop()
+                    .unwrap_or(order);
+                if let Some(avg) = order.avg_fill_price() {
+                    legging_loss_usd += if side.is_buy() {
+                        (avg - group.price(missing)) * order.filled_qty
+                    } else {
+                        let entry = match filled {
+                            Token::Yes => yes.avg_buy(),
+                            Token::No => no.avg_buy(),
+                        };
+                        (entry - avg) * order.filled_qty
+                    };
+                }
+                client_order_ids.push(client_order_id);
+                status = unwound;
+            }
+        }
+
+        let (yes, no) = self.flows(&client_order_ids).await;
+        let matched_qty = yes.net_qty().min(no.net_qty()).max(0.0);
+        let residual = yes.net_qty() - no.net_qty();
+        let (unhedged_token, unhedged_qty) = if residual.abs() > QTY_EPSILON {
+            let token = if residual > 0.0 {
+                Token::Yes
+            } else {
+                Token::No
+            };
+            (Some(token), residual.abs())
+        } else {
+            (None, 0.0)
+        };
+        let held_cost = (yes.net_qty() - matched_qty) * yes.avg_buy()
+            + (no.net_qty() - matched_qty) * no.avg_buy();
+        let realized_pnl_usd =
+            matched_qty + yes.sold_proceeds + no.sold_proceeds - yes.bought_cost - no.bought_cost
+                + held_cost;
+        let risk_flag = unhedged_token.is_some();
+        if risk_flag {
+            status = LegGroupStatus::Held;
+        }
+
+        let outcome = LegGroupOutcome {
+            group_id: group.group_id.clone(),
+            strategy: group.strategy.clone(),
+            market_id: group.market_id,
+            status,
+            matched_qty,
+            unhedged_token,
+            unhedged_qty,
+            realized_pnl_usd,
+            legging_loss_usd,
+            risk_flag,
+            client_order_ids,
+        };
+        self.record(&outcome).await;
+        Ok(outcome)
+    }
+
+    fn order(
+        &self,
+        group: &LegGroup,
+        client_order_id: &str,
+        side: Side,
+        price: f64,
+        qty: f64,
+        tif: TimeInForce,
+    ) -> NewOrder {
+        NewOrder {
+            client_order_id: client_order_id.to_string(),
+            approved_id: group.approved_id,
+            intent_id: group.intent_id,
+            strategy: group.strategy.clone(),
+            market_id: group.market_id,
+            side,
+            price,
+            qty,
+            tif,
+            post_only: false,
+            expires_at_ms: None,
+            reduce_only: false,
+        }
+    }
+
+    async fn wait_until(
+        &self,
+        ids: &[String],
+        deadline: Instant,
+        done: impl Fn(&[ManagedOrder]) -> bool,
+    ) -> Vec<ManagedOrder> {
+        loop {
+            let mut orders = Vec::with_capacity(ids.len());
+            for id in ids {
+                if let Some(order) = self.manager.get(id).await {
+                    orders.push(order);
+                }
+            }
+            if orders.len() < ids.len() || done(&orders) || Instant::now() >= deadline {
+                return orders;
+            }
+            sleep(self.config.poll_interval).await;
+        }
+    }
+
+    async fn flows(&self, ids: &[String]) -> (TokenFlow, TokenFlow) {
+        let mut yes = TokenFlow::default();
+        let mut no = TokenFlow::default();
+        for id in ids {
+            if let Some(order) = self.manager.get(id).await {
+                match order.side.token() {
+                    Token::Yes => yes.add(&order),
+                    Token::No => no.add(&order),
+                }
+            }
+        }
+        (yes, no)
+    }
+
+    /// Attribute the group's result to its strategy, split into the planned
+    /// edge and the legging loss, and flag unhedged leftovers.
+    async fn record(&self, outcome: &LegGroupOutcome) {
+        if outcome.status == LegGroupStatus::Missed {
+            return;
+        }
+        let ts_ms = now_ms();
+        let notes = serde_json::to_string(outcome).ok();
+        let mut entries = vec![PnlEntry {
+            run_id: self.run_id.clone(),
+            ts_ms,
+            market_id: outcome.market_id,
+            strategy: Some(outcome.strategy.clone()),
+            kind: BOX_ARB_PNL_KIND.to_string(),
+            reference: Some(outcome.group_id.clone()),
+            pnl_usd: outcome.realized_pnl_usd + outcome.legging_loss_usd,
+            notes: notes.clone(),
+        }];
+        if outcome.legging_loss_usd.abs() > QTY_EPSILON {
+            entries.push(PnlEntry {
+                kind: LEGGING_LOSS_PNL_KIND.to_string(),
+                pnl_usd: -outcome.legging_loss_usd,
+                ..entries[0].clone()
+            });
+        }
+        for entry in &entries {
+            if let Err(err) = self.store.insert_pnl(entry).await {
+                warn!(error = ?err, group_id = %outcome.group_id, "failed to record leg group pnl");
+            }
+        }
+
+        if outcome.risk_flag {
+            let message = format!(
+                "leg group {} ({}) left {:.4} {:?} unhedged on market {}",
+                outcome.group_id,
+                outcome.strategy,
+                outcome.unhedged_qty,
+                outcome.unhedged_token.unwrap_or(Token::Yes),
+                outcome.market_id
+            );
+            warn!(%message, "leg group unhedged");
+            if let Err(err) = self
+                .store
+                .log_incident(&self.run_id, "warning", LEGGING_INCIDENT, &message)
+                .await
+            {
+                warn!(error = ?err, "failed to record legging incident");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockVenue;
+    use crate::venue::BookLevel;
+    use risk::{PriceBandLimits, PriceBands};
+
+    const RUN: &str = "run-legs";
+
+    struct Harness {
+        venue: MockVenue,
+        store: Store,
+        executor: LegGroupExecutor<MockVenue>,
+    }
+
+    async fn harness(policy: UnwindPolicy) -> Harness {
+        harness_with(policy, true, None).await
+    }
+
+    /// `pump: false` drops the user channel, leaving only trade backfill.
+    async fn harness_with(policy: UnwindPolicy, pump: bool, bands: Option<PriceBands>) -> Harness {
+        let store = storage::init_sqlite("sqlite::memory:").await.unwrap();
+        store.insert_run(RUN, None).await.unwrap();
+        let venue = MockVenue::new();
+        let mut manager = OrderManager::new(venue.clone(), "mock", RUN, store.clone());
+        if let Some(bands) = bands {
+            manager = manager.with_price_bands(bands);
+        }
+        let manager = Arc::new(manager);
+
+        // stand-in for the user websocket task
+        if pump {
+            let pump_venue = venue.clone();
+            let pump_manager = manager.clone();
+            tokio::spawn(async move {
+                loop {
+                    for update in pump_venue.take_user_updates() {
+                        pump_manager.apply_update(update).await;
+                    }
+                    sleep(Duration::from_millis(2)).await;
+                }
+            });
+        }
+
+        let config = LegGroupConfig {
+            deadline: Duration::from_millis(40),
+            cancel_timeout: Duration::from_millis(20),
+            unwind_timeout: Duration::from_millis(40),
+            poll_interval: Duration::from_millis(2),
+            policy,
+        };
+        let executor = LegGroupExecutor::new(manager, store.clone(), RUN, config);
+        Harness {
+            venue,
+            store,
+            executor,
+        }
+    }
+
+    fn level(price: f64) -> Option<BookLevel> {
+        Some(BookLevel { price, qty: 100.0 })
+    }
+
+    fn group() -> LegGroup {
+        LegGroup {
+            group_id: "g-1".into(),
+            strategy: "box_arb".into(),
+            market_id: 9,
+            qty: 10.0,
+            yes_price: 0.40,
+            no_price: 0.55,
+            approved_id: None,
+            intent_id: None,
+        }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[tokio::test]
+    async fn both_legs_fill_and_book_the_edge() {
+        let h = harness(UnwindPolicy::Hold).await;
+        h.venue.set_book(9, Token::Yes, None, level(0.40));
+        h.venue.set_book(9, Token::No, None, level(0.55));
+
+        let outcome = h.executor.execute(&group()).await.unwrap();
+        assert_eq!(outcome.status, LegGroupStatus::Complete);
+        assert!(close(outcome.matched_qty, 10.0));
+        assert!(close(outcome.realized_pnl_usd, 0.5));
+        assert!(!outcome.risk_flag);
+
+        let pnl = h.store.pnl_entries(RUN).await.unwrap();
+        assert_eq!(pnl.len(), 1);
+        assert_eq!(pnl[0].kind, BOX_ARB_PNL_KIND);
+        assert_eq!(pnl[0].strategy.as_deref(), Some("box_arb"));
+    }
+
+    #[tokio::test]
+    async fn sells_back_the_filled_leg() {
+        let h = harness(UnwindPolicy::SellBack { min_price: 0.30 }).await;
+        h.venue.set_book(9, Token::Yes, level(0.38), level(0.40));
+
+        let outcome = h.executor.execute(&group()).await.unwrap();
+        assert_eq!(outcome.status, LegGroupStatus::SoldBack);
+        assert!(close(outcome.legging_loss_usd, 0.2));
+        assert!(close(outcome.realized_pnl_usd, -0.2));
+        assert!(outcome.unhedged_token.is_none());
+
+        let pnl = h.store.pnl_entries(RUN).await.unwrap();
+        let loss = pnl
+            .iter()
+            .find(|e| e.kind == LEGGING_LOSS_PNL_KIND)
+            .expect("legging loss row");
+        assert!(close(loss.pnl_usd, -0.2));
+        assert_eq!(loss.reference.as_deref(), Some("g-1"));
+    }
+
+    #[tokio::test]
+    async fn chases_missing_leg_within_max_price() {
+        let h = harness(UnwindPolicy::Chase { max_price: 0.58 }).await;
+        h.venue.set_book(9, Token::Yes, None, level(0.40));
+        h.venue.set_book(9, Token::No, None, level(0.57));
+
+        let outcome = h.executor.execute(&group()).await.unwrap();
+        assert_eq!(outcome.status, LegGroupStatus::Chased);
+        assert!(close(outcome.matched_qty, 10.0));
+        assert!(close(outcome.legging_loss_usd, 0.2));
+        assert!(close(outcome.realized_pnl_usd, 0.3));
+        assert!(h.venue.open_orders().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn holds_and_flags_when_unwind_cannot_complete() {
+        let h = harness(UnwindPolicy::Chase { max_price: 0.56 }).await;
+        h.venue.set_book(9, Token::Yes, None, level(0.40));
+        h.venue.set_book(9, Token::No, None, level(0.60));
+
+        let outcome = h.executor.execute(&group()).await.unwrap();
+        assert_eq!(outcome.status, LegGroupStatus::Held);
+        assert!(outcome.risk_flag);
+        assert_eq!(outcome.unhedged_token, Some(Token::Yes));
+        assert!(close(outcome.unhedged_qty, 10.0));
+        assert!(close(outcome.realized_pnl_usd, 0.0));
+    }
+
+    #[tokio::test]
+    async fn folds_in_fills_the_user_channel_missed() {
+        let h = harness_with(UnwindPolicy::Hold, false, None).await;
+        h.venue.set_book(9, Token::Yes, None, level(0.40));
+
+        // the NO leg partly fills while working, but no update ever arrives
+        let venue = h.venue.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(10)).await;
+            for order in venue.open_orders().await.unwrap() {
+                venue.fill_resting(&order.order_id, 4.0);
+            }
+        });
+
+        let outcome = h.executor.execute(&group()).await.unwrap();
+        assert_eq!(outcome.status, LegGroupStatus::Held);
+        assert!(close(outcome.matched_qty, 4.0));
+        assert_eq!(outcome.unhedged_token, Some(Token::Yes));
+        assert!(close(outcome.unhedged_qty, 6.0));
+    }
+
+    #[tokio::test]
+    async fn chase_past_the_slippage_cap_is_rejected_and_held() {
+        let bands = PriceBands::new(PriceBandLimits::default());
+        let h = harness_with(UnwindPolicy::Chase { max_price: 0.70 }, true, Some(bands)).await;
+        h.venue.set_book(9, Token::Yes, None, level(0.40));
+        h.venue.set_book(9, Token::No, None, level(0.57));
+
+        let outcome = h.executor.execute(&group()).await.unwrap();
+        assert_eq!(outcome.status, LegGroupStatus::Held);
+        assert!(outcome.risk_flag);
+        assert!(close(outcome.unhedged_qty, 10.0));
+        let chase = h.store.fetch_order("g-1-chase").await.unwrap().unwrap();
+        assert_eq!(chase.status, "Rejected");
+        assert!(chase.notes.unwrap().starts_with("price band: "));
+        assert!(h
+            .venue
+            .placed()
+            .iter()
+            .all(|o| o.client_order_id != "g-1-chase"));
+    }
+}
@@ -1,3 +1,46 @@
+pub mod backend;
+pub mod book_feed;
+pub mod expiry;
+pub mod kill_switch;
+pub mod leg_group;
+pub mod live;
+pub mod mock;
+pub mod order_manager;
+pub mod paper;
+pub mod reconcile;
+pub mod resilience;
+pub mod self_trade;
+pub mod venue;
+
+pub use backend::{ExecutionBackend, ExecutionMode, LIVE_VENUE, PAPER_VENUE};
+pub use book_feed::{BookFeed, BookFeedConfig, BOOK_FEED};
+pub use expiry::{ExpiryConfig, ExpiryTimer, OrderPlan, UrgencyPolicy};
+pub use kill_switch::{KillSwitchConfig, KillSwitchReport, FLATTEN_STRATEGY, KILL_SWITCH_INCIDENT};
+pub use leg_group::{
+    LegGroup, LegGroupConfig, LegGroupExecutor, LegGroupOutcome, LegGroupStatus, UnwindPolicy,
+    LEGGING_INCIDENT,
+};
+pub use live::PolymarketVenue;
+pub use mock::MockVenue;
+pub use order_manager::{
+    ClientOrderIds, ManagedOrder, NewOrder, OrderManager, OrderStatus, ReplaceConfig,
+    ReplaceOutcome, ReplacePolicy, ReplaceStatus, UserUpdate, UserUpdateKind,
+};
+pub use paper::PaperVenue;
+pub use reconcile::{
+    OrphanPolicy, PositionDrift, ReconcileConfig, ReconcileReport, Reconciler,
+    RECONCILE_DRIFT_INCIDENT,
+};
+pub use resilience::{
+    BreakerConfig, BreakerEvent, BreakerState, Endpoint, ResilientVenue, RetryPolicy,
+    VENUE_BREAKER_INCIDENT,
+};
+pub use self_trade::{SelfTradePolicy, SELF_TRADE_INCIDENT};
+pub use venue::{
+    BookLevel, OrderAck, OrderRequest, TimeInForce, TopOfBook, Venue, VenueOrder, VenuePosition,
+    VenueRejection, VenueTrade,
+};
+
 pub struct ExecutionEngine;
 
 impl Default for ExecutionEngine {
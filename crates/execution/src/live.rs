@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use strategies::{Side, Token};
+use venue_polymarket::{
+    ClobClient, ClobLevel, ClobOrderType, ClobRejection, ClobSide, OrderOptions, TokenMap,
+};
+
+use crate::order_manager::now_ms;
+use crate::venue::{
+    BookLevel, OrderAck, OrderRequest, TimeInForce, TopOfBook, Venue, VenueOrder, VenuePosition,
+    VenueRejection, VenueTrade,
+};
+
+/// Venue backed by the Polymarket CLOB. Signs and submits real orders.
+#[derive(Clone)]
+pub struct PolymarketVenue {
+    client: Arc<ClobClient>,
+    tokens: Arc<TokenMap>,
+    /// Client order id of every order placed by this process, by venue
+    /// order id. The CLOB does not echo ours back on orders or trades.
+    client_order_ids: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl PolymarketVenue {
+    pub fn new(client: ClobClient, tokens: TokenMap) -> Self {
+        Self {
+            client: Arc::new(client),
+            tokens: Arc::new(tokens),
+            client_order_ids: Arc::default(),
+        }
+    }
+
+    fn client_order_id(&self, order_id: &str) -> Option<String> {
+        self.client_order_ids
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .get(order_id)
+            .cloned()
+    }
+
+    fn token_id(&self, market_id: i64, token: Token) -> Result<&str> {
+        self.tokens
+            .token_id(market_id, token == Token::Yes)
+            .ok_or_else(|| anyhow!("market {market_id} has no token mapping"))
+    }
+
+    /// Our side for a CLOB token id and direction, if the token is mapped.
+    fn side_of(&self, token_id: &str, side: ClobSide) -> Option<(i64, Side)> {
+        let (market_id, yes) = self.tokens.market_of(token_id)?;
+        let token = if yes { Token::Yes } else { Token::No };
+        let side = match side {
+            ClobSide::Buy => Side::buy(token),
+            ClobSide::Sell => Side::sell(token),
+        };
+        Some((market_id, side))
+    }
+}
+
+/// The CLOB expires GTD orders a minute before their signed expiration
+/// (its security threshold), so sign for `at_ms` plus that minute.
+fn gtd_expiration_s(at_ms: i64) -> u64 {
+    (at_ms.max(0) as u64).div_ceil(1000) + GTD_THRESHOLD_S
+}
+
+const GTD_THRESHOLD_S: u64 = 60;
+
+/// Surface CLOB rejections as [`VenueRejection`] so they are not retried.
+fn classify(err: anyhow::Error) -> anyhow::Error {
+    match err.downcast::<ClobRejection>() {
+        Ok(rejection) => VenueRejection(rejection.0).into(),
+        Err(err) => err,
+    }
+}
+
+fn level(l: Option<&ClobLevel>) -> Option<BookLevel> {
+    l.map(|l| BookLevel {
+        price: l.price,
+        qty: l.size,
+    })
+}
+
+impl Venue for PolymarketVenue {
+    async fn place_order(&self, order: &OrderRequest) -> Result<OrderAck> {
+        let token_id = self.token_id(order.market_id, order.side.token())?;
+        let side = if order.side.is_buy() {
+            ClobSide::Buy
+        } else {
+            ClobSide::Sell
+        };
+        let order_type = match order.tif {
+            TimeInForce::Gtc => ClobOrderType::Gtc,
+            TimeInForce::Gtd => ClobOrderType::Gtd,
+            TimeInForce::Fak => ClobOrderType::Fak,
+            TimeInForce::Fok => ClobOrderType::Fok,
+        };
+        let expiration_s = match (order.tif, order.expires_at_ms) {
+            (TimeInForce::Gtd, Some(at_ms)) => gtd_expiration_s(at_ms),
+            (TimeInForce::Gtd, None) => return Err(anyhow!("GTD order without an expiry")),
+            _ => 0,
+        };
+        let placed = self
+            .client
+            .post_order(
+                token_id,
+                side,
+                order.price,
+                order.qty,
+                OrderOptions {
+                    order_type,
+                    expiration_s,
+                    post_only: order.post_only,
+                },
+            )
+            .await
+            .map_err(classify)?;
+        self.client_order_ids
+            .lock()
+            .unwrap_or_else(|p| p.into_inner())
+            .insert(placed.order_id.clone(), order.client_order_id.clone());
+        Ok(OrderAck {
+            order_id: placed.order_id,
+            ts_ms: now_ms(),
+        })
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> Result<()> {
+        self.client.cancel(order_id).await.map_err(classify)
+    }
+
+    async fn cancel_all(&self) -> Result<()> {
+        self.client.cancel_all().await.map_err(classify)
+    }
+
+    async fn open_orders(&self) -> Result<Vec<VenueOrder>> {
+        let orders = self.client.open_orders().await?;
+        Ok(orders
+            .into_iter()
+            .filter_map(|o| {
+                let (market_id, side) = self.side_of(&o.token_id, o.side)?;
+                Some(VenueOrder {
+                    client_order_id: self.client_order_id(&o.id),
+                    order_id: o.id,
+                    market_id,
+                    side,
+                    price: o.price,
+                    qty: o.original_size,
+                    filled_qty: o.size_matched,
+                })
+            })
+            .collect())
+    }
+
+    async fn recent_trades(&self, since_ms: i64) -> Result<Vec<VenueTrade>> {
+        let trades = self.client.trades(since_ms / 1000).await?;
+        Ok(trades
+            .into_iter()
+            .filter_map(|t| {
+                let (market_id, side) = self.side_of(&t.token_id, t.side)?;
+                Some(VenueTrade {
+                    fill_id: t.fill_id,
+                    client_order_id: self.client_order_id(&t.order_id),
+                    order_id: t.order_id,
+                    market_id,
+                    side,
+                    price: t.price,
+                    qty: t.size,
+                    fee_usd: t.fee_usd,
+                    ts_ms: t.ts_ms,
+                })
+            })
+            .collect())
+    }
+
+    async fn positions(&self) -> Result<Vec<VenuePosition>> {
+        let mut positions = Vec::new();
+        for (market_id, yes, token_id) in self.tokens.all() {
+            let qty = self.client.balance(token_id).await?;
+            if qty.abs() > 1e-9 {
+                positions.push(VenuePosition {
+                    market_id,
+                    token: if yes { Token::Yes } else { Token::No },
+                    qty,
+                });
+            }
+        }
+        Ok(positions)
+    }
+
+    fn supports_gtd(&self) -> bool {
+        true
+    }
+
+    async fn top_of_book(&self, market_id: i64, token: Token) -> Result<TopOfBook> {
+        let book = self.client.book(self.token_id(market_id, token)?).await?;
+        Ok(TopOfBook {
+            bid: level(book.bids.first()),
+            ask: level(book.asks.first()),
+        })
+    }
+}
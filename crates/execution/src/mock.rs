@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+use strategies::Token;
+
+use crate::order_manager::{UserUpdate, UserUpdateKind};
+use crate::venue::{
+    BookLevel, OrderAck, OrderRequest, TimeInForce, TopOfBook, Venue, VenueOrder, VenuePosition,
+    VenueRejection, VenueTrade,
+};
+
+/// In-process venue with a fixed top of book per token.
+///
+/// Used by traderd in shadow mode and by tests. Marketable orders fill
+/// immediately against the configured book; everything else rests until
+/// canceled. Individual operations can be told to fail a number of times.
+#[derive(Clone, Default)]
+pub struct MockVenue {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    next_id: u64,
+    orders: Vec<VenueOrder>,
+    positions: HashMap<(i64, Token), f64>,
+    books: HashMap<(i64, Token), TopOfBook>,
+    failures: HashMap<&'static str, u32>,
+    placed: Vec<OrderRequest>,
+    updates: Vec<UserUpdate>,
+    trades: Vec<VenueTrade>,
+}
+
+impl MockVenue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_book(
+        &self,
+        market_id: i64,
+        token: Token,
+        bid: Option<BookLevel>,
+        ask: Option<BookLevel>,
+    ) {
+        self.lock()
+            .books
+            .insert((market_id, token), TopOfBook { bid, ask });
+    }
+
+    pub fn set_position(&self, market_id: i64, token: Token, qty: f64) {
+        self.lock().positions.insert((market_id, token), qty);
+    }
+
+    /// Make the next `count` calls of `op` (e.g. "cancel_all") return an error.
+    pub fn fail_next(&self, op: &'static str, count: u32) {
+        self.lock().failures.insert(op, count);
+    }
+
+    /// Every order request the venue accepted, in submission order.
+    pub fn placed(&self) -> Vec<OrderRequest> {
+        self.lock().placed.clone()
+    }
+
+    /// Put an order on the book that this process did not submit.
+    pub fn add_resting(&self, order: VenueOrder) {
+        self.lock().orders.push(order);
+    }
+
+    /// Fill `qty` of a resting order, as if a counterparty had crossed it.
+    pub fn fill_resting(&self, order_id: &str, qty: f64) {
+        let mut state = self.lock();
+        let Some(idx) = state.orders.iter().position(|o| o.order_id == order_id) else {
+            return;
+        };
+        let mut order = state.orders[idx].clone();
+        let qty = qty.min(order.qty - order.filled_qty);
+        order.filled_qty += qty;
+        if order.qty - order.filled_qty <= 1e-9 {
+            state.orders.remove(idx);
+        } else {
+            state.orders[idx] = order.clone();
+        }
+        state.record_fill(&order, order.price, qty);
+    }
+
+    /// Drain the user-channel updates (fills and cancels) generated so far, as
+    /// the live user websocket would deliver them.
+    pub fn take_user_updates(&self) -> Vec<UserUpdate> {
+        std::mem::take(&mut self.lock().updates)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+impl MockState {
+    fn check(&mut self, op: &'static str) -> Result<()> {
+        if let Some(n) = self.failures.get_mut(op) {
+            if *n > 0 {
+                *n -= 1;
+                bail!("mock venue: injected {op} failure");
+            }
+        }
+        Ok(())
+    }
+
+    /// Size at the top of book that `order` would cross.
+    fn crossable(&self, order: &OrderRequest) -> f64 {
+        let Some(book) = self.books.get(&(order.market_id, order.side.token())) else {
+            return 0.0;
+        };
+        let level = if order.side.is_buy() {
+            book.ask.as_ref().filter(|l| order.price >= l.price)
+        } else {
+            book.bid.as_ref().filter(|l| order.price <= l.price)
+        };
+        level.map(|l| l.qty).unwrap_or(0.0)
+    }
+
+    /// Fill `order` against the book if it crosses; returns the filled qty.
+    fn cross(&mut self, order_id: &str, order: &OrderRequest) -> f64 {
+        let token = order.side.token();
+        let Some(book) = self.books.get_mut(&(order.market_id, token)) else {
+            return 0.0;
+        };
+        let level = if order.side.is_buy() {
+            book.ask.as_mut().filter(|l| order.price >= l.price)
+        } else {
+            book.bid.as_mut().filter(|l| order.price <= l.price)
+        };
+        let Some(level) = level else {
+            return 0.0;
+        };
+        let filled = order.qty.min(level.qty);
+        let price = level.price;
+        level.qty -= filled;
+        if filled <= 0.0 {
+            return 0.0;
+        }
+        let order = VenueOrder {
+            order_id: order_id.to_string(),
+            client_order_id: Some(order.client_order_id.clone()),
+            market_id: order.market_id,
+            side: order.side,
+            price: order.price,
+            qty: order.qty,
+            filled_qty: 0.0,
+        };
+        self.record_fill(&order, price, filled);
+        filled
+    }
+
+    /// Book a fill: move the position and emit a trade and a user update.
+    fn record_fill(&mut self, order: &VenueOrder, price: f64, qty: f64) {
+        let signed = if order.side.is_buy() { qty } else { -qty };
+        *self
+            .positions
+            .entry((order.market_id, order.side.token()))
+            .or_default() += signed;
+        let trade = VenueTrade {
+            fill_id: format!("{}-fill{}", order.order_id, self.trades.len() + 1),
+            order_id: order.order_id.clone(),
+            client_order_id: order.client_order_id.clone(),
+            market_id: order.market_id,
+            side: order.side,
+            price,
+            qty,
+            fee_usd: 0.0,
+            ts_ms: now_ms(),
+        };
+        self.updates.push(UserUpdate {
+            order_id: trade.order_id.clone(),
+            client_order_id: trade.client_order_id.clone(),
+            ts_ms: trade.ts_ms,
+            kind: UserUpdateKind::Fill {
+                fill_id: trade.fill_id.clone(),
+                price,
+                qty,
+                fee_usd: 0.0,
+            },
+        });
+        self.trades.push(trade);
+    }
+}
+
+impl Venue for MockVenue {
+    async fn place_order(&self, order: &OrderRequest) -> Result<OrderAck> {
+        let mut state = self.lock();
+        state.check("place_order")?;
+        state.next_id += 1;
+        let order_id = format!("mock-{}", state.next_id);
+        let crossable = state.crossable(order);
+        if order.post_only && crossable > 0.0 {
+            return Err(VenueRejection("mock venue: post-only order would cross".into()).into());
+        }
+        state.placed.push(order.clone());
+        let filled = if order.tif == TimeInForce::Fok && crossable < order.qty {
+            0.0
+        } else {
+            state.cross(&order_id, order)
+        };
+        if order.tif.rests() && filled < order.qty {
+            state.orders.push(VenueOrder {
+                order_id: order_id.clone(),
+                client_order_id: Some(order.client_order_id.clone()),
+                market_id: order.market_id,
+                side: order.side,
+                price: order.price,
+                qty: order.qty,
+                filled_qty: filled,
+            });
+        }
+        Ok(OrderAck {
+            order_id,
+            ts_ms: now_ms(),
+        })
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> Result<()> {
+        let mut state = self.lock();
+        state.check("cancel_order")?;
+        let Some(idx) = state.orders.iter().position(|o| o.order_id == order_id) else {
+            return Err(VenueRejection(format!("mock venue: unknown order {order_id}")).into());
+        };
+        let order = state.orders.remove(idx);
+        state.updates.push(UserUpdate {
+            order_id: order.order_id,
+            client_order_id: order.client_order_id,
+            ts_ms: now_ms(),
+            kind: UserUpdateKind::Canceled,
+        });
+        Ok(())
+    }
+
+    async fn cancel_all(&self) -> Result<()> {
+        let mut state = self.lock();
+        state.check("cancel_all")?;
+        for order in std::mem::take(&mut state.orders) {
+            state.updates.push(UserUpdate {
+                order_id: order.order_id,
+                client_order_id: order.client_order_id,
+                ts_ms: now_ms(),
+                kind: UserUpdateKind::Canceled,
+            });
+        }
+        Ok(())
+    }
+
+    async fn open_orders(&self) -> Result<Vec<VenueOrder>> {
+        let mut state = self.lock();
+        state.check("open_orders")?;
+        Ok(state.orders.clone())
+    }
+
+    async fn recent_trades(&self, since_ms: i64) -> Result<Vec<VenueTrade>> {
+        let mut state = self.lock();
+        state.check("recent_trades")?;
+        Ok(state
+            .trades
+            .iter()
+            .filter(|t| t.ts_ms >= since_ms)
+            .cloned()
+            .collect())
+    }
+
+    async fn positions(&self) -> Result<Vec<VenuePosition>> {
+        let mut state = self.lock();
+        state.check("positions")?;
+        let mut positions: Vec<VenuePosition> = state
+            .positions
+            .iter()
+            .map(|(&(market_id, token), &qty)| VenuePosition {
+                market_id,
+                token,
+                qty,
+            })
+            .collect();
+        positions.sort_by_key(|p| (p.market_id, p.token == Token::No));
+        Ok(positions)
+    }
+
+    async fn top_of_book(&self, market_id: i64, token: Token) -> Result<TopOfBook> {
+        let mut state = self.lock();
+        state.check("top_of_book")?;
+        Ok(state
+            .books
+            .get(&(market_id, token))
+            .copied()
+            .unwrap_or_default())
+    }
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
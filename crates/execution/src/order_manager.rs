@@ -0,0 +1,1919 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+use risk::{
+    PnlSample, PriceBands, PriceViolation, QuoteGovernor, RiskGate, StalenessGuard,
+    PRICE_BAND_INCIDENT,
+};
+use serde::{Deserialize, Serialize};
+use storage::{FillRow, OrderRow, Store};
+use strategies::{Intent, IntentKind, Side, Token, Urgency};
+use tokio::sync::Mutex;
+use tokio::time::{self, Instant};
+use tracing::warn;
+
+use crate::expiry::UrgencyPolicy;
+use crate::self_trade::{self, SelfTradePolicy, SELF_TRADE_INCIDENT};
+use crate::venue::{OrderRequest, TimeInForce, TopOfBook, Venue, VenueOrder};
+
+const QTY_EPSILON: f64 = 1e-9;
+
+/// Venue trade timestamps come from the venue's clock; look back this much
+/// before an order's submission when fetching its trades.
+const TRADE_LOOKBACK_SLACK_MS: i64 = 5_000;
+
+/// How often to check for a cancel confirmation while waiting on one.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Order lifecycle, matching the `orders.status` values in storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OrderStatus {
+    Submitted,
+    Acked,
+    Open,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Rejected,
+    Failed,
+}
+
+impl OrderStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OrderStatus::Submitted => "Submitted",
+            OrderStatus::Acked => "Acked",
+            OrderStatus::Open => "Open",
+            OrderStatus::PartiallyFilled => "PartiallyFilled",
+            OrderStatus::Filled => "Filled",
+            OrderStatus::Canceled => "Canceled",
+            OrderStatus::Rejected => "Rejected",
+            OrderStatus::Failed => "Failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "Submitted" => OrderStatus::Submitted,
+            "Acked" => OrderStatus::Acked,
+            "Open" => OrderStatus::Open,
+            "PartiallyFilled" => OrderStatus::PartiallyFilled,
+            "Filled" => OrderStatus::Filled,
+            "Canceled" => OrderStatus::Canceled,
+            "Rejected" => OrderStatus::Rejected,
+            "Failed" => OrderStatus::Failed,
+            _ => return None,
+        })
+    }
+
+    pub fn is_final(self) -> bool {
+        matches!(
+            self,
+            OrderStatus::Filled
+                | OrderStatus::Canceled
+                | OrderStatus::Rejected
+                | OrderStatus::Failed
+        )
+    }
+
+    /// Position in the lifecycle; a status never moves to a lower rank.
+    fn rank(self) -> u8 {
+        match self {
+            OrderStatus::Submitted => 0,
+            OrderStatus::Acked => 1,
+            OrderStatus::Open => 2,
+            OrderStatus::PartiallyFilled => 3,
+            _ => 4,
+        }
+    }
+}
+
+/// What a strategy (via the arbiter) asks execution to place.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewOrder {
+    pub client_order_id: String,
+    pub approved_id: Option<i64>,
+    pub intent_id: Option<i64>,
+    pub strategy: String,
+    pub market_id: i64,
+    pub side: Side,
+    pub price: f64,
+    pub qty: f64,
+    pub tif: TimeInForce,
+    pub post_only: bool,
+    /// When the order lapses; see [`crate::expiry`].
+    pub expires_at_ms: Option<i64>,
+    /// Only closes existing exposure (flatten, unwind), so the risk gate
+    /// lets it through in ReduceOnly, Halted and KillSwitched. Market-data
+    /// checks still apply except on [`OrderManager::submit_via`].
+    pub reduce_only: bool,
+}
+
+/// An order as tracked by the manager.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManagedOrder {
+    pub client_order_id: String,
+    pub order_id: Option<String>,
+    pub approved_id: Option<i64>,
+    pub intent_id: Option<i64>,
+    pub strategy: String,
+    pub market_id: i64,
+    pub side: Side,
+    pub limit_price: f64,
+    pub qty: f64,
+    pub filled_qty: f64,
+    /// Sum of price * qty over fills, for the average fill price.
+    pub filled_notional: f64,
+    /// Fees charged on those fills.
+    pub fees_usd: f64,
+    pub status: OrderStatus,
+    pub tif: TimeInForce,
+    pub expires_at_ms: Option<i64>,
+    pub ts_submitted_ms: i64,
+    pub ts_acked_ms: Option<i64>,
+    pub ts_final_ms: Option<i64>,
+    /// Set while a cancel is in flight and kept once it is confirmed.
+    pub ts_cancel_requested_ms: Option<i64>,
+    pub notes: Option<String>,
+}
+
+impl ManagedOrder {
+    pub fn submit_latency_ms(&self) -> Option<i64> {
+        self.ts_acked_ms.map(|acked| acked - self.ts_submitted_ms)
+    }
+
+    /// Time from our cancel request to the venue confirming it.
+    pub fn cancel_latency_ms(&self) -> Option<i64> {
+        if self.status != OrderStatus::Canceled {
+            return None;
+        }
+        Some((self.ts_final_ms? - self.ts_cancel_requested_ms?).max(0))
+    }
+
+    /// Why a cancel confirmed at `ts_ms` counts as an expiry, if it does.
+    fn expiry_note(&self, ts_ms: i64) -> Option<String> {
+        let expires_at = self.expires_at_ms.filter(|at| ts_ms >= *at)?;
+        let ttl_ms = expires_at - self.ts_submitted_ms;
+        let by = match self.ts_cancel_requested_ms {
+            Some(requested) if requested >= expires_at => "local timer",
+            None if self.tif == TimeInForce::Gtd => "venue GTD",
+            _ => return None,
+        };
+        Some(format!("expired: ttl {ttl_ms}ms lapsed ({by})"))
+    }
+
+    pub fn cancel_in_flight(&self) -> bool {
+        self.ts_cancel_requested_ms.is_some() && !self.status.is_final()
+    }
+
+    pub fn avg_fill_price(&self) -> Option<f64> {
+        (self.filled_qty > QTY_EPSILON).then(|| self.filled_notional / self.filled_qty)
+    }
+
+    pub fn remaining_qty(&self) -> f64 {
+        (self.qty - self.filled_qty).max(0.0)
+    }
+
+    fn to_row(&self, run_id: &str, venue: &str) -> OrderRow {
+        OrderRow {
+            run_id: run_id.to_string(),
+            client_order_id: self.client_order_id.clone(),
+            order_id: self.order_id.clone(),
+            approved_id: self.approved_id,
+            intent_id: self.intent_id,
+            strategy: self.strategy.clone(),
+            market_id: self.market_id,
+            venue: venue.to_string(),
+            status: self.status.as_str().to_string(),
+            side: self.side.as_str().to_string(),
+            limit_price: self.limit_price,
+            qty: self.qty,
+            ts_submitted_ms: self.ts_submitted_ms,
+            ts_acked_ms: self.ts_acked_ms,
+            ts_final_ms: self.ts_final_ms,
+            submit_latency_ms: self.submit_latency_ms(),
+            notes: self.notes.clone(),
+        }
+    }
+
+    fn move_to(&mut self, status: OrderStatus, ts_ms: i64) -> bool {
+        if self.status.is_final() || status.rank() < self.status.rank() {
+            return false;
+        }
+        if status == self.status {
+            return false;
+        }
+        self.status = status;
+        if status.is_final() {
+            self.ts_final_ms.get_or_insert(ts_ms);
+        }
+        true
+    }
+}
+
+/// Order state reported by the venue's user channel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum UserUpdateKind {
+    Acked,
+    Open,
+    Fill {
+        fill_id: String,
+        price: f64,
+        qty: f64,
+        #[serde(default)]
+        fee_usd: f64,
+    },
+    Canceled,
+    Rejected {
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserUpdate {
+    pub order_id: String,
+    pub client_order_id: Option<String>,
+    pub ts_ms: i64,
+    pub kind: UserUpdateKind,
+}
+
+/// When a replacement may go out relative to the cancel of the order it
+/// replaces.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplacePolicy {
+    /// Place the replacement only once the cancel is confirmed.
+    WaitForCancel,
+    /// Send cancel and replacement together when the worst case, both
+    /// orders filling in full, stays within `max_worst_case_usd` notional.
+    Overlap { max_worst_case_usd: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplaceConfig {
+    pub policy: ReplacePolicy,
+    /// How long to wait for a cancel to be confirmed (or for the order to
+    /// fill) before giving up on the replacement.
+    pub cancel_timeout: Duration,
+    pub poll_interval: Duration,
+}
+
+impl Default for ReplaceConfig {
+    fn default() -> Self {
+        Self {
+            policy: ReplacePolicy::WaitForCancel,
+            cancel_timeout: Duration::from_secs(2),
+            poll_interval: Duration::from_millis(25),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplaceStatus {
+    /// Old order canceled (or already done), replacement placed.
+    Replaced,
+    /// Replacement placed while the cancel was in flight.
+    Overlapped,
+    /// The old order filled during the cancel; nothing left to replace.
+    OldFilled,
+    /// The cancel was neither confirmed nor superseded by a fill in time.
+    CancelUnconfirmed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplaceOutcome {
+    pub status: ReplaceStatus,
+    pub old: ManagedOrder,
+    pub new: Option<ManagedOrder>,
+    /// Quantity the old order filled while the cancel was in flight.
+    pub race_filled_qty: f64,
+    pub cancel_latency_ms: Option<i64>,
+}
+
+impl ReplaceOutcome {
+    fn new(
+        status: ReplaceStatus,
+        old: ManagedOrder,
+        new: Option<ManagedOrder>,
+        race_filled_qty: f64,
+    ) -> Self {
+        Self {
+            status,
+            cancel_latency_ms: old.cancel_latency_ms(),
+            old,
+            new,
+            race_filled_qty: race_filled_qty.max(0.0),
+        }
+    }
+}
+
+/// Deterministic client order ids: the same key always yields the same id,
+/// so a retried submission can't create a second order.
+#[derive(Debug)]
+pub struct ClientOrderIds {
+    prefix: String,
+    seq: AtomicU64,
+}
+
+impl ClientOrderIds {
+    pub fn new(run_id: &str) -> Self {
+        let prefix: String = run_id.chars().filter(|c| *c != '-').take(12).collect();
+        Self {
+            prefix,
+            seq: AtomicU64::new(0),
+        }
+    }
+
+    /// Id for an approved intent leg, stable across retries.
+    pub fn for_approval(&self, approved_id: i64, leg: u32) -> String {
+        format!("{}-a{approved_id}-{leg}", self.prefix)
+    }
+
+    /// Fresh id for orders without an approval (manual, unwind, flatten).
+    pub fn next(&self) -> String {
+        let n = self.seq.fetch_add(1, Ordering::Relaxed) + 1;
+        format!("{}-m{n}", self.prefix)
+    }
+}
+
+#[derive(Default)]
+struct Book {
+    orders: HashMap<String, ManagedOrder>,
+    by_order_id: HashMap<String, String>,
+    /// Updates that arrived before we learned which order they belong to.
+    pending: HashMap<String, Vec<UserUpdate>>,
+    seen_fills: HashSet<String>,
+}
+
+/// Tracks every order from submission to a final state and persists each
+/// transition to the `orders` table.
+pub struct OrderManager<V> {
+    venue: V,
+    venue_name: String,
+    run_id: String,
+    store: Store,
+    ids: ClientOrderIds,
+    self_trade: SelfTradePolicy,
+    self_trade_cancel_timeout: Duration,
+    quote_governor: Option<QuoteGovernor>,
+    risk_gate: Option<RiskGate>,
+    staleness: Option<StalenessGuard>,
+    price_bands: Option<PriceBands>,
+    urgency: UrgencyPolicy,
+    book: Mutex<Book>,
+    /// Serializes order writes; see [`OrderManager::persist`].
+    persisting: Mutex<()>,
+}
+
+impl<V: Venue> OrderManager<V> {
+    pub fn new(venue: V, venue_name: &str, run_id: &str, store: Store) -> Self {
+        let urgency = UrgencyPolicy {
+            venue_gtd: venue.supports_gtd(),
+            ..UrgencyPolicy::default()
+        };
+        Self {
+            venue,
+            venue_name: venue_name.to_string(),
+            run_id: run_id.to_string(),
+            store,
+            ids: ClientOrderIds::new(run_id),
+            self_trade: SelfTradePolicy::default(),
+            self_trade_cancel_timeout: Duration::from_secs(2),
+            quote_governor: None,
+            risk_gate: None,
+            staleness: None,
+            price_bands: None,
+            urgency,
+            book: Mutex::new(Book::default()),
+            persisting: Mutex::new(()),
+        }
+    }
+
+    pub fn with_self_trade_policy(mut self, policy: SelfTradePolicy) -> Self {
+        self.self_trade = policy;
+        self
+    }
+
+    /// Under [`SelfTradePolicy::CancelResting`], how long to wait for the
+    /// venue to confirm the resting orders are gone before rejecting the
+    /// outgoing order instead. Default 2s.
+    pub fn with_self_trade_cancel_timeout(mut self, timeout: Duration) -> Self {
+        self.self_trade_cancel_timeout = timeout;
+        self
+    }
+
+    /// Record every place, cancel and fill with `governor` and reject
+    /// placements it throttles or vetoes.
+    pub fn with_quote_governor(mut self, governor: QuoteGovernor) -> Self {
+        self.quote_governor = Some(governor);
+        self
+    }
+
+    /// Reject placements `gate` does not allow: new exposure while the gate
+    /// is not Active or placements are blocked, anything while Paused.
+    pub fn with_risk_gate(mut self, gate: RiskGate) -> Self {
+        self.risk_gate = Some(gate);
+        self
+    }
+
+    /// Reject placements in markets whose book or feeds `guard` considers
+    /// stale. Only the kill switch's flatten orders skip it.
+    pub fn with_staleness_guard(mut self, guard: StalenessGuard) -> Self {
+        self.staleness = Some(guard);
+        self
+    }
+
+    /// Check placements against the venue's top of book and reject those
+    /// outside `bands`, with a `PRICE_BAND` incident. Only the kill switch's
+    /// flatten orders skip it.
+    pub fn with_price_bands(mut self, bands: PriceBands) -> Self {
+        self.price_bands = Some(bands);
+        self
+    }
+
+    /// Map intents onto orders with `policy` in [`OrderManager::submit_intent`].
+    /// The default follows [`Venue::supports_gtd`].
+    pub fn with_urgency_policy(mut self, policy: UrgencyPolicy) -> Self {
+        self.urgency = policy;
+        self
+    }
+
+    pub fn ids(&self) -> &ClientOrderIds {
+        &self.ids
+    }
+
+    pub fn venue(&self) -> &V {
+        &self.venue
+    }
+
+    pub async fn get(&self, client_order_id: &str) -> Option<ManagedOrder> {
+        self.book.lock().await.orders.get(client_order_id).cloned()
+    }
+
+    /// Orders not yet in a final state.
+    pub async fn live_orders(&self) -> Vec<ManagedOrder> {
+        let book = self.book.lock().await;
+        let mut live: Vec<_> = book
+            .orders
+            .values()
+            .filter(|o| !o.status.is_final())
+            .cloned()
+            .collect();
+        live.sort_by_key(|o| o.ts_submitted_ms);
+        live
+    }
+
+    /// Submit an approved intent. Price, order type and expiry follow its
+    /// urgency and TTL per the [`UrgencyPolicy`]; Neutral intents are
+    /// priced off the venue's top of book.
+    pub async fn submit_intent(
+        &self,
+        intent: &Intent,
+        client_order_id: &str,
+        approved_id: Option<i64>,
+        intent_id: Option<i64>,
+    ) -> Result<ManagedOrder> {
+        if !matches!(intent.kind, IntentKind::PlaceOrder) {
+            bail!("intent {} is not a placement", intent.intent_id);
+        }
+        let (Some(side), Some(price), Some(qty)) = (intent.side, intent.price, intent.size) else {
+            bail!("intent {} has no side, price or size", intent.intent_id);
+        };
+        let top = if intent.urgency == Urgency::Neutral {
+            self.venue
+                .top_of_book(intent.market_id, side.token())
+                .await
+                .unwrap_or_else(|err| {
+                    warn!(error = ?err, intent_id = %intent.intent_id, "no book for intent; using its limit");
+                    TopOfBook::default()
+                })
+        } else {
+            TopOfBook::default()
+        };
+        let plan = self
+            .urgency
+            .plan(intent.urgency, side, price, intent.ttl_ms, &top, now_ms());
+        self.submit(NewOrder {
+            client_order_id: client_order_id.to_string(),
+            approved_id,
+            intent_id,
+            strategy: intent.strategy.clone(),
+            market_id: intent.market_id,
+            side,
+            price: plan.price,
+            qty,
+            tif: plan.tif,
+            post_only: plan.post_only,
+            expires_at_ms: plan.expires_at_ms,
+            reduce_only: false,
+        })
+        .await
+    }
+
+    /// Submit an order. Submitting a `client_order_id` that is already known
+    /// returns the tracked order without touching the venue.
+    ///
+    /// An order that would trade against one of our own live orders in the
+    /// same market (either token) is handled per the [`SelfTradePolicy`]:
+    /// it comes back Rejected without reaching the venue, or the resting
+    /// orders are canceled first and it goes out once the venue confirms
+    /// them done. With a [`RiskGate`], an order the gate
+    /// does not allow is Rejected; so is one on stale market data with a
+    /// [`StalenessGuard`], one outside its [`PriceBands`], and with a
+    /// [`QuoteGovernor`] one from a market or strategy over its quote-rate
+    /// limits.
+    pub async fn submit(&self, new: NewOrder) -> Result<ManagedOrder> {
+        self.place(new, &self.venue, true).await
+    }
+
+    /// [`OrderManager::submit`], but placed through `venue` rather than the
+    /// manager's own: the kill switch flattens through the bare venue so an
+    /// open circuit breaker can't stop it. Its reduce-only orders also skip
+    /// the staleness guard and price bands, since it has to flatten on
+    /// whatever book there is within its own slippage limit.
+    pub async fn submit_via<W: Venue>(&self, new: NewOrder, venue: &W) -> Result<ManagedOrder> {
+        let market_checks = !new.reduce_only;
+        self.place(new, venue, market_checks).await
+    }
+
+    async fn place<W: Venue>(
+        &self,
+        new: NewOrder,
+        venue: &W,
+        market_checks: bool,
+    ) -> Result<ManagedOrder> {
+        let band_violation = match self.price_bands.as_ref().filter(|_| market_checks) {
+            Some(bands) => self.price_band_check(bands, &new).await.err(),
+            None => None,
+        };
+        let mut canceled = Vec::new();
+        if self.self_trade == SelfTradePolicy::CancelResting {
+            let resting = {
+                let book = self.book.lock().await;
+                if let Some(existing) = book.orders.get(&new.client_order_id) {
+                    return Ok(existing.clone());
+                }
+                self_trade::conflicts(&new, book.orders.values())
+            };
+            for id in resting {
+                match self.cancel(&id).await {
+                    Ok(()) => canceled.push(id),
+                    Err(err) => {
+                        warn!(error = ?err, client_order_id = %id, "self-trade cancel failed")
+                    }
+                }
+            }
+            // the venue can still match a resting order until it reports the
+            // cancel done; whatever is not done by then blocks the order below
+            let deadline = Instant::now() + self.self_trade_cancel_timeout;
+            for id in &canceled {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                self.wait_final(id, timeout, CANCEL_POLL_INTERVAL).await;
+            }
+        }
+
+        let (submitted, incident) = {
+            let mut book = self.book.lock().await;
+            if let Some(existing) = book.orders.get(&new.client_order_id) {
+                return Ok(existing.clone());
+            }
+            let crossed = self_trade::conflicts(&new, book.orders.values());
+            let mut order = ManagedOrder {
+                client_order_id: new.client_order_id.clone(),
+                order_id: None,
+                approved_id: new.approved_id,
+                intent_id: new.intent_id,
+                strategy: new.strategy.clone(),
+                market_id: new.market_id,
+                side: new.side,
+                limit_price: new.price,
+                qty: new.qty,
+                filled_qty: 0.0,
+                filled_notional: 0.0,
+                fees_usd: 0.0,
+                status: OrderStatus::Submitted,
+                tif: new.tif,
+                expires_at_ms: new.expires_at_ms,
+                ts_submitted_ms: now_ms(),
+                ts_acked_ms: None,
+                ts_final_ms: None,
+                ts_cancel_requested_ms: None,
+                notes: None,
+            };
+            let ts_ms = order.ts_submitted_ms;
+            let stale = self
+                .staleness
+                .as_ref()
+                .filter(|_| market_checks)
+                .and_then(|guard| guard.check(order.market_id, ts_ms, ts_ms).err());
+            let throttled = || {
+                self.quote_governor
+                    .as_ref()
+                    .and_then(|g| g.check(&order.strategy, order.market_id, ts_ms).err())
+            };
+            let rejection = match &self.risk_gate {
+                Some(gate) if !gate.allows_place(&order.strategy, new.reduce_only) => {
+                    Some((gate_note(gate, &order.strategy), None))
+                }
+                _ => match (stale, band_violation) {
+                    (Some(reason), _) => Some((format!("stale: {reason}"), None)),
+                    (None, _) if !crossed.is_empty() => Some((
+                        format!("self-trade: would cross {}", crossed.join(",")),
+                        Some(Incident::SelfTrade(crossed)),
+                    )),
+                    (None, Some(violation)) => Some((
+                        format!("price band: {violation}"),
+                        Some(Incident::PriceBand(violation)),
+                    )),
+                    (None, None) => {
+                        throttled().map(|violation| (format!("quote rate: {violation}"), None))
+                    }
+                },
+            };
+            let incident = match rejection {
+                Some((note, incident)) => {
+                    order.move_to(OrderStatus::Rejected, ts_ms);
+                    order.notes = Some(note);
+                    incident
+                }
+                None => {
+                    if let Some(governor) = &self.quote_governor {
+                        governor.record_message(&order.strategy, order.market_id, ts_ms);
+                    }
+                    None
+                }
+            };
+            book.orders
+                .insert(order.client_order_id.clone(), order.clone());
+            (order, incident)
+        };
+        self.persist(&submitted.client_order_id).await;
+        if submitted.status == OrderStatus::Rejected {
+            match incident {
+                Some(Incident::SelfTrade(crossed)) => {
+                    self.self_trade_incident(&submitted, "blocked", &crossed)
+                        .await
+                }
+                Some(Incident::PriceBand(violation)) => {
+                    self.price_band_incident(&submitted, &violation).await
+                }
+                None => {}
+            }
+            return Ok(submitted);
+        }
+        if !canceled.is_empty() {
+            self.self_trade_incident(&submitted, "canceled resting", &canceled)
+                .await;
+        }
+
+        let request = OrderRequest {
+            client_order_id: submitted.client_order_id.clone(),
+            market_id: submitted.market_id,
+            side: submitted.side,
+            price: submitted.limit_price,
+            qty: submitted.qty,
+            tif: new.tif,
+            post_only: new.post_only,
+            expires_at_ms: new.expires_at_ms,
+        };
+        match venue.place_order(&request).await {
+            Ok(ack) => {
+                self.apply_update(UserUpdate {
+                    order_id: ack.order_id,
+                    client_order_id: Some(submitted.client_order_id.clone()),
+                    ts_ms: now_ms(),
+                    kind: UserUpdateKind::Acked,
+                })
+                .await;
+            }
+            Err(err) => {
+                let failed = {
+                    let mut book = self.book.lock().await;
+                    let order = book.orders.get_mut(&submitted.client_order_id);
+                    match order {
+                        Some(order) if !order.status.is_final() => {
+                            order.move_to(OrderStatus::Failed, now_ms());
+                            order.notes = Some(format!("submit failed: {err}"));
+                            true
+                        }
+                        _ => false,
+                    }
+                };
+                if failed {
+                    self.persist(&submitted.client_order_id).await;
+                }
+            }
+        }
+
+        self.get(&submitted.client_order_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("order {} vanished", submitted.client_order_id))
+    }
+
+    /// Ask the venue to cancel. The order keeps its status, with the cancel
+    /// in flight ([`ManagedOrder::cancel_in_flight`]), until the user channel
+    /// (or the reconciler) reports it Canceled or Filled; the
+    /// request-to-confirmation latency is recorded in its notes then. If the
+    /// venue refuses the request the order is no longer in flight.
+    pub async fn cancel(&self, client_order_id: &str) -> Result<()> {
+        let (order_id, first_request) = {
+            let mut book = self.book.lock().await;
+            let Some(order) = book.orders.get_mut(client_order_id) else {
+                bail!("unknown order {client_order_id}");
+            };
+            if order.status.is_final() {
+                return Ok(());
+            }
+            let Some(order_id) = order.order_id.clone() else {
+                bail!("order {client_order_id} has no venue id yet");
+            };
+            let ts_ms = now_ms();
+            let first_request = order.ts_cancel_requested_ms.is_none();
+            order.ts_cancel_requested_ms.get_or_insert(ts_ms);
+            if let Some(governor) = &self.quote_governor {
+                governor.record_message(&order.strategy, order.market_id, ts_ms);
+            }
+            (order_id, first_request)
+        };
+        if let Err(err) = self.venue.cancel_order(&order_id).await {
+            if first_request {
+                let mut book = self.book.lock().await;
+                if let Some(order) = book.orders.get_mut(client_order_id) {
+                    if !order.status.is_final() {
+                        order.ts_cancel_requested_ms = None;
+                    }
+                }
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Live orders with a cancel request the venue has not confirmed yet.
+    pub async fn pending_cancels(&self) -> Vec<ManagedOrder> {
+        let book = self.book.lock().await;
+        book.orders
+            .values()
+            .filter(|o| o.cancel_in_flight())
+            .cloned()
+            .collect()
+    }
+
+    /// Cancel `client_order_id` and place `new` in its place.
+    ///
+    /// By default the replacement goes out only after the venue reports the
+    /// old order done, so the two orders are never live together. Under
+    /// [`ReplacePolicy::Overlap`] both are sent at once when the old order's
+    /// remaining size plus the new size fits the cap. If the old order
+    /// fills while the cancel is in flight, the replacement is shrunk by
+    /// the filled amount, or skipped when nothing is left. The venue's
+    /// trades for the old order are checked once it is done, since a fill
+    /// can reach the user channel after the cancel confirmation.
+    pub async fn replace(
+        &self,
+        client_order_id: &str,
+        new: NewOrder,
+        config: &ReplaceConfig,
+    ) -> Result<ReplaceOutcome> {
+        let Some(old) = self.get(client_order_id).await else {
+            bail!("unknown order {client_order_id}");
+        };
+        if old.status.is_final() {
+            let placed = self.submit(new).await?;
+            return Ok(ReplaceOutcome::new(
+                ReplaceStatus::Replaced,
+                old,
+                Some(placed),
+                0.0,
+            ));
+        }
+        let filled_at_start = old.filled_qty;
+
+        if let ReplacePolicy::Overlap { max_worst_case_usd } = config.policy {
+            let worst_case = old.remaining_qty() * old.limit_price + new.qty * new.price;
+            if worst_case <= max_worst_case_usd + QTY_EPSILON {
+                let (canceled, placed) = tokio::join!(
+                    time::timeout(config.cancel_timeout, self.cancel(client_order_id)),
+                    self.submit(new)
+                );
+                let placed = placed?;
+                if let Ok(Err(err)) = canceled {
+                    warn!(error = ?err, %client_order_id, "cancel rejected during replace");
+                }
+                let confirmed = self
+                    .wait_final(client_order_id, config.cancel_timeout, config.poll_interval)
+                    .await;
+                if confirmed {
+                    self.backfill_fills(client_order_id).await;
+                }
+                let old = self.get(client_order_id).await.unwrap_or(old);
+                let status = if confirmed {
+                    ReplaceStatus::Overlapped
+                } else {
+                    ReplaceStatus::CancelUnconfirmed
+                };
+                let raced = old.filled_qty - filled_at_start;
+                return Ok(ReplaceOutcome::new(status, old, Some(placed), raced));
+            }
+        }
+
+        let canceled = time::timeout(config.cancel_timeout, self.cancel(client_order_id)).await;
+        if let Ok(Err(err)) = canceled {
+            // usually "order not found" because it filled first; the user
+            // channel will tell us
+            warn!(error = ?err, %client_order_id, "cancel rejected during replace");
+        }
+        let confirmed = self
+            .wait_final(client_order_id, config.cancel_timeout, config.poll_interval)
+            .await;
+        if confirmed {
+            self.backfill_fills(client_order_id).await;
+        }
+        let old = self.get(client_order_id).await.unwrap_or(old);
+        if !confirmed {
+            warn!(%client_order_id, "cancel unconfirmed; replacement not placed");
+            return Ok(ReplaceOutcome::new(
+                ReplaceStatus::CancelUnconfirmed,
+                old,
+                None,
+                0.0,
+            ));
+        }
+
+        let raced = old.filled_qty - filled_at_start;
+        let qty = new.qty - raced;
+        if qty <= QTY_EPSILON {
+            return Ok(ReplaceOutcome::new(
+                ReplaceStatus::OldFilled,
+                old,
+                None,
+                raced,
+            ));
+        }
+        let placed = self.submit(NewOrder { qty, ..new }).await?;
+        Ok(ReplaceOutcome::new(
+            ReplaceStatus::Replaced,
+            old,
+            Some(placed),
+            raced,
+        ))
+    }
+
+    /// Apply the venue's trades for `client_order_id` that the user channel
+    /// has not delivered yet; returns how many were new.
+    pub async fn backfill_fills(&self, client_order_id: &str) -> usize {
+        let Some(order) = self.get(client_order_id).await else {
+            return 0;
+        };
+        let Some(order_id) = order.order_id else {
+            return 0;
+        };
+        let since_ms = order.ts_submitted_ms - TRADE_LOOKBACK_SLACK_MS;
+        let trades = match self.venue.recent_trades(since_ms).await {
+            Ok(trades) => trades,
+            Err(err) => {
+                warn!(error = ?err, %client_order_id, "trade backfill failed");
+                return 0;
+            }
+        };
+        let mut applied = 0;
+        for trade in trades.into_iter().filter(|t| t.order_id == order_id) {
+            if self.has_fill(&trade.fill_id).await {
+                continue;
+            }
+            self.apply_update(UserUpdate {
+                order_id: trade.order_id,
+                client_order_id: Some(client_order_id.to_string()),
+                ts_ms: trade.ts_ms,
+                kind: UserUpdateKind::Fill {
+                    fill_id: trade.fill_id,
+                    price: trade.price,
+                    qty: trade.qty,
+                    fee_usd: trade.fee_usd,
+                },
+            })
+            .await;
+            applied += 1;
+        }
+        applied
+    }
+
+    /// Poll until the order reaches a final state; false if `timeout`
+    /// passes first.
+    async fn wait_final(
+        &self,
+        client_order_id: &str,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.get(client_order_id).await {
+                Some(order) if order.status.is_final() => return true,
+                None => return false,
+                Some(_) => {}
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            time::sleep(poll_interval).await;
+        }
+    }
+
+    /// Apply a user-channel update. Updates may arrive in any order:
+    /// statuses never move backwards, duplicate fills are ignored, and
+    /// updates for a not-yet-known venue order id are held until it is.
+    pub async fn apply_update(&self, update: UserUpdate) -> Option<ManagedOrder> {
+        let mut book = self.book.lock().await;
+
+        let client_order_id = match book.by_order_id.get(&update.order_id) {
+            Some(id) => Some(id.clone()),
+            None => update
+                .client_order_id
+                .clone()
+                .filter(|id| book.orders.contains_key(id)),
+        };
+        let Some(client_order_id) = client_order_id else {
+            book.pending
+                .entry(update.order_id.clone())
+                .or_default()
+                .push(update);
+            return None;
+        };
+
+        if !book.by_order_id.contains_key(&update.order_id) {
+            book.by_order_id
+                .insert(update.order_id.clone(), client_order_id.clone());
+        }
+        let mut updates = vec![update.clone()];
+        if let Some(held) = book.pending.remove(&update.order_id) {
+            updates.extend(held);
+        }
+
+        let mut changed = false;
+        let mut new_fills = Vec::new();
+        for update in updates {
+            let applied = apply_one(&mut book, &client_order_id, &update);
+            if applied && matches!(update.kind, UserUpdateKind::Fill { .. }) {
+                new_fills.push(update);
+            }
+            changed |= applied;
+        }
+
+        let order = book.orders.get(&client_order_id).cloned()?;
+        drop(book);
+        if changed {
+            self.persist(&client_order_id).await;
+        }
+        for fill in &new_fills {
+            if let Some(governor) = &self.quote_governor {
+                governor.record_fill(&order.strategy, order.market_id, fill.ts_ms);
+            }
+            self.persist_fill(&order, fill).await;
+        }
+        Some(order)
+    }
+
+    /// Whether a venue fill id has already been applied.
+    pub async fn has_fill(&self, fill_id: &str) -> bool {
+        self.book.lock().await.seen_fills.contains(fill_id)
+    }
+
+    /// The client order id tracked for a venue order id, if any.
+    pub async fn client_order_id_for(&self, order_id: &str) -> Option<String> {
+        self.book.lock().await.by_order_id.get(order_id).cloned()
+    }
+
+    /// Start tracking an order found on the venue that this process did not
+    /// submit (e.g. left over from a previous run).
+    pub async fn adopt(&self, venue_order: &VenueOrder, strategy: &str) -> ManagedOrder {
+        let mut book = self.book.lock().await;
+        let client_order_id = venue_order
+            .client_order_id
+            .clone()
+            .unwrap_or_else(|| format!("adopted-{}", venue_order.order_id));
+        let ts_ms = now_ms();
+        let status = if venue_order.filled_qty > QTY_EPSILON {
+            OrderStatus::PartiallyFilled
+        } else {
+            OrderStatus::Open
+        };
+        let order = ManagedOrder {
+            client_order_id: client_order_id.clone(),
+            order_id: Some(venue_order.order_id.clone()),
+            approved_id: None,
+            intent_id: None,
+            strategy: strategy.to_string(),
+            market_id: venue_order.market_id,
+            side: venue_order.side,
+            limit_price: venue_order.price,
+            qty: venue_order.qty,
+            filled_qty: venue_order.filled_qty,
+            filled_notional: venue_order.filled_qty * venue_order.price,
+            fees_usd: 0.0,
+            status,
+            tif: TimeInForce::Gtc,
+            expires_at_ms: None,
+            ts_submitted_ms: ts_ms,
+            ts_acked_ms: Some(ts_ms),
+            ts_final_ms: None,
+            ts_cancel_requested_ms: None,
+            notes: Some("adopted at reconcile".to_string()),
+        };
+        book.by_order_id
+            .insert(venue_order.order_id.clone(), client_order_id.clone());
+        book.orders.insert(client_order_id.clone(), order.clone());
+        drop(book);
+        self.persist(&client_order_id).await;
+        order
+    }
+
+    /// Close a live order the venue no longer knows about. Backfill its
+    /// trades first ([`OrderManager::backfill_fills`]): one whose fills cover
+    /// its size closes as Filled, anything else as Canceled. A lapsed GTD
+    /// order most likely expired on the venue, which is noted as well.
+    pub async fn close_missing(&self, client_order_id: &str, note: &str) -> Option<ManagedOrder> {
+        let order = {
+            let mut book = self.book.lock().await;
+            let order = book.orders.get_mut(client_order_id)?;
+            let ts_ms = now_ms();
+            if order.remaining_qty() <= QTY_EPSILON {
+                if !order.move_to(OrderStatus::Filled, ts_ms) {
+                    return Some(order.clone());
+                }
+            } else {
+                if !order.move_to(OrderStatus::Canceled, ts_ms) {
+                    return Some(order.clone());
+                }
+                let notes: Vec<String> = order
+                    .expiry_note(ts_ms)
+                    .into_iter()
+                    .chain([note.to_string()])
+                    .collect();
+                order.notes = Some(notes.join("; "));
+            }
+            order.clone()
+        };
+        self.persist(client_order_id).await;
+        Some(order)
+    }
+
+    /// Net filled quantity per (market, token) across every tracked order.
+    pub async fn net_positions(&self) -> HashMap<(i64, Token), f64> {
+        let book = self.book.lock().await;
+        let mut positions = HashMap::new();
+        for order in book.orders.values() {
+            let signed = if order.side.is_buy() {
+                order.filled_qty
+            } else {
+                -order.filled_qty
+            };
+            *positions
+                .entry((order.market_id, order.side.token()))
+                .or_default() += signed;
+        }
+        positions
+    }
+
+    /// PnL of the fills tracked here at average cost, for the risk gate:
+    /// the quantity sold back against buys is realized, open inventory is
+    /// marked at the venue mid (at cost when the book is empty). Fees paid
+    /// count as realized losses. Equity is `starting_equity_usd` plus both.
+    pub async fn pnl_sample(&self, starting_equity_usd: f64) -> PnlSample {
+        #[derive(Default)]
+        struct Leg {
+            bought: f64,
+            bought_usd: f64,
+            sold: f64,
+            sold_usd: f64,
+            fees_usd: f64,
+        }
+        let mut legs: HashMap<(String, i64, Token), Leg> = HashMap::new();
+        {
+            let book = self.book.lock().await;
+            for order in book.orders.values().filter(|o| o.filled_qty > QTY_EPSILON) {
+                let leg = legs
+                    .entry((order.strategy.clone(), order.market_id, order.side.token()))
+                    .or_default();
+                leg.fees_usd += order.fees_usd;
+                if order.side.is_buy() {
+                    leg.bought += order.filled_qty;
+                    leg.bought_usd += order.filled_notional;
+                } else {
+                    leg.sold += order.filled_qty;
+                    leg.sold_usd += order.filled_notional;
+                }
+            }
+        }
+
+        let mut sample = PnlSample {
+            ts_ms: now_ms(),
+            equity_usd: starting_equity_usd,
+            ..PnlSample::default()
+        };
+        let mut marks: HashMap<(i64, Token), Option<f64>> = HashMap::new();
+        for ((strategy, market_id, token), leg) in legs {
+            let avg = |qty: f64, usd: f64| if qty > QTY_EPSILON { usd / qty } else { 0.0 };
+            let (avg_buy, avg_sell) =
+                (avg(leg.bought, leg.bought_usd), avg(leg.sold, leg.sold_usd));
+            let realized = leg.bought.min(leg.sold) * (avg_sell - avg_buy) - leg.fees_usd;
+            let open = leg.bought - leg.sold;
+            let mut unrealized = 0.0;
+            if open.abs() > QTY_EPSILON {
+                let mark = match marks.get(&(market_id, token)) {
+                    Some(mark) => *mark,
+                    None => {
+                        let mark = self.mid(market_id, token).await;
+                        marks.insert((market_id, token), mark);
+                        mark
+                    }
+                };
+                let cost = if open > 0.0 { avg_buy } else { avg_sell };
+                let mark = mark.unwrap_or(cost);
+                unrealized = open * (mark - cost);
+            }
+            sample.realized_pnl_usd += realized;
+            *sample
+                .strategy_realized_pnl_usd
+                .entry(strategy)
+                .or_default() += realized;
+            sample.equity_usd += realized + unrealized;
+        }
+        sample
+    }
+
+    async fn mid(&self, market_id: i64, token: Token) -> Option<f64> {
+        let book = match self.venue.top_of_book(market_id, token).await {
+            Ok(book) => book,
+            Err(err) => {
+                warn!(error = ?err, market_id, "no book to mark inventory");
+                return None;
+            }
+        };
+        match (book.bid, book.ask) {
+            (Some(bid), Some(ask)) => Some((bid.price + ask.price) / 2.0),
+            (Some(level), None) | (None, Some(level)) => Some(level.price),
+            (None, None) => None,
+        }
+    }
+
+    async fn persist_fill(&self, order: &ManagedOrder, update: &UserUpdate) {
+        let UserUpdateKind::Fill {
+            fill_id,
+            price,
+            qty,
+            fee_usd,
+        } = &update.kind
+        else {
+            return;
+        };
+        let row = FillRow {
+            run_id: self.run_id.clone(),
+            ts_ms: update.ts_ms,
+            venue: self.venue_name.clone(),
+            fill_id: Some(fill_id.clone()),
+            order_id: Some(update.order_id.clone()),
+            client_order_id: Some(order.client_order_id.clone()),
+            market_id: order.market_id,
+            strategy: order.strategy.clone(),
+            side: order.side.as_str().to_string(),
+            price: *price,
+            qty: *qty,
+            fee_usd: Some(*fee_usd),
+            liquidity: None,
+            raw_json: serde_json::to_string(update).ok(),
+        };
+        if let Err(err) = self.store.insert_fill(&row).await {
+            warn!(error = ?err, %fill_id, "failed to persist fill");
+        }
+    }
+
+    async fn price_band_check(
+        &self,
+        bands: &PriceBands,
+        new: &NewOrder,
+    ) -> Result<(), PriceViolation> {
+        let book = match self
+            .venue
+            .top_of_book(new.market_id, new.side.token())
+            .await
+        {
+            Ok(book) => book,
+            Err(err) => {
+                warn!(error = ?err, market_id = new.market_id, "no book for price band check");
+                return Err(PriceViolation::NoBook);
+            }
+        };
+        bands.check_order(
+            &new.strategy,
+            new.market_id,
+            new.side,
+            new.price,
+            new.qty,
+            &book,
+        )
+    }
+
+    async fn price_band_incident(&self, order: &ManagedOrder, violation: &PriceViolation) {
+        warn!(client_order_id = %order.client_order_id, %violation, "order rejected by price band");
+        let payload = serde_json::json!({
+            "violation": violation,
+            "order": {
+                "client_order_id": order.client_order_id,
+                "strategy": order.strategy,
+                "market_id": order.market_id,
+                "side": order.side.as_str(),
+                "price": order.limit_price,
+                "qty": order.qty,
+            },
+        });
+        if let Err(err) = self
+            .store
+            .log_incident(
+                &self.run_id,
+                "warning",
+                PRICE_BAND_INCIDENT,
+                &payload.to_string(),
+            )
+            .await
+        {
+            warn!(error = ?err, "failed to record price band incident");
+        }
+    }
+
+    async fn self_trade_incident(&self, order: &ManagedOrder, action: &str, others: &[String]) {
+        let message = format!(
+            "{} {} {}@{} in market {} crosses our {}; {action}",
+            order.client_order_id,
+            order.strategy,
+            order.side.as_str(),
+            order.limit_price,
+            order.market_id,
+            others.join(","),
+        );
+        warn!(%message, "self-trade prevented");
+        if let Err(err) = self
+            .store
+            .log_incident(&self.run_id, "warning", SELF_TRADE_INCIDENT, &message)
+            .await
+        {
+            warn!(error = ?err, "failed to record self-trade incident");
+        }
+    }
+
+    /// Write the order's current state. Called after the book lock is
+    /// released; writes are serialized and each reads the latest state, so
+    /// a slow write can never land over a newer one.
+    async fn persist(&self, client_order_id: &str) {
+        let _writing = self.persisting.lock().await;
+        let Some(order) = self.get(client_order_id).await else {
+            return;
+        };
+        if let Err(err) = self
+            .store
+            .upsert_order(&order.to_row(&self.run_id, &self.venue_name))
+            .await
+        {
+            warn!(error = ?err, client_order_id = %order.client_order_id, "failed to persist order");
+        }
+    }
+}
+
+/// An incident to record for a rejected placement.
+enum Incident {
+    SelfTrade(Vec<String>),
+    PriceBand(PriceViolation),
+}
+
+/// Why the risk gate refused a placement, for the order's notes.
+fn gate_note(gate: &RiskGate, strategy: &str) -> String {
+    let mut note = format!(
+        "risk gate: {} (strategy {strategy} {})",
+        gate.status().as_str(),
+        gate.strategy_status(strategy).as_str()
+    );
+    let blocks = gate.placement_blocks();
+    if !blocks.is_empty() {
+        note.push_str(&format!("; blocked by {}", blocks.join(",")));
+    }
+    note
+}
+
+fn apply_one(book: &mut Book, client_order_id: &str, update: &UserUpdate) -> bool {
+    if let UserUpdateKind::Fill { fill_id, .. } = &update.kind {
+        if !book.seen_fills.insert(fill_id.clone()) {
+            return false;
+        }
+    }
+    let Some(order) = book.orders.get_mut(client_order_id) else {
+        return false;
+    };
+
+    let mut changed = false;
+    if order.order_id.is_none() {
+        order.order_id = Some(update.order_id.clone());
+        changed = true;
+    }
+
+    match &update.kind {
+        UserUpdateKind::Acked | UserUpdateKind::Open => {
+            if order.ts_acked_ms.is_none() {
+                order.ts_acked_ms = Some(update.ts_ms);
+                changed = true;
+            }
+            let status = if matches!(update.kind, UserUpdateKind::Open) {
+                OrderStatus::Open
+            } else {
+                OrderStatus::Acked
+            };
+            changed |= order.move_to(status, update.ts_ms);
+        }
+        UserUpdateKind::Fill {
+            price,
+            qty,
+            fee_usd,
+            ..
+        } => {
+            order.filled_qty += qty;
+            order.filled_notional += price * qty;
+            order.fees_usd += fee_usd;
+            changed = true;
+            if order.remaining_qty() <= QTY_EPSILON {
+                // a fill that completes the order wins over a racing cancel
+                if order.status == OrderStatus::Canceled {
+                    order.status = OrderStatus::Filled;
+                } else {
+                    order.move_to(OrderStatus::Filled, update.ts_ms);
+                }
+            } else {
+                order.move_to(OrderStatus::PartiallyFilled, update.ts_ms);
+            }
+        }
+        UserUpdateKind::Canceled => {
+            if order.move_to(OrderStatus::Canceled, update.ts_ms) {
+                let notes: Vec<String> = order
+                    .expiry_note(update.ts_ms)
+                    .into_iter()
+                    .chain(
+                        order
+                            .cancel_latency_ms()
+                            .map(|latency| format!("cancel_latency_ms={latency}")),
+                    )
+                    .collect();
+                if !notes.is_empty() {
+                    order.notes = Some(notes.join("; "));
+                }
+                changed = true;
+            }
+        }
+        UserUpdateKind::Rejected { reason } => {
+            if order.move_to(OrderStatus::Rejected, update.ts_ms) {
+                order.notes = Some(format!("rejected: {reason}"));
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+pub(crate) fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockVenue;
+    use risk::{DrawdownLimits, QuoteRateLimits, RiskState};
+
+    async fn manager() -> (OrderManager<MockVenue>, Store) {
+        let store = storage::init_sqlite("sqlite::memory:").await.unwrap();
+        store.insert_run("run-om", None).await.unwrap();
+        let venue = MockVenue::new();
+        (
+            OrderManager::new(venue, "polymarket", "run-om", store.clone()),
+            store,
+        )
+    }
+
+    fn new_order(client_order_id: &str, qty: f64) -> NewOrder {
+        NewOrder {
+            client_order_id: client_order_id.into(),
+            approved_id: None,
+            intent_id: None,
+            strategy: "mm".into(),
+            market_id: 5,
+            side: Side::BuyYes,
+            price: 0.40,
+            qty,
+            tif: TimeInForce::Gtc,
+            post_only: false,
+            expires_at_ms: None,
+            reduce_only: false,
+        }
+    }
+
+    /// Hand the mock venue's user updates to the manager, as the user
+    /// websocket would.
+    async fn deliver(om: &OrderManager<MockVenue>) {
+        for update in om.venue().take_user_updates() {
+            om.apply_update(update).await;
+        }
+    }
+
+    fn fill(order_id: &str, fill_id: &str, qty: f64) -> UserUpdate {
+        UserUpdate {
+            order_id: order_id.into(),
+            client_order_id: None,
+            ts_ms: now_ms(),
+            kind: UserUpdateKind::Fill {
+                fill_id: fill_id.into(),
+                price: 0.40,
+                qty,
+                fee_usd: 0.0,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn submit_acks_and_persists_with_latency() {
+        let (om, store) = manager().await;
+        let order = om.submit(new_order("c-1", 10.0)).await.unwrap();
+        assert_eq!(order.status, OrderStatus::Acked);
+        assert!(order.order_id.is_some());
+
+        let row = store.fetch_order("c-1").await.unwrap().expect("row");
+        assert_eq!(row.status, "Acked");
+        assert_eq!(row.venue, "polymarket");
+        assert_eq!(row.order_id, order.order_id);
+        assert!(row.submit_latency_ms.unwrap() >= 0);
+    }
+
+    #[tokio::test]
+    async fn resubmitting_same_client_id_is_idempotent() {
+        let (om, _store) = manager().await;
+        let first = om.submit(new_order("c-1", 10.0)).await.unwrap();
+        let again = om.submit(new_order("c-1", 10.0)).await.unwrap();
+        assert_eq!(first.order_id, again.order_id);
+        assert_eq!(om.venue().placed().len(), 1);
+
+        let ids = ClientOrderIds::new("0f3c-aa11-22");
+        assert_eq!(ids.for_approval(7, 0), ids.for_approval(7, 0));
+        assert_ne!(ids.next(), ids.next());
+    }
+
+    #[tokio::test]
+    async fn fills_move_to_partial_then_filled_and_ignore_duplicates() {
+        let (om, store) = manager().await;
+        let order = om.submit(new_order("c-1", 10.0)).await.unwrap();
+        let oid = order.order_id.unwrap();
+
+        let o = om.apply_update(fill(&oid, "f-1", 4.0)).await.unwrap();
+        assert_eq!(o.status, OrderStatus::PartiallyFilled);
+        let o = om.apply_update(fill(&oid, "f-1", 4.0)).await.unwrap();
+        assert!((o.filled_qty - 4.0).abs() < 1e-9);
+
+        let o = om.apply_update(fill(&oid, "f-2", 6.0)).await.unwrap();
+        assert_eq!(o.status, OrderStatus::Filled);
+        assert!(o.ts_final_ms.is_some());
+        assert_eq!(
+            store.fetch_order("c-1").await.unwrap().unwrap().status,
+            "Filled"
+        );
+
+        // a late Open must not resurrect a filled order
+        let o = om
+            .apply_update(UserUpdate {
+                order_id: oid.clone(),
+                client_order_id: None,
+                ts_ms: now_ms(),
+                kind: UserUpdateKind::Open,
+            })
+            .await
+            .unwrap();
+        assert_eq!(o.status, OrderStatus::Filled);
+    }
+
+    #[tokio::test]
+    async fn updates_for_unknown_order_ids_are_held_until_ack() {
+        let (om, _store) = manager().await;
+        // the mock assigns ids sequentially, so the first order is mock-1
+        assert!(om.apply_update(fill("mock-1", "f-9", 10.0)).await.is_none());
+
+        let order = om.submit(new_order("c-1", 10.0)).await.unwrap();
+        assert_eq!(order.order_id.as_deref(), Some("mock-1"));
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert!(order.ts_acked_ms.is_some());
+    }
+
+    #[tokio::test]
+    async fn cancel_and_venue_failure_are_final() {
+        let (om, store) = manager().await;
+        om.submit(new_order("c-1", 10.0)).await.unwrap();
+        om.cancel("c-1").await.unwrap();
+        let pending = om.get("c-1").await.unwrap();
+        assert_eq!(pending.status, OrderStatus::Acked);
+        assert!(pending.cancel_in_flight());
+        let row = store.fetch_order("c-1").await.unwrap().unwrap();
+        assert_eq!(row.status, "Acked");
+        deliver(&om).await;
+        assert_eq!(om.get("c-1").await.unwrap().status, OrderStatus::Canceled);
+
+        om.venue().fail_next("place_order", 1);
+        let failed = om.submit(new_order("c-2", 10.0)).await.unwrap();
+        assert_eq!(failed.status, OrderStatus::Failed);
+        let row = store.fetch_order("c-2").await.unwrap().unwrap();
+        assert_eq!(row.status, "Failed");
+        assert!(row.notes.unwrap().contains("submit failed"));
+        assert!(om.live_orders().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn refused_cancel_keeps_the_partial_fill() {
+        let (om, store) = manager().await;
+        let order_id = om
+            .submit(new_order("c-1", 10.0))
+            .await
+            .unwrap()
+            .order_id
+            .unwrap();
+        om.apply_update(fill(&order_id, "f-1", 4.0)).await;
+        om.venue().fail_next("cancel_order", 1);
+
+        assert!(om.cancel("c-1").await.is_err());
+        let order = om.get("c-1").await.unwrap();
+        assert_eq!(order.status, OrderStatus::PartiallyFilled);
+        assert!(!order.cancel_in_flight());
+        let row = store.fetch_order("c-1").await.unwrap().unwrap();
+        assert_eq!(row.status, "PartiallyFilled");
+    }
+
+    #[tokio::test]
+    async fn quote_governor_counts_places_and_cancels_and_throttles() {
+        let store = storage::init_sqlite("sqlite::memory:").await.unwrap();
+        store.insert_run("run-om", None).await.unwrap();
+        let (governor, _episodes) = QuoteGovernor::new(QuoteRateLimits {
+            rate_window_ms: 60_000,
+            max_market_msgs_per_sec: 3.0 / 60.0,
+            ..QuoteRateLimits::default()
+        });
+        let om = OrderManager::new(MockVenue::new(), "polymarket", "run-om", store.clone())
+            .with_quote_governor(governor.clone());
+
+        om.submit(new_order("c-1", 10.0)).await.unwrap();
+        om.cancel("c-1").await.unwrap();
+        om.submit(new_order("c-2", 10.0)).await.unwrap();
+        let throttled = om.submit(new_order("c-3", 10.0)).await.unwrap();
+        assert_eq!(throttled.status, OrderStatus::Rejected);
+        assert!(throttled.notes.unwrap().starts_with("quote rate: market 5"));
+        assert_eq!(om.venue().placed().len(), 2);
+
+        // cancels still go out while throttled
+        om.cancel("c-2").await.unwrap();
+        let stats = governor.stats(now_ms());
+        assert_eq!(stats[0].messages, 4);
+    }
+
+    #[tokio::test]
+    async fn risk_gate_rejects_new_exposure_but_lets_reductions_through() {
+        let store = storage::init_sqlite("sqlite::memory:").await.unwrap();
+        store.insert_run("run-om", None).await.unwrap();
+        let gate = RiskGate::new();
+        let om = OrderManager::new(MockVenue::new(), "polymarket", "run-om", store.clone())
+            .with_risk_gate(gate.clone());
+
+        gate.kill_switch("test");
+        let blocked = om.submit(new_order("c-1", 10.0)).await.unwrap();
+        assert_eq!(blocked.status, OrderStatus::Rejected);
+        assert!(blocked
+            .notes
+            .unwrap()
+            .starts_with("risk gate: kill_switched"));
+        let row = store.fetch_order("c-1").await.unwrap().unwrap();
+        assert_eq!(row.status, "Rejected");
+
+        let flatten = om
+            .submit(NewOrder {
+                side: Side::SellYes,
+                reduce_only: true,
+                ..new_order("c-2", 10.0)
+            })
+            .await
+            .unwrap();
+        assert_eq!(flatten.status, OrderStatus::Acked);
+        assert_eq!(om.venue().placed().len(), 1);
+
+        gate.reset_kill_switch();
+        gate.block_placements("breaker:place_order");
+        let blocked = om.submit(new_order("c-3", 10.0)).await.unwrap();
+        assert!(blocked
+            .notes
+            .unwrap()
+            .ends_with("blocked by breaker:place_order"));
+    }
+
+    #[tokio::test]
+    async fn stale_markets_reject_new_orders() {
+        let store = storage::init_sqlite("sqlite::memory:").await.unwrap();
+        store.insert_run("run-om", None).await.unwrap();
+        let (guard, mut episodes) = StalenessGuard::new(risk::StalenessLimits::default());
+        let om = OrderManager::new(MockVenue::new(), "polymarket", "run-om", store)
+            .with_staleness_guard(guard.clone());
+
+        let stale = om.submit(new_order("c-1", 10.0)).await.unwrap();
+        assert_eq!(stale.status, OrderStatus::Rejected);
+        assert_eq!(
+            stale.notes.as_deref(),
+            Some("stale: no book update received")
+        );
+        assert!(episodes.try_recv().unwrap().reason.is_some());
+
+        guard.record_book_update(5, now_ms());
+        let fresh = om.submit(new_order("c-2", 10.0)).await.unwrap();
+        assert_eq!(fresh.status, OrderStatus::Acked);
+        assert!(episodes.try_recv().unwrap().reason.is_none());
+    }
+
+    #[tokio::test]
+    async fn price_bands_reject_fat_fingers() {
+        let (om, store) = manager().await;
+        let om = om.with_price_bands(PriceBands::default());
+        let level = |price| Some(crate::venue::BookLevel { price, qty: 100.0 });
+        om.venue().set_book(5, Token::Yes, level(0.39), level(0.41));
+
+        let ok = om.submit(new_order("c-1", 10.0)).await.unwrap();
+        assert_eq!(ok.status, OrderStatus::Acked);
+        let fat = om
+            .submit(NewOrder {
+                price: 0.90,
+                ..new_order("c-2", 10.0)
+            })
+            .await
+            .unwrap();
+        assert_eq!(fat.status, OrderStatus::Rejected);
+        assert!(fat.notes.unwrap().starts_with("price band: "));
+        assert_eq!(om.venue().placed().len(), 1);
+        let row = store.fetch_order("c-2").await.unwrap().unwrap();
+        assert_eq!(row.status, "Rejected");
+    }
+
+    #[tokio::test]
+    async fn pnl_sample_realizes_round_trips_and_marks_inventory() {
+        let (om, _store) = manager().await;
+        let buy = om.submit(new_order("c-1", 10.0)).await.unwrap();
+        om.apply_update(fill(&buy.order_id.unwrap(), "f-1", 10.0))
+            .await
+            .unwrap();
+        let sell = om
+            .submit(NewOrder {
+                side: Side::SellYes,
+                price: 0.50,
+                ..new_order("c-2", 4.0)
+            })
+            .await
+            .unwrap();
+        om.apply_update(UserUpdate {
+            kind: UserUpdateKind::Fill {
+                fill_id: "f-2".into(),
+                price: 0.50,
+                qty: 4.0,
+                fee_usd: 0.0,
+            },
+            ..fill(&sell.order_id.unwrap(), "f-2", 4.0)
+        })
+        .await
+        .unwrap();
+        om.venue().set_book(
+            5,
+            Token::Yes,
+            Some(crate::venue::BookLevel {
+                price: 0.44,
+                qty: 1.0,
+            }),
+            Some(crate::venue::BookLevel {
+                price: 0.46,
+                qty: 1.0,
+            }),
+        );
+
+        let sample = om.pnl_sample(100.0).await;
+        // 4 sold at 0.10 over cost; 6 held, marked 0.05 over cost
+        assert!((sample.realized_pnl_usd - 0.4).abs() < 1e-9);
+        assert!((sample.strategy_realized_pnl_usd["mm"] - 0.4).abs() < 1e-9);
+        assert!((sample.equity_usd - 100.7).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn fees_alone_breach_the_daily_loss_limit() {
+        let (om, _store) = manager().await;
+        let (gate, _rx) = RiskGate::with_limits(DrawdownLimits {
+            max_daily_loss_usd: 1.0,
+            ..DrawdownLimits::default()
+        });
+        gate.observe_pnl(&om.pnl_sample(100.0).await);
+        assert_eq!(gate.status(), RiskState::Active);
+
+        // a flat round trip at the same price: only the fees are lost
+        for (i, side) in [Side::BuyYes, Side::SellYes].into_iter().enumerate() {
+            let order = om
+                .submit(NewOrder {
+                    side,
+                    ..new_order(&format!("c-{i}"), 10.0)
+                })
+                .await
+                .unwrap();
+            let fill_id = format!("f-{i}");
+            om.apply_update(UserUpdate {
+                kind: UserUpdateKind::Fill {
+                    fill_id: fill_id.clone(),
+                    price: 0.40,
+                    qty: 10.0,
+                    fee_usd: 0.6,
+                },
+                ..fill(&order.order_id.unwrap(), &fill_id, 10.0)
+            })
+            .await
+            .unwrap();
+        }
+
+        let sample = om.pnl_sample(100.0).await;
+        assert!((sample.realized_pnl_usd + 1.2).abs() < 1e-9);
+        assert!((sample.strategy_realized_pnl_usd["mm"] + 1.2).abs() < 1e-9);
+        assert!((sample.equity_usd - 98.8).abs() < 1e-9);
+        gate.observe_pnl(&sample);
+        assert_eq!(gate.status(), RiskState::Halted);
+    }
+
+    fn quick_replace(policy: ReplacePolicy) -> ReplaceConfig {
+        ReplaceConfig {
+            policy,
+            cancel_timeout: Duration::from_millis(300),
+            poll_interval: Duration::from_millis(5),
+        }
+    }
+
+    #[tokio::test]
+    async fn replace_waits_for_cancel_and_records_latency() {
+        let (om, store) = manager().await;
+        let om = std::sync::Arc::new(om);
+        om.submit(new_order("c-1", 10.0)).await.unwrap();
+        let pump = {
+            let om = om.clone();
+            tokio::spawn(async move {
+                loop {
+                    time::sleep(Duration::from_millis(5)).await;
+                    deliver(&om).await;
+                }
+            })
+        };
+
+        let out = om
+            .replace(
+                "c-1",
+                new_order("c-2", 10.0),
+                &quick_replace(ReplacePolicy::WaitForCancel),
+            )
+            .await
+            .unwrap();
+        assert_eq!(out.status, ReplaceStatus::Replaced);
+        assert_eq!(out.old.status, OrderStatus::Canceled);
+        assert!(out.cancel_latency_ms.unwrap() >= 0);
+        assert_eq!(out.new.unwrap().status, OrderStatus::Acked);
+        assert!(om.pending_cancels().await.is_empty());
+        pump.abort();
+
+        let row = store.fetch_order("c-1").await.unwrap().unwrap();
+        assert!(row.notes.unwrap().starts_with("cancel_latency_ms="));
+    }
+
+    #[tokio::test]
+    async fn fill_during_cancel_skips_the_replacement() {
+        let (om, _store) = manager().await;
+        let om = std::sync::Arc::new(om);
+        let oid = om
+            .submit(new_order("c-1", 10.0))
+            .await
+            .unwrap()
+            .order_id
+            .unwrap();
+        // the order fills on the venue before our cancel lands, and the fill
+        // reaches us only after the cancel is rejected
+        om.venue().fill_resting(&oid, 10.0);
+        let pump = {
+            let om = om.clone();
+            tokio::spawn(async move {
+                time::sleep(Duration::from_millis(20)).await;
+                deliver(&om).await;
+            })
+        };
+
+        let out = om
+            .replace(
+                "c-1",
+                new_order("c-2", 10.0),
+                &quick_replace(ReplacePolicy::WaitForCancel),
+            )
+            .await
+            .unwrap();
+        pump.await.unwrap();
+        assert_eq!(out.status, ReplaceStatus::OldFilled);
+        assert!((out.race_filled_qty - 10.0).abs() < 1e-9);
+        assert!(out.new.is_none());
+        assert_eq!(om.venue().placed().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn fill_reported_after_the_cancel_shrinks_the_replacement() {
+        let (om, _store) = manager().await;
+        let om = std::sync::Arc::new(om);
+        let oid = om
+            .submit(new_order("c-1", 10.0))
+            .await
+            .unwrap()
+            .order_id
+            .unwrap();
+        om.venue().fill_resting(&oid, 4.0);
+        // the venue confirms the cancel before the fill reaches the user
+        // channel
+        let late = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let pump = {
+            let (om, late) = (om.clone(), late.clone());
+            tokio::spawn(async move {
+                loop {
+                    time::sleep(Duration::from_millis(5)).await;
+                    for update in om.venue().take_user_updates() {
+                        match update.kind {
+                            UserUpdateKind::Fill { .. } => late.lock().unwrap().push(update),
+                            _ => {
+                                om.apply_update(update).await;
+                            }
+                        }
+                    }
+                }
+            })
+        };
+
+        let out = om
+            .replace(
+                "c-1",
+                new_order("c-2", 10.0),
+                &quick_replace(ReplacePolicy::WaitForCancel),
+            )
+            .await
+            .unwrap();
+        pump.abort();
+        assert_eq!(out.status, ReplaceStatus::Replaced);
+        assert!((out.race_filled_qty - 4.0).abs() < 1e-9);
+        assert_eq!(out.new.unwrap().qty, 6.0);
+
+        // the late delivery is a duplicate by then
+        let updates: Vec<UserUpdate> = late.lock().unwrap().drain(..).collect();
+        assert_eq!(updates.len(), 1);
+        for update in updates {
+            om.apply_update(update).await;
+        }
+        assert_eq!(om.get("c-1").await.unwrap().filled_qty, 4.0);
+    }
+
+    #[tokio::test]
+    async fn overlap_only_within_worst_case_cap() {
+        let (om, _store) = manager().await;
+        om.submit(new_order("c-1", 10.0)).await.unwrap();
+        om.venue().fail_next("cancel_order", 1);
+
+        // 10 @ 0.40 resting + 10 @ 0.40 new = $8 worst case, over a $5 cap:
+        // falls back to waiting, and the failed cancel blocks the replacement
+        let out = om
+            .replace(
+                "c-1",
+                new_order("c-2", 10.0),
+                &quick_replace(ReplacePolicy::Overlap {
+                    max_worst_case_usd: 5.0,
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(out.status, ReplaceStatus::CancelUnconfirmed);
+        assert!(out.new.is_none());
+        assert!(!out.old.cancel_in_flight());
+
+        om.venue().fail_next("cancel_order", 1);
+        let out = om
+            .replace(
+                "c-1",
+                new_order("c-3", 10.0),
+                &quick_replace(ReplacePolicy::Overlap {
+                    max_worst_case_usd: 10.0,
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(out.status, ReplaceStatus::CancelUnconfirmed);
+        assert_eq!(out.new.unwrap().status, OrderStatus::Acked);
+        assert_eq!(om.live_orders().await.len(), 2);
+    }
+}
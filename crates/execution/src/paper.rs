@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use strategies::Token;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::order_manager::{now_ms, UserUpdate, UserUpdateKind};
+use crate::venue::{
+    BookLevel, OrderAck, OrderRequest, TimeInForce, TopOfBook, Venue, VenueOrder, VenuePosition,
+    VenueRejection, VenueTrade,
+};
+
+const QTY_EPSILON: f64 = 1e-9;
+const PRICE_EPSILON: f64 = 1e-9;
+
+/// Visible depth for one token, best level first on each side.
+#[derive(Debug, Clone, Default)]
+struct Depth {
+    bids: Vec<BookLevel>,
+    asks: Vec<BookLevel>,
+}
+
+impl Depth {
+    fn own_side(&self, buy: bool) -> &[BookLevel] {
+        if buy {
+            &self.bids
+        } else {
+            &self.asks
+        }
+    }
+
+    fn qty_at(&self, buy: bool, price: f64) -> f64 {
+        self.own_side(buy)
+            .iter()
+            .filter(|l| (l.price - price).abs() < PRICE_EPSILON)
+            .map(|l| l.qty)
+            .sum()
+    }
+
+    /// Opposite-side size an order at `limit` could take.
+    fn crossing_qty(&self, buy: bool, limit: f64) -> f64 {
+        let levels = if buy { &self.asks } else { &self.bids };
+        levels
+            .iter()
+            .filter(|l| {
+                if buy {
+                    l.price <= limit + PRICE_EPSILON
+                } else {
+                    l.price >= limit - PRICE_EPSILON
+                }
+            })
+            .map(|l| l.qty)
+            .sum()
+    }
+
+    /// Take up to `qty` from the opposite side at prices no worse than
+    /// `limit`; returns the (price, qty) slices taken.
+    fn take(&mut self, buy: bool, limit: f64, mut qty: f64) -> Vec<(f64, f64)> {
+        let levels = if buy { &mut self.asks } else { &mut self.bids };
+        let mut taken = Vec::new();
+        for level in levels.iter_mut() {
+            let crosses = if buy {
+                level.price <= limit + PRICE_EPSILON
+            } else {
+                level.price >= limit - PRICE_EPSILON
+            };
+            if qty <= QTY_EPSILON || !crosses {
+                break;
+            }
+            let n = qty.min(level.qty);
+            if n > QTY_EPSILON {
+                taken.push((level.price, n));
+                level.qty -= n;
+                qty -= n;
+            }
+        }
+        levels.retain(|l| l.qty > QTY_EPSILON);
+        taken
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Resting {
+    order: VenueOrder,
+    /// Visible size at our price that was there before us.
+    queue_ahead: f64,
+}
+
+impl Resting {
+    fn remaining(&self) -> f64 {
+        self.order.qty - self.order.filled_qty
+    }
+}
+
+#[derive(Default)]
+struct PaperState {
+    next_id: u64,
+    next_fill: u64,
+    books: HashMap<(i64, Token), Depth>,
+    resting: Vec<Resting>,
+    positions: HashMap<(i64, Token), f64>,
+    trades: Vec<VenueTrade>,
+}
+
+/// Simulated venue for paper trading.
+///
+/// Orders are matched against the live book fed through [`PaperVenue::on_book`]
+/// and [`PaperVenue::on_trade`]: marketable orders take visible depth, resting
+/// orders join the back of the queue at their price and fill only once the
+/// size ahead of them has traded or been pulled. Acks, fills and cancels are
+/// reported on the user-update channel like the live user websocket. Nothing
+/// here can sign or send an order.
+#[derive(Clone)]
+pub struct PaperVenue {
+    state: Arc<Mutex<PaperState>>,
+    updates: UnboundedSender<UserUpdate>,
+}
+
+impl PaperVenue {
+    pub fn new() -> (Self, UnboundedReceiver<UserUpdate>) {
+        let (tx, rx) = unbounded_channel();
+        let venue = Self {
+            state: Arc::new(Mutex::new(PaperState::default())),
+            updates: tx,
+        };
+        (venue, rx)
+    }
+
+    /// Replace the book for a token. Resting orders the book has moved
+    /// through are filled; queue positions shrink with the visible size.
+    pub fn on_book(
+        &self,
+        market_id: i64,
+        token: Token,
+        bids: Vec<BookLevel>,
+        asks: Vec<BookLevel>,
+    ) {
+        let mut state = self.lock();
+        let mut depth = Depth { bids, asks };
+        depth.bids.sort_by(|a, b| b.price.total_cmp(&a.price));
+        depth.asks.sort_by(|a, b| a.price.total_cmp(&b.price));
+
+        let mut fills = Vec::new();
+        for resting in state
+            .resting
+            .iter_mut()
+            .filter(|r| r.order.market_id == market_id && r.order.side.token() == token)
+        {
+            let buy = resting.order.side.is_buy();
+            let taken: f64 = depth
+                .take(buy, resting.order.price, resting.remaining())
+                .iter()
+                .map(|(_, q)| q)
+                .sum();
+            if taken > QTY_EPSILON {
+                resting.queue_ahead = 0.0;
+                fills.push((resting.order.order_id.clone(), resting.order.price, taken));
+            } else {
+                resting.queue_ahead = resting
+                    .queue_ahead
+                    .min(depth.qty_at(buy, resting.order.price));
+            }
+        }
+        state.books.insert((market_id, token), depth);
+        for (order_id, price, qty) in fills {
+            self.fill_resting(&mut state, &order_id, price, qty);
+        }
+    }
+
+    /// A public trade print. Volume at or through a resting order's price
+    /// first consumes the queue ahead of it, then fills it.
+    pub fn on_trade(&self, market_id: i64, token: Token, price: f64, qty: f64) {
+        let mut state = self.lock();
+        let mut volume = qty;
+        let mut fills = Vec::new();
+        for resting in state
+            .resting
+            .iter_mut()
+            .filter(|r| r.order.market_id == market_id && r.order.side.token() == token)
+        {
+            let buy = resting.order.side.is_buy();
+            let (at, through) = if buy {
+                (
+                    price <= resting.order.price + PRICE_EPSILON,
+                    price < resting.order.price - PRICE_EPSILON,
+                )
+            } else {
+                (
+                    price >= resting.order.price - PRICE_EPSILON,
+                    price > resting.order.price + PRICE_EPSILON,
+                )
+            };
+            if !at {
+                continue;
+            }
+            if through {
+                resting.queue_ahead = 0.0;
+            }
+            let consumed = volume.min(resting.queue_ahead);
+            resting.queue_ahead -= consumed;
+            volume -= consumed;
+            let fill = volume.min(resting.remaining());
+            if fill > QTY_EPSILON {
+                volume -= fill;
+                fills.push((resting.order.order_id.clone(), resting.order.price, fill));
+            }
+        }
+        for (order_id, price, qty) in fills {
+            self.fill_resting(&mut state, &order_id, price, qty);
+        }
+    }
+
+    fn fill_resting(&self, state: &mut PaperState, order_id: &str, price: f64, qty: f64) {
+        let Some(idx) = state
+            .resting
+            .iter()
+            .position(|r| r.order.order_id == order_id)
+        else {
+            return;
+        };
+        state.resting[idx].order.filled_qty += qty;
+        let order = state.resting[idx].order.clone();
+        if state.resting[idx].remaining() <= QTY_EPSILON {
+            state.resting.remove(idx);
+        }
+        self.record_fill(state, &order, price, qty);
+    }
+
+    fn record_fill(&self, state: &mut PaperState, order: &VenueOrder, price: f64, qty: f64) {
+        let signed = if order.side.is_buy() { qty } else { -qty };
+        *state
+            .positions
+            .entry((order.market_id, order.side.token()))
+            .or_default() += signed;
+        state.next_fill += 1;
+        let trade = VenueTrade {
+            fill_id: format!("paper-fill-{}", state.next_fill),
+            order_id: order.order_id.clone(),
+            client_order_id: order.client_order_id.clone(),
+            market_id: order.market_id,
+            side: order.side,
+            price,
+            qty,
+            fee_usd: 0.0,
+            ts_ms: now_ms(),
+        };
+        self.send(
+            &trade.order_id,
+            trade.client_order_id.clone(),
+            UserUpdateKind::Fill {
+                fill_id: trade.fill_id.clone(),
+                price,
+                qty,
+                fee_usd: 0.0,
+            },
+        );
+        state.trades.push(trade);
+    }
+
+    fn send(&self, order_id: &str, client_order_id: Option<String>, kind: UserUpdateKind) {
+        let _ = self.updates.send(UserUpdate {
+            order_id: order_id.to_string(),
+            client_order_id,
+            ts_ms: now_ms(),
+            kind,
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PaperState> {
+        self.state.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+impl Venue for PaperVenue {
+    async fn place_order(&self, order: &OrderRequest) -> Result<OrderAck> {
+        let mut state = self.lock();
+        state.next_id += 1;
+        let order_id = format!("paper-{}", state.next_id);
+        let key = (order.market_id, order.side.token());
+        let buy = order.side.is_buy();
+        let mut venue_order = VenueOrder {
+            order_id: order_id.clone(),
+            client_order_id: Some(order.client_order_id.clone()),
+            market_id: order.market_id,
+            side: order.side,
+            price: order.price,
+            qty: order.qty,
+            filled_qty: 0.0,
+        };
+
+        let depth = state.books.entry(key).or_default();
+        let crossing = depth.crossing_qty(buy, order.price);
+        if order.post_only && crossing > QTY_EPSILON {
+            return Err(VenueRejection("paper venue: post-only order would cross".into()).into());
+        }
+        let taken = if order.tif == TimeInForce::Fok && crossing < order.qty - QTY_EPSILON {
+            Vec::new()
+        } else {
+            depth.take(buy, order.price, order.qty)
+        };
+        let queue_ahead = depth.qty_at(buy, order.price);
+        for (price, qty) in taken {
+            venue_order.filled_qty += qty;
+            self.record_fill(&mut state, &venue_order, price, qty);
+        }
+
+        let remaining = order.qty - venue_order.filled_qty;
+        if remaining > QTY_EPSILON {
+            if order.tif.rests() {
+                state.resting.push(Resting {
+                    order: venue_order,
+                    queue_ahead,
+                });
+            } else {
+                self.send(
+                    &order_id,
+                    Some(order.client_order_id.clone()),
+                    UserUpdateKind::Canceled,
+                );
+            }
+        }
+        Ok(OrderAck {
+            order_id,
+            ts_ms: now_ms(),
+        })
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> Result<()> {
+        let mut state = self.lock();
+        let idx = state
+            .resting
+            .iter()
+            .position(|r| r.order.order_id == order_id)
+            .ok_or_else(|| VenueRejection(format!("paper venue: unknown order {order_id}")))?;
+        let resting = state.resting.remove(idx);
+        self.send(
+            order_id,
+            resting.order.client_order_id,
+            UserUpdateKind::Canceled,
+        );
+        Ok(())
+    }
+
+    async fn cancel_all(&self) -> Result<()> {
+        let mut state = self.lock();
+        for resting in std::mem::take(&mut state.resting) {
+            self.send(
+                &resting.order.order_id,
+                resting.order.client_order_id,
+                UserUpdateKind::Canceled,
+            );
+        }
+        Ok(())
+    }
+
+    async fn open_orders(&self) -> Result<Vec<VenueOrder>> {
+        Ok(self
+            .lock()
+            .resting
+            .iter()
+            .map(|r| r.order.clone())
+            .collect())
+    }
+
+    async fn recent_trades(&self, since_ms: i64) -> Result<Vec<VenueTrade>> {
+        Ok(self
+            .lock()
+            .trades
+            .iter()
+            .filter(|t| t.ts_ms >= since_ms)
+            .cloned()
+            .collect())
+    }
+
+    async fn positions(&self) -> Result<Vec<VenuePosition>> {
+        let state = self.lock();
+        let mut positions: Vec<VenuePosition> = state
+            .positions
+            .iter()
+            .filter(|(_, qty)| qty.abs() > QTY_EPSILON)
+            .map(|(&(market_id, token), &qty)| VenuePosition {
+                market_id,
+                token,
+                qty,
+            })
+            .collect();
+        positions.sort_by_key(|p| (p.market_id, p.token == Token::No));
+        Ok(positions)
+    }
+
+    async fn top_of_book(&self, market_id: i64, token: Token) -> Result<TopOfBook> {
+        let state = self.lock();
+        let depth = state.books.get(&(market_id, token));
+        Ok(TopOfBook {
+            bid: depth.and_then(|d| d.bids.first().copied()),
+            ask: depth.and_then(|d| d.asks.first().copied()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strategies::Side;
+
+    fn lvl(price: f64, qty: f64) -> BookLevel {
+        BookLevel { price, qty }
+    }
+
+    fn request(side: Side, price: f64, qty: f64, tif: TimeInForce) -> OrderRequest {
+        OrderRequest {
+            client_order_id: "c-1".into(),
+            market_id: 1,
+            side,
+            price,
+            qty,
+            tif,
+            post_only: false,
+            expires_at_ms: None,
+        }
+    }
+
+    fn fills(rx: &mut UnboundedReceiver<UserUpdate>) -> Vec<(f64, f64)> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|u| match u.kind {
+                UserUpdateKind::Fill { price, qty, .. } => Some((price, qty)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn marketable_orders_walk_visible_depth() {
+        let (venue, mut rx) = PaperVenue::new();
+        venue.on_book(1, Token::Yes, vec![], vec![lvl(0.41, 5.0), lvl(0.42, 10.0)]);
+
+        venue
+            .place_order(&request(Side::BuyYes, 0.42, 8.0, TimeInForce::Gtc))
+            .await
+            .unwrap();
+        assert_eq!(fills(&mut rx), vec![(0.41, 5.0), (0.42, 3.0)]);
+        assert!(venue.open_orders().await.unwrap().is_empty());
+        let top = venue.top_of_book(1, Token::Yes).await.unwrap();
+        assert_eq!(top.ask, Some(lvl(0.42, 7.0)));
+        assert_eq!(venue.positions().await.unwrap()[0].qty, 8.0);
+    }
+
+    #[tokio::test]
+    async fn resting_orders_wait_for_the_queue_ahead() {
+        let (venue, mut rx) = PaperVenue::new();
+        venue.on_book(1, Token::Yes, vec![lvl(0.40, 100.0)], vec![lvl(0.45, 10.0)]);
+        venue
+            .place_order(&request(Side::BuyYes, 0.40, 10.0, TimeInForce::Gtc))
+            .await
+            .unwrap();
+
+        venue.on_trade(1, Token::Yes, 0.40, 60.0);
+        assert!(fills(&mut rx).is_empty());
+
+        // cancels ahead of us: only 20 visible now
+        venue.on_book(1, Token::Yes, vec![lvl(0.40, 20.0)], vec![lvl(0.45, 10.0)]);
+        venue.on_trade(1, Token::Yes, 0.40, 24.0);
+        assert_eq!(fills(&mut rx), vec![(0.40, 4.0)]);
+
+        // the book trading through our price fills the rest at our limit
+        venue.on_book(1, Token::Yes, vec![lvl(0.38, 50.0)], vec![lvl(0.39, 50.0)]);
+        assert_eq!(fills(&mut rx), vec![(0.40, 6.0)]);
+        assert!(venue.open_orders().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn fak_remainder_is_canceled() {
+        let (venue, mut rx) = PaperVenue::new();
+        venue.on_book(1, Token::No, vec![lvl(0.55, 4.0)], vec![]);
+        venue
+            .place_order(&request(Side::SellNo, 0.55, 10.0, TimeInForce::Fak))
+            .await
+            .unwrap();
+
+        let updates: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(updates.len(), 2);
+        assert!(matches!(updates[0].kind, UserUpdateKind::Fill { qty, .. } if qty == 4.0));
+        assert_eq!(updates[1].kind, UserUpdateKind::Canceled);
+        assert!(venue.open_orders().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,575 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use risk::RiskGate;
+use serde::{Deserialize, Serialize};
+use storage::{FillRow, Store};
+use strategies::Token;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::order_manager::{now_ms, OrderManager, OrderStatus, UserUpdate, UserUpdateKind};
+use crate::venue::{Venue, VenueTrade};
+
+/// Incident kind recorded when venue balances disagree with our fills.
+pub const RECONCILE_DRIFT_INCIDENT: &str = "RECONCILE_DRIFT";
+
+/// Strategy recorded for venue orders and trades we can't attribute.
+pub const UNATTRIBUTED_STRATEGY: &str = "unattributed";
+
+/// What to do with open venue orders the order manager doesn't know.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrphanPolicy {
+    /// Track them as unattributed orders.
+    Adopt,
+    Cancel,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconcileConfig {
+    pub interval: Duration,
+    pub orphan_policy: OrphanPolicy,
+    /// How far back to fetch venue trades on each pass.
+    pub trade_lookback: Duration,
+    /// Acked orders younger than this may not show up on the venue yet.
+    pub ack_grace: Duration,
+    /// Position difference (in shares) tolerated before declaring drift.
+    pub position_tolerance: f64,
+    /// Consecutive passes a position must drift on before it forces
+    /// ReduceOnly, since venue balances can lag the trade history by a
+    /// pass; 0 or 1 escalates on the first.
+    pub drift_passes: u32,
+}
+
+impl Default for ReconcileConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            orphan_policy: OrphanPolicy::Cancel,
+            trade_lookback: Duration::from_secs(600),
+            ack_grace: Duration::from_secs(5),
+            position_tolerance: 1e-6,
+            drift_passes: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionDrift {
+    pub market_id: i64,
+    pub token: Token,
+    pub expected: f64,
+    pub actual: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReconcileReport {
+    pub ts_ms: i64,
+    pub adopted: Vec<String>,
+    pub canceled_orphans: Vec<String>,
+    /// Local orders the venue no longer lists and that did not fill,
+    /// closed as Canceled.
+    pub closed_missing: Vec<String>,
+    pub backfilled_fills: usize,
+    /// Drift seen on `drift_passes` passes in a row; forces ReduceOnly.
+    pub drift: Vec<PositionDrift>,
+    /// Drift not yet seen on enough passes to act on.
+    pub pending_drift: Vec<PositionDrift>,
+    pub errors: Vec<String>,
+}
+
+impl ReconcileReport {
+    pub fn is_clean(&self) -> bool {
+        self.adopted.is_empty()
+            && self.canceled_orphans.is_empty()
+            && self.closed_missing.is_empty()
+            && self.backfilled_fills == 0
+            && self.drift.is_empty()
+            && self.pending_drift.is_empty()
+            && self.errors.is_empty()
+    }
+}
+
+/// Diffs venue orders, trades and balances against the order manager.
+///
+/// The first pass (on boot) takes venue balances as the starting position;
+/// later passes expect balances to move only by the fills we know about.
+pub struct Reconciler<V> {
+    manager: Arc<OrderManager<V>>,
+    store: Store,
+    gate: RiskGate,
+    run_id: String,
+    venue_name: String,
+    config: ReconcileConfig,
+    /// Venue balances not explained by the order manager's fills.
+    baseline: Mutex<Option<HashMap<(i64, Token), f64>>>,
+    /// Passes in a row each position has drifted on.
+    drift_streaks: Mutex<HashMap<(i64, Token), u32>>,
+}
+
+impl<V: Venue> Reconciler<V> {
+    pub fn new(
+        manager: Arc<OrderManager<V>>,
+        store: Store,
+        gate: RiskGate,
+        run_id: &str,
+        venue_name: &str,
+        config: ReconcileConfig,
+    ) -> Self {
+        Self {
+            manager,
+            store,
+            gate,
+            run_id: run_id.to_string(),
+            venue_name: venue_name.to_string(),
+            config,
+            baseline: Mutex::new(None),
+            drift_streaks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Reconcile every `interval` until the task is dropped. The boot pass is
+    /// left to the caller so it can finish before trading starts.
+    pub async fn run(&self) {
+        let start = tokio::time::Instant::now() + self.config.interval;
+        let mut ticker = tokio::time::interval_at(start, self.config.interval);
+        loop {
+            ticker.tick().await;
+            match self.reconcile().await {
+                Ok(report) if report.is_clean() => {}
+                Ok(report) => info!(
+                    adopted = report.adopted.len(),
+                    canceled_orphans = report.canceled_orphans.len(),
+                    closed_missing = report.closed_missing.len(),
+                    backfilled_fills = report.backfilled_fills,
+                    drift = report.drift.len(),
+                    pending_drift = report.pending_drift.len(),
+                    errors = report.errors.len(),
+                    "reconcile pass"
+                ),
+                Err(err) => warn!(error = ?err, "reconcile failed"),
+            }
+        }
+    }
+
+    pub async fn reconcile(&self) -> Result<ReconcileReport> {
+        let mut baseline = self.baseline.lock().await;
+        let mut report = ReconcileReport {
+            ts_ms: now_ms(),
+            ..ReconcileReport::default()
+        };
+        let venue = self.manager.venue();
+
+        // 1. Trades: apply fills the user channel missed.
+        let since_ms = report.ts_ms - self.config.trade_lookback.as_millis() as i64;
+        let mut unattributed = HashMap::new();
+        for trade in venue.recent_trades(since_ms).await? {
+            if self.manager.has_fill(&trade.fill_id).await {
+                continue;
+            }
+            let known = match self.manager.client_order_id_for(&trade.order_id).await {
+                Some(id) => Some(id),
+                None => match &trade.client_order_id {
+                    Some(id) if self.manager.get(id).await.is_some() => Some(id.clone()),
+                    _ => None,
+                },
+            };
+            if known.is_some() {
+                self.manager
+                    .apply_update(UserUpdate {
+                        order_id: trade.order_id.clone(),
+                        client_order_id: known,
+                        ts_ms: trade.ts_ms,
+                        kind: UserUpdateKind::Fill {
+                            fill_id: trade.fill_id.clone(),
+                            price: trade.price,
+                            qty: trade.qty,
+                            fee_usd: trade.fee_usd,
+                        },
+                    })
+                    .await;
+                report.backfilled_fills += 1;
+            } else if !self
+                .store
+                .has_fill(&self.venue_name, &trade.fill_id)
+                .await?
+            {
+                self.store
+                    .insert_fill(&self.unattributed_fill(&trade))
+                    .await?;
+                let signed = if trade.side.is_buy() {
+                    trade.qty
+                } else {
+                    -trade.qty
+                };
+                *unattributed
+                    .entry((trade.market_id, trade.side.token()))
+                    .or_insert(0.0) += signed;
+                report.backfilled_fills += 1;
+            }
+        }
+
+        // 2. Open orders: adopt or cancel orphans, close what vanished.
+        let open = venue.open_orders().await?;
+        let mut open_ids = HashSet::new();
+        for venue_order in &open {
+            open_ids.insert(venue_order.order_id.clone());
+            let known = self
+                .manager
+                .client_order_id_for(&venue_order.order_id)
+                .await
+                .is_some()
+                || match &venue_order.client_order_id {
+                    Some(id) => self.manager.get(id).await.is_some(),
+                    None => false,
+                };
+            if known {
+                continue;
+            }
+            match self.config.orphan_policy {
+                OrphanPolicy::Adopt => {
+                    self.manager.adopt(venue_order, UNATTRIBUTED_STRATEGY).await;
+                    report.adopted.push(venue_order.order_id.clone());
+                }
+                OrphanPolicy::Cancel => match venue.cancel_order(&venue_order.order_id).await {
+                    Ok(()) => report.canceled_orphans.push(venue_order.order_id.clone()),
+                    Err(err) => report
+                        .errors
+                        .push(format!("cancel orphan {}: {err}", venue_order.order_id)),
+                },
+            }
+        }
+
+        let grace_ms = self.config.ack_grace.as_millis() as i64;
+        for order in self.manager.live_orders().await {
+            let Some(order_id) = &order.order_id else {
+                continue;
+            };
+            let settled = order
+                .ts_acked_ms
+                .is_some_and(|t| report.ts_ms - t >= grace_ms);
+            if order.status == OrderStatus::Submitted || !settled || open_ids.contains(order_id) {
+                continue;
+            }
+            // it may have filled after the trades above were fetched
+            report.backfilled_fills += self.manager.backfill_fills(&order.client_order_id).await;
+            let closed = self
+                .manager
+                .close_missing(&order.client_order_id, "not open on venue at reconcile")
+                .await;
+            if closed.is_some_and(|o| o.status == OrderStatus::Canceled) {
+                report.closed_missing.push(order.client_order_id.clone());
+            }
+        }
+
+        // 3. Balances against the starting position plus known fills.
+        let actual: HashMap<(i64, Token), f64> = venue
+            .positions()
+            .await?
+            .into_iter()
+            .map(|p| ((p.market_id, p.token), p.qty))
+            .collect();
+        let local = self.manager.net_positions().await;
+        match baseline.as_mut() {
+            None => {
+                let mut start = actual.clone();
+                for (key, qty) in &local {
+                    *start.entry(*key).or_insert(0.0) -= qty;
+                }
+                *baseline = Some(start);
+            }
+            Some(start) => {
+                for (key, qty) in unattributed {
+                    *start.entry(key).or_insert(0.0) += qty;
+                }
+                let keys: HashSet<_> = start
+                    .keys()
+                    .chain(local.keys())
+                    .chain(actual.keys())
+                    .collect();
+                let mut keys: Vec<_> = keys.into_iter().copied().collect();
+                keys.sort_by_key(|(market_id, token)| (*market_id, *token == Token::No));
+                let mut streaks = self.drift_streaks.lock().await;
+                for key in keys {
+                    let expected = start.get(&key).copied().unwrap_or(0.0)
+                        + local.get(&key).copied().unwrap_or(0.0);
+                    let actual = actual.get(&key).copied().unwrap_or(0.0);
+                    if (expected - actual).abs() <= self.config.position_tolerance {
+                        streaks.remove(&key);
+                        continue;
+                    }
+                    let drift = PositionDrift {
+                        market_id: key.0,
+                        token: key.1,
+                        expected,
+                        actual,
+                    };
+                    let streak = streaks.entry(key).or_insert(0);
+                    *streak += 1;
+                    if *streak >= self.config.drift_passes {
+                        report.drift.push(drift);
+                    } else {
+                        report.pending_drift.push(drift);
+                    }
+                }
+            }
+        }
+
+        if !report.drift.is_empty() {
+            self.raise_drift(&report.drift).await;
+        }
+        Ok(report)
+    }
+
+    fn unattributed_fill(&self, trade: &VenueTrade) -> FillRow {
+        FillRow {
+            run_id: self.run_id.clone(),
+            ts_ms: trade.ts_ms,
+            venue: self.venue_name.clone(),
+            fill_id: Some(trade.fill_id.clone()),
+            order_id: Some(trade.order_id.clone()),
+            client_order_id: trade.client_order_id.clone(),
+            market_id: trade.market_id,
+            strategy: UNATTRIBUTED_STRATEGY.to_string(),
+            side: trade.side.as_str().to_string(),
+            price: trade.price,
+            qty: trade.qty,
+            fee_usd: Some(trade.fee_usd),
+            liquidity: None,
+            raw_json: serde_json::to_string(trade).ok(),
+        }
+    }
+
+    async fn raise_drift(&self, drift: &[PositionDrift]) {
+        let detail: Vec<String> = drift
+            .iter()
+            .map(|d| {
+                format!(
+                    "market {} {:?}: expected {:.4} venue {:.4}",
+                    d.market_id, d.token, d.expected, d.actual
+                )
+            })
+            .collect();
+        let message = format!("position drift: {}", detail.join("; "));
+        warn!(%message, "reconcile found position drift");
+        self.gate.force_reduce_only("position drift");
+        if let Err(err) = self
+            .store
+            .log_incident(&self.run_id, "critical", RECONCILE_DRIFT_INCIDENT, &message)
+            .await
+        {
+            warn!(error = ?err, "failed to record drift incident");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockVenue;
+    use crate::order_manager::NewOrder;
+    use crate::venue::{TimeInForce, VenueOrder};
+    use risk::RiskState;
+    use strategies::Side;
+
+    const RUN: &str = "run-recon";
+
+    async fn setup(
+        policy: OrphanPolicy,
+    ) -> (
+        MockVenue,
+        Arc<OrderManager<MockVenue>>,
+        Store,
+        RiskGate,
+        Reconciler<MockVenue>,
+    ) {
+        let store = storage::init_sqlite("sqlite::memory:").await.unwrap();
+        store.insert_run(RUN, None).await.unwrap();
+        let venue = MockVenue::new();
+        let manager = Arc::new(OrderManager::new(venue.clone(), "mock", RUN, store.clone()));
+        let gate = RiskGate::new();
+        let config = ReconcileConfig {
+            orphan_policy: policy,
+            ack_grace: Duration::ZERO,
+            ..ReconcileConfig::default()
+        };
+        let reconciler = Reconciler::new(
+            manager.clone(),
+            store.clone(),
+            gate.clone(),
+            RUN,
+            "mock",
+            config,
+        );
+        (venue, manager, store, gate, reconciler)
+    }
+
+    fn resting(order_id: &str) -> VenueOrder {
+        VenueOrder {
+            order_id: order_id.into(),
+            client_order_id: None,
+            market_id: 4,
+            side: Side::BuyNo,
+            price: 0.30,
+            qty: 5.0,
+            filled_qty: 0.0,
+        }
+    }
+
+    async fn submit(manager: &OrderManager<MockVenue>, client_order_id: &str) -> String {
+        manager
+            .submit(NewOrder {
+                client_order_id: client_order_id.into(),
+                approved_id: None,
+                intent_id: None,
+                strategy: "mm".into(),
+                market_id: 4,
+                side: Side::BuyYes,
+                price: 0.45,
+                qty: 10.0,
+                tif: TimeInForce::Gtc,
+                post_only: false,
+                expires_at_ms: None,
+                reduce_only: false,
+            })
+            .await
+            .unwrap()
+            .order_id
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn boot_takes_balances_as_baseline_and_handles_orphans() {
+        let (venue, _manager, _store, gate, reconciler) = setup(OrphanPolicy::Cancel).await;
+        venue.set_position(4, Token::Yes, 25.0);
+        venue.add_resting(resting("old-1"));
+
+        let report = reconciler.reconcile().await.unwrap();
+        assert_eq!(report.canceled_orphans, vec!["old-1".to_string()]);
+        assert!(report.drift.is_empty());
+        assert!(venue.open_orders().await.unwrap().is_empty());
+        assert_eq!(gate.status(), RiskState::Active);
+
+        let (venue, manager, _store, _gate, reconciler) = setup(OrphanPolicy::Adopt).await;
+        venue.add_resting(resting("old-2"));
+        let report = reconciler.reconcile().await.unwrap();
+        assert_eq!(report.adopted, vec!["old-2".to_string()]);
+        let adopted = manager.get("adopted-old-2").await.unwrap();
+        assert_eq!(adopted.status, OrderStatus::Open);
+        assert_eq!(adopted.strategy, UNATTRIBUTED_STRATEGY);
+    }
+
+    #[tokio::test]
+    async fn backfills_fills_missed_by_the_user_channel() {
+        let (venue, manager, store, gate, reconciler) = setup(OrphanPolicy::Cancel).await;
+        reconciler.reconcile().await.unwrap();
+
+        let order_id = submit(&manager, "c-1").await;
+        venue.fill_resting(&order_id, 10.0);
+        // the websocket dropped the fill
+        venue.take_user_updates();
+
+        let report = reconciler.reconcile().await.unwrap();
+        assert_eq!(report.backfilled_fills, 1);
+        assert!(report.drift.is_empty(), "{:?}", report.drift);
+        assert!(report.closed_missing.is_empty());
+        assert_eq!(
+            manager.get("c-1").await.unwrap().status,
+            OrderStatus::Filled
+        );
+        assert_eq!(gate.status(), RiskState::Active);
+
+        let fills = store.fills_for_run(RUN).await.unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].client_order_id.as_deref(), Some("c-1"));
+
+        // a second pass finds nothing new
+        assert!(reconciler.reconcile().await.unwrap().is_clean());
+    }
+
+    #[tokio::test]
+    async fn vanished_orders_that_filled_close_as_filled() {
+        let (venue, manager, store, _gate, reconciler) = setup(OrphanPolicy::Cancel).await;
+        reconciler.reconcile().await.unwrap();
+
+        // filled long enough ago to fall outside the trade lookback
+        let order_id = submit(&manager, "c-1").await;
+        venue.fill_resting(&order_id, 10.0);
+        venue.take_user_updates();
+        let reconciler = Reconciler::new(
+            manager.clone(),
+            store.clone(),
+            RiskGate::new(),
+            RUN,
+            "mock",
+            ReconcileConfig {
+                trade_lookback: Duration::ZERO,
+                ack_grace: Duration::ZERO,
+                ..ReconcileConfig::default()
+            },
+        );
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        let report = reconciler.reconcile().await.unwrap();
+        assert!(report.closed_missing.is_empty());
+        assert_eq!(report.backfilled_fills, 1);
+        let order = manager.get("c-1").await.unwrap();
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert!((order.filled_qty - 10.0).abs() < 1e-9);
+        let row = store.fetch_order("c-1").await.unwrap().unwrap();
+        assert_eq!(row.status, "Filled");
+    }
+
+    #[tokio::test]
+    async fn drift_forces_reduce_only_and_closes_vanished_orders() {
+        let (venue, manager, _store, gate, reconciler) = setup(OrphanPolicy::Cancel).await;
+        reconciler.reconcile().await.unwrap();
+
+        let order_id = submit(&manager, "c-1").await;
+        venue.cancel_order(&order_id).await.unwrap();
+        venue.set_position(4, Token::Yes, 3.0);
+        let drift = vec![PositionDrift {
+            market_id: 4,
+            token: Token::Yes,
+            expected: 0.0,
+            actual: 3.0,
+        }];
+
+        let report = reconciler.reconcile().await.unwrap();
+        assert_eq!(report.closed_missing, vec!["c-1".to_string()]);
+        assert_eq!(report.pending_drift, drift);
+        assert!(report.drift.is_empty());
+        assert_eq!(gate.status(), RiskState::Active);
+
+        let report = reconciler.reconcile().await.unwrap();
+        assert_eq!(report.drift, drift);
+        assert_eq!(gate.status(), RiskState::ReduceOnly);
+        assert_eq!(
+            manager.get("c-1").await.unwrap().status,
+            OrderStatus::Canceled
+        );
+    }
+
+    #[tokio::test]
+    async fn balance_lagging_a_pass_is_not_drift() {
+        let (venue, manager, _store, gate, reconciler) = setup(OrphanPolicy::Cancel).await;
+        reconciler.reconcile().await.unwrap();
+
+        let order_id = submit(&manager, "c-1").await;
+        venue.fill_resting(&order_id, 10.0);
+        // the fill is in the trade history but the balance hasn't caught up
+        venue.set_position(4, Token::Yes, 0.0);
+
+        let report = reconciler.reconcile().await.unwrap();
+        assert!(report.drift.is_empty());
+        assert_eq!(report.pending_drift.len(), 1);
+        assert_eq!(gate.status(), RiskState::Active);
+
+        venue.set_position(4, Token::Yes, 10.0);
+        let report = reconciler.reconcile().await.unwrap();
+        assert!(report.is_clean(), "{report:?}");
+        assert_eq!(gate.status(), RiskState::Active);
+    }
+}
@@ -0,0 +1,611 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use strategies::Token;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::{self, Instant};
+use tracing::warn;
+
+use crate::order_manager::now_ms;
+use crate::venue::{
+    OrderAck, OrderRequest, TopOfBook, Venue, VenueOrder, VenuePosition, VenueRejection, VenueTrade,
+};
+
+/// `incidents.kind` for circuit breaker transitions.
+pub const VENUE_BREAKER_INCIDENT: &str = "VENUE_BREAKER";
+
+/// Venue call, one circuit breaker each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    PlaceOrder,
+    CancelOrder,
+    CancelAll,
+    OpenOrders,
+    RecentTrades,
+    Positions,
+    TopOfBook,
+}
+
+impl Endpoint {
+    pub const ALL: [Endpoint; 7] = [
+        Endpoint::PlaceOrder,
+        Endpoint::CancelOrder,
+        Endpoint::CancelAll,
+        Endpoint::OpenOrders,
+        Endpoint::RecentTrades,
+        Endpoint::Positions,
+        Endpoint::TopOfBook,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Endpoint::PlaceOrder => "place_order",
+            Endpoint::CancelOrder => "cancel_order",
+            Endpoint::CancelAll => "cancel_all",
+            Endpoint::OpenOrders => "open_orders",
+            Endpoint::RecentTrades => "recent_trades",
+            Endpoint::Positions => "positions",
+            Endpoint::TopOfBook => "top_of_book",
+        }
+    }
+
+    /// Whether a failed call may be sent again. A placement that timed out
+    /// or got a 5xx may still have reached the book, and the CLOB signs
+    /// each attempt with a fresh salt, so resending it could open a second
+    /// live order.
+    pub fn retryable(self) -> bool {
+        self != Endpoint::PlaceOrder
+    }
+}
+
+/// Retries with exponential backoff; each delay is shortened by a random
+/// fraction of up to `jitter` so clients don't retry in lockstep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total attempts including the first; 1 disables retries.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (1-based).
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(1u32 << retry.saturating_sub(1).min(16));
+        let capped = exp.min(self.max_delay);
+        capped.mul_f64(1.0 - self.jitter.clamp(0.0, 1.0) * fastrand::f64())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreakerConfig {
+    /// Open after this many errors in a row; 0 disables.
+    pub consecutive_errors: u32,
+    /// Open when the error rate over `window` reaches this; 0 disables.
+    pub error_rate: f64,
+    pub window: Duration,
+    /// Calls needed in the window before the error rate counts.
+    pub min_calls: usize,
+    /// How long the breaker stays open before letting a probe through.
+    pub open_for: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            consecutive_errors: 5,
+            error_rate: 0.5,
+            window: Duration::from_secs(60),
+            min_calls: 20,
+            open_for: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BreakerState {
+    #[default]
+    Closed,
+    Open,
+    /// Cooled down; a single probe call goes out and closes or reopens it,
+    /// other calls fail fast until it does.
+    HalfOpen,
+}
+
+impl BreakerState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BreakerState::Closed => "closed",
+            BreakerState::Open => "open",
+            BreakerState::HalfOpen => "half_open",
+        }
+    }
+
+    /// Gauge value: 0 closed, 1 open, 2 half open.
+    pub fn level(self) -> i64 {
+        match self {
+            BreakerState::Closed => 0,
+            BreakerState::Open => 1,
+            BreakerState::HalfOpen => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreakerEvent {
+    pub endpoint: Endpoint,
+    pub from: BreakerState,
+    pub to: BreakerState,
+    pub reason: String,
+    pub ts_ms: i64,
+}
+
+impl BreakerEvent {
+    pub fn severity(&self) -> &'static str {
+        match self.to {
+            BreakerState::Open => "critical",
+            BreakerState::HalfOpen => "warning",
+            BreakerState::Closed => "info",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Breaker {
+    state: BreakerState,
+    consecutive: u32,
+    calls: VecDeque<(Instant, bool)>,
+    opened_at: Option<Instant>,
+    /// When the half-open probe in flight went out.
+    probe_sent_at: Option<Instant>,
+}
+
+impl Breaker {
+    /// Whether a call may go out now; may move Open to HalfOpen. While
+    /// half open only one probe is let through at a time; a probe whose
+    /// result never came back (its caller gave up) is replaced after
+    /// `open_for`.
+    fn admit(&mut self, config: &BreakerConfig, now: Instant) -> (bool, Option<BreakerState>) {
+        match self.state {
+            BreakerState::Closed => (true, None),
+            BreakerState::HalfOpen => {
+                let free = self
+                    .probe_sent_at
+                    .is_none_or(|at| now.duration_since(at) >= config.open_for);
+                if free {
+                    self.probe_sent_at = Some(now);
+                }
+                (free, None)
+            }
+            BreakerState::Open => {
+                let cooled = self
+                    .opened_at
+                    .is_some_and(|at| now.duration_since(at) >= config.open_for);
+                if cooled {
+                    let from = self.state;
+                    self.state = BreakerState::HalfOpen;
+                    self.probe_sent_at = Some(now);
+                    (true, Some(from))
+                } else {
+                    (false, None)
+                }
+            }
+        }
+    }
+
+    /// Record a call result; returns the previous state and a reason if the
+    /// state changed.
+    fn record(
+        &mut self,
+        config: &BreakerConfig,
+        ok: bool,
+        now: Instant,
+    ) -> Option<(BreakerState, String)> {
+        self.calls.push_back((now, ok));
+        while let Some((at, _)) = self.calls.front() {
+            if now.duration_since(*at) > config.window {
+                self.calls.pop_front();
+            } else {
+                break;
+            }
+        }
+        let from = self.state;
+        self.probe_sent_at = None;
+        if ok {
+            self.consecutive = 0;
+            if from == BreakerState::HalfOpen {
+                self.state = BreakerState::Closed;
+                self.opened_at = None;
+                return Some((from, "probe succeeded".to_string()));
+            }
+            return None;
+        }
+
+        self.consecutive += 1;
+        let reason = if from == BreakerState::HalfOpen {
+            Some("probe failed".to_string())
+        } else if config.consecutive_errors > 0 && self.consecutive >= config.consecutive_errors {
+            Some(format!("{} consecutive errors", self.consecutive))
+        } else {
+            let errors = self.calls.iter().filter(|(_, ok)| !ok).count();
+            let rate = errors as f64 / self.calls.len() as f64;
+            (config.error_rate > 0.0
+                && self.calls.len() >= config.min_calls
+                && rate >= config.error_rate)
+                .then(|| {
+                    format!(
+                        "error rate {:.0}% over {} calls",
+                        rate * 100.0,
+                        self.calls.len()
+                    )
+                })
+        };
+        match reason {
+            Some(reason) if from != BreakerState::Open => {
+                self.state = BreakerState::Open;
+                self.opened_at = Some(now);
+                self.calls.clear();
+                Some((from, reason))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Wraps a venue with retries and per-endpoint circuit breakers.
+///
+/// Cancels and queries are idempotent and retried; placements are not (see
+/// [`Endpoint::retryable`]): a failed placement comes back at once, and an
+/// order that landed anyway is picked up by the reconciler as an orphan.
+/// Rejections ([`VenueRejection`]) are returned at once and don't count as
+/// errors.
+/// While a breaker is open its endpoint fails fast; transitions go out on
+/// the channel returned by [`ResilientVenue::new`] so traderd can block
+/// placements in risk and record incidents and metrics.
+#[derive(Clone)]
+pub struct ResilientVenue<V> {
+    inner: V,
+    retry: RetryPolicy,
+    config: BreakerConfig,
+    breakers: Arc<Mutex<HashMap<Endpoint, Breaker>>>,
+    events: UnboundedSender<BreakerEvent>,
+}
+
+impl<V: Venue> ResilientVenue<V> {
+    pub fn new(
+        inner: V,
+        retry: RetryPolicy,
+        config: BreakerConfig,
+    ) -> (Self, UnboundedReceiver<BreakerEvent>) {
+        let (tx, rx) = unbounded_channel();
+        let venue = Self {
+            inner,
+            retry,
+            config,
+            breakers: Arc::new(Mutex::new(HashMap::new())),
+            events: tx,
+        };
+        (venue, rx)
+    }
+
+    pub fn inner(&self) -> &V {
+        &self.inner
+    }
+
+    pub fn breaker_state(&self, endpoint: Endpoint) -> BreakerState {
+        self.lock()
+            .get(&endpoint)
+            .map(|b| b.state)
+            .unwrap_or_default()
+    }
+
+    /// Endpoints whose breaker is not closed.
+    pub fn tripped(&self) -> Vec<Endpoint> {
+        Endpoint::ALL
+            .into_iter()
+            .filter(|e| self.breaker_state(*e) != BreakerState::Closed)
+            .collect()
+    }
+
+    async fn call<T, F, Fut>(&self, endpoint: Endpoint, op: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            if !self.admit(endpoint) {
+                return Err(anyhow!("circuit breaker open for {}", endpoint.as_str()));
+            }
+            let err = match op().await {
+                Ok(value) => {
+                    self.record(endpoint, true);
+                    return Ok(value);
+                }
+                Err(err) if VenueRejection::is_rejection(&err) => {
+                    self.record(endpoint, true);
+                    return Err(err);
+                }
+                Err(err) => err,
+            };
+            self.record(endpoint, false);
+            if !endpoint.retryable() || attempt >= self.retry.max_attempts {
+                return Err(err);
+            }
+            warn!(endpoint = endpoint.as_str(), attempt, error = ?err, "venue call failed; retrying");
+            time::sleep(self.retry.delay(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    fn admit(&self, endpoint: Endpoint) -> bool {
+        let (allowed, from) = {
+            let mut breakers = self.lock();
+            breakers
+                .entry(endpoint)
+                .or_default()
+                .admit(&self.config, Instant::now())
+        };
+        if let Some(from) = from {
+            self.emit(endpoint, from, BreakerState::HalfOpen, "cool-down elapsed");
+        }
+        allowed
+    }
+
+    fn record(&self, endpoint: Endpoint, ok: bool) {
+        let (changed, to) = {
+            let mut breakers = self.lock();
+            let breaker = breakers.entry(endpoint).or_default();
+            let changed = breaker.record(&self.config, ok, Instant::now());
+            (changed, breaker.state)
+        };
+        if let Some((from, reason)) = changed {
+            self.emit(endpoint, from, to, &reason);
+        }
+    }
+
+    fn emit(&self, endpoint: Endpoint, from: BreakerState, to: BreakerState, reason: &str) {
+        let _ = self.events.send(BreakerEvent {
+            endpoint,
+            from,
+            to,
+            reason: reason.to_string(),
+            ts_ms: now_ms(),
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Endpoint, Breaker>> {
+        self.breakers.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+impl<V: Venue> Venue for ResilientVenue<V> {
+    async fn place_order(&self, order: &OrderRequest) -> Result<OrderAck> {
+        self.call(Endpoint::PlaceOrder, || self.inner.place_order(order))
+            .await
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> Result<()> {
+        self.call(Endpoint::CancelOrder, || self.inner.cancel_order(order_id))
+            .await
+    }
+
+    async fn cancel_all(&self) -> Result<()> {
+        self.call(Endpoint::CancelAll, || self.inner.cancel_all())
+            .await
+    }
+
+    async fn open_orders(&self) -> Result<Vec<VenueOrder>> {
+        self.call(Endpoint::OpenOrders, || self.inner.open_orders())
+            .await
+    }
+
+    async fn recent_trades(&self, since_ms: i64) -> Result<Vec<VenueTrade>> {
+        self.call(Endpoint::RecentTrades, || {
+            self.inner.recent_trades(since_ms)
+        })
+        .await
+    }
+
+    async fn positions(&self) -> Result<Vec<VenuePosition>> {
+        self.call(Endpoint::Positions, || self.inner.positions())
+            .await
+    }
+
+    fn supports_gtd(&self) -> bool {
+        self.inner.supports_gtd()
+    }
+
+    async fn top_of_book(&self, market_id: i64, token: Token) -> Result<TopOfBook> {
+        self.call(Endpoint::TopOfBook, || {
+            self.inner.top_of_book(market_id, token)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockVenue;
+    use crate::venue::TimeInForce;
+    use strategies::Side;
+
+    fn fast_retry(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(2),
+            jitter: 0.5,
+        }
+    }
+
+    fn order() -> OrderRequest {
+        OrderRequest {
+            client_order_id: "c-1".into(),
+            market_id: 1,
+            side: Side::BuyYes,
+            price: 0.4,
+            qty: 1.0,
+            tif: TimeInForce::Gtc,
+            post_only: false,
+            expires_at_ms: None,
+        }
+    }
+
+    #[test]
+    fn backoff_grows_and_stays_under_cap() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+            jitter: 0.5,
+        };
+        for _ in 0..50 {
+            let first = policy.delay(1);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            assert!(policy.delay(4) <= Duration::from_millis(300));
+            assert!(policy.delay(4) >= Duration::from_millis(150));
+        }
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_retried() {
+        let mock = MockVenue::new();
+        let (venue, _events) =
+            ResilientVenue::new(mock.clone(), fast_retry(3), BreakerConfig::default());
+        mock.fail_next("open_orders", 2);
+        venue.open_orders().await.unwrap();
+
+        // rejections are final and don't count against the venue
+        let err = venue.cancel_order("nope").await.unwrap_err();
+        assert!(VenueRejection::is_rejection(&err));
+        assert_eq!(
+            venue.breaker_state(Endpoint::CancelOrder),
+            BreakerState::Closed
+        );
+    }
+
+    #[tokio::test]
+    async fn placements_are_never_resent() {
+        let mock = MockVenue::new();
+        let (venue, _events) =
+            ResilientVenue::new(mock.clone(), fast_retry(3), BreakerConfig::default());
+        mock.fail_next("place_order", 1);
+        assert!(venue.place_order(&order()).await.is_err());
+        venue.place_order(&order()).await.unwrap();
+        assert_eq!(mock.placed().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn breaker_opens_fails_fast_and_recovers_after_probe() {
+        let mock = MockVenue::new();
+        let config = BreakerConfig {
+            consecutive_errors: 3,
+            open_for: Duration::from_millis(30),
+            ..BreakerConfig::default()
+        };
+        let (venue, mut events) = ResilientVenue::new(mock.clone(), fast_retry(1), config);
+        mock.fail_next("open_orders", 3);
+        for _ in 0..3 {
+            assert!(venue.open_orders().await.is_err());
+        }
+        let opened = events.try_recv().unwrap();
+        assert_eq!(opened.endpoint, Endpoint::OpenOrders);
+        assert_eq!(opened.to, BreakerState::Open);
+        assert_eq!(venue.tripped(), vec![Endpoint::OpenOrders]);
+
+        // fails fast without reaching the venue while open
+        let err = venue.open_orders().await.unwrap_err();
+        assert!(err.to_string().contains("circuit breaker open"));
+        // other endpoints are unaffected
+        venue.positions().await.unwrap();
+
+        time::sleep(Duration::from_millis(40)).await;
+        venue.open_orders().await.unwrap();
+        assert_eq!(events.try_recv().unwrap().to, BreakerState::HalfOpen);
+        assert_eq!(events.try_recv().unwrap().to, BreakerState::Closed);
+        assert!(venue.tripped().is_empty());
+    }
+
+    #[test]
+    fn half_open_admits_one_probe_at_a_time() {
+        let config = BreakerConfig {
+            consecutive_errors: 1,
+            open_for: Duration::from_secs(10),
+            ..BreakerConfig::default()
+        };
+        let mut breaker = Breaker::default();
+        let start = Instant::now();
+        breaker.record(&config, false, start);
+        assert_eq!(breaker.state, BreakerState::Open);
+
+        let cooled = start + config.open_for;
+        assert_eq!(
+            breaker.admit(&config, cooled),
+            (true, Some(BreakerState::Open))
+        );
+        assert_eq!(breaker.state, BreakerState::HalfOpen);
+        // the probe is still out: everything else fails fast
+        assert_eq!(breaker.admit(&config, cooled), (false, None));
+        assert_eq!(
+            breaker.admit(&config, cooled + Duration::from_secs(1)),
+            (false, None)
+        );
+
+        // a failed probe reopens; the next cool-down lets one probe through
+        breaker.record(&config, false, cooled + Duration::from_secs(1));
+        assert_eq!(breaker.state, BreakerState::Open);
+        let cooled = cooled + Duration::from_secs(1) + config.open_for;
+        assert_eq!(
+            breaker.admit(&config, cooled),
+            (true, Some(BreakerState::Open))
+        );
+        assert_eq!(breaker.admit(&config, cooled), (false, None));
+
+        // a probe that never reports back is replaced after another cool-down
+        let abandoned = cooled + config.open_for;
+        assert_eq!(breaker.admit(&config, abandoned), (true, None));
+        breaker.record(&config, true, abandoned);
+        assert_eq!(breaker.state, BreakerState::Closed);
+        assert_eq!(breaker.admit(&config, abandoned), (true, None));
+        assert_eq!(breaker.admit(&config, abandoned), (true, None));
+    }
+
+    #[test]
+    fn error_rate_opens_without_a_streak() {
+        let config = BreakerConfig {
+            consecutive_errors: 0,
+            error_rate: 0.5,
+            min_calls: 10,
+            ..BreakerConfig::default()
+        };
+        let mut breaker = Breaker::default();
+        let now = Instant::now();
+        let mut opened = None;
+        for i in 0..10 {
+            if let Some(change) = breaker.record(&config, i % 2 == 0, now) {
+                opened = Some(change);
+            }
+        }
+        assert_eq!(breaker.state, BreakerState::Open);
+        assert!(opened.unwrap().1.contains("error rate"));
+    }
+}
@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use strategies::{Side, Token};
+
+use crate::order_manager::{ManagedOrder, NewOrder};
+
+/// `incidents.kind` for an order stopped or cleared by the self-trade check.
+pub const SELF_TRADE_INCIDENT: &str = "SELF_TRADE";
+
+/// What to do when an outgoing order would trade against one of our own
+/// resting orders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SelfTradePolicy {
+    /// Reject the outgoing order; the resting order stays.
+    #[default]
+    Block,
+    /// Cancel the resting orders and send the outgoing order once the venue
+    /// reports them done. If the venue refuses a cancel, or does not confirm
+    /// it within the manager's self-trade cancel timeout, the outgoing order
+    /// is rejected instead.
+    CancelResting,
+}
+
+impl SelfTradePolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SelfTradePolicy::Block => "block",
+            SelfTradePolicy::CancelResting => "cancel-resting",
+        }
+    }
+}
+
+/// Which side of the YES book an order sits on, and at what YES price.
+///
+/// Buying NO at `p` matches against buying YES at `1 - p` (the pair mints a
+/// complete set), so it is an ask on the YES book; selling NO is a bid.
+fn on_yes_book(side: Side, price: f64) -> (bool, f64) {
+    match side.token() {
+        Token::Yes => (side.is_buy(), price),
+        Token::No => (!side.is_buy(), 1.0 - price),
+    }
+}
+
+/// Whether an order at `price` would match a resting order of ours at
+/// `resting_price`, in the same token or across the complement.
+pub fn crosses(side: Side, price: f64, resting_side: Side, resting_price: f64) -> bool {
+    const EPS: f64 = 1e-9;
+    let (bid, p) = on_yes_book(side, price);
+    let (resting_bid, q) = on_yes_book(resting_side, resting_price);
+    match (bid, resting_bid) {
+        (true, false) => p + EPS >= q,
+        (false, true) => p <= q + EPS,
+        _ => false,
+    }
+}
+
+/// Our live orders in `new`'s market that it would trade against. Orders
+/// with a cancel in flight still count: the venue may not have pulled them.
+pub(crate) fn conflicts<'a>(
+    new: &NewOrder,
+    live: impl IntoIterator<Item = &'a ManagedOrder>,
+) -> Vec<String> {
+    let mut ids: Vec<String> = live
+        .into_iter()
+        .filter(|o| o.market_id == new.market_id && !o.status.is_final())
+        .filter(|o| o.remaining_qty() > 0.0)
+        .filter(|o| crosses(new.side, new.price, o.side, o.limit_price))
+        .map(|o| o.client_order_id.clone())
+        .collect();
+    ids.sort();
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockVenue;
+    use crate::order_manager::{OrderManager, OrderStatus};
+    use crate::venue::TimeInForce;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use storage::Store;
+
+    fn order(id: &str, side: Side, price: f64) -> NewOrder {
+        NewOrder {
+            client_order_id: id.into(),
+            approved_id: None,
+            intent_id: None,
+            strategy: "mm".into(),
+            market_id: 9,
+            side,
+            price,
+            qty: 10.0,
+            tif: TimeInForce::Gtc,
+            post_only: false,
+            expires_at_ms: None,
+            reduce_only: false,
+        }
+    }
+
+    async fn manager(policy: SelfTradePolicy) -> (OrderManager<MockVenue>, Store) {
+        let store = storage::init_sqlite("sqlite::memory:").await.unwrap();
+        store.insert_run("run-stp", None).await.unwrap();
+        let om = OrderManager::new(MockVenue::new(), "polymarket", "run-stp", store.clone())
+            .with_self_trade_policy(policy)
+            .with_self_trade_cancel_timeout(Duration::from_millis(50));
+        (om, store)
+    }
+
+    /// Stand-in for the user websocket task.
+    fn pump(om: &Arc<OrderManager<MockVenue>>) {
+        let om = om.clone();
+        tokio::spawn(async move {
+            loop {
+                for update in om.venue().take_user_updates() {
+                    om.apply_update(update).await;
+                }
+                tokio::time::sleep(Duration::from_millis(2)).await;
+            }
+        });
+    }
+
+    #[test]
+    fn crossing_covers_same_token_and_complement() {
+        assert!(crosses(Side::BuyYes, 0.55, Side::SellYes, 0.55));
+        assert!(!crosses(Side::BuyYes, 0.54, Side::SellYes, 0.55));
+        assert!(crosses(Side::SellNo, 0.40, Side::BuyNo, 0.45));
+        // buy YES 0.60 + buy NO 0.45 >= 1 mints against ourselves
+        assert!(crosses(Side::BuyYes, 0.60, Side::BuyNo, 0.45));
+        assert!(!crosses(Side::BuyYes, 0.50, Side::BuyNo, 0.45));
+        // sell YES 0.50 + sell NO 0.45 <= 1 merges against ourselves
+        assert!(crosses(Side::SellYes, 0.50, Side::SellNo, 0.45));
+        assert!(!crosses(Side::SellYes, 0.60, Side::SellNo, 0.45));
+        assert!(!crosses(Side::BuyYes, 0.60, Side::BuyYes, 0.70));
+    }
+
+    #[tokio::test]
+    async fn block_rejects_the_outgoing_order() {
+        let (om, store) = manager(SelfTradePolicy::Block).await;
+        om.submit(order("ask", Side::SellYes, 0.52)).await.unwrap();
+        om.submit(order("bid", Side::BuyYes, 0.48)).await.unwrap();
+
+        let crossing = om.submit(order("cross", Side::BuyNo, 0.53)).await.unwrap();
+        assert_eq!(crossing.status, OrderStatus::Rejected);
+        assert!(crossing.notes.unwrap().contains("bid"));
+        assert!(crossing.order_id.is_none());
+        assert!(om
+            .venue()
+            .placed()
+            .iter()
+            .all(|o| o.client_order_id != "cross"));
+        assert_eq!(om.get("bid").await.unwrap().status, OrderStatus::Acked);
+        let row = store.fetch_order("cross").await.unwrap().unwrap();
+        assert_eq!(row.status, "Rejected");
+    }
+
+    #[tokio::test]
+    async fn cancel_resting_waits_for_the_venue_to_confirm() {
+        let (om, _store) = manager(SelfTradePolicy::CancelResting).await;
+        let om = Arc::new(om);
+        pump(&om);
+        om.submit(order("ask", Side::SellYes, 0.52)).await.unwrap();
+        om.submit(NewOrder {
+            market_id: 10,
+            ..order("other-market", Side::SellYes, 0.50)
+        })
+        .await
+        .unwrap();
+
+        let crossing = om.submit(order("cross", Side::BuyYes, 0.55)).await.unwrap();
+        assert_eq!(crossing.status, OrderStatus::Acked);
+        // the resting order was gone before the crossing one went out
+        let ask = om.get("ask").await.unwrap();
+        assert_eq!(ask.status, OrderStatus::Canceled);
+        assert!(ask.ts_final_ms.unwrap() <= crossing.ts_submitted_ms);
+        assert_eq!(
+            om.get("other-market").await.unwrap().status,
+            OrderStatus::Acked
+        );
+    }
+
+    #[tokio::test]
+    async fn cancel_still_in_flight_rejects_the_outgoing_order() {
+        let (om, _store) = manager(SelfTradePolicy::CancelResting).await;
+        om.submit(order("ask", Side::SellYes, 0.52)).await.unwrap();
+
+        // the venue accepts the cancel but never reports it done
+        let crossing = om.submit(order("cross", Side::BuyYes, 0.55)).await.unwrap();
+        assert_eq!(crossing.status, OrderStatus::Rejected);
+        assert!(crossing.notes.unwrap().contains("ask"));
+        assert!(om
+            .venue()
+            .placed()
+            .iter()
+            .all(|o| o.client_order_id != "cross"));
+        assert!(om.get("ask").await.unwrap().cancel_in_flight());
+    }
+
+    #[tokio::test]
+    async fn unconfirmed_cancel_rejects_the_outgoing_order() {
+        let (om, _store) = manager(SelfTradePolicy::CancelResting).await;
+        om.submit(order("ask", Side::SellYes, 0.52)).await.unwrap();
+        om.venue().fail_next("cancel_order", 1);
+
+        let crossing = om.submit(order("cross", Side::BuyYes, 0.55)).await.unwrap();
+        assert_eq!(crossing.status, OrderStatus::Rejected);
+        assert_eq!(om.get("ask").await.unwrap().status, OrderStatus::Acked);
+    }
+}
@@ -0,0 +1,134 @@
+use std::future::Future;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+pub use state::{BookLevel, TopOfBook};
+use strategies::{Side, Token};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeInForce {
+    /// Rests on the book until filled or canceled.
+    Gtc,
+    /// Rests until `OrderRequest::expires_at_ms`, then the venue cancels it.
+    Gtd,
+    /// Fill what crosses immediately, cancel the remainder.
+    Fak,
+    /// Fill the whole size immediately or not at all.
+    Fok,
+}
+
+impl TimeInForce {
+    /// Whether an unfilled remainder stays on the book.
+    pub fn rests(self) -> bool {
+        matches!(self, TimeInForce::Gtc | TimeInForce::Gtd)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderRequest {
+    pub client_order_id: String,
+    pub market_id: i64,
+    pub side: Side,
+    pub price: f64,
+    pub qty: f64,
+    pub tif: TimeInForce,
+    /// Reject instead of taking liquidity.
+    #[serde(default)]
+    pub post_only: bool,
+    /// When the order should stop working; the venue enforces it for
+    /// [`TimeInForce::Gtd`], execution's local timer otherwise.
+    #[serde(default)]
+    pub expires_at_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderAck {
+    pub order_id: String,
+    pub ts_ms: i64,
+}
+
+/// An order resting on the venue, as reported by an open-orders query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VenueOrder {
+    pub order_id: String,
+    pub client_order_id: Option<String>,
+    pub market_id: i64,
+    pub side: Side,
+    pub price: f64,
+    pub qty: f64,
+    pub filled_qty: f64,
+}
+
+/// A trade (fill) of one of our orders, as reported by a trade history query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VenueTrade {
+    pub fill_id: String,
+    pub order_id: String,
+    pub client_order_id: Option<String>,
+    pub market_id: i64,
+    pub side: Side,
+    pub price: f64,
+    pub qty: f64,
+    /// Fee charged on the fill, 0 when the venue doesn't report one.
+    #[serde(default)]
+    pub fee_usd: f64,
+    pub ts_ms: i64,
+}
+
+/// Token balance held on the venue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VenuePosition {
+    pub market_id: i64,
+    pub token: Token,
+    pub qty: f64,
+}
+
+/// The venue refused a request on its merits (unknown order, invalid
+/// price, ...), as opposed to failing to serve it. Not worth retrying and
+/// not a sign the venue is unhealthy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VenueRejection(pub String);
+
+impl std::fmt::Display for VenueRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for VenueRejection {}
+
+impl VenueRejection {
+    pub fn is_rejection(err: &anyhow::Error) -> bool {
+        err.downcast_ref::<VenueRejection>().is_some()
+    }
+}
+
+/// Venue operations the execution layer relies on.
+///
+/// Implemented by the Polymarket adapter and by in-process venues used for
+/// shadow runs and tests.
+pub trait Venue: Send + Sync {
+    fn place_order(&self, order: &OrderRequest) -> impl Future<Output = Result<OrderAck>> + Send;
+
+    fn cancel_order(&self, order_id: &str) -> impl Future<Output = Result<()>> + Send;
+
+    fn cancel_all(&self) -> impl Future<Output = Result<()>> + Send;
+
+    fn open_orders(&self) -> impl Future<Output = Result<Vec<VenueOrder>>> + Send;
+
+    /// Our trades since `since_ms`, oldest first.
+    fn recent_trades(&self, since_ms: i64) -> impl Future<Output = Result<Vec<VenueTrade>>> + Send;
+
+    fn positions(&self) -> impl Future<Output = Result<Vec<VenuePosition>>> + Send;
+
+    fn top_of_book(
+        &self,
+        market_id: i64,
+        token: Token,
+    ) -> impl Future<Output = Result<TopOfBook>> + Send;
+
+    /// Whether the venue expires [`TimeInForce::Gtd`] orders itself.
+    fn supports_gtd(&self) -> bool {
+        false
+    }
+}
@@ -3,7 +3,10 @@ use hyper::{
     service::{make_service_fn, service_fn},
     Body, Request, Response, Server,
 };
-use prometheus::{Counter, Encoder, Registry, TextEncoder};
+use prometheus::{
+    Counter, Encoder, GaugeVec, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
 use std::net::SocketAddr;
 use tracing::info;
 
@@ -11,6 +14,19 @@ use tracing::info;
 pub struct MetricsHandle {
     registry: Registry,
     heartbeat_counter: Counter,
+    risk_state: IntGauge,
+    risk_transitions: IntCounterVec,
+    venue_breaker_state: IntGaugeVec,
+    venue_breaker_trips: IntCounterVec,
+    quote_msgs_per_sec: GaugeVec,
+    quote_order_to_trade: GaugeVec,
+    quote_rate_breaches: IntCounterVec,
+    storage_queue_depth: IntGauge,
+    storage_flush_seconds: Histogram,
+    storage_dropped: IntCounter,
+    storage_write_failures: IntCounter,
+    retention_archived_rows: IntCounter,
+    retention_deleted_rows: IntCounter,
 }
 
 impl Default for MetricsHandle {
@@ -29,9 +45,158 @@ impl MetricsHandle {
             .register(Box::new(heartbeat_counter.clone()))
             .expect("heartbeat counter should register");
 
+        let risk_state = IntGauge::new(
+            "risk_state",
+            "Global risk state (0=active, 1=reduce_only, 2=paused, 3=halted, 4=kill_switched)",
+        )
+        .expect("risk state gauge should be valid");
+        registry
+            .register(Box::new(risk_state.clone()))
+            .expect("risk state gauge should register");
+
+        let risk_transitions = IntCounterVec::new(
+            Opts::new(
+                "risk_transitions_total",
+                "Risk state transitions by scope and target state",
+            ),
+            &["scope", "to"],
+        )
+        .expect("risk transitions counter should be valid");
+        registry
+            .register(Box::new(risk_transitions.clone()))
+            .expect("risk transitions counter should register");
+
+        let venue_breaker_state = IntGaugeVec::new(
+            Opts::new(
+                "venue_breaker_state",
+                "Venue circuit breaker state by endpoint (0=closed, 1=open, 2=half_open)",
+            ),
+            &["endpoint"],
+        )
+        .expect("venue breaker gauge should be valid");
+        registry
+            .register(Box::new(venue_breaker_state.clone()))
+            .expect("venue breaker gauge should register");
+
+        let venue_breaker_trips = IntCounterVec::new(
+            Opts::new(
+                "venue_breaker_trips_total",
+                "Times a venue circuit breaker opened, by endpoint",
+            ),
+            &["endpoint"],
+        )
+        .expect("venue breaker trips counter should be valid");
+        registry
+            .register(Box::new(venue_breaker_trips.clone()))
+            .expect("venue breaker trips counter should register");
+
+        let quote_msgs_per_sec = GaugeVec::new(
+            Opts::new(
+                "quote_msgs_per_sec",
+                "Order messages (places and cancels) per second over the rate window, by scope",
+            ),
+            &["scope", "id"],
+        )
+        .expect("quote rate gauge should be valid");
+        registry
+            .register(Box::new(quote_msgs_per_sec.clone()))
+            .expect("quote rate gauge should register");
+
+        let quote_order_to_trade = GaugeVec::new(
+            Opts::new(
+                "quote_order_to_trade_ratio",
+                "Order messages per fill over the ratio window, by scope",
+            ),
+            &["scope", "id"],
+        )
+        .expect("order-to-trade gauge should be valid");
+        registry
+            .register(Box::new(quote_order_to_trade.clone()))
+            .expect("order-to-trade gauge should register");
+
+        let quote_rate_breaches = IntCounterVec::new(
+            Opts::new(
+                "quote_rate_breaches_total",
+                "Quote-rate limit breaches by scope and limit",
+            ),
+            &["scope", "limit"],
+        )
+        .expect("quote rate breaches counter should be valid");
+        registry
+            .register(Box::new(quote_rate_breaches.clone()))
+            .expect("quote rate breaches counter should register");
+
+        let storage_queue_depth = IntGauge::new(
+            "storage_queue_depth",
+            "Rows waiting in the storage writer queue",
+        )
+        .expect("storage queue gauge should be valid");
+        registry
+            .register(Box::new(storage_queue_depth.clone()))
+            .expect("storage queue gauge should register");
+
+        let storage_flush_seconds = Histogram::with_opts(
+            HistogramOpts::new("storage_flush_seconds", "Time to write one storage batch").buckets(
+                vec![0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5],
+            ),
+        )
+        .expect("storage flush histogram should be valid");
+        registry
+            .register(Box::new(storage_flush_seconds.clone()))
+            .expect("storage flush histogram should register");
+
+        let storage_dropped = IntCounter::new(
+            "storage_dropped_total",
+            "Low-priority rows dropped by the storage writer under load",
+        )
+        .expect("storage dropped counter should be valid");
+        registry
+            .register(Box::new(storage_dropped.clone()))
+            .expect("storage dropped counter should register");
+
+        let storage_write_failures = IntCounter::new(
+            "storage_write_failures_total",
+            "Rows the storage writer failed to insert",
+        )
+        .expect("storage failures counter should be valid");
+        registry
+            .register(Box::new(storage_write_failures.clone()))
+            .expect("storage failures counter should register");
+
+        let retention_archived_rows = IntCounter::new(
+            "retention_archived_rows_total",
+            "raw_events rows exported to the archive",
+        )
+        .expect("retention archived counter should be valid");
+        registry
+            .register(Box::new(retention_archived_rows.clone()))
+            .expect("retention archived counter should register");
+
+        let retention_deleted_rows = IntCounter::new(
+            "retention_deleted_rows_total",
+            "raw_events rows deleted after archiving",
+        )
+        .expect("retention deleted counter should be valid");
+        registry
+            .register(Box::new(retention_deleted_rows.clone()))
+            .expect("retention deleted counter should register");
+
         Self {
             registry,
             heartbeat_counter,
+            risk_state,
+            risk_transitions,
+            venue_breaker_state,
+            venue_breaker_trips,
+            quote_msgs_per_sec,
+            quote_order_to_trade,
+            quote_rate_breaches,
+            storage_queue_depth,
+            storage_flush_seconds,
+            storage_dropped,
+            storage_write_failures,
+            retention_archived_rows,
+            retention_deleted_rows,
         }
     }
 
@@ -43,6 +208,58 @@ impl MetricsHandle {
         self.heartbeat_counter.clone()
     }
 
+    pub fn risk_state(&self) -> IntGauge {
+        self.risk_state.clone()
+    }
+
+    pub fn risk_transitions(&self) -> IntCounterVec {
+        self.risk_transitions.clone()
+    }
+
+    pub fn venue_breaker_state(&self) -> IntGaugeVec {
+        self.venue_breaker_state.clone()
+    }
+
+    pub fn venue_breaker_trips(&self) -> IntCounterVec {
+        self.venue_breaker_trips.clone()
+    }
+
+    pub fn quote_msgs_per_sec(&self) -> GaugeVec {
+        self.quote_msgs_per_sec.clone()
+    }
+
+    pub fn quote_order_to_trade(&self) -> GaugeVec {
+        self.quote_order_to_trade.clone()
+    }
+
+    pub fn quote_rate_breaches(&self) -> IntCounterVec {
+        self.quote_rate_breaches.clone()
+    }
+
+    pub fn storage_queue_depth(&self) -> IntGauge {
+        self.storage_queue_depth.clone()
+    }
+
+    pub fn storage_flush_seconds(&self) -> Histogram {
+        self.storage_flush_seconds.clone()
+    }
+
+    pub fn storage_dropped(&self) -> IntCounter {
+        self.storage_dropped.clone()
+    }
+
+    pub fn storage_write_failures(&self) -> IntCounter {
+        self.storage_write_failures.clone()
+    }
+
+    pub fn retention_archived_rows(&self) -> IntCounter {
+        self.retention_archived_rows.clone()
+    }
+
+    pub fn retention_deleted_rows(&self) -> IntCounter {
+        self.retention_deleted_rows.clone()
+    }
+
     pub async fn serve(self, addr: SocketAddr) -> Result<()> {
         let registry = self.registry.clone();
         let make_svc = make_service_fn(move |_| {
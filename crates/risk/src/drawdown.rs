@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+const DAY_MS: i64 = 86_400_000;
+
+/// Loss thresholds that drive automatic risk transitions.
+///
+/// A limit of zero (or less) disables that particular check. Every check is
+/// turned into a utilization ratio (loss / limit); the gate enters ReduceOnly
+/// at `reduce_only_fraction` and Halted at 1.0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawdownLimits {
+    /// Max intraday drop from peak equity, in USD.
+    pub max_drawdown_usd: f64,
+    /// Max intraday drop from peak equity, as a fraction of the peak (0.05 = 5%).
+    pub max_drawdown_pct: f64,
+    /// Max realized loss since the start of the UTC day, in USD.
+    pub max_daily_loss_usd: f64,
+    /// Default max daily realized loss per strategy, in USD.
+    pub max_strategy_loss_usd: f64,
+    /// Per-strategy overrides for `max_strategy_loss_usd`.
+    pub strategy_loss_overrides: HashMap<String, f64>,
+    /// Utilization at which new exposure is blocked (ReduceOnly).
+    pub reduce_only_fraction: f64,
+    /// How far utilization must fall below `reduce_only_fraction` before
+    /// ReduceOnly clears back to Active.
+    pub hysteresis_fraction: f64,
+}
+
+impl Default for DrawdownLimits {
+    fn default() -> Self {
+        Self {
+            max_drawdown_usd: 0.0,
+            max_drawdown_pct: 0.0,
+            max_daily_loss_usd: 0.0,
+            max_strategy_loss_usd: 0.0,
+            strategy_loss_overrides: HashMap::new(),
+            reduce_only_fraction: 0.75,
+            hysteresis_fraction: 0.15,
+        }
+    }
+}
+
+impl DrawdownLimits {
+    fn strategy_limit(&self, strategy: &str) -> f64 {
+        self.strategy_loss_overrides
+            .get(strategy)
+            .copied()
+            .unwrap_or(self.max_strategy_loss_usd)
+    }
+
+    pub(crate) fn level(&self, utilization: f64, currently_reduced: bool) -> BreachLevel {
+        if utilization >= 1.0 {
+            BreachLevel::Hard
+        } else if utilization >= self.reduce_only_fraction {
+            BreachLevel::Soft
+        } else if currently_reduced
+            && utilization >= self.reduce_only_fraction - self.hysteresis_fraction
+        {
+            // inside the hysteresis band: keep the reduced state
+            BreachLevel::Soft
+        } else {
+            BreachLevel::Clear
+        }
+    }
+}
+
+/// Point-in-time PnL view fed to the risk gate.
+///
+/// Realized figures are cumulative for the run; the tracker rebases them at
+/// each UTC day boundary to derive daily losses.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PnlSample {
+    pub ts_ms: i64,
+    pub equity_usd: f64,
+    pub realized_pnl_usd: f64,
+    pub strategy_realized_pnl_usd: HashMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum BreachLevel {
+    Clear,
+    Soft,
+    Hard,
+}
+
+/// Result of evaluating a sample: the global utilization and one per strategy.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Utilization {
+    pub global: f64,
+    pub global_reason: String,
+    pub strategies: Vec<(String, f64)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DrawdownTracker {
+    day: Option<i64>,
+    peak_equity_usd: f64,
+    last_equity_usd: Option<f64>,
+    day_start_realized_usd: f64,
+    day_start_strategy_usd: HashMap<String, f64>,
+}
+
+impl DrawdownTracker {
+    pub fn observe(&mut self, limits: &DrawdownLimits, sample: &PnlSample) -> Utilization {
+        let day = sample.ts_ms.div_euclid(DAY_MS);
+        if self.day != Some(day) {
+            self.day = Some(day);
+            self.peak_equity_usd = sample.equity_usd;
+            self.day_start_realized_usd = sample.realized_pnl_usd;
+            self.day_start_strategy_usd = sample.strategy_realized_pnl_usd.clone();
+        }
+        if sample.equity_usd > self.peak_equity_usd {
+            self.peak_equity_usd = sample.equity_usd;
+        }
+        self.last_equity_usd = Some(sample.equity_usd);
+
+        let drawdown_usd = (self.peak_equity_usd - sample.equity_usd).max(0.0);
+        let drawdown_pct = if self.peak_equity_usd > 0.0 {
+            drawdown_usd / self.peak_equity_usd
+        } else {
+            0.0
+        };
+        let daily_loss_usd = (self.day_start_realized_usd - sample.realized_pnl_usd).max(0.0);
+
+        let mut util = Utilization::default();
+        for (name, value, limit) in [
+            ("drawdown_usd", drawdown_usd, limits.max_drawdown_usd),
+            ("drawdown_pct", drawdown_pct, limits.max_drawdown_pct),
+            ("daily_loss_usd", daily_loss_usd, limits.max_daily_loss_usd),
+        ] {
+            let ratio = ratio(value, limit);
+            if ratio > util.global {
+                util.global = ratio;
+                util.global_reason = format!("{name}={value:.4} limit={limit:.4}");
+            }
+        }
+
+        for (strategy, realized) in &sample.strategy_realized_pnl_usd {
+            let start = *self
+                .day_start_strategy_usd
+                .entry(strategy.clone())
+                .or_insert(*realized);
+            let loss = (start - realized).max(0.0);
+            util.strategies.push((
+                strategy.clone(),
+                ratio(loss, limits.strategy_limit(strategy)),
+            ));
+        }
+        util.strategies.sort_by(|a, b| a.0.cmp(&b.0));
+
+        util
+    }
+
+    /// Operator acknowledged the current drawdown: measure from here on.
+    pub fn rebase_peak(&mut self) {
+        if let Some(equity) = self.last_equity_usd {
+            self.peak_equity_usd = equity;
+        }
+    }
+}
+
+fn ratio(value: f64, limit: f64) -> f64 {
+    if limit > 0.0 {
+        value / limit
+    } else {
+        0.0
+    }
+}
@@ -1,37 +1,527 @@
+use std::collections::{BTreeSet, HashMap};
 use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+mod drawdown;
+mod price_band;
+mod quote_rate;
+mod staleness;
+
+use drawdown::{BreachLevel, DrawdownTracker};
+pub use drawdown::{DrawdownLimits, PnlSample};
+pub use price_band::{
+    price_band_payload, PriceBandLimits, PriceBands, PriceViolation, ReferencePrice,
+    PRICE_BAND_INCIDENT,
+};
+pub use quote_rate::{
+    QuoteGovernor, QuoteRateEpisode, QuoteRateLimits, QuoteRateStats, QuoteScope, QuoteViolation,
+    QUOTE_RATE_INCIDENT,
+};
+pub use staleness::{
+    StaleEpisode, StaleReason, StalenessGuard, StalenessLimits, STALE_STATE_INCIDENT,
+};
+
+/// Incident kind recorded for every risk state transition.
+pub const RISK_HALT_INCIDENT: &str = "RISK_HALT";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum RiskState {
     #[default]
     Active,
+    /// Only orders that reduce exposure (cancels, flattens) are allowed.
+    ReduceOnly,
+    /// Operator pause; cleared by `resume`.
     Paused,
+    /// Hard loss limit breached; only an operator `resume` clears it.
+    Halted,
+    /// Kill switch engaged; nothing but cancel/flatten until explicitly reset.
+    KillSwitched,
+}
+
+impl RiskState {
+    /// Numeric severity used for the `risk_state` gauge.
+    pub fn level(self) -> i64 {
+        match self {
+            RiskState::Active => 0,
+            RiskState::ReduceOnly => 1,
+            RiskState::Paused => 2,
+            RiskState::Halted => 3,
+            RiskState::KillSwitched => 4,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RiskState::Active => "active",
+            RiskState::ReduceOnly => "reduce_only",
+            RiskState::Paused => "paused",
+            RiskState::Halted => "halted",
+            RiskState::KillSwitched => "kill_switched",
+        }
+    }
+
+    /// Whether orders that add exposure may be placed.
+    pub fn allows_new_exposure(self) -> bool {
+        matches!(self, RiskState::Active)
+    }
+
+    /// Whether orders that only reduce exposure may be placed.
+    pub fn allows_reducing(self) -> bool {
+        !matches!(self, RiskState::Paused)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RiskScope {
+    Global,
+    Strategy(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RiskTransition {
+    pub ts_ms: i64,
+    pub scope: RiskScope,
+    pub from: RiskState,
+    pub to: RiskState,
+    pub reason: String,
+}
+
+impl RiskTransition {
+    /// Severity string for the incident row.
+    pub fn severity(&self) -> &'static str {
+        if self.to.level() > self.from.level() {
+            match self.to {
+                RiskState::Halted | RiskState::KillSwitched => "critical",
+                _ => "warning",
+            }
+        } else {
+            "info"
+        }
+    }
+}
+
+#[derive(Default)]
+struct GateInner {
+    state: RiskState,
+    limits: DrawdownLimits,
+    tracker: DrawdownTracker,
+    strategies: HashMap<String, RiskState>,
+    /// ReduceOnly imposed by a component rather than by loss limits; only an
+    /// operator resume lifts it.
+    forced_reduce_only: bool,
+    /// Components currently blocking new placements (e.g. an open venue
+    /// circuit breaker). Independent of the risk state.
+    placement_blocks: BTreeSet<String>,
 }
 
 #[derive(Clone, Default)]
 pub struct RiskGate {
-    state: Arc<RwLock<RiskState>>,
+    inner: Arc<RwLock<GateInner>>,
+    transitions: Option<UnboundedSender<RiskTransition>>,
 }
 
 impl RiskGate {
     pub fn new() -> Self {
-        Self {
-            state: Arc::new(RwLock::new(RiskState::Active)),
-        }
+        Self::default()
+    }
+
+    /// Gate with loss limits; every transition is also sent on the returned
+    /// channel so the caller can record incidents and metrics.
+    pub fn with_limits(limits: DrawdownLimits) -> (Self, UnboundedReceiver<RiskTransition>) {
+        let (tx, rx) = unbounded_channel();
+        let gate = Self {
+            inner: Arc::new(RwLock::new(GateInner {
+                limits,
+                ..GateInner::default()
+            })),
+            transitions: Some(tx),
+        };
+        (gate, rx)
     }
 
     pub fn pause(&self) {
-        if let Ok(mut guard) = self.state.write() {
-            *guard = RiskState::Paused;
-        }
+        self.operator_set(RiskState::Paused, "operator pause");
     }
 
+    /// Operator resume. Clears Paused, ReduceOnly, Halted and any per-strategy
+    /// halts and rebases the intraday drawdown peak; the kill switch is left
+    /// engaged and must be reset separately.
     pub fn resume(&self) {
-        if let Ok(mut guard) = self.state.write() {
-            *guard = RiskState::Active;
+        let mut emitted = Vec::new();
+        if let Ok(mut guard) = self.inner.write() {
+            if guard.state == RiskState::KillSwitched {
+                return;
+            }
+            guard.tracker.rebase_peak();
+            guard.forced_reduce_only = false;
+            let halted: Vec<(String, RiskState)> = guard
+                .strategies
+                .drain()
+                .filter(|(_, s)| *s != RiskState::Active)
+                .collect();
+            for (name, from) in halted {
+                emitted.push(transition(
+                    RiskScope::Strategy(name),
+                    from,
+                    RiskState::Active,
+                    "operator resume",
+                ));
+            }
+            let from = guard.state;
+            if from != RiskState::Active {
+                guard.state = RiskState::Active;
+                emitted.push(transition(
+                    RiskScope::Global,
+                    from,
+                    RiskState::Active,
+                    "operator resume",
+                ));
+            }
+        }
+        self.emit(emitted);
+    }
+
+    /// Force ReduceOnly (e.g. on position drift). Only tightens an Active
+    /// gate and holds until an operator resume.
+    pub fn force_reduce_only(&self, reason: &str) {
+        let mut emitted = Vec::new();
+        if let Ok(mut guard) = self.inner.write() {
+            guard.forced_reduce_only = true;
+            if guard.state == RiskState::Active {
+                guard.state = RiskState::ReduceOnly;
+                emitted.push(transition(
+                    RiskScope::Global,
+                    RiskState::Active,
+                    RiskState::ReduceOnly,
+                    reason,
+                ));
+            }
+        }
+        self.emit(emitted);
+    }
+
+    pub fn kill_switch(&self, reason: &str) {
+        self.operator_set(RiskState::KillSwitched, reason);
+    }
+
+    /// Release the kill switch into Paused; trading still needs a `resume`.
+    pub fn reset_kill_switch(&self) {
+        let mut emitted = Vec::new();
+        if let Ok(mut guard) = self.inner.write() {
+            if guard.state == RiskState::KillSwitched {
+                guard.state = RiskState::Paused;
+                emitted.push(transition(
+                    RiskScope::Global,
+                    RiskState::KillSwitched,
+                    RiskState::Paused,
+                    "operator kill switch reset",
+                ));
+            }
         }
+        self.emit(emitted);
     }
 
     pub fn status(&self) -> RiskState {
-        self.state.read().map(|g| *g).unwrap_or(RiskState::Paused)
+        self.inner
+            .read()
+            .map(|g| g.state)
+            .unwrap_or(RiskState::Paused)
+    }
+
+    pub fn strategy_status(&self, strategy: &str) -> RiskState {
+        self.inner
+            .read()
+            .map(|g| g.strategies.get(strategy).copied().unwrap_or_default())
+            .unwrap_or(RiskState::Paused)
+    }
+
+    /// Block new placements until `unblock_placements(source)`.
+    pub fn block_placements(&self, source: &str) {
+        if let Ok(mut guard) = self.inner.write() {
+            guard.placement_blocks.insert(source.to_string());
+        }
+    }
+
+    pub fn unblock_placements(&self, source: &str) {
+        if let Ok(mut guard) = self.inner.write() {
+            guard.placement_blocks.remove(source);
+        }
+    }
+
+    pub fn placement_blocks(&self) -> Vec<String> {
+        self.inner
+            .read()
+            .map(|g| g.placement_blocks.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether `strategy` may place an order; `reduces_exposure` marks
+    /// cancels/flattens that are still allowed in ReduceOnly and Halted,
+    /// and while placements are blocked.
+    pub fn allows_place(&self, strategy: &str, reduces_exposure: bool) -> bool {
+        let Ok(guard) = self.inner.read() else {
+            return false;
+        };
+        if !reduces_exposure && !guard.placement_blocks.is_empty() {
+            return false;
+        }
+        let strategy_state = guard.strategies.get(strategy).copied().unwrap_or_default();
+        if reduces_exposure {
+            guard.state.allows_reducing() && strategy_state.allows_reducing()
+        } else {
+            guard.state.allows_new_exposure() && strategy_state.allows_new_exposure()
+        }
+    }
+
+    /// Feed a PnL sample and apply any automatic transitions it triggers.
+    pub fn observe_pnl(&self, sample: &PnlSample) -> Vec<RiskTransition> {
+        let mut emitted = Vec::new();
+        if let Ok(mut guard) = self.inner.write() {
+            let inner = &mut *guard;
+            let util = inner.tracker.observe(&inner.limits, sample);
+
+            let from = inner.state;
+            let level = inner
+                .limits
+                .level(util.global, from == RiskState::ReduceOnly);
+            let to = match (from, level) {
+                (RiskState::KillSwitched | RiskState::Halted, _) => from,
+                (_, BreachLevel::Hard) => RiskState::Halted,
+                (RiskState::Active, BreachLevel::Soft) => RiskState::ReduceOnly,
+                (RiskState::ReduceOnly, BreachLevel::Clear) if !inner.forced_reduce_only => {
+                    RiskState::Active
+                }
+                _ => from,
+            };
+            if to != from {
+                inner.state = to;
+                emitted.push(transition_at(
+                    sample.ts_ms,
+                    RiskScope::Global,
+                    from,
+                    to,
+                    &util.global_reason,
+                ));
+            }
+
+            for (strategy, ratio) in util.strategies {
+                let from = inner.strategies.get(&strategy).copied().unwrap_or_default();
+                let level = inner.limits.level(ratio, from == RiskState::ReduceOnly);
+                let to = match (from, level) {
+                    (RiskState::Halted, _) => from,
+                    (_, BreachLevel::Hard) => RiskState::Halted,
+                    (RiskState::Active, BreachLevel::Soft) => RiskState::ReduceOnly,
+                    (RiskState::ReduceOnly, BreachLevel::Clear) => RiskState::Active,
+                    _ => from,
+                };
+                if to != from {
+                    inner.strategies.insert(strategy.clone(), to);
+                    emitted.push(transition_at(
+                        sample.ts_ms,
+                        RiskScope::Strategy(strategy),
+                        from,
+                        to,
+                        &format!("strategy_loss utilization={ratio:.4}"),
+                    ));
+                }
+            }
+        }
+        self.emit(emitted.clone());
+        emitted
+    }
+
+    fn operator_set(&self, to: RiskState, reason: &str) {
+        let mut emitted = Vec::new();
+        if let Ok(mut guard) = self.inner.write() {
+            let from = guard.state;
+            if from != to && from != RiskState::KillSwitched {
+                guard.state = to;
+                emitted.push(transition(RiskScope::Global, from, to, reason));
+            }
+        }
+        self.emit(emitted);
+    }
+
+    fn emit(&self, transitions: Vec<RiskTransition>) {
+        if let Some(tx) = &self.transitions {
+            for t in transitions {
+                let _ = tx.send(t);
+            }
+        }
+    }
+}
+
+fn transition(scope: RiskScope, from: RiskState, to: RiskState, reason: &str) -> RiskTransition {
+    transition_at(now_ms(), scope, from, to, reason)
+}
+
+fn transition_at(
+    ts_ms: i64,
+    scope: RiskScope,
+    from: RiskState,
+    to: RiskState,
+    reason: &str,
+) -> RiskTransition {
+    RiskTransition {
+        ts_ms,
+        scope,
+        from,
+        to,
+        reason: reason.to_string(),
+    }
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY0: i64 = 1_700_000_000_000;
+
+    fn limits() -> DrawdownLimits {
+        DrawdownLimits {
+            max_drawdown_usd: 100.0,
+            max_daily_loss_usd: 50.0,
+            max_strategy_loss_usd: 20.0,
+            ..DrawdownLimits::default()
+        }
+    }
+
+    fn sample(ts_ms: i64, equity: f64, realized: f64) -> PnlSample {
+        PnlSample {
+            ts_ms,
+            equity_usd: equity,
+            realized_pnl_usd: realized,
+            ..PnlSample::default()
+        }
+    }
+
+    #[test]
+    fn drawdown_moves_through_reduce_only_to_halted() {
+        let (gate, mut rx) = RiskGate::with_limits(limits());
+        gate.observe_pnl(&sample(DAY0, 1_000.0, 0.0));
+        assert_eq!(gate.status(), RiskState::Active);
+
+        gate.observe_pnl(&sample(DAY0 + 1, 920.0, 0.0));
+        assert_eq!(gate.status(), RiskState::ReduceOnly);
+        assert!(!gate.allows_place("boxarb", false));
+        assert!(gate.allows_place("boxarb", true));
+
+        gate.observe_pnl(&sample(DAY0 + 2, 899.0, 0.0));
+        assert_eq!(gate.status(), RiskState::Halted);
+
+        let seen: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[1].to, RiskState::Halted);
+        assert_eq!(seen[1].severity(), "critical");
+    }
+
+    #[test]
+    fn reduce_only_clears_only_below_hysteresis_band() {
+        let (gate, _rx) = RiskGate::with_limits(limits());
+        gate.observe_pnl(&sample(DAY0, 1_000.0, 0.0));
+        gate.observe_pnl(&sample(DAY0 + 1, 920.0, 0.0));
+        assert_eq!(gate.status(), RiskState::ReduceOnly);
+
+        // 70% utilization: below the 75% trigger but inside the band
+        gate.observe_pnl(&sample(DAY0 + 2, 930.0, 0.0));
+        assert_eq!(gate.status(), RiskState::ReduceOnly);
+
+        gate.observe_pnl(&sample(DAY0 + 3, 950.0, 0.0));
+        assert_eq!(gate.status(), RiskState::Active);
+    }
+
+    #[test]
+    fn forced_reduce_only_survives_recovery_until_resume() {
+        let (gate, _rx) = RiskGate::with_limits(limits());
+        gate.observe_pnl(&sample(DAY0, 1_000.0, 0.0));
+        gate.force_reduce_only("position drift");
+        assert_eq!(gate.status(), RiskState::ReduceOnly);
+
+        gate.observe_pnl(&sample(DAY0 + 1, 1_010.0, 0.0));
+        assert_eq!(gate.status(), RiskState::ReduceOnly);
+
+        gate.resume();
+        gate.observe_pnl(&sample(DAY0 + 2, 1_010.0, 0.0));
+        assert_eq!(gate.status(), RiskState::Active);
+    }
+
+    #[test]
+    fn placement_blocks_stop_new_exposure_only() {
+        let gate = RiskGate::new();
+        gate.block_placements("breaker:place_order");
+        assert!(!gate.allows_place("mm", false));
+        assert!(gate.allows_place("mm", true));
+        assert_eq!(gate.status(), RiskState::Active);
+
+        gate.unblock_placements("breaker:place_order");
+        assert!(gate.allows_place("mm", false));
+    }
+
+    #[test]
+    fn halted_requires_operator_resume() {
+        let (gate, _rx) = RiskGate::with_limits(limits());
+        gate.observe_pnl(&sample(DAY0, 1_000.0, 0.0));
+        gate.observe_pnl(&sample(DAY0 + 1, 1_000.0, -60.0));
+        assert_eq!(gate.status(), RiskState::Halted);
+
+        gate.observe_pnl(&sample(DAY0 + 2, 1_000.0, 0.0));
+        assert_eq!(gate.status(), RiskState::Halted);
+
+        gate.resume();
+        assert_eq!(gate.status(), RiskState::Active);
+    }
+
+    #[test]
+    fn daily_loss_resets_on_new_utc_day() {
+        let (gate, _rx) = RiskGate::with_limits(limits());
+        gate.observe_pnl(&sample(DAY0, 1_000.0, 0.0));
+        gate.observe_pnl(&sample(DAY0 + 1, 1_000.0, -30.0));
+        assert_eq!(gate.status(), RiskState::Active);
+
+        let next_day = DAY0 + 86_400_000;
+        gate.observe_pnl(&sample(next_day, 1_000.0, -30.0));
+        gate.observe_pnl(&sample(next_day + 1, 1_000.0, -60.0));
+        assert_eq!(gate.status(), RiskState::Active);
+    }
+
+    #[test]
+    fn strategy_loss_halts_only_that_strategy() {
+        let (gate, mut rx) = RiskGate::with_limits(limits());
+        let mut s = sample(DAY0, 1_000.0, 0.0);
+        s.strategy_realized_pnl_usd.insert("mm".into(), 0.0);
+        gate.observe_pnl(&s);
+
+        s.ts_ms += 1;
+        s.strategy_realized_pnl_usd.insert("mm".into(), -25.0);
+        gate.observe_pnl(&s);
+
+        assert_eq!(gate.strategy_status("mm"), RiskState::Halted);
+        assert!(!gate.allows_place("mm", false));
+        assert!(gate.allows_place("boxarb", false));
+        let t = rx.try_recv().expect("strategy transition");
+        assert_eq!(t.scope, RiskScope::Strategy("mm".into()));
+    }
+
+    #[test]
+    fn resume_does_not_clear_kill_switch() {
+        let gate = RiskGate::new();
+        gate.kill_switch("test");
+        gate.resume();
+        assert_eq!(gate.status(), RiskState::KillSwitched);
+        gate.pause();
+        assert_eq!(gate.status(), RiskState::KillSwitched);
+
+        gate.reset_kill_switch();
+        assert_eq!(gate.status(), RiskState::Paused);
     }
 }
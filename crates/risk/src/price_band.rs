@@ -0,0 +1,410 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use state::TopOfBook;
+use strategies::{Intent, IntentKind, Side};
+use tracing::warn;
+
+/// Incident kind recorded when an outgoing order fails a price sanity check.
+pub const PRICE_BAND_INCIDENT: &str = "PRICE_BAND";
+
+const EPSILON: f64 = 1e-9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReferencePrice {
+    Mid,
+    Microprice,
+}
+
+/// Pre-trade price sanity limits. Zero disables a check.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceBandLimits {
+    pub tick: f64,
+    pub reference: ReferencePrice,
+    /// Max distance from the reference price, in ticks.
+    pub max_ticks_from_reference: f64,
+    /// Max distance past the touch for marketable orders, in ticks.
+    pub max_taker_slippage_ticks: f64,
+    /// Max order size as a multiple of the visible size at the touch.
+    pub max_size_to_depth: f64,
+}
+
+impl Default for PriceBandLimits {
+    fn default() -> Self {
+        Self {
+            tick: 0.01,
+            reference: ReferencePrice::Mid,
+            max_ticks_from_reference: 10.0,
+            max_taker_slippage_ticks: 2.0,
+            max_size_to_depth: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PriceViolation {
+    /// PlaceOrder without side, price or size.
+    Incomplete,
+    /// No reference price: the book has neither side.
+    NoBook,
+    /// Price outside [tick, 1 - tick].
+    OutOfRange {
+        price: f64,
+    },
+    FarFromReference {
+        price: f64,
+        reference: f64,
+        ticks: f64,
+    },
+    TakerSlippage {
+        price: f64,
+        touch: f64,
+        ticks: f64,
+    },
+    SizeExceedsDepth {
+        size: f64,
+        depth: f64,
+    },
+}
+
+impl fmt::Display for PriceViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PriceViolation::Incomplete => write!(f, "place order without side/price/size"),
+            PriceViolation::NoBook => write!(f, "no book to price against"),
+            PriceViolation::OutOfRange { price } => {
+                write!(f, "price {price} outside tradable range")
+            }
+            PriceViolation::FarFromReference {
+                price,
+                reference,
+                ticks,
+            } => write!(
+                f,
+                "price {price} is {ticks:.1} ticks from reference {reference}"
+            ),
+            PriceViolation::TakerSlippage {
+                price,
+                touch,
+                ticks,
+            } => {
+                write!(f, "price {price} is {ticks:.1} ticks through touch {touch}")
+            }
+            PriceViolation::SizeExceedsDepth { size, depth } => {
+                write!(
+                    f,
+                    "size {size} exceeds allowed share of visible depth {depth}"
+                )
+            }
+        }
+    }
+}
+
+/// Price band limits with per-strategy and per-market overrides.
+///
+/// A market override wins over a strategy override, which wins over the
+/// default.
+#[derive(Debug, Clone, Default)]
+pub struct PriceBands {
+    pub default: PriceBandLimits,
+    pub per_strategy: HashMap<String, PriceBandLimits>,
+    pub per_market: HashMap<i64, PriceBandLimits>,
+}
+
+impl PriceBands {
+    pub fn new(default: PriceBandLimits) -> Self {
+        Self {
+            default,
+            ..Self::default()
+        }
+    }
+
+    /// Add overrides from JSON of the form
+    /// `{"strategies": {"mm": {...}}, "markets": {"12": {...}}}`. Fields an
+    /// override leaves out keep the default's value.
+    pub fn with_overrides_json(mut self, json: &str) -> serde_json::Result<Self> {
+        #[derive(Deserialize)]
+        struct Overrides {
+            #[serde(default)]
+            strategies: HashMap<String, serde_json::Map<String, serde_json::Value>>,
+            #[serde(default)]
+            markets: HashMap<i64, serde_json::Map<String, serde_json::Value>>,
+        }
+        let overrides: Overrides = serde_json::from_str(json)?;
+        let merged = |fields: serde_json::Map<String, serde_json::Value>| {
+            let mut limits = serde_json::to_value(&self.default)?;
+            for (key, value) in fields {
+                limits[key] = value;
+            }
+            serde_json::from_value::<PriceBandLimits>(limits)
+        };
+        for (strategy, fields) in overrides.strategies {
+            let limits = merged(fields)?;
+            self.per_strategy.insert(strategy, limits);
+        }
+        for (market_id, fields) in overrides.markets {
+            let limits = merged(fields)?;
+            self.per_market.insert(market_id, limits);
+        }
+        Ok(self)
+    }
+
+    pub fn limits_for(&self, strategy: &str, market_id: i64) -> &PriceBandLimits {
+        self.per_market
+            .get(&market_id)
+            .or_else(|| self.per_strategy.get(strategy))
+            .unwrap_or(&self.default)
+    }
+
+    /// Check a PlaceOrder intent against the book for its token. Other intent
+    /// kinds always pass. Violations are logged with the offending intent.
+    pub fn check(&self, intent: &Intent, book: &TopOfBook) -> Result<(), PriceViolation> {
+        if !matches!(intent.kind, IntentKind::PlaceOrder) {
+            return Ok(());
+        }
+        let verdict = match (intent.side, intent.price, intent.size) {
+            (Some(side), Some(price), Some(size)) => {
+                self.check_order(&intent.strategy, intent.market_id, side, price, size, book)
+            }
+            _ => Err(PriceViolation::Incomplete),
+        };
+        if let Err(violation) = &verdict {
+            warn!(
+                intent = %serde_json::to_string(intent).unwrap_or_default(),
+                %violation,
+                "order rejected by price band"
+            );
+        }
+        verdict
+    }
+
+    /// Check an outgoing order against the book for its token.
+    pub fn check_order(
+        &self,
+        strategy: &str,
+        market_id: i64,
+        side: Side,
+        price: f64,
+        size: f64,
+        book: &TopOfBook,
+    ) -> Result<(), PriceViolation> {
+        check_limits(
+            self.limits_for(strategy, market_id),
+            side,
+            price,
+            size,
+            book,
+        )
+    }
+}
+
+/// JSON payload for a `PRICE_BAND` incident: the violation and the intent.
+pub fn price_band_payload(intent: &Intent, violation: &PriceViolation) -> String {
+    serde_json::json!({ "violation": violation, "intent": intent }).to_string()
+}
+
+fn check_limits(
+    limits: &PriceBandLimits,
+    side: Side,
+    price: f64,
+    size: f64,
+    book: &TopOfBook,
+) -> Result<(), PriceViolation> {
+    let tick = limits.tick;
+
+    if price < tick - EPSILON || price > 1.0 - tick + EPSILON {
+        return Err(PriceViolation::OutOfRange { price });
+    }
+
+    let reference = match limits.reference {
+        ReferencePrice::Mid => book.mid(),
+        ReferencePrice::Microprice => book.microprice(),
+    }
+    .or_else(|| book.bid.or(book.ask).map(|l| l.price))
+    .ok_or(PriceViolation::NoBook)?;
+
+    if limits.max_ticks_from_reference > 0.0 {
+        let ticks = (price - reference).abs() / tick;
+        if ticks > limits.max_ticks_from_reference + EPSILON {
+            return Err(PriceViolation::FarFromReference {
+                price,
+                reference,
+                ticks,
+            });
+        }
+    }
+
+    // The level a marketable order would trade against, and the level it
+    // would queue behind otherwise.
+    let (opposite, same) = if side.is_buy() {
+        (book.ask, book.bid)
+    } else {
+        (book.bid, book.ask)
+    };
+    let crossing = opposite.filter(|touch| {
+        if side.is_buy() {
+            price >= touch.price
+        } else {
+            price <= touch.price
+        }
+    });
+
+    if let Some(touch) = crossing {
+        let ticks = (price - touch.price).abs() / tick;
+        if limits.max_taker_slippage_ticks > 0.0
+            && ticks > limits.max_taker_slippage_ticks + EPSILON
+        {
+            return Err(PriceViolation::TakerSlippage {
+                price,
+                touch: touch.price,
+                ticks,
+            });
+        }
+    }
+
+    if limits.max_size_to_depth > 0.0 {
+        if let Some(level) = crossing.or(same) {
+            if size > level.qty * limits.max_size_to_depth + EPSILON {
+                return Err(PriceViolation::SizeExceedsDepth {
+                    size,
+                    depth: level.qty,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use state::BookLevel;
+    use strategies::{Side, Urgency};
+
+    fn book() -> TopOfBook {
+        TopOfBook {
+            bid: Some(BookLevel {
+                price: 0.39,
+                qty: 100.0,
+            }),
+            ask: Some(BookLevel {
+                price: 0.41,
+                qty: 50.0,
+            }),
+        }
+    }
+
+    fn order(side: Side, price: f64, size: f64) -> Intent {
+        Intent {
+            intent_id: "i-1".into(),
+            strategy: "mm".into(),
+            market_id: 1,
+            kind: IntentKind::PlaceOrder,
+            side: Some(side),
+            price: Some(price),
+            size: Some(size),
+            urgency: Urgency::Neutral,
+            ttl_ms: 0,
+            expected_value: 0.0,
+        }
+    }
+
+    #[test]
+    fn rejects_fat_finger_far_from_mid() {
+        let bands = PriceBands::default();
+        assert!(bands
+            .check(&order(Side::BuyYes, 0.40, 10.0), &book())
+            .is_ok());
+        assert!(matches!(
+            bands.check(&order(Side::BuyYes, 0.99, 10.0), &book()),
+            Err(PriceViolation::FarFromReference { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_prices_outside_tick_range() {
+        let bands = PriceBands::default();
+        assert_eq!(
+            bands.check(&order(Side::SellYes, 0.0, 1.0), &book()),
+            Err(PriceViolation::OutOfRange { price: 0.0 })
+        );
+        assert_eq!(
+            bands.check(&order(Side::BuyYes, 1.0, 1.0), &book()),
+            Err(PriceViolation::OutOfRange { price: 1.0 })
+        );
+    }
+
+    #[test]
+    fn limits_taker_slippage_and_size_against_depth() {
+        let bands = PriceBands::default();
+        assert!(bands
+            .check(&order(Side::BuyYes, 0.43, 10.0), &book())
+            .is_ok());
+        assert!(matches!(
+            bands.check(&order(Side::BuyYes, 0.44, 10.0), &book()),
+            Err(PriceViolation::TakerSlippage { .. })
+        ));
+        assert_eq!(
+            bands.check(&order(Side::BuyYes, 0.41, 60.0), &book()),
+            Err(PriceViolation::SizeExceedsDepth {
+                size: 60.0,
+                depth: 50.0
+            })
+        );
+        // passive orders are sized against their own side of the book
+        assert!(bands
+            .check(&order(Side::BuyYes, 0.39, 60.0), &book())
+            .is_ok());
+    }
+
+    #[test]
+    fn market_override_beats_strategy_override() {
+        let mut bands = PriceBands::default();
+        bands.per_strategy.insert(
+            "mm".into(),
+            PriceBandLimits {
+                max_ticks_from_reference: 1.0,
+                ..PriceBandLimits::default()
+            },
+        );
+        assert!(bands
+            .check(&order(Side::BuyYes, 0.37, 1.0), &book())
+            .is_err());
+
+        bands.per_market.insert(1, PriceBandLimits::default());
+        assert!(bands
+            .check(&order(Side::BuyYes, 0.37, 1.0), &book())
+            .is_ok());
+    }
+
+    #[test]
+    fn overrides_from_json_keep_unset_fields_of_the_default() {
+        let bands = PriceBands::new(PriceBandLimits {
+            max_taker_slippage_ticks: 3.0,
+            ..PriceBandLimits::default()
+        })
+        .with_overrides_json(
+            r#"{"strategies": {"mm": {"max_ticks_from_reference": 1.0}},
+                "markets": {"7": {"max_size_to_depth": 0.0}}}"#,
+        )
+        .unwrap();
+        let mm = bands.limits_for("mm", 1);
+        assert_eq!(mm.max_ticks_from_reference, 1.0);
+        assert_eq!(mm.max_taker_slippage_ticks, 3.0);
+        assert_eq!(bands.limits_for("mm", 7).max_size_to_depth, 0.0);
+        assert!(PriceBands::default()
+            .with_overrides_json(r#"{"markets": {"7": {"tick": "x"}}}"#)
+            .is_err());
+    }
+
+    #[test]
+    fn payload_carries_intent_and_violation() {
+        let intent = order(Side::BuyNo, 0.99, 1.0);
+        let payload = price_band_payload(&intent, &PriceViolation::OutOfRange { price: 0.99 });
+        let value: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(value["intent"]["intent_id"], "i-1");
+        assert!(value["violation"]["OutOfRange"].is_object());
+    }
+}
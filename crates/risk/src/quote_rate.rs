@@ -0,0 +1,487 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+use strategies::{Intent, IntentKind};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// Incident kind recorded when a quote-rate limit is breached or clears.
+pub const QUOTE_RATE_INCIDENT: &str = "QUOTE_RATE";
+
+/// Message-rate and order-to-trade limits. Zero disables a check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuoteRateLimits {
+    /// Window for the messages-per-second rate.
+    pub rate_window_ms: i64,
+    pub max_market_msgs_per_sec: f64,
+    pub max_strategy_msgs_per_sec: f64,
+    /// Window for the order-to-trade ratio.
+    pub ratio_window_ms: i64,
+    /// Max order messages (places and cancels) per fill.
+    pub max_order_to_trade: f64,
+    /// The ratio is only enforced once a scope has sent this many messages
+    /// in the ratio window, so a quiet book does not veto the first quotes.
+    pub min_ratio_messages: usize,
+}
+
+impl Default for QuoteRateLimits {
+    fn default() -> Self {
+        Self {
+            rate_window_ms: 5_000,
+            max_market_msgs_per_sec: 5.0,
+            max_strategy_msgs_per_sec: 20.0,
+            ratio_window_ms: 300_000,
+            max_order_to_trade: 50.0,
+            min_ratio_messages: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum QuoteScope {
+    Market(i64),
+    Strategy(String),
+}
+
+impl QuoteScope {
+    /// `scope` label for metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            QuoteScope::Market(_) => "market",
+            QuoteScope::Strategy(_) => "strategy",
+        }
+    }
+
+    /// `id` label for metrics.
+    pub fn id(&self) -> String {
+        match self {
+            QuoteScope::Market(id) => id.to_string(),
+            QuoteScope::Strategy(name) => name.clone(),
+        }
+    }
+}
+
+impl fmt::Display for QuoteScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.kind(), self.id())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum QuoteViolation {
+    /// Throttle: placements resume once older messages leave the window.
+    MessageRate {
+        scope: QuoteScope,
+        per_sec: f64,
+        limit: f64,
+        retry_after_ms: i64,
+    },
+    /// Veto: placements resume once fills catch up or messages age out.
+    OrderToTrade {
+        scope: QuoteScope,
+        ratio: f64,
+        limit: f64,
+    },
+}
+
+impl QuoteViolation {
+    pub fn scope(&self) -> &QuoteScope {
+        match self {
+            QuoteViolation::MessageRate { scope, .. }
+            | QuoteViolation::OrderToTrade { scope, .. } => scope,
+        }
+    }
+
+    /// `limit` label for metrics.
+    pub fn limit_name(&self) -> &'static str {
+        match self {
+            QuoteViolation::MessageRate { .. } => "msgs_per_sec",
+            QuoteViolation::OrderToTrade { .. } => "order_to_trade",
+        }
+    }
+}
+
+impl fmt::Display for QuoteViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuoteViolation::MessageRate {
+                scope,
+                per_sec,
+                limit,
+                retry_after_ms,
+            } => write!(
+                f,
+                "{scope} at {per_sec:.2} msgs/s (limit {limit}); retry in {retry_after_ms}ms"
+            ),
+            QuoteViolation::OrderToTrade {
+                scope,
+                ratio,
+                limit,
+            } => write!(f, "{scope} order-to-trade {ratio:.1} (limit {limit})"),
+        }
+    }
+}
+
+/// Start or end of a period during which a scope was over a limit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuoteRateEpisode {
+    pub scope: QuoteScope,
+    pub ts_ms: i64,
+    /// Set when the episode starts; `None` marks recovery.
+    pub violation: Option<QuoteViolation>,
+    pub duration_ms: Option<i64>,
+}
+
+/// Current rates for one scope, for the Prometheus gauges.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuoteRateStats {
+    pub scope: QuoteScope,
+    pub msgs_per_sec: f64,
+    /// Messages per fill over the ratio window; fills count as at least one.
+    pub order_to_trade: f64,
+    pub messages: usize,
+    pub fills: usize,
+}
+
+#[derive(Default)]
+struct Activity {
+    messages: VecDeque<i64>,
+    fills: VecDeque<i64>,
+}
+
+impl Activity {
+    fn prune(&mut self, cutoff_ms: i64) {
+        while self.messages.front().is_some_and(|t| *t <= cutoff_ms) {
+            self.messages.pop_front();
+        }
+        while self.fills.front().is_some_and(|t| *t <= cutoff_ms) {
+            self.fills.pop_front();
+        }
+    }
+
+    /// Messages after `since_ms`, with the index of the first one.
+    fn messages_since(&self, since_ms: i64) -> (usize, usize) {
+        let first = self.messages.partition_point(|t| *t <= since_ms);
+        (first, self.messages.len() - first)
+    }
+
+    fn fills_since(&self, since_ms: i64) -> usize {
+        self.fills.len() - self.fills.partition_point(|t| *t <= since_ms)
+    }
+}
+
+#[derive(Default)]
+struct GovernorInner {
+    activity: HashMap<QuoteScope, Activity>,
+    /// Scopes currently over a limit, with the episode start time.
+    breached: HashMap<QuoteScope, i64>,
+}
+
+/// Rolling order-to-trade and messages-per-second limits per market and per
+/// strategy. Every place and cancel sent to the venue is recorded; new
+/// placements are throttled or vetoed while a scope is over a limit.
+/// Cancels are never held back.
+#[derive(Clone)]
+pub struct QuoteGovernor {
+    limits: QuoteRateLimits,
+    inner: Arc<RwLock<GovernorInner>>,
+    episodes: UnboundedSender<QuoteRateEpisode>,
+}
+
+impl QuoteGovernor {
+    pub fn new(limits: QuoteRateLimits) -> (Self, UnboundedReceiver<QuoteRateEpisode>) {
+        let (tx, rx) = unbounded_channel();
+        let governor = Self {
+            limits,
+            inner: Arc::new(RwLock::new(GovernorInner::default())),
+            episodes: tx,
+        };
+        (governor, rx)
+    }
+
+    /// Record an order message (place or cancel) sent to the venue.
+    pub fn record_message(&self, strategy: &str, market_id: i64, ts_ms: i64) {
+        self.record(strategy, market_id, ts_ms, |a| &mut a.messages);
+    }
+
+    pub fn record_fill(&self, strategy: &str, market_id: i64, ts_ms: i64) {
+        self.record(strategy, market_id, ts_ms, |a| &mut a.fills);
+    }
+
+    /// Whether `strategy` may place a new order in `market_id`. Opens or
+    /// closes breach episodes as a side effect.
+    pub fn check(&self, strategy: &str, market_id: i64, now_ms: i64) -> Result<(), QuoteViolation> {
+        let Ok(mut inner) = self.inner.write() else {
+            return Ok(());
+        };
+        let mut verdict = Ok(());
+        for scope in scopes(strategy, market_id) {
+            let result = self.evaluate(&mut inner, &scope, now_ms);
+            self.track_episode(&mut inner, scope, result.as_ref().err(), now_ms);
+            if verdict.is_ok() {
+                verdict = result;
+            }
+        }
+        verdict
+    }
+
+    /// Veto PlaceOrder intents over a limit; cancels and flattens always pass.
+    pub fn veto_intent(&self, intent: &Intent, now_ms: i64) -> Option<QuoteViolation> {
+        match intent.kind {
+            IntentKind::PlaceOrder => self.check(&intent.strategy, intent.market_id, now_ms).err(),
+            _ => None,
+        }
+    }
+
+    /// Current rates for every scope with recent activity. Also closes
+    /// episodes for scopes back under their limits and forgets idle scopes.
+    pub fn stats(&self, now_ms: i64) -> Vec<QuoteRateStats> {
+        let Ok(mut inner) = self.inner.write() else {
+            return Vec::new();
+        };
+        let mut scopes: Vec<QuoteScope> = inner.activity.keys().cloned().collect();
+        scopes.sort();
+        let mut stats = Vec::with_capacity(scopes.len());
+        for scope in scopes {
+            let result = self.evaluate(&mut inner, &scope, now_ms);
+            self.track_episode(&mut inner, scope.clone(), result.as_ref().err(), now_ms);
+            let Some(activity) = inner.activity.get(&scope) else {
+                continue;
+            };
+            if activity.messages.is_empty() && activity.fills.is_empty() {
+                continue;
+            }
+            let (_, recent) = activity.messages_since(now_ms - self.limits.rate_window_ms);
+            let since = now_ms - self.limits.ratio_window_ms;
+            let (_, messages) = activity.messages_since(since);
+            let fills = activity.fills_since(since);
+            stats.push(QuoteRateStats {
+                msgs_per_sec: per_sec(recent, self.limits.rate_window_ms),
+                order_to_trade: messages as f64 / fills.max(1) as f64,
+                messages,
+                fills,
+                scope,
+            });
+        }
+        inner
+            .activity
+            .retain(|_, a| !a.messages.is_empty() || !a.fills.is_empty());
+        stats
+    }
+
+    fn record(
+        &self,
+        strategy: &str,
+        market_id: i64,
+        ts_ms: i64,
+        series: impl Fn(&mut Activity) -> &mut VecDeque<i64>,
+    ) {
+        if let Ok(mut inner) = self.inner.write() {
+            for scope in scopes(strategy, market_id) {
+                let events = series(inner.activity.entry(scope).or_default());
+                // Keep the window sorted even if callers race on timestamps.
+                let at = events.partition_point(|t| *t <= ts_ms);
+                events.insert(at, ts_ms);
+            }
+        }
+    }
+
+    fn evaluate(
+        &self,
+        inner: &mut GovernorInner,
+        scope: &QuoteScope,
+        now_ms: i64,
+    ) -> Result<(), QuoteViolation> {
+        let limits = &self.limits;
+        let Some(activity) = inner.activity.get_mut(scope) else {
+            return Ok(());
+        };
+        activity.prune(now_ms - limits.rate_window_ms.max(limits.ratio_window_ms));
+
+        let max_rate = match scope {
+            QuoteScope::Market(_) => limits.max_market_msgs_per_sec,
+            QuoteScope::Strategy(_) => limits.max_strategy_msgs_per_sec,
+        };
+        if max_rate > 0.0 && limits.rate_window_ms > 0 {
+            let since = now_ms - limits.rate_window_ms;
+            let (first, recent) = activity.messages_since(since);
+            let allowed = (max_rate * limits.rate_window_ms as f64 / 1_000.0).floor() as usize;
+            if recent >= allowed.max(1) {
+                // Room opens when enough of the oldest messages age out.
+                let freeing = activity.messages[first + recent - allowed.max(1)];
+                return Err(QuoteViolation::MessageRate {
+                    scope: scope.clone(),
+                    per_sec: per_sec(recent, limits.rate_window_ms),
+                    limit: max_rate,
+                    retry_after_ms: (freeing + limits.rate_window_ms - now_ms).max(1),
+                });
+            }
+        }
+
+        if limits.max_order_to_trade > 0.0 && limits.ratio_window_ms > 0 {
+            let since = now_ms - limits.ratio_window_ms;
+            let (_, messages) = activity.messages_since(since);
+            let fills = activity.fills_since(since);
+            let ratio = messages as f64 / fills.max(1) as f64;
+            if messages >= limits.min_ratio_messages && ratio > limits.max_order_to_trade {
+                return Err(QuoteViolation::OrderToTrade {
+                    scope: scope.clone(),
+                    ratio,
+                    limit: limits.max_order_to_trade,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn track_episode(
+        &self,
+        inner: &mut GovernorInner,
+        scope: QuoteScope,
+        violation: Option<&QuoteViolation>,
+        now_ms: i64,
+    ) {
+        match (violation, inner.breached.get(&scope).copied()) {
+            (Some(violation), None) => {
+                inner.breached.insert(scope.clone(), now_ms);
+                let _ = self.episodes.send(QuoteRateEpisode {
+                    scope,
+                    ts_ms: now_ms,
+                    violation: Some(violation.clone()),
+                    duration_ms: None,
+                });
+            }
+            (None, Some(started_ms)) => {
+                inner.breached.remove(&scope);
+                let _ = self.episodes.send(QuoteRateEpisode {
+                    scope,
+                    ts_ms: now_ms,
+                    violation: None,
+                    duration_ms: Some(now_ms - started_ms),
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+fn scopes(strategy: &str, market_id: i64) -> [QuoteScope; 2] {
+    [
+        QuoteScope::Market(market_id),
+        QuoteScope::Strategy(strategy.to_string()),
+    ]
+}
+
+fn per_sec(count: usize, window_ms: i64) -> f64 {
+    if window_ms <= 0 {
+        return 0.0;
+    }
+    count as f64 * 1_000.0 / window_ms as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const T0: i64 = 1_700_000_000_000;
+
+    fn limits() -> QuoteRateLimits {
+        QuoteRateLimits {
+            rate_window_ms: 1_000,
+            max_market_msgs_per_sec: 3.0,
+            max_strategy_msgs_per_sec: 5.0,
+            ratio_window_ms: 60_000,
+            max_order_to_trade: 4.0,
+            min_ratio_messages: 8,
+        }
+    }
+
+    #[test]
+    fn throttles_per_market_and_per_strategy() {
+        let (gov, mut rx) = QuoteGovernor::new(limits());
+        for i in 0..3 {
+            assert_eq!(gov.check("mm", 1, T0 + i * 10), Ok(()));
+            gov.record_message("mm", 1, T0 + i * 10);
+        }
+        let Err(QuoteViolation::MessageRate {
+            scope,
+            retry_after_ms,
+            ..
+        }) = gov.check("mm", 1, T0 + 30)
+        else {
+            panic!("expected a market throttle");
+        };
+        assert_eq!(scope, QuoteScope::Market(1));
+        assert_eq!(retry_after_ms, 970);
+        // another market still has room, until the strategy total runs out
+        assert_eq!(gov.check("mm", 2, T0 + 30), Ok(()));
+        gov.record_message("mm", 2, T0 + 40);
+        gov.record_message("mm", 2, T0 + 50);
+        assert!(matches!(
+            gov.check("mm", 2, T0 + 60),
+            Err(QuoteViolation::MessageRate {
+                scope: QuoteScope::Strategy(_),
+                ..
+            })
+        ));
+
+        // the window rolls on
+        assert_eq!(gov.check("mm", 1, T0 + 1_051), Ok(()));
+        let start = rx.try_recv().expect("episode start");
+        assert_eq!(start.scope, QuoteScope::Market(1));
+        assert!(start.violation.is_some());
+    }
+
+    #[test]
+    fn vetoes_on_order_to_trade_until_fills_catch_up() {
+        let (gov, mut rx) = QuoteGovernor::new(limits());
+        for i in 0..10 {
+            gov.record_message("mm", 1, T0 + i * 500);
+        }
+        let now = T0 + 10_000;
+        assert!(matches!(
+            gov.check("mm", 1, now),
+            Err(QuoteViolation::OrderToTrade { ratio, .. }) if ratio == 10.0
+        ));
+        gov.record_fill("mm", 1, now);
+        gov.record_fill("mm", 1, now);
+        gov.record_fill("mm", 1, now);
+        assert_eq!(gov.check("mm", 1, now), Ok(()));
+
+        let stats = gov.stats(now);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].scope, QuoteScope::Market(1));
+        assert!((stats[0].order_to_trade - 10.0 / 3.0).abs() < 1e-9);
+
+        let episodes: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(episodes.len(), 4);
+        assert!(episodes[2].violation.is_none());
+        assert_eq!(episodes[2].duration_ms, Some(0));
+    }
+
+    #[test]
+    fn cancels_pass_and_idle_scopes_are_forgotten() {
+        let (gov, _rx) = QuoteGovernor::new(limits());
+        for i in 0..5 {
+            gov.record_message("mm", 1, T0 + i);
+        }
+        let mut intent = Intent {
+            intent_id: "i-1".into(),
+            strategy: "mm".into(),
+            market_id: 1,
+            kind: IntentKind::CancelAll,
+            side: None,
+            price: None,
+            size: None,
+            urgency: Default::default(),
+            ttl_ms: 0,
+            expected_value: 0.0,
+        };
+        assert_eq!(gov.veto_intent(&intent, T0 + 10), None);
+        intent.kind = IntentKind::PlaceOrder;
+        assert!(gov.veto_intent(&intent, T0 + 10).is_some());
+
+        assert!(gov.stats(T0 + 120_000).is_empty());
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+use state::StateSnapshot;
+use strategies::{Intent, IntentKind};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// Incident kind recorded when a market goes stale or recovers.
+pub const STALE_STATE_INCIDENT: &str = "STALE_STATE";
+
+/// Age thresholds; a value of zero (or less) disables that check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StalenessLimits {
+    /// Max time since the market's book was last updated.
+    pub max_book_age_ms: i64,
+    /// Max time since any message on a tracked feed.
+    pub max_feed_age_ms: i64,
+    /// Max age of the snapshot a decision is based on.
+    pub max_snapshot_age_ms: i64,
+    /// Max |local receive time - venue server time|.
+    pub max_clock_skew_ms: i64,
+    /// Feeds that count as stale until their first message arrives.
+    pub required_feeds: Vec<String>,
+}
+
+impl Default for StalenessLimits {
+    fn default() -> Self {
+        Self {
+            max_book_age_ms: 2_000,
+            max_feed_age_ms: 5_000,
+            max_snapshot_age_ms: 500,
+            max_clock_skew_ms: 1_000,
+            required_feeds: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StaleReason {
+    BookMissing,
+    BookStale { age_ms: i64 },
+    FeedMissing { feed: String },
+    FeedStale { feed: String, age_ms: i64 },
+    SnapshotStale { age_ms: i64 },
+    ClockSkew { skew_ms: i64 },
+}
+
+impl fmt::Display for StaleReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StaleReason::BookMissing => write!(f, "no book update received"),
+            StaleReason::BookStale { age_ms } => write!(f, "book age {age_ms}ms"),
+            StaleReason::FeedMissing { feed } => write!(f, "feed {feed} has not reported"),
+            StaleReason::FeedStale { feed, age_ms } => write!(f, "feed {feed} age {age_ms}ms"),
+            StaleReason::SnapshotStale { age_ms } => write!(f, "snapshot age {age_ms}ms"),
+            StaleReason::ClockSkew { skew_ms } => write!(f, "clock skew {skew_ms}ms"),
+        }
+    }
+}
+
+/// Start or end of a period during which a market was not tradable.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StaleEpisode {
+    pub market_id: i64,
+    pub ts_ms: i64,
+    /// Set when the episode starts; `None` marks recovery.
+    pub reason: Option<StaleReason>,
+    /// Length of the episode, set on recovery.
+    pub duration_ms: Option<i64>,
+}
+
+#[derive(Default)]
+struct GuardInner {
+    books: HashMap<i64, i64>,
+    feeds: HashMap<String, i64>,
+    clock_skew_ms: Option<i64>,
+    /// Markets currently in a stale episode, with the episode start time.
+    stale: HashMap<i64, i64>,
+}
+
+/// Tracks data freshness and vetoes PlaceOrder when state can't be trusted.
+#[derive(Clone)]
+pub struct StalenessGuard {
+    limits: StalenessLimits,
+    inner: Arc<RwLock<GuardInner>>,
+    episodes: UnboundedSender<StaleEpisode>,
+}
+
+impl StalenessGuard {
+    pub fn new(limits: StalenessLimits) -> (Self, UnboundedReceiver<StaleEpisode>) {
+        let (tx, rx) = unbounded_channel();
+        let guard = Self {
+            limits,
+            inner: Arc::new(RwLock::new(GuardInner::default())),
+            episodes: tx,
+        };
+        (guard, rx)
+    }
+
+    pub fn record_book_update(&self, market_id: i64, local_ms: i64) {
+        if let Ok(mut inner) = self.inner.write() {
+            inner.books.insert(market_id, local_ms);
+        }
+    }
+
+    /// Record a feed message; `server_ts_ms` (from the WS payload) refreshes
+    /// the clock skew estimate.
+    pub fn record_feed_message(&self, feed: &str, local_ms: i64, server_ts_ms: Option<i64>) {
+        if let Ok(mut inner) = self.inner.write() {
+            inner.feeds.insert(feed.to_string(), local_ms);
+            if let Some(server_ms) = server_ts_ms {
+                inner.clock_skew_ms = Some(local_ms - server_ms);
+            }
+        }
+    }
+
+    pub fn clock_skew_ms(&self) -> Option<i64> {
+        self.inner.read().ok().and_then(|g| g.clock_skew_ms)
+    }
+
+    /// Check freshness for a decision on `market_id` based on a snapshot taken
+    /// at `snapshot_ts_ms`. Opens or closes stale episodes as a side effect.
+    pub fn check(
+        &self,
+        market_id: i64,
+        snapshot_ts_ms: i64,
+        now_ms: i64,
+    ) -> Result<(), StaleReason> {
+        let Ok(mut inner) = self.inner.write() else {
+            return Err(StaleReason::BookMissing);
+        };
+        let verdict = self.evaluate(&inner, market_id, snapshot_ts_ms, now_ms);
+
+        match (&verdict, inner.stale.get(&market_id).copied()) {
+            (Err(reason), None) => {
+                inner.stale.insert(market_id, now_ms);
+                let _ = self.episodes.send(StaleEpisode {
+                    market_id,
+                    ts_ms: now_ms,
+                    reason: Some(reason.clone()),
+                    duration_ms: None,
+                });
+            }
+            (Ok(()), Some(started_ms)) => {
+                inner.stale.remove(&market_id);
+                let _ = self.episodes.send(StaleEpisode {
+                    market_id,
+                    ts_ms: now_ms,
+                    reason: None,
+                    duration_ms: Some(now_ms - started_ms),
+                });
+            }
+            _ => {}
+        }
+        verdict
+    }
+
+    /// Veto PlaceOrder intents on stale state; cancels and flattens always pass.
+    pub fn veto_intent(
+        &self,
+        intent: &Intent,
+        snapshot_ts_ms: i64,
+        now_ms: i64,
+    ) -> Option<StaleReason> {
+        match intent.kind {
+            IntentKind::PlaceOrder => self.check(intent.market_id, snapshot_ts_ms, now_ms).err(),
+            _ => None,
+        }
+    }
+
+    /// Set `snapshot.can_trade` from the current freshness of its market.
+    pub fn apply(&self, snapshot: &mut StateSnapshot, now_ms: i64) -> Option<StaleReason> {
+        let verdict = self.check(snapshot.market_id, snapshot.ts_ms, now_ms);
+        snapshot.can_trade = verdict.is_ok();
+        verdict.err()
+    }
+
+    fn evaluate(
+        &self,
+        inner: &GuardInner,
+        market_id: i64,
+        snapshot_ts_ms: i64,
+        now_ms: i64,
+    ) -> Result<(), StaleReason> {
+        let limits = &self.limits;
+
+        if limits.max_book_age_ms > 0 {
+            let Some(last) = inner.books.get(&market_id) else {
+                return Err(StaleReason::BookMissing);
+            };
+            let age_ms = now_ms - last;
+            if age_ms > limits.max_book_age_ms {
+                return Err(StaleReason::BookStale { age_ms });
+            }
+        }
+
+        for feed in &limits.required_feeds {
+            if !inner.feeds.contains_key(feed) {
+                return Err(StaleReason::FeedMissing { feed: feed.clone() });
+            }
+        }
+        if limits.max_feed_age_ms > 0 {
+            let mut feeds: Vec<_> = inner.feeds.iter().collect();
+            feeds.sort();
+            for (feed, last) in feeds {
+                let age_ms = now_ms - last;
+                if age_ms > limits.max_feed_age_ms {
+                    return Err(StaleReason::FeedStale {
+                        feed: feed.clone(),
+                        age_ms,
+                    });
+                }
+            }
+        }
+
+        if limits.max_snapshot_age_ms > 0 {
+            let age_ms = now_ms - snapshot_ts_ms;
+            if age_ms > limits.max_snapshot_age_ms {
+                return Err(StaleReason::SnapshotStale { age_ms });
+            }
+        }
+
+        if limits.max_clock_skew_ms > 0 {
+            if let Some(skew_ms) = inner.clock_skew_ms {
+                if skew_ms.abs() > limits.max_clock_skew_ms {
+                    return Err(StaleReason::ClockSkew { skew_ms });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strategies::Urgency;
+
+    const T0: i64 = 1_700_000_000_000;
+
+    fn place(market_id: i64) -> Intent {
+        Intent {
+            intent_id: "i-1".into(),
+            strategy: "mm".into(),
+            market_id,
+            kind: IntentKind::PlaceOrder,
+            side: None,
+            price: None,
+            size: None,
+            urgency: Urgency::Neutral,
+            ttl_ms: 0,
+            expected_value: 0.0,
+        }
+    }
+
+    #[test]
+    fn vetoes_place_on_old_book_but_not_cancel() {
+        let (guard, _rx) = StalenessGuard::new(StalenessLimits::default());
+        guard.record_book_update(1, T0);
+
+        assert_eq!(guard.veto_intent(&place(1), T0 + 100, T0 + 200), None);
+        assert_eq!(
+            guard.veto_intent(&place(1), T0 + 2_900, T0 + 3_000),
+            Some(StaleReason::BookStale { age_ms: 3_000 })
+        );
+
+        let mut cancel = place(1);
+        cancel.kind = IntentKind::CancelAll;
+        assert_eq!(guard.veto_intent(&cancel, T0, T0 + 10_000), None);
+    }
+
+    #[test]
+    fn flags_old_snapshots_missing_feeds_and_clock_skew() {
+        let limits = StalenessLimits {
+            required_feeds: vec!["polymarket_market_ws".into()],
+            ..StalenessLimits::default()
+        };
+        let (guard, _rx) = StalenessGuard::new(limits);
+        guard.record_book_update(1, T0);
+        assert!(matches!(
+            guard.check(1, T0, T0),
+            Err(StaleReason::FeedMissing { .. })
+        ));
+
+        guard.record_feed_message("polymarket_market_ws", T0, Some(T0 - 50));
+        assert_eq!(guard.check(1, T0, T0), Ok(()));
+        assert_eq!(
+            guard.check(1, T0 - 600, T0),
+            Err(StaleReason::SnapshotStale { age_ms: 600 })
+        );
+
+        guard.record_feed_message("polymarket_market_ws", T0, Some(T0 - 1_500));
+        assert_eq!(
+            guard.check(1, T0, T0),
+            Err(StaleReason::ClockSkew { skew_ms: 1_500 })
+        );
+    }
+
+    #[test]
+    fn sets_can_trade_and_reports_episodes() {
+        let (guard, mut rx) = StalenessGuard::new(StalenessLimits::default());
+        let mut snapshot = StateSnapshot {
+            snapshot_id: "s-1".into(),
+            market_id: 3,
+            ts_ms: T0,
+            can_trade: true,
+        };
+
+        assert_eq!(
+            guard.apply(&mut snapshot, T0),
+            Some(StaleReason::BookMissing)
+        );
+        assert!(!snapshot.can_trade);
+        guard.apply(&mut snapshot, T0 + 1);
+
+        guard.record_book_update(3, T0 + 100);
+        snapshot.ts_ms = T0 + 100;
+        assert_eq!(guard.apply(&mut snapshot, T0 + 150), None);
+        assert!(snapshot.can_trade);
+
+        let start = rx.try_recv().expect("episode start");
+        assert_eq!(start.reason, Some(StaleReason::BookMissing));
+        let end = rx.try_recv().expect("episode end");
+        assert_eq!(end.reason, None);
+        assert_eq!(end.duration_ms, Some(150));
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -7,6 +7,40 @@ pub struct StateSnapshot {
     pub snapshot_id: String,
     pub market_id: i64,
     pub ts_ms: i64,
+    /// False when risk considers the underlying data stale.
+    pub can_trade: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BookLevel {
+    pub price: f64,
+    pub qty: f64,
+}
+
+/// Best bid and ask for one outcome token.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TopOfBook {
+    pub bid: Option<BookLevel>,
+    pub ask: Option<BookLevel>,
+}
+
+impl TopOfBook {
+    pub fn mid(&self) -> Option<f64> {
+        match (self.bid, self.ask) {
+            (Some(bid), Some(ask)) => Some((bid.price + ask.price) / 2.0),
+            _ => None,
+        }
+    }
+
+    /// Size-weighted mid: leans toward the side with less resting size.
+    pub fn microprice(&self) -> Option<f64> {
+        match (self.bid, self.ask) {
+            (Some(bid), Some(ask)) if bid.qty + ask.qty > 0.0 => {
+                Some((bid.price * ask.qty + ask.price * bid.qty) / (bid.qty + ask.qty))
+            }
+            _ => self.mid(),
+        }
+    }
 }
 
 pub fn initial_snapshot() -> StateSnapshot {
@@ -14,5 +48,6 @@ pub fn initial_snapshot() -> StateSnapshot {
         snapshot_id: "bootstrap".into(),
         market_id: 0,
         ts_ms: chrono::Utc::now().timestamp_millis(),
+        can_trade: false,
     }
 }
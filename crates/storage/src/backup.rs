@@ -0,0 +1,484 @@
+//! Online backups taken while traderd keeps writing.
+//!
+//! SQLite databases are copied with `VACUUM INTO`, which reads one
+//! consistent snapshot without blocking writers for longer than a page
+//! read. The copy is opened read-only and checked with `integrity_check`
+//! and [`Store::validate_required_tables`] before it is given its final
+//! name. Postgres databases are exported table by table, in foreign-key
+//! order, to zstd-compressed JSONL from a single repeatable-read
+//! transaction; the export is verified by re-reading every file against
+//! its manifest. Backups beyond `keep` are removed, oldest first.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::{info, warn};
+
+use crate::retention::HashingWriter;
+use crate::{Store, StorePool};
+
+/// `incidents.kind` for a failed backup.
+pub const BACKUP_INCIDENT: &str = "BACKUP";
+
+/// Name of the manifest inside a Postgres export directory.
+pub const EXPORT_MANIFEST_FILE: &str = "manifest.json";
+
+const BACKUP_PREFIX: &str = "backup-";
+const PARTIAL_SUFFIX: &str = ".partial";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupConfig {
+    pub dir: PathBuf,
+    /// Backups kept after a new one succeeds. 0 keeps all of them.
+    pub keep: usize,
+    /// Time between scheduled backups. Zero disables the schedule; admin
+    /// requests still work.
+    pub interval: Duration,
+    pub zstd_level: i32,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("backups"),
+            keep: 7,
+            interval: Duration::from_secs(86_400),
+            zstd_level: 3,
+        }
+    }
+}
+
+/// One table of a Postgres export.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableExport {
+    pub table: String,
+    /// Relative to the export directory.
+    pub path: String,
+    pub rows: u64,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+/// `manifest.json` of a Postgres export.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportManifest {
+    pub created_at_ms: i64,
+    pub tables: Vec<TableExport>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupReport {
+    /// The backup file (SQLite) or export directory (Postgres).
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// Row counts per table; only filled for Postgres exports.
+    pub tables: Vec<TableExport>,
+    /// Older backups removed by rotation.
+    pub rotated: Vec<PathBuf>,
+    pub elapsed: Duration,
+}
+
+fn backup_name(now_ms: i64, extension: &str) -> String {
+    let stamp = DateTime::<Utc>::from_timestamp_millis(now_ms)
+        .map(|d| d.format("%Y%m%dT%H%M%S%.3fZ").to_string())
+        .unwrap_or_else(|| now_ms.to_string());
+    format!("{BACKUP_PREFIX}{stamp}{extension}")
+}
+
+/// Finished backups in `dir`, oldest first. Names embed a UTC timestamp,
+/// so name order is age order.
+pub fn list_backups(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("listing {}", dir.display())),
+    };
+    let mut backups = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(BACKUP_PREFIX) && !name.ends_with(PARTIAL_SUFFIX) {
+            backups.push(entry.path());
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+/// Remove all but the newest `keep` backups; `keep == 0` removes nothing.
+fn rotate(dir: &Path, keep: usize) -> Result<Vec<PathBuf>> {
+    if keep == 0 {
+        return Ok(Vec::new());
+    }
+    let backups = list_backups(dir)?;
+    let excess = backups.len().saturating_sub(keep);
+    let mut removed = Vec::with_capacity(excess);
+    for path in backups.into_iter().take(excess) {
+        let result = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        match result {
+            Ok(()) => removed.push(path),
+            // a concurrent rotation got there first
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err).with_context(|| format!("removing {}", path.display())),
+        }
+    }
+    Ok(removed)
+}
+
+/// Re-read a Postgres export: every file must match its manifest entry in
+/// size, sha256 and line count, and every table must be present.
+pub fn verify_export(dir: &Path) -> Result<ExportManifest> {
+    let path = dir.join(EXPORT_MANIFEST_FILE);
+    let manifest: ExportManifest = serde_json::from_slice(
+        &fs::read(&path).with_context(|| format!("reading {}", path.display()))?,
+    )
+    .context("bad export manifest")?;
+    for table in crate::schema::tables() {
+        if !manifest.tables.iter().any(|t| t.table == table) {
+            bail!("export is missing table {table}");
+        }
+    }
+    for table in &manifest.tables {
+        let file = dir.join(&table.path);
+        let mut hashing = HashingWriter::new(io::sink());
+        io::copy(
+            &mut File::open(&file).with_context(|| format!("opening {}", file.display()))?,
+            &mut hashing,
+        )?;
+        let sha256 = hex::encode(hashing.hasher.finalize());
+        if hashing.bytes != table.bytes || sha256 != table.sha256 {
+            bail!("{}: checksum mismatch", table.path);
+        }
+        let lines = io::BufReader::new(zstd::Decoder::new(File::open(&file)?)?)
+            .lines()
+            .try_fold(0u64, |n, line| line.map(|_| n + 1))?;
+        if lines != table.rows {
+            bail!("{}: {lines} rows, manifest says {}", table.path, table.rows);
+        }
+    }
+    Ok(manifest)
+}
+
+impl Store {
+    /// Take a verified backup into `config.dir`, then rotate old ones.
+    pub async fn backup(&self, config: &BackupConfig, now_ms: i64) -> Result<BackupReport> {
+        let started = Instant::now();
+        fs::create_dir_all(&config.dir)
+            .with_context(|| format!("creating {}", config.dir.display()))?;
+        let (path, bytes, tables) = match &self.pool {
+            #[cfg(feature = "sqlite")]
+            StorePool::Sqlite(pool) => {
+                let path = config.dir.join(backup_name(now_ms, ".db"));
+                let bytes = sqlite_backup(pool, &path).await?;
+                (path, bytes, Vec::new())
+            }
+            #[cfg(feature = "postgres")]
+            StorePool::Postgres(pool) => {
+                let path = config.dir.join(backup_name(now_ms, ".pg"));
+                let tables = postgres_export(pool, &path, config.zstd_level, now_ms).await?;
+                (path, tables.iter().map(|t| t.bytes).sum(), tables)
+            }
+        };
+        let rotated = rotate(&config.dir, config.keep)?;
+        info!(path = %path.display(), bytes, rotated = rotated.len(), "backup written");
+        Ok(BackupReport {
+            path,
+            bytes,
+            tables,
+            rotated,
+            elapsed: started.elapsed(),
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+async fn sqlite_backup(pool: &sqlx::SqlitePool, path: &Path) -> Result<u64> {
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+    // VACUUM INTO from an in-memory database writes to memory too
+    let (_, _, file): (i64, String, String) = sqlx::query_as("PRAGMA database_list")
+        .fetch_one(pool)
+        .await?;
+    if file.is_empty() {
+        bail!("in-memory sqlite databases cannot be backed up");
+    }
+
+    let tmp = partial(path);
+    let _ = fs::remove_file(&tmp);
+    let target = tmp
+        .to_str()
+        .with_context(|| format!("backup path {} is not utf-8", tmp.display()))?;
+    sqlx::query(&format!("VACUUM INTO '{}'", target.replace('\'', "''")))
+        .execute(pool)
+        .await
+        .with_context(|| format!("VACUUM INTO {}", tmp.display()))?;
+
+    let verified = async {
+        let copy = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(SqliteConnectOptions::new().filename(&tmp).read_only(true))
+            .await?;
+        let store = Store {
+            pool: StorePool::Sqlite(copy.clone()),
+            read_pool: StorePool::Sqlite(copy.clone()),
+            backend: crate::DatabaseBackend::Sqlite,
+            fill_writes: Default::default(),
+        };
+        let integrity: String = sqlx::query_scalar("PRAGMA integrity_check")
+            .fetch_one(&copy)
+            .await?;
+        let missing = store.validate_required_tables().await?;
+        copy.close().await;
+        if integrity != "ok" {
+            bail!("integrity_check: {integrity}");
+        }
+        if !missing.is_empty() {
+            bail!("missing tables: {}", missing.join(", "));
+        }
+        Ok(())
+    }
+    .await;
+    if let Err(err) = verified {
+        let _ = fs::remove_file(&tmp);
+        return Err(err.context(format!("verifying {}", tmp.display())));
+    }
+
+    File::open(&tmp)?.sync_all()?;
+    fs::rename(&tmp, path).with_context(|| format!("renaming {}", tmp.display()))?;
+    Ok(fs::metadata(path)?.len())
+}
+
+#[cfg(feature = "postgres")]
+async fn postgres_export(
+    pool: &sqlx::PgPool,
+    dir: &Path,
+    zstd_level: i32,
+    now_ms: i64,
+) -> Result<Vec<TableExport>> {
+    use futures_util::TryStreamExt;
+    use std::io::{BufWriter, Write};
+
+    let tmp = partial(dir);
+    let _ = fs::remove_dir_all(&tmp);
+    fs::create_dir_all(&tmp).with_context(|| format!("creating {}", tmp.display()))?;
+
+    // one snapshot for every table, so foreign keys line up in the export
+    let mut tx = pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+    let mut tables = Vec::new();
+    for table in crate::schema::tables() {
+        let rel = format!("{table}.jsonl.zst");
+        let file = File::create(tmp.join(&rel))?;
+        let mut encoder = zstd::Encoder::new(HashingWriter::new(BufWriter::new(file)), zstd_level)?;
+        let sql = format!("SELECT CAST(row_to_json(t) AS TEXT) FROM {table} t");
+        let mut rows = sqlx::query_scalar::<_, String>(&sql).fetch(&mut *tx);
+        let mut count = 0u64;
+        while let Some(line) = rows.try_next().await? {
+            encoder.write_all(line.as_bytes())?;
+            encoder.write_all(b"\n")?;
+            count += 1;
+        }
+        drop(rows);
+        let mut hashing = encoder.finish()?;
+        hashing.flush()?;
+        let HashingWriter {
+            inner,
+            hasher,
+            bytes,
+        } = hashing;
+        inner.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        tables.push(TableExport {
+            table: table.to_string(),
+            path: rel,
+            rows: count,
+            bytes,
+            sha256: hex::encode(hasher.finalize()),
+        });
+    }
+    tx.commit().await?;
+
+    let manifest = ExportManifest {
+        created_at_ms: now_ms,
+        tables,
+    };
+    let manifest_path = tmp.join(EXPORT_MANIFEST_FILE);
+    let mut file = File::create(&manifest_path)?;
+    serde_json::to_writer_pretty(&mut file, &manifest)?;
+    file.sync_all()?;
+
+    if let Err(err) = verify_export(&tmp) {
+        let _ = fs::remove_dir_all(&tmp);
+        return Err(err.context(format!("verifying {}", tmp.display())));
+    }
+    fs::rename(&tmp, dir).with_context(|| format!("renaming {}", tmp.display()))?;
+    Ok(manifest.tables)
+}
+
+fn partial(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(PARTIAL_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Takes a backup every `config.interval` and reports each attempt.
+pub struct BackupJob {
+    store: Store,
+    config: BackupConfig,
+    reports: UnboundedSender<Result<BackupReport, String>>,
+}
+
+impl BackupJob {
+    pub fn new(
+        store: Store,
+        config: BackupConfig,
+    ) -> (Self, UnboundedReceiver<Result<BackupReport, String>>) {
+        let (reports, rx) = mpsc::unbounded_channel();
+        (
+            Self {
+                store,
+                config,
+                reports,
+            },
+            rx,
+        )
+    }
+
+    /// Loops until the report receiver is dropped. The first backup is
+    /// taken one interval after start, not at boot.
+    pub async fn run(&self) {
+        if self.config.interval.is_zero() {
+            return;
+        }
+        let start = tokio::time::Instant::now() + self.config.interval;
+        let mut ticker = tokio::time::interval_at(start, self.config.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let result = self
+                .store
+                .backup(&self.config, Utc::now().timestamp_millis())
+                .await
+                .map_err(|err| format!("{err:#}"));
+            if let Err(err) = &result {
+                warn!(error = %err, "scheduled backup failed");
+            }
+            if self.reports.send(result).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{test_stores, unique};
+    use crate::DatabaseBackend;
+
+    const NOW: i64 = 1_727_740_800_000; // 2024-10-01T00:00:00Z
+
+    #[tokio::test]
+    async fn backups_verify_and_rotate() -> Result<()> {
+        let db = tempfile::tempdir()?;
+        let file_url = format!("sqlite://{}?mode=rwc", db.path().join("bot.db").display());
+        let mut stores = vec![crate::init_sqlite(&file_url).await?];
+        stores.extend(
+            test_stores()
+                .await?
+                .into_iter()
+                .filter(|s| s.backend() == DatabaseBackend::Postgres),
+        );
+        for store in stores {
+            let dir = tempfile::tempdir()?;
+            let run = unique("run-backup");
+            store.insert_run(&run, None).await?;
+            store.log_event(&run, "internal", "tick", "{}").await?;
+            let config = BackupConfig {
+                dir: dir.path().to_path_buf(),
+                keep: 2,
+                ..BackupConfig::default()
+            };
+
+            let first = store.backup(&config, NOW).await?;
+            assert!(first.bytes > 0);
+            assert!(first.rotated.is_empty());
+            match store.backend() {
+                DatabaseBackend::Sqlite => {
+                    assert!(first.path.ends_with("backup-20241001T000000.000Z.db"));
+                    let copy =
+                        crate::init_sqlite(&format!("sqlite://{}", first.path.display())).await?;
+                    assert!(copy.validate_required_tables().await?.is_empty());
+                    let events = with_count(&copy, &run).await?;
+                    assert_eq!(events, 1);
+                }
+                DatabaseBackend::Postgres => {
+                    let manifest = verify_export(&first.path)?;
+                    let runs = manifest.tables.iter().find(|t| t.table == "runs").unwrap();
+                    assert!(runs.rows >= 1);
+                    assert_eq!(manifest.tables, first.tables);
+                }
+            }
+
+            store.backup(&config, NOW + 1_000).await?;
+            let third = store.backup(&config, NOW + 2_000).await?;
+            assert_eq!(third.rotated, vec![first.path.clone()]);
+            let left = list_backups(dir.path())?;
+            assert_eq!(left.len(), 2);
+            assert_eq!(left[1], third.path);
+        }
+        Ok(())
+    }
+
+    async fn with_count(store: &Store, run: &str) -> Result<i64> {
+        let sql = store
+            .backend
+            .sql("SELECT COUNT(*) FROM raw_events WHERE run_id = ?1");
+        Ok(crate::query::with_pool!(&store.pool, |pool| {
+            sqlx::query_scalar::<_, i64>(&sql)
+                .bind(run)
+                .fetch_one(pool)
+                .await?
+        }))
+    }
+
+    #[tokio::test]
+    async fn refuses_in_memory_sqlite() -> Result<()> {
+        let store = crate::init_sqlite("sqlite::memory:").await?;
+        let dir = tempfile::tempdir()?;
+        let config = BackupConfig {
+            dir: dir.path().to_path_buf(),
+            ..BackupConfig::default()
+        };
+        assert!(store.backup(&config, NOW).await.is_err());
+        assert!(list_backups(dir.path())?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn zero_keep_disables_rotation() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for i in 0..3 {
+            fs::write(dir.path().join(backup_name(NOW + i, ".db")), b"x")?;
+        }
+        fs::write(dir.path().join("backup-x.db.partial"), b"x")?;
+        fs::write(dir.path().join("unrelated.db"), b"x")?;
+        assert!(rotate(dir.path(), 0)?.is_empty());
+        assert_eq!(list_backups(dir.path())?.len(), 3);
+        assert_eq!(rotate(dir.path(), 1)?.len(), 2);
+        assert_eq!(list_backups(dir.path())?.len(), 1);
+        Ok(())
+    }
+}
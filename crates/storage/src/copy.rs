@@ -0,0 +1,441 @@
+//! Whole-database copy between stores, typically SQLite → Postgres
+//! (`traderctl db migrate-to`). Tables are copied parents first with their
+//! primary keys, so every id and foreign key survives the move; the copy is
+//! then checked table by table against the source.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+use crate::query::with_pool;
+use crate::rows::{bind_value, Kind, TablePlan, Value};
+use crate::schema::COLUMNS;
+use crate::{DatabaseBackend, Store};
+
+/// Bind parameters per INSERT, under SQLite's limit of 32766 (Postgres
+/// allows 65535).
+const MAX_PARAMS: usize = 30_000;
+
+/// Owned by the migrations, which have already filled it on the target.
+const MIGRATION_TABLE: &str = "schema_meta";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CopyConfig {
+    /// Rows read and inserted per statement; capped so one INSERT stays
+    /// under the backend's bind parameter limit.
+    pub batch_size: usize,
+}
+
+impl Default for CopyConfig {
+    fn default() -> Self {
+        Self { batch_size: 1_000 }
+    }
+}
+
+/// Row count and checksum of one table, equal on source and target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableCopy {
+    pub table: String,
+    pub rows: u64,
+    /// Hex digest over every row, independent of row order.
+    pub checksum: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct CopyReport {
+    pub tables: Vec<TableCopy>,
+    pub elapsed: Duration,
+}
+
+impl TablePlan {
+    fn page_size(&self, config: &CopyConfig) -> usize {
+        config
+            .batch_size
+            .min(MAX_PARAMS / self.columns.len())
+            .max(1)
+    }
+}
+
+/// Order-independent table digest: the XOR of each row's sha256. Rows are
+/// distinct by primary key, so none cancel out, and the two backends may
+/// sort text keys differently without changing the result.
+#[derive(Default)]
+struct TableDigest {
+    rows: u64,
+    acc: [u8; 32],
+}
+
+impl TableDigest {
+    fn add(&mut self, row: &[Value]) {
+        let mut hasher = Sha256::new();
+        for value in row {
+            match value {
+                Value::Int(Some(v)) => hasher.update(format!("i{v}")),
+                // Postgres stores REAL as 4 bytes, so compare at that
+                // precision; a SQLite f64 → f32 → f64 round trip is exact.
+                Value::Float(Some(v)) => hasher.update(format!("f{}", (*v as f32).to_bits())),
+                Value::Text(Some(v)) => hasher.update(format!("t{}:{v}", v.len())),
+                Value::Int(None) | Value::Float(None) | Value::Text(None) => hasher.update("n"),
+            }
+            hasher.update([0x1f]);
+        }
+        for (acc, byte) in self.acc.iter_mut().zip(hasher.finalize()) {
+            *acc ^= byte;
+        }
+        self.rows += 1;
+    }
+
+    fn finish(self, table: &str) -> TableCopy {
+        TableCopy {
+            table: table.to_string(),
+            rows: self.rows,
+            checksum: hex::encode(self.acc),
+        }
+    }
+}
+
+impl Store {
+    /// Copy every table into `target`, which must be freshly migrated and
+    /// empty, keeping primary keys. Postgres sequences on the target are
+    /// moved past the copied ids. Fails unless every table then has the
+    /// same row count and checksum on both sides. Stop writers on the
+    /// source first; rows written during the copy may be missed.
+    pub async fn copy_to(&self, target: &Store, config: &CopyConfig) -> Result<CopyReport> {
+        let started = Instant::now();
+        let (from, to) = (self.schema_version().await?, target.schema_version().await?);
+        if from != to {
+            bail!("schema versions differ: source {from:?}, target {to:?}");
+        }
+
+        let mut plans = Vec::with_capacity(COLUMNS.len());
+        for (table, columns) in COLUMNS {
+            plans.push(self.table_plan(table, columns).await?);
+        }
+        for plan in plans.iter().filter(|p| p.table != MIGRATION_TABLE) {
+            let rows = target.count_rows(plan.table).await?;
+            if rows > 0 {
+                bail!(
+                    "target is not empty: {} has {rows} rows; copy into a new database",
+                    plan.table
+                );
+            }
+        }
+
+        for plan in plans.iter().filter(|p| p.table != MIGRATION_TABLE) {
+            let mut copied = 0u64;
+            let mut after = None;
+            loop {
+                let page = self
+                    .fetch_page(plan, None, after.as_ref(), plan.page_size(config))
+                    .await?;
+                let Some(last) = page.last() else { break };
+                after = Some(last[0].clone());
+                target
+                    .insert_page(plan, &page)
+                    .await
+                    .with_context(|| format!("inserting into {}", plan.table))?;
+                copied += page.len() as u64;
+            }
+            info!(table = plan.table, rows = copied, "table copied");
+        }
+        target.reset_sequences(&plans).await?;
+
+        let mut tables = Vec::with_capacity(plans.len());
+        let mut mismatched = Vec::new();
+        for plan in &plans {
+            let source = self.digest(plan, config).await?;
+            let copy = target.digest(plan, config).await?;
+            if source != copy {
+                mismatched.push(format!(
+                    "{} (source {} rows {}, target {} rows {})",
+                    plan.table, source.rows, source.checksum, copy.rows, copy.checksum
+                ));
+            }
+            tables.push(source);
+        }
+        if !mismatched.is_empty() {
+            bail!("copy does not match the source: {}", mismatched.join(", "));
+        }
+        Ok(CopyReport {
+            tables,
+            elapsed: started.elapsed(),
+        })
+    }
+
+    /// Row counts and checksums of every table, as [`Store::copy_to`]
+    /// compares them.
+    pub async fn table_checksums(&self, config: &CopyConfig) -> Result<Vec<TableCopy>> {
+        let mut tables = Vec::with_capacity(COLUMNS.len());
+        for (table, columns) in COLUMNS {
+            let plan = self.table_plan(table, columns).await?;
+            tables.push(self.digest(&plan, config).await?);
+        }
+        Ok(tables)
+    }
+
+    async fn count_rows(&self, table: &str) -> Result<i64> {
+        let sql = format!("SELECT COUNT(*) FROM {table}");
+        let rows = with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar::<_, i64>(&sql).fetch_one(pool).await?
+        });
+        Ok(rows)
+    }
+
+    /// Insert `page` as one statement, so a batch lands whole or not at all.
+    async fn insert_page(&self, plan: &TablePlan, page: &[Vec<Value>]) -> Result<()> {
+        let width = plan.columns.len();
+        let values = (0..page.len())
+            .map(|r| {
+                let row = (1..=width)
+                    .map(|c| format!("?{}", r * width + c))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("({row})")
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let stmt = format!(
+            "INSERT INTO {} ({}) VALUES {values}",
+            plan.table,
+            plan.columns.join(", ")
+        );
+        let sql = self.backend.sql(&stmt);
+        with_pool!(&self.pool, |pool| {
+            let mut query = sqlx::query(&sql);
+            for value in page.iter().flatten() {
+                query = bind_value!(query, value);
+            }
+            query.execute(pool).await?;
+        });
+        Ok(())
+    }
+
+    /// Point each Postgres SERIAL sequence past the largest copied id, so
+    /// the next insert does not collide. SQLite derives the next rowid from
+    /// the table itself.
+    async fn reset_sequences(&self, plans: &[TablePlan]) -> Result<()> {
+        if self.backend != DatabaseBackend::Postgres {
+            return Ok(());
+        }
+        for plan in plans.iter().filter(|p| p.kinds[0] == Kind::Int) {
+            let key = plan.primary_key();
+            // setval ignores a NULL sequence, i.e. a key that is not SERIAL.
+            let stmt = format!(
+                "SELECT CAST(setval(pg_get_serial_sequence(?1, ?2), COALESCE(MAX({key}), 0) + 1, false) AS BIGINT) FROM {}",
+                plan.table
+            );
+            let sql = self.backend.sql(&stmt);
+            with_pool!(&self.pool, |pool| {
+                sqlx::query_scalar::<_, Option<i64>>(&sql)
+                    .bind(plan.table)
+                    .bind(key)
+                    .fetch_one(pool)
+                    .await?;
+            });
+        }
+        Ok(())
+    }
+
+    async fn digest(&self, plan: &TablePlan, config: &CopyConfig) -> Result<TableCopy> {
+        let mut digest = TableDigest::default();
+        let mut after = None;
+        loop {
+            let page = self
+                .fetch_page(plan, None, after.as_ref(), plan.page_size(config))
+                .await?;
+            let Some(last) = page.last() else { break };
+            after = Some(last[0].clone());
+            for row in &page {
+                digest.add(row);
+            }
+        }
+        Ok(digest.finish(plan.table))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fresh_postgres_url;
+    use crate::{ApprovalRow, FillRow, IntentRow, OrderRow, PnlEntry, SnapshotRow};
+
+    async fn seed(store: &Store) -> Result<i64> {
+        for run in ["run-b", "run-a"] {
+            store.insert_run(run, Some("abc123")).await?;
+            store
+                .log_event(run, "polymarket", "book", r#"{"px":0.5}"#)
+                .await?;
+            store.log_incident(run, "warning", "TEST", "héllo").await?;
+        }
+        let snapshot_id = store
+            .insert_snapshot(&SnapshotRow {
+                run_id: "run-a".into(),
+                ts_ms: 1_000,
+                market_id: 7,
+                best_bid_px: Some(0.25),
+                best_bid_qty: None,
+                best_ask_px: Some(0.75),
+                best_ask_qty: Some(3.0),
+                spread: Some(0.5),
+                yes_qty: 1.0,
+                no_qty: 0.0,
+                net_exposure_usd: 0.125,
+                can_trade: true,
+                drawdown_halt: false,
+                crowding_score: 0.0,
+                toxicity_score: 0.0,
+                spread_compression: 0.0,
+                feature_schema_version: 1,
+                features_json: "{}".into(),
+            })
+            .await?;
+        let intent_id = store
+            .insert_intent(&IntentRow {
+                run_id: "run-a".into(),
+                ts_ms: 1_001,
+                snapshot_id,
+                strategy: "mm".into(),
+                market_id: 7,
+                intent_kind: "PlaceOrder".into(),
+                side: Some("BuyYes".into()),
+                price: Some(0.25),
+                size: Some(4.0),
+                urgency: "maker".into(),
+                ttl_ms: 500,
+                expected_value: 0.015625,
+                confidence: 0.5,
+                risk_cost: 0.0,
+                tags_json: r#"["quote"]"#.into(),
+                rationale_json: None,
+                tier: Some(1),
+                leg_group: Some("g1".into()),
+            })
+            .await?;
+        let approved_id = store
+            .insert_approval(&ApprovalRow {
+                run_id: "run-a".into(),
+                ts_ms: 1_002,
+                intent_id,
+                approved: true,
+                reason: None,
+                owner_strategy: Some("mm".into()),
+            })
+            .await?;
+        store
+            .upsert_order(&OrderRow {
+                run_id: "run-a".into(),
+                client_order_id: "c-1".into(),
+                order_id: Some("0x1".into()),
+                approved_id: Some(approved_id),
+                intent_id: Some(intent_id),
+                strategy: "mm".into(),
+                market_id: 7,
+                venue: "polymarket".into(),
+                status: "Filled".into(),
+                side: "BuyYes".into(),
+                limit_price: 0.25,
+                qty: 4.0,
+                ts_submitted_ms: 1_003,
+                ts_acked_ms: None,
+                ts_final_ms: Some(1_010),
+                submit_latency_ms: Some(2),
+                notes: None,
+            })
+            .await?;
+        store
+            .insert_fill(&FillRow {
+                run_id: "run-a".into(),
+                ts_ms: 1_005,
+                venue: "polymarket".into(),
+                fill_id: Some("f-1".into()),
+                order_id: Some("0x1".into()),
+                client_order_id: Some("c-1".into()),
+                market_id: 7,
+                strategy: "mm".into(),
+                side: "BuyYes".into(),
+                price: 0.25,
+                qty: 4.0,
+                fee_usd: None,
+                liquidity: Some("maker".into()),
+                raw_json: None,
+            })
+            .await?;
+        store
+            .insert_pnl(&PnlEntry {
+                run_id: "run-a".into(),
+                ts_ms: 1_006,
+                market_id: 7,
+                strategy: Some("mm".into()),
+                kind: "fee".into(),
+                reference: Some("f-1".into()),
+                pnl_usd: -0.0625,
+                notes: None,
+            })
+            .await?;
+        Ok(intent_id)
+    }
+
+    #[tokio::test]
+    async fn copies_every_table_with_ids_and_verifies() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let source = Store::connect(&format!(
+            "sqlite://{}?mode=rwc",
+            dir.path().join("source.db").display()
+        ))
+        .await?;
+        let intent_id = seed(&source).await?;
+        // Leave a gap so ids only line up if they are copied, not renumbered.
+        source.log_event("run-a", "polymarket", "gap", "{}").await?;
+        with_pool!(&source.pool, |pool| {
+            sqlx::query("DELETE FROM raw_events WHERE topic = 'gap'")
+                .execute(pool)
+                .await?;
+        });
+        source
+            .log_event("run-a", "polymarket", "after", "{}")
+            .await?;
+
+        let mut targets = vec![format!(
+            "sqlite://{}?mode=rwc",
+            dir.path().join("target.db").display()
+        )];
+        targets.extend(fresh_postgres_url().await?);
+        for url in targets {
+            let target = Store::connect(&url).await?;
+            let config = CopyConfig { batch_size: 2 };
+            let report = source.copy_to(&target, &config).await?;
+            assert_eq!(report.tables.len(), COLUMNS.len());
+            let rows = |table: &str| {
+                report
+                    .tables
+                    .iter()
+                    .find(|t| t.table == table)
+                    .map(|t| t.rows)
+            };
+            assert_eq!(rows("runs"), Some(2));
+            assert_eq!(rows("raw_events"), Some(3));
+            assert_eq!(
+                target.table_checksums(&config).await?,
+                source.table_checksums(&config).await?
+            );
+            assert_eq!(
+                target.fetch_intent(intent_id).await?,
+                source.fetch_intent(intent_id).await?
+            );
+
+            // Sequences continue after the copied ids.
+            let fill = source.fills_for_run("run-a").await?.remove(0);
+            let next = FillRow {
+                fill_id: Some("f-2".into()),
+                ..fill
+            };
+            assert_eq!(target.insert_fill(&next).await?, 2);
+
+            let err = source.copy_to(&target, &config).await.unwrap_err();
+            assert!(err.to_string().contains("not empty"), "{err}");
+        }
+        Ok(())
+    }
+}
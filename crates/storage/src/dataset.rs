@@ -0,0 +1,792 @@
+//! Per-run Parquet dataset for the research repo (see Upstreams.md), so
+//! training code reads typed columns instead of parsing `features_json`.
+//!
+//! `snapshots`, `strategy_intents`, `fills` and `pnl_ledger` rows of one
+//! run are written under Hive-style partitions:
+//!
+//! ```text
+//! <dir>/<table>/run=<run_id>/day=<YYYY-MM-DD>/part-0.parquet
+//! <dir>/snapshots/run=<run_id>/feature_schema_version=<v>/day=<YYYY-MM-DD>/part-0.parquet
+//! <dir>/manifests/run=<run_id>.json
+//! ```
+//!
+//! Snapshot features are expanded into one nullable `f_<name>` float
+//! column per name in `feature_schemas`, so each feature schema version
+//! has its own Parquet schema. A version without a `feature_schemas` row
+//! keeps the raw `features_json` text column. Files are written to
+//! `<dir>/.staging` (hidden from Parquet dataset readers) and moved into
+//! place once every table is written, replacing an earlier export of the
+//! run; the manifest, with every column's type and every file's sha256,
+//! is written last.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use chrono::{DateTime, Utc};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use tracing::info;
+
+use crate::query::with_pool;
+use crate::retention::{path_safe, HashingWriter};
+use crate::rows::{Kind, TablePlan, Value};
+use crate::schema::COLUMNS;
+use crate::Store;
+
+/// Tables exported, in manifest order.
+pub const DATASET_TABLES: [&str; 4] = ["snapshots", "strategy_intents", "fills", "pnl_ledger"];
+
+/// Directory under the dataset root holding one manifest per run.
+pub const DATASET_MANIFEST_DIR: &str = "manifests";
+
+/// Prefix of the columns expanded from `snapshots.features_json`.
+pub const FEATURE_COLUMN_PREFIX: &str = "f_";
+
+const STAGING_DIR: &str = ".staging";
+const PART_FILE: &str = "part-0.parquet";
+
+/// INTEGER columns that only ever hold 0 or 1.
+const BOOLEAN_COLUMNS: &[(&str, &str)] =
+    &[("snapshots", "can_trade"), ("snapshots", "drawdown_halt")];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetConfig {
+    pub dir: PathBuf,
+    /// Rows read from the database per query.
+    pub batch_size: usize,
+    /// Rows per Parquet row group.
+    pub row_group_rows: usize,
+    pub zstd_level: i32,
+}
+
+impl Default for DatasetConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("dataset"),
+            batch_size: 10_000,
+            row_group_rows: 100_000,
+            zstd_level: 3,
+        }
+    }
+}
+
+/// One column of an exported table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatasetColumn {
+    pub name: String,
+    /// Arrow type: `int64`, `float64`, `boolean` or `utf8`.
+    pub data_type: String,
+    pub nullable: bool,
+}
+
+/// One Parquet file of an exported table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatasetFile {
+    /// Relative to the dataset root.
+    pub path: String,
+    pub day: String,
+    pub rows: u64,
+    pub bytes: u64,
+    pub sha256: String,
+}
+
+/// One table of a run's export. Snapshots have one entry per feature
+/// schema version seen in the run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatasetTable {
+    pub table: String,
+    pub feature_schema_version: Option<i64>,
+    /// Feature names, in `features_json` order, behind the `f_` columns.
+    /// Empty when the version has no `feature_schemas` row and
+    /// `features_json` is kept as text.
+    pub features: Vec<String>,
+    pub columns: Vec<DatasetColumn>,
+    pub rows: u64,
+    pub files: Vec<DatasetFile>,
+}
+
+/// `manifests/run=<run_id>.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatasetManifest {
+    pub run_id: String,
+    pub exported_at_ms: i64,
+    /// Database schema version the rows were read from.
+    pub schema_version: Option<i64>,
+    pub tables: Vec<DatasetTable>,
+}
+
+pub fn dataset_manifest_path(dir: &Path, run_id: &str) -> PathBuf {
+    dir.join(DATASET_MANIFEST_DIR)
+        .join(format!("run={}.json", path_safe(run_id)))
+}
+
+pub fn read_dataset_manifest(dir: &Path, run_id: &str) -> Result<DatasetManifest> {
+    let path = dataset_manifest_path(dir, run_id);
+    let file = File::open(&path).with_context(|| format!("opening {}", path.display()))?;
+    serde_json::from_reader(file).with_context(|| format!("parsing {}", path.display()))
+}
+
+/// Re-hash every file in a run's manifest. Returns the problems found;
+/// empty means the export is intact.
+pub fn verify_dataset(dir: &Path, run_id: &str) -> Result<Vec<String>> {
+    let manifest = read_dataset_manifest(dir, run_id)?;
+    let mut problems = Vec::new();
+    for file in manifest.tables.iter().flat_map(|t| &t.files) {
+        let mut source = match File::open(dir.join(&file.path)) {
+            Ok(source) => source,
+            Err(err) => {
+                problems.push(format!("{}: {err}", file.path));
+                continue;
+            }
+        };
+        let mut hashing = HashingWriter::new(io::sink());
+        io::copy(&mut source, &mut hashing)?;
+        let sha256 = hex::encode(hashing.hasher.finalize());
+        if hashing.bytes != file.bytes || sha256 != file.sha256 {
+            problems.push(format!(
+                "{}: expected {} bytes sha256 {}, found {} bytes sha256 {sha256}",
+                file.path, file.bytes, file.sha256, hashing.bytes
+            ));
+        }
+    }
+    Ok(problems)
+}
+
+fn day_of(ts_ms: i64) -> String {
+    DateTime::<Utc>::from_timestamp_millis(ts_ms)
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| ts_ms.to_string())
+}
+
+fn remove_dir(path: &Path) -> Result<()> {
+    match fs::remove_dir_all(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("removing {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// `features_json` as values in `names` order: either an array in that
+/// order or an object keyed by name. JSON nulls and missing keys are
+/// NULL.
+fn parse_features(json: &str, names: &[String]) -> Result<Vec<Option<f64>>> {
+    let number = |name: &str, v: &serde_json::Value| match v {
+        serde_json::Value::Null => Ok(None),
+        v => v
+            .as_f64()
+            .map(Some)
+            .with_context(|| format!("feature {name} is not a number: {v}")),
+    };
+    match serde_json::from_str(json)? {
+        serde_json::Value::Array(values) => {
+            if values.len() != names.len() {
+                bail!("{} features, schema has {}", values.len(), names.len());
+            }
+            names
+                .iter()
+                .zip(&values)
+                .map(|(n, v)| number(n, v))
+                .collect()
+        }
+        serde_json::Value::Object(values) => names
+            .iter()
+            .map(|n| values.get(n).map_or(Ok(None), |v| number(n, v)))
+            .collect(),
+        other => bail!("expected an array or object, got {other}"),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Source {
+    Int(usize),
+    Bool(usize),
+    Float(usize),
+    Text(usize),
+    /// Index into the parsed features of the row.
+    Feature(usize),
+}
+
+/// Arrow schema of one table (and feature schema version) and where each
+/// column comes from.
+struct Layout {
+    schema: SchemaRef,
+    sources: Vec<Source>,
+    /// Position of `features_json` in the row when it is expanded.
+    features_at: Option<usize>,
+    features: Vec<String>,
+    /// Partition directory below the table's run directory, if any.
+    subdir: Option<String>,
+}
+
+impl Layout {
+    fn new(plan: &TablePlan, version: Option<i64>, features: Option<Vec<String>>) -> Self {
+        let mut fields = Vec::new();
+        let mut sources = Vec::new();
+        let mut features_at = None;
+        for (i, column) in plan.columns.iter().enumerate() {
+            if *column == "features_json" && features.is_some() {
+                features_at = Some(i);
+                continue;
+            }
+            let (data_type, source) = match plan.kinds[i] {
+                _ if BOOLEAN_COLUMNS.contains(&(plan.table, *column)) => {
+                    (DataType::Boolean, Source::Bool(i))
+                }
+                Kind::Int => (DataType::Int64, Source::Int(i)),
+                Kind::Float => (DataType::Float64, Source::Float(i)),
+                Kind::Text => (DataType::Utf8, Source::Text(i)),
+            };
+            fields.push(Field::new(*column, data_type, plan.nullable[i]));
+            sources.push(source);
+        }
+        let features = features.unwrap_or_default();
+        for (j, name) in features.iter().enumerate() {
+            fields.push(Field::new(
+                format!("{FEATURE_COLUMN_PREFIX}{name}"),
+                DataType::Float64,
+                true,
+            ));
+            sources.push(Source::Feature(j));
+        }
+        Self {
+            schema: Arc::new(Schema::new(fields)),
+            sources,
+            features_at,
+            features,
+            subdir: version.map(|v| format!("feature_schema_version={v}")),
+        }
+    }
+
+    fn columns(&self) -> Vec<DatasetColumn> {
+        self.schema
+            .fields()
+            .iter()
+            .map(|f| DatasetColumn {
+                name: f.name().clone(),
+                data_type: match f.data_type() {
+                    DataType::Int64 => "int64",
+                    DataType::Float64 => "float64",
+                    DataType::Boolean => "boolean",
+                    _ => "utf8",
+                }
+                .to_string(),
+                nullable: f.is_nullable(),
+            })
+            .collect()
+    }
+
+    fn batch(&self, rows: &[&Vec<Value>]) -> Result<RecordBatch> {
+        let features = match self.features_at {
+            Some(at) => rows
+                .iter()
+                .map(|row| {
+                    let (Value::Int(Some(id)), Value::Text(Some(json))) = (&row[0], &row[at])
+                    else {
+                        bail!("snapshot without id or features_json");
+                    };
+                    parse_features(json, &self.features)
+                        .with_context(|| format!("features_json of snapshot {id}"))
+                })
+                .collect::<Result<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        let int = |row: &Vec<Value>, i: usize| match &row[i] {
+            Value::Int(v) => *v,
+            _ => None,
+        };
+        let arrays = self
+            .sources
+            .iter()
+            .map(|source| -> ArrayRef {
+                match *source {
+                    Source::Int(i) => {
+                        Arc::new(Int64Array::from_iter(rows.iter().map(|r| int(r, i))))
+                    }
+                    Source::Bool(i) => Arc::new(BooleanArray::from_iter(
+                        rows.iter().map(|r| int(r, i).map(|v| v != 0)),
+                    )),
+                    Source::Float(i) => {
+                        Arc::new(Float64Array::from_iter(rows.iter().map(|r| match &r[i] {
+                            Value::Float(v) => *v,
+                            _ => None,
+                        })))
+                    }
+                    Source::Text(i) => {
+                        Arc::new(StringArray::from_iter(rows.iter().map(|r| match &r[i] {
+                            Value::Text(v) => v.as_deref(),
+                            _ => None,
+                        })))
+                    }
+                    Source::Feature(j) => {
+                        Arc::new(Float64Array::from_iter(features.iter().map(|f| f[j])))
+                    }
+                }
+            })
+            .collect();
+        Ok(RecordBatch::try_new(self.schema.clone(), arrays)?)
+    }
+}
+
+/// An open Parquet file of one partition.
+struct Part {
+    /// Relative to the table's run directory.
+    rel: PathBuf,
+    day: String,
+    rows: u64,
+    writer: ArrowWriter<HashingWriter<BufWriter<File>>>,
+}
+
+impl Part {
+    fn finish(self, final_dir: &str) -> Result<DatasetFile> {
+        let HashingWriter {
+            inner,
+            hasher,
+            bytes,
+        } = self.writer.into_inner()?;
+        inner.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        Ok(DatasetFile {
+            path: format!(
+                "{final_dir}/{}",
+                self.rel.to_string_lossy().replace('\\', "/")
+            ),
+            day: self.day,
+            rows: self.rows,
+            bytes,
+            sha256: hex::encode(hasher.finalize()),
+        })
+    }
+}
+
+impl Store {
+    /// Write one run's snapshots, intents, fills and PnL ledger to
+    /// Parquet under `config.dir`, replacing an earlier export of the run,
+    /// and return the manifest written alongside.
+    pub async fn export_dataset(
+        &self,
+        run_id: &str,
+        config: &DatasetConfig,
+        now_ms: i64,
+    ) -> Result<DatasetManifest> {
+        let sql = self
+            .backend
+            .sql("SELECT COUNT(*) FROM runs WHERE run_id = ?1");
+        let known: i64 = with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar(&sql)
+                .bind(run_id)
+                .fetch_one(pool)
+                .await?
+        });
+        if known == 0 {
+            bail!("run {run_id} not found");
+        }
+
+        let run_dir = format!("run={}", path_safe(run_id));
+        let staging = config.dir.join(STAGING_DIR);
+        for table in DATASET_TABLES {
+            remove_dir(&staging.join(table).join(&run_dir))?;
+        }
+
+        let mut tables = Vec::new();
+        for table in DATASET_TABLES {
+            let (table, columns) = COLUMNS
+                .iter()
+                .find(|(name, _)| *name == table)
+                .with_context(|| format!("no columns for {table}"))?;
+            let plan = self.table_plan(table, columns).await?;
+            let staged = staging.join(table).join(&run_dir);
+            tables.extend(
+                self.export_table(
+                    &plan,
+                    run_id,
+                    &staged,
+                    &format!("{table}/{run_dir}"),
+                    config,
+                )
+                .await
+                .with_context(|| format!("exporting {table}"))?,
+            );
+        }
+
+        for table in DATASET_TABLES {
+            let staged = staging.join(table).join(&run_dir);
+            let target = config.dir.join(table).join(&run_dir);
+            remove_dir(&target)?;
+            if staged.exists() {
+                fs::create_dir_all(config.dir.join(table))?;
+                fs::rename(&staged, &target)
+                    .with_context(|| format!("moving {} into place", staged.display()))?;
+            }
+            // Only succeeds once empty, i.e. no other run is being exported.
+            let _ = fs::remove_dir(staging.join(table));
+        }
+        let _ = fs::remove_dir(&staging);
+
+        let manifest = DatasetManifest {
+            run_id: run_id.to_string(),
+            exported_at_ms: now_ms,
+            schema_version: self.schema_version().await?,
+            tables,
+        };
+        let path = dataset_manifest_path(&config.dir, run_id);
+        let tmp = path.with_extension("json.tmp");
+        fs::create_dir_all(config.dir.join(DATASET_MANIFEST_DIR))?;
+        fs::write(&tmp, serde_json::to_vec_pretty(&manifest)?)
+            .with_context(|| format!("writing {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("renaming {}", tmp.display()))?;
+        info!(
+            run_id,
+            files = manifest.tables.iter().map(|t| t.files.len()).sum::<usize>(),
+            dir = %config.dir.display(),
+            "dataset exported"
+        );
+        Ok(manifest)
+    }
+
+    /// Stream one table of the run into `staged`, one file per partition.
+    /// `final_dir` is where the files will live relative to the root.
+    async fn export_table(
+        &self,
+        plan: &TablePlan,
+        run_id: &str,
+        staged: &Path,
+        final_dir: &str,
+        config: &DatasetConfig,
+    ) -> Result<Vec<DatasetTable>> {
+        let ts_at = plan.position("ts_ms").context("no ts_ms column")?;
+        let version_at = plan.position("feature_schema_version");
+        let props = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::try_new(config.zstd_level)?))
+            .set_max_row_group_size(config.row_group_rows.max(1))
+            .build();
+
+        let mut layouts: BTreeMap<Option<i64>, Layout> = BTreeMap::new();
+        if version_at.is_none() {
+            layouts.insert(None, Layout::new(plan, None, None));
+        }
+        let mut parts: BTreeMap<(Option<i64>, String), Part> = BTreeMap::new();
+        let mut after = None;
+        loop {
+            let page = self
+                .fetch_page(plan, Some(run_id), after.as_ref(), config.batch_size.max(1))
+                .await?;
+            let Some(last) = page.last() else { break };
+            after = Some(last[0].clone());
+
+            let mut groups: BTreeMap<(Option<i64>, String), Vec<&Vec<Value>>> = BTreeMap::new();
+            for row in &page {
+                let version = match version_at.map(|at| &row[at]) {
+                    Some(Value::Int(Some(v))) => Some(*v),
+                    Some(_) => bail!("row without feature_schema_version"),
+                    None => None,
+                };
+                let Value::Int(Some(ts_ms)) = row[ts_at] else {
+                    bail!("row without ts_ms");
+                };
+                groups
+                    .entry((version, day_of(ts_ms)))
+                    .or_default()
+                    .push(row);
+            }
+
+            for ((version, day), rows) in groups {
+                if let (Some(v), false) = (version, layouts.contains_key(&version)) {
+                    let features = self.feature_schema(v).await?;
+                    layouts.insert(version, Layout::new(plan, version, features));
+                }
+                let layout = &layouts[&version];
+                let key = (version, day);
+                if !parts.contains_key(&key) {
+                    let mut rel = PathBuf::new();
+                    if let Some(subdir) = &layout.subdir {
+                        rel.push(subdir);
+                    }
+                    rel.push(format!("day={}", key.1));
+                    rel.push(PART_FILE);
+                    let path = staged.join(&rel);
+                    if let Some(dir) = path.parent() {
+                        fs::create_dir_all(dir)
+                            .with_context(|| format!("creating {}", dir.display()))?;
+                    }
+                    let file = File::create(&path)
+                        .with_context(|| format!("creating {}", path.display()))?;
+                    let writer = ArrowWriter::try_new(
+                        HashingWriter::new(BufWriter::new(file)),
+                        layout.schema.clone(),
+                        Some(props.clone()),
+                    )?;
+                    parts.insert(
+                        key.clone(),
+                        Part {
+                            rel,
+                            day: key.1.clone(),
+                            rows: 0,
+                            writer,
+                        },
+                    );
+                }
+                let part = parts.get_mut(&key).expect("inserted above");
+                part.writer.write(&layout.batch(&rows)?)?;
+                part.rows += rows.len() as u64;
+            }
+        }
+
+        let mut tables: BTreeMap<Option<i64>, DatasetTable> = layouts
+            .iter()
+            .map(|(version, layout)| {
+                let table = DatasetTable {
+                    table: plan.table.to_string(),
+                    feature_schema_version: *version,
+                    features: layout.features.clone(),
+                    columns: layout.columns(),
+                    rows: 0,
+                    files: Vec::new(),
+                };
+                (*version, table)
+            })
+            .collect();
+        for ((version, _), part) in parts {
+            let table = tables.get_mut(&version).expect("layout per partition");
+            let file = part.finish(final_dir)?;
+            table.rows += file.rows;
+            table.files.push(file);
+        }
+        Ok(tables.into_values().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::*;
+    use crate::tests::fresh_stores;
+    use crate::{FillRow, IntentRow, PnlEntry, SnapshotRow};
+
+    const DAY_MS: i64 = 86_400_000;
+
+    fn snapshot(run: &str, ts_ms: i64, version: i64, features_json: &str) -> SnapshotRow {
+        SnapshotRow {
+            run_id: run.into(),
+            ts_ms,
+            market_id: 7,
+            best_bid_px: Some(0.25),
+            best_bid_qty: None,
+            best_ask_px: Some(0.75),
+            best_ask_qty: Some(3.0),
+            spread: Some(0.5),
+            yes_qty: 1.0,
+            no_qty: 0.0,
+            net_exposure_usd: 0.125,
+            can_trade: true,
+            drawdown_halt: false,
+            crowding_score: 0.0,
+            toxicity_score: 0.0,
+            spread_compression: 0.0,
+            feature_schema_version: version,
+            features_json: features_json.into(),
+        }
+    }
+
+    async fn seed(store: &Store, run: &str) -> Result<()> {
+        store.insert_run(run, None).await?;
+        store.insert_run("other", None).await?;
+        let names = vec!["mid".to_string(), "imbalance".to_string()];
+        store
+            .register_feature_schema(1, "mid and imbalance", &names, 0)
+            .await?;
+        assert!(store
+            .register_feature_schema(1, "changed", &names[..1], 0)
+            .await
+            .is_err());
+
+        let first = store
+            .insert_snapshot(&snapshot(run, 1_000, 1, "[0.5, null]"))
+            .await?;
+        store
+            .insert_snapshot(&snapshot(run, DAY_MS + 1_000, 1, r#"{"imbalance": -0.25}"#))
+            .await?;
+        store
+            .insert_snapshot(&snapshot(run, DAY_MS + 2_000, 9, r#"{"raw": 1}"#))
+            .await?;
+        store
+            .insert_snapshot(&snapshot("other", 1_000, 1, "[0.5, 0.5]"))
+            .await?;
+        store
+            .insert_intent(&IntentRow {
+                run_id: run.into(),
+                ts_ms: 1_001,
+                snapshot_id: first,
+                strategy: "mm".into(),
+                market_id: 7,
+                intent_kind: "PlaceOrder".into(),
+                side: Some("BuyYes".into()),
+                price: Some(0.25),
+                size: Some(4.0),
+                urgency: "maker".into(),
+                ttl_ms: 500,
+                expected_value: 0.015625,
+                confidence: 0.5,
+                risk_cost: 0.0,
+                tags_json: r#"["quote"]"#.into(),
+                rationale_json: None,
+                tier: Some(1),
+                leg_group: None,
+            })
+            .await?;
+        store
+            .insert_fill(&FillRow {
+                run_id: run.into(),
+                ts_ms: 1_005,
+                venue: "polymarket".into(),
+                fill_id: Some("f-1".into()),
+                order_id: Some("0x1".into()),
+                client_order_id: Some("c-1".into()),
+                market_id: 7,
+                strategy: "mm".into(),
+                side: "BuyYes".into(),
+                price: 0.25,
+                qty: 4.0,
+                fee_usd: None,
+                liquidity: Some("maker".into()),
+                raw_json: None,
+            })
+            .await?;
+        store
+            .insert_pnl(&PnlEntry {
+                run_id: run.into(),
+                ts_ms: 1_006,
+                market_id: 7,
+                strategy: Some("mm".into()),
+                kind: "fee".into(),
+                reference: Some("f-1".into()),
+                pnl_usd: -0.0625,
+                notes: None,
+            })
+            .await?;
+        Ok(())
+    }
+
+    fn read(path: &Path) -> Result<RecordBatch> {
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?.build()?;
+        let mut batches = reader.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(batches.len(), 1, "one batch per test file");
+        Ok(batches.remove(0))
+    }
+
+    #[tokio::test]
+    async fn exports_typed_partitions_with_expanded_features() -> Result<()> {
+        for store in fresh_stores().await? {
+            let dir = tempfile::tempdir()?;
+            let config = DatasetConfig {
+                dir: dir.path().to_path_buf(),
+                ..DatasetConfig::default()
+            };
+            seed(&store, "run/1").await?;
+            assert!(store.export_dataset("nope", &config, 0).await.is_err());
+
+            // A second export replaces the first.
+            store.export_dataset("run/1", &config, 1).await?;
+            let manifest = store.export_dataset("run/1", &config, 2).await?;
+            assert_eq!(read_dataset_manifest(dir.path(), "run/1")?, manifest);
+            assert!(verify_dataset(dir.path(), "run/1")?.is_empty());
+            assert!(!dir.path().join(STAGING_DIR).exists());
+
+            let shape: Vec<_> = manifest
+                .tables
+                .iter()
+                .map(|t| {
+                    (
+                        t.table.as_str(),
+                        t.feature_schema_version,
+                        t.rows,
+                        t.files.len(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                shape,
+                [
+                    ("snapshots", Some(1), 2, 2),
+                    ("snapshots", Some(9), 1, 1),
+                    ("strategy_intents", None, 1, 1),
+                    ("fills", None, 1, 1),
+                    ("pnl_ledger", None, 1, 1),
+                ]
+            );
+
+            let v1 = &manifest.tables[0];
+            assert_eq!(v1.features, ["mid", "imbalance"]);
+            assert!(!v1.columns.iter().any(|c| c.name == "features_json"));
+            let column = |name: &str| v1.columns.iter().find(|c| c.name == name).cloned();
+            assert_eq!(
+                column("can_trade").map(|c| (c.data_type, c.nullable)),
+                Some(("boolean".to_string(), false))
+            );
+            assert_eq!(
+                column("best_bid_qty").map(|c| (c.data_type, c.nullable)),
+                Some(("float64".to_string(), true))
+            );
+            assert_eq!(
+                column("snapshot_id").map(|c| (c.data_type, c.nullable)),
+                Some(("int64".to_string(), false))
+            );
+            assert_eq!(
+                v1.files.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(),
+                [
+                    "snapshots/run=run_1/feature_schema_version=1/day=1970-01-01/part-0.parquet",
+                    "snapshots/run=run_1/feature_schema_version=1/day=1970-01-02/part-0.parquet",
+                ]
+            );
+
+            let day1 = read(&dir.path().join(&v1.files[0].path))?;
+            let day2 = read(&dir.path().join(&v1.files[1].path))?;
+            let floats = |batch: &RecordBatch, name: &str| -> Vec<Option<f64>> {
+                let array = batch.column_by_name(name).expect(name);
+                let array = array.as_any().downcast_ref::<Float64Array>().expect(name);
+                array.iter().collect()
+            };
+            assert_eq!(floats(&day1, "f_mid"), [Some(0.5)]);
+            assert_eq!(floats(&day1, "f_imbalance"), [None]);
+            assert_eq!(floats(&day2, "f_mid"), [None]);
+            assert_eq!(floats(&day2, "f_imbalance"), [Some(-0.25)]);
+            let can_trade = day1.column_by_name("can_trade").expect("can_trade");
+            let can_trade = can_trade.as_any().downcast_ref::<BooleanArray>();
+            assert_eq!(can_trade.map(|a| a.value(0)), Some(true));
+
+            // Unregistered versions keep the raw JSON.
+            let v9 = &manifest.tables[1];
+            assert!(v9.features.is_empty());
+            let raw = read(&dir.path().join(&v9.files[0].path))?;
+            let json = raw.column_by_name("features_json").expect("features_json");
+            let json = json.as_any().downcast_ref::<StringArray>();
+            assert_eq!(json.map(|a| a.value(0)), Some(r#"{"raw": 1}"#));
+
+            let fills = read(&dir.path().join(&manifest.tables[3].files[0].path))?;
+            assert_eq!(fills.num_rows(), 1);
+            assert_eq!(floats(&fills, "fee_usd"), [None]);
+            assert_eq!(floats(&fills, "price"), [Some(0.25)]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn features_parse_from_arrays_and_objects() -> Result<()> {
+        let names = ["a".to_string(), "b".to_string()];
+        assert_eq!(parse_features("[1, null]", &names)?, [Some(1.0), None]);
+        assert_eq!(parse_features(r#"{"b": 2}"#, &names)?, [None, Some(2.0)]);
+        assert!(parse_features("[1]", &names).is_err());
+        assert!(parse_features(r#"["x", 1]"#, &names).is_err());
+        assert!(parse_features("3", &names).is_err());
+        Ok(())
+    }
+}
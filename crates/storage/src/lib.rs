@@ -1,6 +1,7 @@
+use std::sync::Arc;
+
 use anyhow::{bail, Result};
 use chrono::Utc;
-use sqlx::migrate::Migrator;
 #[cfg(feature = "postgres")]
 use sqlx::postgres::PgPoolOptions;
 #[cfg(feature = "sqlite")]
@@ -9,15 +10,50 @@ use sqlx::sqlite::SqlitePoolOptions;
 use sqlx::PgPool;
 #[cfg(feature = "sqlite")]
 use sqlx::SqlitePool;
+use tokio::sync::Mutex;
 use tracing::info;
 
-const REQUIRED_TABLES: &[&str] = &["runs", "raw_events", "incidents"];
-
-#[cfg(feature = "sqlite")]
-static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("../../migrations/sqlite");
-
-#[cfg(feature = "postgres")]
-static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("../../migrations/postgres");
+mod backup;
+mod copy;
+#[cfg(feature = "parquet")]
+mod dataset;
+mod options;
+mod pnl_report;
+mod quality;
+mod query;
+mod records;
+mod retention;
+mod rollup;
+mod rows;
+mod schema;
+mod trace;
+mod writer;
+
+use query::{bind_fill, bind_order, bind_pnl, with_pool};
+
+pub use backup::{
+    list_backups, verify_export, BackupConfig, BackupJob, BackupReport, ExportManifest,
+    TableExport, BACKUP_INCIDENT, EXPORT_MANIFEST_FILE,
+};
+pub use copy::{CopyConfig, CopyReport, TableCopy};
+#[cfg(feature = "parquet")]
+pub use dataset::{
+    dataset_manifest_path, read_dataset_manifest, verify_dataset, DatasetColumn, DatasetConfig,
+    DatasetFile, DatasetManifest, DatasetTable, DATASET_MANIFEST_DIR, DATASET_TABLES,
+    FEATURE_COLUMN_PREFIX,
+};
+pub use options::{StoreOptions, Synchronous};
+pub use pnl_report::{DailyPnl, PnlBreakdown, PnlQuery, PnlReport, UNATTRIBUTED};
+pub use quality::{ExecutionQuery, ExecutionStats};
+pub use records::{ApprovalRow, IntentRow, PluginSignalRow, PortfolioSnapshotRow, SnapshotRow};
+pub use retention::{
+    read_manifest, verify_archive, ArchiveEntry, RawEventPartition, RetentionConfig, RetentionJob,
+    RetentionReport, MANIFEST_FILE, RETENTION_INCIDENT,
+};
+pub use rollup::{OrderRollup, RollupConfig, RollupJob, RollupReport, ROLLUP_INCIDENT};
+pub use schema::SCHEMA_VERSION;
+pub use trace::{OrderTrace, TradeTrace};
+pub use writer::{FlushReport, StoreWriter, WriteOp, WriterConfig};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DatabaseBackend {
@@ -37,6 +73,61 @@ impl DatabaseBackend {
     }
 }
 
+/// One row of the `orders` table.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct OrderRow {
+    pub run_id: String,
+    pub client_order_id: String,
+    pub order_id: Option<String>,
+    pub approved_id: Option<i64>,
+    pub intent_id: Option<i64>,
+    pub strategy: String,
+    pub market_id: i64,
+    pub venue: String,
+    pub status: String,
+    pub side: String,
+    pub limit_price: f64,
+    pub qty: f64,
+    pub ts_submitted_ms: i64,
+    pub ts_acked_ms: Option<i64>,
+    pub ts_final_ms: Option<i64>,
+    pub submit_latency_ms: Option<i64>,
+    pub notes: Option<String>,
+}
+
+/// One row of the `fills` table.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct FillRow {
+    pub run_id: String,
+    pub ts_ms: i64,
+    pub venue: String,
+    pub fill_id: Option<String>,
+    pub order_id: Option<String>,
+    pub client_order_id: Option<String>,
+    pub market_id: i64,
+    pub strategy: String,
+    pub side: String,
+    pub price: f64,
+    pub qty: f64,
+    pub fee_usd: Option<f64>,
+    pub liquidity: Option<String>,
+    pub raw_json: Option<String>,
+}
+
+/// One row of the `pnl_ledger` table.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct PnlEntry {
+    pub run_id: String,
+    pub ts_ms: i64,
+    pub market_id: i64,
+    pub strategy: Option<String>,
+    pub kind: String,
+    /// What the entry refers to (order, fill or leg group id).
+    pub reference: Option<String>,
+    pub pnl_usd: f64,
+    pub notes: Option<String>,
+}
+
 #[derive(Clone)]
 enum StorePool {
     #[cfg(feature = "sqlite")]
@@ -48,44 +139,131 @@ enum StorePool {
 #[derive(Clone)]
 pub struct Store {
     pool: StorePool,
+    /// Read-only connections for analytics; the same pool as `pool` on
+    /// Postgres, for in-memory SQLite and when disabled.
+    read_pool: StorePool,
     backend: DatabaseBackend,
+    /// Held across every fill insert until it commits, so fill ids become
+    /// visible in id order and the rollup watermark can't pass a fill that
+    /// is still being written. Assumes one process writes fills.
+    fill_writes: Arc<Mutex<()>>,
 }
 
 impl Store {
     pub async fn connect(url: &str) -> Result<Self> {
-        let backend = DatabaseBackend::from_url(url)?;
-
-        #[cfg(all(not(feature = "sqlite"), feature = "postgres"))]
-        if matches!(backend, DatabaseBackend::Sqlite) {
-            bail!("sqlite backend is not enabled");
-        }
+        Self::connect_with(url, &StoreOptions::default()).await
+    }
 
-        #[cfg(all(feature = "sqlite", not(feature = "postgres")))]
-        if matches!(backend, DatabaseBackend::Postgres) {
-            bail!("postgres backend is not enabled");
-        }
+    /// Connect, refuse a schema newer than this binary, and apply pending
+    /// migrations.
+    pub async fn connect_with(url: &str, options: &StoreOptions) -> Result<Self> {
+        let store = Self::open(url, options).await?;
+        store.migrate().await?;
+        Ok(store)
+    }
 
+    /// Open `url` read-only for inspection (trace, reports, exports): no
+    /// migrations run and every connection refuses writes, so it is safe
+    /// against a production database or a read-only file. Refuses a schema
+    /// this binary does not read.
+    pub async fn open_reader(url: &str) -> Result<Self> {
+        let backend = enabled_backend(url)?;
+        let options = StoreOptions::default();
         let pool = match backend {
+            #[cfg(feature = "sqlite")]
+            DatabaseBackend::Sqlite => StorePool::Sqlite(
+                SqlitePoolOptions::new()
+                    .max_connections(options.read_connections.max(1))
+                    .connect_with(options.sqlite_reader(url)?)
+                    .await?,
+            ),
+            #[cfg(feature = "postgres")]
+            DatabaseBackend::Postgres => StorePool::Postgres(
+                PgPoolOptions::new()
+                    .max_connections(options.read_connections.max(1))
+                    .after_connect(|conn, _| {
+                        Box::pin(async move {
+                            sqlx::query("SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY")
+                                .execute(conn)
+                                .await?;
+                            Ok(())
+                        })
+                    })
+                    .connect(url)
+                    .await?,
+            ),
+            #[allow(unreachable_patterns)]
+            _ => bail!("unsupported backend"),
+        };
+        let store = Self {
+            pool: pool.clone(),
+            read_pool: pool,
+            backend,
+            fill_writes: Arc::default(),
+        };
+        store.check_readable().await?;
+        Ok(store)
+    }
+
+    async fn open(url: &str, options: &StoreOptions) -> Result<Self> {
+        let backend = enabled_backend(url)?;
+        let (pool, read_pool) = match backend {
             #[cfg(feature = "sqlite")]
             DatabaseBackend::Sqlite => {
+                let journal = options.sqlite_journal_pragma();
                 let pool = SqlitePoolOptions::new()
-                    .max_connections(5)
-                    .connect(url)
+                    .max_connections(options.max_connections)
+                    .after_connect(move |conn, _| {
+                        Box::pin(async move {
+                            sqlx::query(journal).execute(conn).await?;
+                            Ok(())
+                        })
+                    })
+                    .connect_with(options.sqlite(url)?)
                     .await?;
-                SQLITE_MIGRATOR.run(&pool).await?;
-                StorePool::Sqlite(pool)
+                // a read-only connection to an in-memory database would
+                // open a second, empty database
+                let in_memory = url.contains(":memory:") || url.contains("mode=memory");
+                let read_pool = if options.read_connections == 0 || in_memory {
+                    pool.clone()
+                } else {
+                    SqlitePoolOptions::new()
+                        .max_connections(options.read_connections)
+                        .connect_with(options.sqlite_reader(url)?)
+                        .await?
+                };
+                (StorePool::Sqlite(pool), StorePool::Sqlite(read_pool))
             }
             #[cfg(feature = "postgres")]
             DatabaseBackend::Postgres => {
-                let pool = PgPoolOptions::new().max_connections(5).connect(url).await?;
-                POSTGRES_MIGRATOR.run(&pool).await?;
-                StorePool::Postgres(pool)
+                let pool = PgPoolOptions::new()
+                    .max_connections(options.max_connections)
+                    .connect(url)
+                    .await?;
+                (StorePool::Postgres(pool.clone()), StorePool::Postgres(pool))
             }
             #[allow(unreachable_patterns)]
             _ => bail!("unsupported backend"),
         };
 
-        Ok(Self { pool, backend })
+        Ok(Self {
+            pool,
+            read_pool,
+            backend,
+            fill_writes: Arc::default(),
+        })
+    }
+
+    /// A handle whose queries run on the read-only pool, for reports and
+    /// other long reads that should not hold write connections. Writes
+    /// through it fail on SQLite.
+    pub fn reader(&self) -> Store {
+        Store {
+            pool: self.read_pool.clone(),
+            read_pool: self.read_pool.clone(),
+            backend: self.backend,
+            fill_writes: self.fill_writes.clone(),
+        }
     }
 
     pub fn backend(&self) -> DatabaseBackend {
@@ -99,34 +277,19 @@ impl Store {
             .to_string();
         let ts_ms = Utc::now().timestamp_millis();
 
-        match &self.pool {
-            #[cfg(feature = "sqlite")]
-            StorePool::Sqlite(pool) => {
-                sqlx::query(
-                    "INSERT INTO runs (run_id, started_at_ms, git_sha, host) VALUES (?1, ?2, ?3, ?4)
-                     ON CONFLICT(run_id) DO UPDATE SET started_at_ms = excluded.started_at_ms, git_sha = excluded.git_sha, host = excluded.host",
-                )
-                .bind(run_id)
-                .bind(ts_ms)
-                .bind(git_sha)
-                .bind(host)
-                .execute(pool)
-                .await?;
-            }
-            #[cfg(feature = "postgres")]
-            StorePool::Postgres(pool) => {
-                sqlx::query(
-                    "INSERT INTO runs (run_id, started_at_ms, git_sha, host) VALUES ($1, $2, $3, $4)
-                     ON CONFLICT(run_id) DO UPDATE SET started_at_ms = excluded.started_at_ms, git_sha = excluded.git_sha, host = excluded.host",
-                )
+        let sql = self.backend.sql(
+            "INSERT INTO runs (run_id, started_at_ms, git_sha, host) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(run_id) DO UPDATE SET started_at_ms = excluded.started_at_ms, git_sha = excluded.git_sha, host = excluded.host",
+        );
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(&sql)
                 .bind(run_id)
                 .bind(ts_ms)
                 .bind(git_sha)
-                .bind(host)
+                .bind(&host)
                 .execute(pool)
                 .await?;
-            }
-        }
+        });
         Ok(())
     }
 
@@ -139,25 +302,9 @@ impl Store {
     ) -> Result<()> {
         let ts_ms = Utc::now().timestamp_millis();
 
-        match &self.pool {
-            #[cfg(feature = "sqlite")]
-            StorePool::Sqlite(pool) => {
-                sqlx::query(
-                    "INSERT INTO raw_events (run_id, ts_ms, source, topic, payload_json) VALUES (?1, ?2, ?3, ?4, ?5)",
-                )
-                .bind(run_id)
-                .bind(ts_ms)
-                .bind(source)
-                .bind(topic)
-                .bind(payload_json)
-                .execute(pool)
-                .await?;
-            }
-            #[cfg(feature = "postgres")]
-            StorePool::Postgres(pool) => {
-                sqlx::query(
-                    "INSERT INTO raw_events (run_id, ts_ms, source, topic, payload_json) VALUES ($1, $2, $3, $4, $5)",
-                )
+        let sql = self.backend.sql(query::INSERT_EVENT);
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(&sql)
                 .bind(run_id)
                 .bind(ts_ms)
                 .bind(source)
@@ -165,8 +312,7 @@ impl Store {
                 .bind(payload_json)
                 .execute(pool)
                 .await?;
-            }
-        }
+        });
         Ok(())
     }
 
@@ -179,12 +325,9 @@ impl Store {
     ) -> Result<()> {
         let ts_ms = Utc::now().timestamp_millis();
 
-        match &self.pool {
-            #[cfg(feature = "sqlite")]
-            StorePool::Sqlite(pool) => {
-                sqlx::query(
-                    "INSERT INTO incidents (run_id, ts_ms, severity, kind, message) VALUES (?1, ?2, ?3, ?4, ?5)",
-                )
+        let sql = self.backend.sql(query::INSERT_INCIDENT);
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(&sql)
                 .bind(run_id)
                 .bind(ts_ms)
                 .bind(severity)
@@ -192,59 +335,131 @@ impl Store {
                 .bind(message)
                 .execute(pool)
                 .await?;
-            }
-            #[cfg(feature = "postgres")]
-            StorePool::Postgres(pool) => {
-                sqlx::query(
-                    "INSERT INTO incidents (run_id, ts_ms, severity, kind, message) VALUES ($1, $2, $3, $4, $5)",
-                )
-                .bind(run_id)
-                .bind(ts_ms)
-                .bind(severity)
-                .bind(kind)
-                .bind(message)
-                .execute(pool)
-                .await?;
-            }
-        }
+        });
         Ok(())
     }
 
-    pub async fn validate_required_tables(&self) -> Result<Vec<String>> {
-        let mut missing = Vec::new();
+    pub async fn insert_fill(&self, fill: &FillRow) -> Result<i64> {
+        let sql = self.backend.sql(query::INSERT_FILL);
+        let _ordered = self.fill_writes.lock().await;
+        let id = with_pool!(&self.pool, |pool| {
+            bind_fill!(sqlx::query_scalar::<_, i64>(&sql), fill)
+                .fetch_one(pool)
+                .await?
+        });
+        Ok(id)
+    }
 
-        for table in REQUIRED_TABLES {
-            let exists = match &self.pool {
-                #[cfg(feature = "sqlite")]
-                StorePool::Sqlite(pool) => {
-                    sqlx::query_scalar::<_, Option<String>>(
-                        "SELECT name FROM sqlite_master WHERE type='table' AND name = ?1",
-                    )
-                    .bind(table)
-                    .fetch_optional(pool)
-                    .await?
-                    .is_some()
-                }
-                #[cfg(feature = "postgres")]
-                StorePool::Postgres(pool) => {
-                    sqlx::query_scalar::<_, bool>(
-                        "SELECT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_schema = current_schema() AND table_name = $1)",
-                    )
-                    .bind(table)
-                    .fetch_one(pool)
-                    .await?
-                }
-            };
+    /// Whether a fill with this venue fill id has already been recorded.
+    pub async fn has_fill(&self, venue: &str, fill_id: &str) -> Result<bool> {
+        let sql = self
+            .backend
+            .sql("SELECT COUNT(*) FROM fills WHERE venue = ?1 AND fill_id = ?2");
+        let found = with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar::<_, i64>(&sql)
+                .bind(venue)
+                .bind(fill_id)
+                .fetch_one(pool)
+                .await?
+        });
+        Ok(found > 0)
+    }
+
+    pub async fn fills_for_run(&self, run_id: &str) -> Result<Vec<FillRow>> {
+        let sql = self.backend.sql(
+            "SELECT run_id, ts_ms, venue, fill_id, order_id, client_order_id, market_id, strategy, side, CAST(price AS DOUBLE PRECISION) AS price, CAST(qty AS DOUBLE PRECISION) AS qty, CAST(fee_usd AS DOUBLE PRECISION) AS fee_usd, liquidity, raw_json
+             FROM fills WHERE run_id = ?1 ORDER BY id",
+        );
+        let rows = with_pool!(&self.pool, |pool| {
+            sqlx::query_as::<_, FillRow>(&sql)
+                .bind(run_id)
+                .fetch_all(pool)
+                .await?
+        });
+        Ok(rows)
+    }
+
+    pub async fn insert_pnl(&self, entry: &PnlEntry) -> Result<i64> {
+        let sql = self.backend.sql(query::INSERT_PNL);
+        let id = with_pool!(&self.pool, |pool| {
+            bind_pnl!(sqlx::query_scalar::<_, i64>(&sql), entry)
+                .fetch_one(pool)
+                .await?
+        });
+        Ok(id)
+    }
 
-            if !exists {
-                missing.push((*table).to_string());
+    pub async fn pnl_entries(&self, run_id: &str) -> Result<Vec<PnlEntry>> {
+        let sql = self.backend.sql(
+            "SELECT run_id, ts_ms, market_id, strategy, kind, ref AS reference, CAST(pnl_usd AS DOUBLE PRECISION) AS pnl_usd, notes
+             FROM pnl_ledger WHERE run_id = ?1 ORDER BY id",
+        );
+        let rows = with_pool!(&self.pool, |pool| {
+            sqlx::query_as::<_, PnlEntry>(&sql)
+                .bind(run_id)
+                .fetch_all(pool)
+                .await?
+        });
+        Ok(rows)
+    }
+
+    /// Insert or update an order keyed by `client_order_id`; returns the row id.
+    pub async fn upsert_order(&self, order: &OrderRow) -> Result<i64> {
+        let sql = self.backend.sql(query::UPSERT_ORDER);
+        let id = with_pool!(&self.pool, |pool| {
+            bind_order!(sqlx::query_scalar::<_, i64>(&sql), order)
+                .fetch_one(pool)
+                .await?
+        });
+        Ok(id)
+    }
+
+    pub async fn fetch_order(&self, client_order_id: &str) -> Result<Option<OrderRow>> {
+        let sql = self
+            .backend
+            .sql(&format!(
+                "SELECT {} FROM orders WHERE client_order_id = ?1",
+                query::ORDER_COLUMNS
+            ))
+            .into_owned();
+        let row = with_pool!(&self.pool, |pool| {
+            sqlx::query_as::<_, OrderRow>(&sql)
+                .bind(client_order_id)
+                .fetch_optional(pool)
+                .await?
+        });
+        Ok(row)
+    }
+
+    /// Tables of the current schema that do not exist.
+    pub async fn validate_required_tables(&self) -> Result<Vec<String>> {
+        let mut missing = Vec::new();
+        for table in schema::tables() {
+            if !self.table_exists(table).await? {
+                missing.push(table.to_string());
             }
         }
-
         Ok(missing)
     }
 }
 
+/// The backend for `url`, if this build includes it.
+fn enabled_backend(url: &str) -> Result<DatabaseBackend> {
+    let backend = DatabaseBackend::from_url(url)?;
+
+    #[cfg(all(not(feature = "sqlite"), feature = "postgres"))]
+    if matches!(backend, DatabaseBackend::Sqlite) {
+        bail!("sqlite backend is not enabled");
+    }
+
+    #[cfg(all(feature = "sqlite", not(feature = "postgres")))]
+    if matches!(backend, DatabaseBackend::Postgres) {
+        bail!("postgres backend is not enabled");
+    }
+
+    Ok(backend)
+}
+
 pub async fn init_sqlite(path: &str) -> Result<Store> {
     let store = Store::connect(path).await?;
     info!(path = %path, "sqlite initialized");
@@ -261,17 +476,239 @@ pub async fn init_postgres(url: &str) -> Result<Store> {
 mod tests {
     use super::*;
 
+    /// Stores the behavioral tests run against: in-memory SQLite always,
+    /// plus Postgres when `TEST_POSTGRES_URL` is set. The Postgres database
+    /// is shared between runs, so tests key their rows with [`unique`].
+    pub(crate) async fn test_stores() -> Result<Vec<Store>> {
+        let sqlite = init_sqlite("sqlite::memory:").await?;
+        match std::env::var("TEST_POSTGRES_URL") {
+            #[cfg(feature = "postgres")]
+            Ok(url) => Ok(vec![sqlite, Store::connect(&url).await?]),
+            _ => {
+                eprintln!("skipping postgres backend; TEST_POSTGRES_URL not set");
+                Ok(vec![sqlite])
+            }
+        }
+    }
+
+    /// URL of a new, empty Postgres schema on `TEST_POSTGRES_URL`, for
+    /// tests that need a database nobody else has migrated.
+    pub(crate) async fn fresh_postgres_url() -> Result<Option<String>> {
+        #[cfg(feature = "postgres")]
+        if let Ok(url) = std::env::var("TEST_POSTGRES_URL") {
+            let schema = unique("t").replace('-', "_");
+            let pool = PgPoolOptions::new()
+                .max_connections(1)
+                .connect(&url)
+                .await?;
+            sqlx::query(&format!("CREATE SCHEMA {schema}"))
+                .execute(&pool)
+                .await?;
+            pool.close().await;
+            let sep = if url.contains('?') { '&' } else { '?' };
+            return Ok(Some(format!(
+                "{url}{sep}options=-c%20search_path%3D{schema}"
+            )));
+        }
+        Ok(None)
+    }
+
+    /// Like [`test_stores`], but the Postgres store gets a schema of its
+    /// own, for tests of whole-table state such as rollup watermarks.
+    pub(crate) async fn fresh_stores() -> Result<Vec<Store>> {
+        let mut stores = vec![init_sqlite("sqlite::memory:").await?];
+        if let Some(url) = fresh_postgres_url().await? {
+            stores.push(Store::connect(&url).await?);
+        }
+        Ok(stores)
+    }
+
+    pub(crate) fn unique(prefix: &str) -> String {
+        format!("{prefix}-{}", uuid::Uuid::new_v4())
+    }
+
     #[tokio::test]
     async fn init_and_validate_required_tables() -> Result<()> {
-        let store = init_sqlite("sqlite::memory:?cache=shared").await?;
-        let missing_tables = store.validate_required_tables().await?;
+        for store in test_stores().await? {
+            let missing_tables = store.validate_required_tables().await?;
+            assert!(
+                missing_tables.is_empty(),
+                "missing tables on {:?}: {:?}",
+                store.backend(),
+                missing_tables
+            );
+        }
+        Ok(())
+    }
 
-        assert!(
-            missing_tables.is_empty(),
-            "missing tables: {:?}",
-            missing_tables
-        );
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn sqlite_file_store_applies_connection_options() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let url = format!("sqlite://{}?mode=rwc", dir.path().join("bot.db").display());
+        let options = StoreOptions {
+            sqlite_synchronous: Synchronous::Full,
+            sqlite_busy_timeout: std::time::Duration::from_millis(1_500),
+            ..StoreOptions::default()
+        };
+        let store = Store::connect_with(&url, &options).await?;
+        let journal: String = with_pool!(&store.pool, |pool| {
+            sqlx::query_scalar("PRAGMA journal_mode")
+                .fetch_one(pool)
+                .await?
+        });
+        assert_eq!(journal, "wal");
+        for (pragma, expected) in [
+            ("synchronous", 2),
+            ("busy_timeout", 1_500),
+            ("foreign_keys", 1),
+            ("auto_vacuum", 2),
+        ] {
+            let value: i64 = with_pool!(&store.pool, |pool| {
+                sqlx::query_scalar(&format!("PRAGMA {pragma}"))
+                    .fetch_one(pool)
+                    .await?
+            });
+            assert_eq!(value, expected, "PRAGMA {pragma}");
+        }
 
+        store.insert_run("run-opts", None).await?;
+        let reader = store.reader();
+        assert!(reader.validate_required_tables().await?.is_empty());
+        assert!(reader.insert_run("run-opts-2", None).await.is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn open_reader_never_migrates_or_writes() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("bot.db");
+        let url = format!("sqlite://{}", path.display());
+        assert!(Store::open_reader(&url).await.is_err());
+        assert!(!path.exists());
+
+        // a file nobody has migrated yet
+        Store::open(&format!("{url}?mode=rwc"), &StoreOptions::default()).await?;
+        let err = Store::open_reader(&url).await.err().expect("unmigrated");
+        assert!(err.to_string().contains("migrate it"), "{err}");
+
+        let store = Store::connect(&url).await?;
+        store.insert_run("run-ro", None).await?;
+        let reader = Store::open_reader(&url).await?;
+        assert!(reader.validate_required_tables().await?.is_empty());
+        assert!(reader.insert_run("run-ro-2", None).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn open_reader_on_postgres_refuses_writes() -> Result<()> {
+        let Some(url) = fresh_postgres_url().await? else {
+            return Ok(());
+        };
+        assert!(Store::open_reader(&url).await.is_err());
+        Store::connect(&url).await?;
+        let reader = Store::open_reader(&url).await?;
+        assert!(reader.validate_required_tables().await?.is_empty());
+        assert!(reader.insert_run(&unique("run"), None).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn upsert_order_updates_in_place() -> Result<()> {
+        for store in test_stores().await? {
+            let run = unique("run");
+            store.insert_run(&run, None).await?;
+            let client_order_id = unique("c");
+            let mut order = OrderRow {
+                run_id: run.clone(),
+                client_order_id: client_order_id.clone(),
+                order_id: None,
+                approved_id: None,
+                intent_id: None,
+                strategy: "mm".into(),
+                market_id: 7,
+                venue: "polymarket".into(),
+                status: "Submitted".into(),
+                side: "BuyYes".into(),
+                limit_price: 0.375,
+                qty: 10.0,
+                ts_submitted_ms: 1_000,
+                ts_acked_ms: None,
+                ts_final_ms: None,
+                submit_latency_ms: None,
+                notes: None,
+            };
+            let id = store.upsert_order(&order).await?;
+
+            order.order_id = Some("0xabc".into());
+            order.status = "Acked".into();
+            order.ts_acked_ms = Some(1_040);
+            order.submit_latency_ms = Some(40);
+            assert_eq!(store.upsert_order(&order).await?, id);
+
+            assert_eq!(store.fetch_order(&client_order_id).await?, Some(order));
+            assert_eq!(store.fetch_order(&unique("missing")).await?, None);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fills_pnl_and_batches_round_trip() -> Result<()> {
+        for store in test_stores().await? {
+            let run = unique("run");
+            store.insert_run(&run, None).await?;
+            let fill = FillRow {
+                run_id: run.clone(),
+                ts_ms: 1_000,
+                venue: "polymarket".into(),
+                fill_id: Some(unique("f")),
+                order_id: None,
+                client_order_id: None,
+                market_id: 3,
+                strategy: "mm".into(),
+                side: "SellNo".into(),
+                price: 0.625,
+                qty: 4.0,
+                fee_usd: None,
+                liquidity: None,
+                raw_json: None,
+            };
+            let pnl = PnlEntry {
+                run_id: run.clone(),
+                ts_ms: 1_001,
+                market_id: 3,
+                strategy: None,
+                kind: "fee".into(),
+                reference: None,
+                pnl_usd: -0.125,
+                notes: Some("maker rebate".into()),
+            };
+            store.insert_fill(&fill).await?;
+            store
+                .write_batch(&[
+                    WriteOp::Fill(fill.clone()),
+                    WriteOp::Pnl(pnl.clone()),
+                    WriteOp::Incident {
+                        run_id: run.clone(),
+                        ts_ms: 1_002,
+                        severity: "info".into(),
+                        kind: "TEST".into(),
+                        message: "batched".into(),
+                    },
+                ])
+                .await?;
+            store.log_event(&run, "test", "trade", "{}").await?;
+
+            assert!(
+                store
+                    .has_fill("polymarket", fill.fill_id.as_ref().unwrap())
+                    .await?
+            );
+            assert!(!store.has_fill("polymarket", &unique("f")).await?);
+            assert_eq!(store.fills_for_run(&run).await?, vec![fill.clone(), fill]);
+            assert_eq!(store.pnl_entries(&run).await?, vec![pnl]);
+        }
         Ok(())
     }
 
@@ -294,25 +731,4 @@ mod tests {
             DatabaseBackend::Postgres
         );
     }
-
-    #[cfg(feature = "postgres")]
-    #[tokio::test]
-    async fn runs_migrations_on_postgres_when_available() -> Result<()> {
-        let url = match std::env::var("TEST_POSTGRES_URL") {
-            Ok(url) => url,
-            Err(_) => {
-                eprintln!("skipping postgres migration test; TEST_POSTGRES_URL not set");
-                return Ok(());
-            }
-        };
-
-        let store = Store::connect(&url).await?;
-        let missing_tables = store.validate_required_tables().await?;
-        assert!(
-            missing_tables.is_empty(),
-            "missing tables: {:?}",
-            missing_tables
-        );
-        Ok(())
-    }
 }
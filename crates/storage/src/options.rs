@@ -0,0 +1,145 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// SQLite `PRAGMA synchronous` level.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Synchronous {
+    Off,
+    /// Safe against process crashes in WAL mode; a power loss can drop the
+    /// last few commits but never corrupts the file.
+    #[default]
+    Normal,
+    /// Also durable across power loss, at an fsync per commit.
+    Full,
+    Extra,
+}
+
+impl Synchronous {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Synchronous::Off => "off",
+            Synchronous::Normal => "normal",
+            Synchronous::Full => "full",
+            Synchronous::Extra => "extra",
+        }
+    }
+}
+
+impl fmt::Display for Synchronous {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Synchronous {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" => Ok(Synchronous::Off),
+            "normal" => Ok(Synchronous::Normal),
+            "full" => Ok(Synchronous::Full),
+            "extra" => Ok(Synchronous::Extra),
+            other => Err(format!(
+                "unknown synchronous level {other} (off|normal|full|extra)"
+            )),
+        }
+    }
+}
+
+/// Connection settings for [`crate::Store::connect_with`]. The SQLite
+/// fields are ignored on Postgres.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoreOptions {
+    pub max_connections: u32,
+    /// Size of the read-only pool behind [`crate::Store::reader`]. 0 makes
+    /// the reader share the read-write pool.
+    pub read_connections: u32,
+    /// Write-ahead logging: readers never block the writer and a killed
+    /// process loses no committed transaction.
+    pub sqlite_wal: bool,
+    pub sqlite_synchronous: Synchronous,
+    /// How long a connection waits on a locked database before failing.
+    pub sqlite_busy_timeout: Duration,
+    /// Enforce the schema's foreign keys, which SQLite ignores unless asked.
+    pub sqlite_foreign_keys: bool,
+}
+
+impl Default for StoreOptions {
+    fn default() -> Self {
+        Self {
+            max_connections: 5,
+            read_connections: 4,
+            sqlite_wal: true,
+            sqlite_synchronous: Synchronous::Normal,
+            sqlite_busy_timeout: Duration::from_secs(5),
+            sqlite_foreign_keys: true,
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl StoreOptions {
+    pub(crate) fn sqlite(&self, url: &str) -> anyhow::Result<sqlx::sqlite::SqliteConnectOptions> {
+        use sqlx::sqlite::{SqliteAutoVacuum, SqliteConnectOptions, SqliteSynchronous};
+
+        let synchronous = match self.sqlite_synchronous {
+            Synchronous::Off => SqliteSynchronous::Off,
+            Synchronous::Normal => SqliteSynchronous::Normal,
+            Synchronous::Full => SqliteSynchronous::Full,
+            Synchronous::Extra => SqliteSynchronous::Extra,
+        };
+        // Incremental auto-vacuum lets retention hand freed pages back
+        // without a full VACUUM. It only takes effect on a new file;
+        // existing databases need a one-off VACUUM (see RUNBOOK).
+        Ok(SqliteConnectOptions::from_str(url)?
+            .auto_vacuum(SqliteAutoVacuum::Incremental)
+            .synchronous(synchronous)
+            .busy_timeout(self.sqlite_busy_timeout)
+            .foreign_keys(self.sqlite_foreign_keys))
+    }
+
+    /// Run on every new read-write connection. sqlx would send a
+    /// configured journal mode before `auto_vacuum`, and switching a new
+    /// file to WAL writes its header, fixing auto-vacuum at off.
+    pub(crate) fn sqlite_journal_pragma(&self) -> &'static str {
+        if self.sqlite_wal {
+            "PRAGMA journal_mode = WAL"
+        } else {
+            "PRAGMA journal_mode = DELETE"
+        }
+    }
+
+    /// Options for the read-only pool. Journal mode and auto-vacuum are
+    /// properties of the file, set by the read-write pool; asking for them
+    /// again here would be a write.
+    pub(crate) fn sqlite_reader(
+        &self,
+        url: &str,
+    ) -> anyhow::Result<sqlx::sqlite::SqliteConnectOptions> {
+        Ok(sqlx::sqlite::SqliteConnectOptions::from_str(url)?
+            .create_if_missing(false)
+            .read_only(true)
+            .busy_timeout(self.sqlite_busy_timeout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn synchronous_round_trips_through_strings() {
+        for level in [
+            Synchronous::Off,
+            Synchronous::Normal,
+            Synchronous::Full,
+            Synchronous::Extra,
+        ] {
+            assert_eq!(level.to_string().parse::<Synchronous>(), Ok(level));
+        }
+        assert_eq!("FULL".parse::<Synchronous>(), Ok(Synchronous::Full));
+        assert!("fast".parse::<Synchronous>().is_err());
+    }
+}
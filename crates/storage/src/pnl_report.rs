@@ -0,0 +1,671 @@
+//! PnL attribution report (data_schema.md §3, §5.1): daily PnL per
+//! strategy and totals by strategy, market, strategy tier and intent tag,
+//! with hit rate, fee drag and each group's share of the max drawdown.
+//!
+//! Ledger rows carry a `ref` (fill id, client or venue order id, or leg
+//! group id) that leads back to the intent behind them, which is where
+//! tier and tags live. Fees come from `fills.fee_usd`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::Result;
+use chrono::DateTime;
+use serde::Serialize;
+
+use crate::query::with_pool;
+use crate::Store;
+
+/// Group key for rows without a strategy, tier or tag.
+pub const UNATTRIBUTED: &str = "-";
+
+/// Which ledger rows and fills a report covers. Unset bounds are open.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PnlQuery {
+    pub run_id: Option<String>,
+    pub since_ms: Option<i64>,
+    pub until_ms: Option<i64>,
+}
+
+/// PnL of one strategy, market, tier or tag.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct PnlBreakdown {
+    pub key: String,
+    pub pnl_usd: f64,
+    /// Every kind except `mtm` and `fee`.
+    pub realized_usd: f64,
+    pub mtm_usd: f64,
+    /// From the group's fills.
+    pub fees_usd: f64,
+    /// Fees over PnL before ledger fee rows, when that is positive.
+    pub fee_drag: Option<f64>,
+    pub entries: u64,
+    /// Realized entries above / below zero.
+    pub wins: u64,
+    pub losses: u64,
+    pub hit_rate: Option<f64>,
+    /// The group's PnL between the peak and trough of the report's max
+    /// drawdown; the groups' contributions sum to `-max_drawdown_usd`.
+    pub drawdown_contribution_usd: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct DailyPnl {
+    /// UTC date, `YYYY-MM-DD`.
+    pub day: String,
+    pub strategy: String,
+    pub pnl_usd: f64,
+    pub fees_usd: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct PnlReport {
+    pub pnl_usd: f64,
+    pub fees_usd: f64,
+    /// Largest fall of cumulative PnL from a previous high, in entry order.
+    pub max_drawdown_usd: f64,
+    pub drawdown_start_ms: Option<i64>,
+    pub drawdown_end_ms: Option<i64>,
+    /// By day, then PnL descending.
+    pub daily: Vec<DailyPnl>,
+    /// Breakdowns are sorted by PnL descending.
+    pub by_strategy: Vec<PnlBreakdown>,
+    pub by_market: Vec<PnlBreakdown>,
+    pub by_tier: Vec<PnlBreakdown>,
+    /// An entry counts once under every tag of its intent, so tag totals
+    /// can exceed the report total.
+    pub by_tag: Vec<PnlBreakdown>,
+}
+
+#[derive(sqlx::FromRow)]
+struct LedgerRow {
+    ts_ms: i64,
+    market_id: i64,
+    strategy: Option<String>,
+    kind: String,
+    reference: Option<String>,
+    pnl_usd: f64,
+}
+
+#[derive(sqlx::FromRow)]
+struct FillRef {
+    fill_id: Option<String>,
+    client_order_id: Option<String>,
+    order_id: Option<String>,
+    strategy: String,
+    market_id: i64,
+    ts_ms: i64,
+    fee_usd: Option<f64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct IntentRef {
+    client_order_id: Option<String>,
+    order_id: Option<String>,
+    leg_group: Option<String>,
+    tier: Option<i64>,
+    tags_json: String,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Attribution {
+    tier: Option<i64>,
+    tags: Vec<String>,
+}
+
+impl Attribution {
+    fn from_intent(intent: &IntentRef) -> Self {
+        Self {
+            tier: intent.tier,
+            tags: serde_json::from_str(&intent.tags_json).unwrap_or_default(),
+        }
+    }
+}
+
+/// Intent attribution keyed by each way a ledger `ref` can point at it.
+#[derive(Default)]
+struct Attributions {
+    by_order: HashMap<String, Attribution>,
+    by_venue_order: HashMap<String, Attribution>,
+    by_leg_group: HashMap<String, Attribution>,
+    by_fill: HashMap<String, Attribution>,
+}
+
+impl Attributions {
+    fn for_fill(&self, fill: &FillRef) -> Option<&Attribution> {
+        fill.client_order_id
+            .as_ref()
+            .and_then(|id| self.by_order.get(id))
+            .or_else(|| {
+                fill.order_id
+                    .as_ref()
+                    .and_then(|id| self.by_venue_order.get(id))
+            })
+    }
+
+    fn for_reference(&self, reference: &str) -> Option<&Attribution> {
+        self.by_fill
+            .get(reference)
+            .or_else(|| self.by_order.get(reference))
+            .or_else(|| self.by_venue_order.get(reference))
+            .or_else(|| self.by_leg_group.get(reference))
+    }
+}
+
+/// `?1` run, `?2` since, `?3` until, on a table aliased `t`.
+const WINDOW_FILTER: &str = "(?1 IS NULL OR t.run_id = ?1)
+    AND (?2 IS NULL OR t.ts_ms >= ?2)
+    AND (?3 IS NULL OR t.ts_ms < ?3)";
+
+/// The ledger `ref`s inside the window, and the fills the report reads:
+/// those inside the window, for fees, plus older ones a ledger row cites.
+fn window_refs() -> String {
+    format!(
+        "WITH ledger_refs AS (
+             SELECT t.ref FROM pnl_ledger t WHERE {WINDOW_FILTER} AND t.ref IS NOT NULL
+         ),
+         window_fills AS (
+             SELECT t.fill_id, t.client_order_id, t.order_id, t.strategy, t.market_id,
+                    t.ts_ms, t.fee_usd
+             FROM fills t
+             WHERE {WINDOW_FILTER}
+                OR ((?1 IS NULL OR t.run_id = ?1)
+                    AND t.fill_id IN (SELECT ref FROM ledger_refs))
+         )"
+    )
+}
+
+impl Store {
+    pub async fn pnl_report(&self, query: &PnlQuery) -> Result<PnlReport> {
+        let ledger_stmt = format!(
+            "SELECT CAST(t.ts_ms AS BIGINT) AS ts_ms, CAST(t.market_id AS BIGINT) AS market_id,
+                    t.strategy, t.kind, t.ref AS reference,
+                    CAST(t.pnl_usd AS DOUBLE PRECISION) AS pnl_usd
+             FROM pnl_ledger t WHERE {WINDOW_FILTER} ORDER BY t.ts_ms, t.id"
+        );
+        // A ledger row may cite a fill from before the window; only fills
+        // inside it count for fees.
+        let fills_stmt = format!(
+            "{} SELECT fill_id, client_order_id, order_id, strategy,
+                    CAST(market_id AS BIGINT) AS market_id, CAST(ts_ms AS BIGINT) AS ts_ms,
+                    CAST(fee_usd AS DOUBLE PRECISION) AS fee_usd
+             FROM window_fills",
+            window_refs()
+        );
+        let fills_sql = self.backend.sql(&fills_stmt);
+        // Intents of the orders behind those fills and of every order or
+        // leg group a ledger row cites.
+        let intents_stmt = format!(
+            "{} SELECT o.client_order_id, o.order_id, i.leg_group,
+                    CAST(i.tier AS BIGINT) AS tier, i.tags_json
+             FROM strategy_intents i LEFT JOIN orders o ON o.intent_id = i.intent_id
+             WHERE (?1 IS NULL OR i.run_id = ?1)
+               AND (o.client_order_id IN (SELECT client_order_id FROM window_fills)
+                    OR o.order_id IN (SELECT order_id FROM window_fills)
+                    OR o.client_order_id IN (SELECT ref FROM ledger_refs)
+                    OR o.order_id IN (SELECT ref FROM ledger_refs)
+                    OR i.leg_group IN (SELECT ref FROM ledger_refs))
+             ORDER BY i.intent_id",
+            window_refs()
+        );
+        let intents_sql = self.backend.sql(&intents_stmt);
+        let ledger_sql = self.backend.sql(&ledger_stmt);
+        let (ledger, fills, intents) = with_pool!(&self.pool, |pool| {
+            let ledger = sqlx::query_as::<_, LedgerRow>(&ledger_sql)
+                .bind(query.run_id.as_deref())
+                .bind(query.since_ms)
+                .bind(query.until_ms)
+                .fetch_all(pool)
+                .await?;
+            let fills = sqlx::query_as::<_, FillRef>(&fills_sql)
+                .bind(query.run_id.as_deref())
+                .bind(query.since_ms)
+                .bind(query.until_ms)
+                .fetch_all(pool)
+                .await?;
+            let intents = sqlx::query_as::<_, IntentRef>(&intents_sql)
+                .bind(query.run_id.as_deref())
+                .bind(query.since_ms)
+                .bind(query.until_ms)
+                .fetch_all(pool)
+                .await?;
+            (ledger, fills, intents)
+        });
+
+        let mut attributions = Attributions::default();
+        for intent in &intents {
+            let attribution = Attribution::from_intent(intent);
+            if let Some(id) = &intent.client_order_id {
+                attributions
+                    .by_order
+                    .insert(id.clone(), attribution.clone());
+            }
+            if let Some(id) = &intent.order_id {
+                attributions
+                    .by_venue_order
+                    .insert(id.clone(), attribution.clone());
+            }
+            // A leg group's intents share tier and tags; keep the first.
+            if let Some(group) = &intent.leg_group {
+                attributions
+                    .by_leg_group
+                    .entry(group.clone())
+                    .or_insert(attribution);
+            }
+        }
+        for fill in &fills {
+            if let (Some(id), Some(attribution)) = (&fill.fill_id, attributions.for_fill(fill)) {
+                attributions.by_fill.insert(id.clone(), attribution.clone());
+            }
+        }
+
+        let mut report = PnlReport::default();
+        let mut groups = Groups::default();
+        let mut daily: BTreeMap<(String, String), DailyPnl> = BTreeMap::new();
+
+        let in_window = |ts: i64| {
+            query.since_ms.is_none_or(|since| ts >= since)
+                && query.until_ms.is_none_or(|until| ts < until)
+        };
+        for fill in fills.iter().filter(|f| in_window(f.ts_ms)) {
+            let fee = fill.fee_usd.unwrap_or(0.0);
+            report.fees_usd += fee;
+            let attribution = attributions.for_fill(fill).cloned().unwrap_or_default();
+            for group in groups.matching(Some(&fill.strategy), fill.market_id, &attribution) {
+                group.fees_usd += fee;
+            }
+            daily_entry(&mut daily, fill.ts_ms, Some(&fill.strategy)).fees_usd += fee;
+        }
+
+        let drawdown = max_drawdown(&ledger);
+        report.max_drawdown_usd = drawdown.depth;
+        report.drawdown_start_ms = drawdown.start.map(|i| ledger[i].ts_ms);
+        report.drawdown_end_ms = drawdown.end.map(|i| ledger[i].ts_ms);
+        for (i, row) in ledger.iter().enumerate() {
+            report.pnl_usd += row.pnl_usd;
+            let attribution = row
+                .reference
+                .as_deref()
+                .and_then(|r| attributions.for_reference(r))
+                .cloned()
+                .unwrap_or_default();
+            let in_drawdown = drawdown.contains(i);
+            for group in groups.matching(row.strategy.as_deref(), row.market_id, &attribution) {
+                group.add(row, in_drawdown);
+            }
+            daily_entry(&mut daily, row.ts_ms, row.strategy.as_deref()).pnl_usd += row.pnl_usd;
+        }
+
+        report.daily = daily.into_values().collect();
+        report.daily.sort_by(|a, b| {
+            a.day
+                .cmp(&b.day)
+                .then(b.pnl_usd.total_cmp(&a.pnl_usd))
+                .then(a.strategy.cmp(&b.strategy))
+        });
+        report.by_strategy = finish(groups.strategy);
+        report.by_market = finish(groups.market);
+        report.by_tier = finish(groups.tier);
+        report.by_tag = finish(groups.tag);
+        Ok(report)
+    }
+}
+
+#[derive(Default)]
+struct Groups {
+    strategy: HashMap<String, PnlBreakdown>,
+    market: HashMap<String, PnlBreakdown>,
+    tier: HashMap<String, PnlBreakdown>,
+    tag: HashMap<String, PnlBreakdown>,
+}
+
+impl Groups {
+    /// Every group a row with these attributes falls in.
+    fn matching(
+        &mut self,
+        strategy: Option<&str>,
+        market_id: i64,
+        attribution: &Attribution,
+    ) -> Vec<&mut PnlBreakdown> {
+        fn entry(map: &mut HashMap<String, PnlBreakdown>, key: String) -> &mut PnlBreakdown {
+            map.entry(key.clone()).or_insert_with(|| PnlBreakdown {
+                key,
+                ..PnlBreakdown::default()
+            })
+        }
+        let mut out = vec![
+            entry(
+                &mut self.strategy,
+                strategy.unwrap_or(UNATTRIBUTED).to_string(),
+            ),
+            entry(&mut self.market, market_id.to_string()),
+            entry(
+                &mut self.tier,
+                attribution
+                    .tier
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|| UNATTRIBUTED.into()),
+            ),
+        ];
+        let tags = if attribution.tags.is_empty() {
+            vec![UNATTRIBUTED.to_string()]
+        } else {
+            attribution.tags.clone()
+        };
+        for tag in &tags {
+            entry(&mut self.tag, tag.clone());
+        }
+        out.extend(
+            self.tag
+                .iter_mut()
+                .filter(|(key, _)| tags.contains(key))
+                .map(|(_, group)| group),
+        );
+        out
+    }
+}
+
+impl PnlBreakdown {
+    fn add(&mut self, row: &LedgerRow, in_drawdown: bool) {
+        self.pnl_usd += row.pnl_usd;
+        self.entries += 1;
+        if row.kind.eq_ignore_ascii_case("mtm") {
+            self.mtm_usd += row.pnl_usd;
+        } else if !row.kind.eq_ignore_ascii_case("fee") {
+            self.realized_usd += row.pnl_usd;
+            if row.pnl_usd > 0.0 {
+                self.wins += 1;
+            } else if row.pnl_usd < 0.0 {
+                self.losses += 1;
+            }
+        }
+        if in_drawdown {
+            self.drawdown_contribution_usd += row.pnl_usd;
+        }
+    }
+}
+
+fn finish(groups: HashMap<String, PnlBreakdown>) -> Vec<PnlBreakdown> {
+    let mut groups: Vec<PnlBreakdown> = groups
+        .into_values()
+        .map(|mut g| {
+            let decided = g.wins + g.losses;
+            g.hit_rate = (decided > 0).then(|| g.wins as f64 / decided as f64);
+            let gross = g.realized_usd + g.mtm_usd;
+            g.fee_drag = (gross > 0.0).then(|| g.fees_usd / gross);
+            g
+        })
+        .collect();
+    groups.sort_by(|a, b| b.pnl_usd.total_cmp(&a.pnl_usd).then(a.key.cmp(&b.key)));
+    groups
+}
+
+fn daily_entry<'a>(
+    daily: &'a mut BTreeMap<(String, String), DailyPnl>,
+    ts_ms: i64,
+    strategy: Option<&str>,
+) -> &'a mut DailyPnl {
+    let day = DateTime::from_timestamp_millis(ts_ms)
+        .map(|t| t.date_naive().to_string())
+        .unwrap_or_default();
+    let strategy = strategy.unwrap_or(UNATTRIBUTED).to_string();
+    daily
+        .entry((day.clone(), strategy.clone()))
+        .or_insert_with(|| DailyPnl {
+            day,
+            strategy,
+            ..DailyPnl::default()
+        })
+}
+
+/// Max drawdown of cumulative PnL and the ledger rows `start..=end` that
+/// took it from its peak to its trough.
+#[derive(Debug, Default, PartialEq)]
+struct Drawdown {
+    depth: f64,
+    start: Option<usize>,
+    end: Option<usize>,
+}
+
+impl Drawdown {
+    fn contains(&self, i: usize) -> bool {
+        matches!((self.start, self.end), (Some(s), Some(e)) if s <= i && i <= e)
+    }
+}
+
+fn max_drawdown(ledger: &[LedgerRow]) -> Drawdown {
+    let mut drawdown = Drawdown::default();
+    let (mut cumulative, mut peak, mut peak_next) = (0.0, 0.0, 0);
+    for (i, row) in ledger.iter().enumerate() {
+        cumulative += row.pnl_usd;
+        if cumulative > peak {
+            peak = cumulative;
+            peak_next = i + 1;
+        }
+        if peak - cumulative > drawdown.depth {
+            drawdown = Drawdown {
+                depth: peak - cumulative,
+                start: Some(peak_next),
+                end: Some(i),
+            };
+        }
+    }
+    drawdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{test_stores, unique};
+    use crate::{FillRow, IntentRow, OrderRow, PnlEntry, SnapshotRow};
+
+    const DAY1: i64 = 1_700_000_000_000; // 2023-11-14T22:13:20Z
+    const DAY2: i64 = DAY1 + 86_400_000;
+
+    async fn intent(
+        store: &Store,
+        run: &str,
+        strategy: &str,
+        tier: i64,
+        tags: &str,
+        leg_group: Option<&str>,
+    ) -> Result<i64> {
+        let snapshot_id = store
+            .insert_snapshot(&SnapshotRow {
+                run_id: run.into(),
+                ts_ms: DAY1,
+                market_id: 7,
+                best_bid_px: None,
+                best_bid_qty: None,
+                best_ask_px: None,
+                best_ask_qty: None,
+                spread: None,
+                yes_qty: 0.0,
+                no_qty: 0.0,
+                net_exposure_usd: 0.0,
+                can_trade: true,
+                drawdown_halt: false,
+                crowding_score: 0.0,
+                toxicity_score: 0.0,
+                spread_compression: 0.0,
+                feature_schema_version: 1,
+                features_json: "{}".into(),
+            })
+            .await?;
+        store
+            .insert_intent(&IntentRow {
+                run_id: run.into(),
+                ts_ms: DAY1,
+                snapshot_id,
+                strategy: strategy.into(),
+                market_id: 7,
+                intent_kind: "PlaceOrder".into(),
+                side: Some("BuyYes".into()),
+                price: Some(0.5),
+                size: Some(1.0),
+                urgency: "taker".into(),
+                ttl_ms: 0,
+                expected_value: 0.0,
+                confidence: 0.5,
+                risk_cost: 0.0,
+                tags_json: tags.into(),
+                rationale_json: None,
+                tier: Some(tier),
+                leg_group: leg_group.map(Into::into),
+            })
+            .await
+    }
+
+    async fn order_and_fill(
+        store: &Store,
+        run: &str,
+        strategy: &str,
+        intent_id: i64,
+        ts_ms: i64,
+        fee: f64,
+    ) -> Result<(String, String)> {
+        let client_order_id = unique("c");
+        let fill_id = unique("f");
+        store
+            .upsert_order(&OrderRow {
+                run_id: run.into(),
+                client_order_id: client_order_id.clone(),
+                order_id: None,
+                approved_id: None,
+                intent_id: Some(intent_id),
+                strategy: strategy.into(),
+                market_id: 7,
+                venue: "polymarket".into(),
+                status: "Filled".into(),
+                side: "BuyYes".into(),
+                limit_price: 0.5,
+                qty: 1.0,
+                ts_submitted_ms: ts_ms,
+                ts_acked_ms: None,
+                ts_final_ms: None,
+                submit_latency_ms: None,
+                notes: None,
+            })
+            .await?;
+        store
+            .insert_fill(&FillRow {
+                run_id: run.into(),
+                ts_ms,
+                venue: "polymarket".into(),
+                fill_id: Some(fill_id.clone()),
+                order_id: None,
+                client_order_id: Some(client_order_id.clone()),
+                market_id: 7,
+                strategy: strategy.into(),
+                side: "BuyYes".into(),
+                price: 0.5,
+                qty: 1.0,
+                fee_usd: Some(fee),
+                liquidity: None,
+                raw_json: None,
+            })
+            .await?;
+        Ok((client_order_id, fill_id))
+    }
+
+    fn find<'a>(groups: &'a [PnlBreakdown], key: &str) -> &'a PnlBreakdown {
+        groups.iter().find(|g| g.key == key).expect(key)
+    }
+
+    #[tokio::test]
+    async fn attributes_pnl_by_strategy_market_tier_and_tag() -> Result<()> {
+        for store in test_stores().await? {
+            let run = unique("run-pnl");
+            store.insert_run(&run, None).await?;
+            let group = unique("g");
+            let arb = intent(&store, &run, "arb", 0, r#"["box","fast"]"#, Some(&group)).await?;
+            let mm = intent(&store, &run, "mm", 1, r#"["quote"]"#, None).await?;
+            let (_, arb_fill) = order_and_fill(&store, &run, "arb", arb, DAY1, 0.25).await?;
+            let (mm_order, mm_fill) = order_and_fill(&store, &run, "mm", mm, DAY2, 0.5).await?;
+
+            let ledger = [
+                (DAY1 + 1, Some("arb"), "BOX_ARB", Some(group.clone()), 2.0),
+                (DAY1 + 2, Some("mm"), "realized", Some(mm_fill), -1.0),
+                (DAY2 + 3, Some("mm"), "realized", Some(mm_order), -0.5),
+                (DAY2 + 4, None, "mtm", None, 1.0),
+                (DAY2 + 5, Some("arb"), "fee", Some(arb_fill), -0.25),
+            ];
+            for (ts_ms, strategy, kind, reference, pnl_usd) in ledger {
+                store
+                    .insert_pnl(&PnlEntry {
+                        run_id: run.clone(),
+                        ts_ms,
+                        market_id: 7,
+                        strategy: strategy.map(Into::into),
+                        kind: kind.into(),
+                        reference,
+                        pnl_usd,
+                        notes: None,
+                    })
+                    .await?;
+            }
+
+            let query = PnlQuery {
+                run_id: Some(run.clone()),
+                ..PnlQuery::default()
+            };
+            let report = store.pnl_report(&query).await?;
+            assert_eq!((report.pnl_usd, report.fees_usd), (1.25, 0.75));
+            assert_eq!(report.max_drawdown_usd, 1.5);
+            assert_eq!(report.drawdown_start_ms, Some(DAY1 + 2));
+            assert_eq!(report.drawdown_end_ms, Some(DAY2 + 3));
+
+            let keys: Vec<&str> = report.by_strategy.iter().map(|g| g.key.as_str()).collect();
+            assert_eq!(keys, ["arb", UNATTRIBUTED, "mm"]);
+            let arb = find(&report.by_strategy, "arb");
+            assert_eq!(
+                (arb.pnl_usd, arb.realized_usd, arb.fees_usd),
+                (1.75, 2.0, 0.25)
+            );
+            assert_eq!((arb.hit_rate, arb.fee_drag), (Some(1.0), Some(0.125)));
+            let mm = find(&report.by_strategy, "mm");
+            assert_eq!((mm.losses, mm.hit_rate, mm.fee_drag), (2, Some(0.0), None));
+            assert_eq!(mm.drawdown_contribution_usd, -1.5);
+            assert_eq!(find(&report.by_strategy, UNATTRIBUTED).mtm_usd, 1.0);
+
+            assert_eq!(report.by_market.len(), 1);
+            assert_eq!(report.by_market[0].fees_usd, 0.75);
+            assert_eq!(find(&report.by_tier, "0").pnl_usd, 1.75);
+            assert_eq!(find(&report.by_tier, "1").pnl_usd, -1.5);
+            assert_eq!(find(&report.by_tag, "box").pnl_usd, 1.75);
+            assert_eq!(find(&report.by_tag, "fast").pnl_usd, 1.75);
+            assert_eq!(find(&report.by_tag, "quote").fees_usd, 0.5);
+            assert_eq!(find(&report.by_tag, UNATTRIBUTED).pnl_usd, 1.0);
+
+            let daily: Vec<(&str, &str, f64)> = report
+                .daily
+                .iter()
+                .map(|d| (d.day.as_str(), d.strategy.as_str(), d.pnl_usd))
+                .collect();
+            assert_eq!(
+                daily,
+                [
+                    ("2023-11-14", "arb", 2.0),
+                    ("2023-11-14", "mm", -1.0),
+                    ("2023-11-15", UNATTRIBUTED, 1.0),
+                    ("2023-11-15", "arb", -0.25),
+                    ("2023-11-15", "mm", -0.5),
+                ]
+            );
+            assert_eq!(report.daily[0].fees_usd, 0.25);
+
+            let since = store
+                .pnl_report(&PnlQuery {
+                    since_ms: Some(DAY2),
+                    ..query
+                })
+                .await?;
+            assert_eq!((since.pnl_usd, since.fees_usd), (0.25, 0.5));
+            assert_eq!(since.daily.len(), 3);
+            assert_eq!(find(&since.by_tier, "1").pnl_usd, -0.5);
+            // the fee row cites a fill from before the window
+            assert_eq!(find(&since.by_tier, "0").pnl_usd, -0.25);
+            assert_eq!(find(&since.by_tag, "box").pnl_usd, -0.25);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+//! Execution-quality report (data_schema.md §4): fill, partial fill and
+//! cancel rates, time to fill and a toxic-fill proxy per strategy and
+//! market. Fill quantities come from `order_rollups`, so the report is as
+//! fresh as the last rollup pass.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+
+use crate::query::with_pool;
+use crate::Store;
+
+/// Which orders a report covers. Unset bounds are open.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecutionQuery {
+    pub run_id: Option<String>,
+    /// Orders submitted at or after this time.
+    pub since_ms: Option<i64>,
+    /// Orders submitted before this time.
+    pub until_ms: Option<i64>,
+    /// How long after a fill the mid is checked for an adverse move.
+    pub toxic_horizon_ms: i64,
+}
+
+impl Default for ExecutionQuery {
+    fn default() -> Self {
+        Self {
+            run_id: None,
+            since_ms: None,
+            until_ms: None,
+            toxic_horizon_ms: 5_000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExecutionStats {
+    pub strategy: String,
+    pub market_id: i64,
+    pub orders: u64,
+    /// Orders whose rollup reaches the submitted quantity.
+    pub filled: u64,
+    /// Orders with some but not all of their quantity filled.
+    pub partially_filled: u64,
+    pub canceled: u64,
+    /// Rejected by the venue or failed to submit.
+    pub rejected: u64,
+    pub fill_rate: f64,
+    pub partial_fill_rate: f64,
+    pub cancel_rate: f64,
+    pub qty_submitted: f64,
+    pub qty_filled: f64,
+    pub vwap: Option<f64>,
+    pub fee_usd: f64,
+    /// First fill minus submission, over orders with a fill.
+    pub time_to_fill_mean_ms: Option<f64>,
+    pub time_to_fill_p50_ms: Option<i64>,
+    pub time_to_fill_p90_ms: Option<i64>,
+    pub fills: u64,
+    /// Fills with a snapshot at least the horizon later to judge them by.
+    pub fills_checked: u64,
+    /// Checked fills the mid then moved against.
+    pub toxic_fills: u64,
+    pub toxic_fill_rate: Option<f64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct OrderQuality {
+    strategy: String,
+    market_id: i64,
+    status: String,
+    qty: f64,
+    ts_submitted_ms: i64,
+    qty_filled: Option<f64>,
+    vwap: Option<f64>,
+    fee_usd: Option<f64>,
+    ts_first_fill_ms: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct FillMarkout {
+    strategy: String,
+    market_id: i64,
+    side: String,
+    price: f64,
+    mid_after: Option<f64>,
+}
+
+/// Orders in the query's run and window; `?1` run, `?2` since, `?3` until.
+const ORDER_FILTER: &str = "(?1 IS NULL OR o.run_id = ?1)
+    AND (?2 IS NULL OR o.ts_submitted_ms >= ?2)
+    AND (?3 IS NULL OR o.ts_submitted_ms < ?3)";
+
+impl Store {
+    /// Execution quality per strategy and market, ordered by both.
+    pub async fn execution_quality(&self, query: &ExecutionQuery) -> Result<Vec<ExecutionStats>> {
+        let orders_stmt = format!(
+            "SELECT o.strategy, CAST(o.market_id AS BIGINT) AS market_id, o.status,
+                    CAST(o.qty AS DOUBLE PRECISION) AS qty,
+                    CAST(o.ts_submitted_ms AS BIGINT) AS ts_submitted_ms,
+                    CAST(r.qty_filled AS DOUBLE PRECISION) AS qty_filled,
+                    CAST(r.vwap AS DOUBLE PRECISION) AS vwap,
+                    CAST(r.fee_usd AS DOUBLE PRECISION) AS fee_usd,
+                    CAST(r.ts_first_fill_ms AS BIGINT) AS ts_first_fill_ms
+             FROM orders o LEFT JOIN order_rollups r ON r.client_order_id = o.client_order_id
+             WHERE {ORDER_FILTER}"
+        );
+        // The mid of the first two-sided snapshot at least the horizon
+        // after each fill of those orders.
+        let markouts_stmt = format!(
+            "SELECT o.strategy, CAST(o.market_id AS BIGINT) AS market_id, f.side,
+                    CAST(f.price AS DOUBLE PRECISION) AS price,
+                    (SELECT CAST((s.best_bid_px + s.best_ask_px) / 2 AS DOUBLE PRECISION)
+                     FROM snapshots s
+                     WHERE s.run_id = f.run_id AND s.market_id = f.market_id
+                       AND s.ts_ms >= f.ts_ms + ?4
+                       AND s.best_bid_px IS NOT NULL AND s.best_ask_px IS NOT NULL
+                     ORDER BY s.ts_ms LIMIT 1) AS mid_after
+             FROM fills f JOIN orders o ON o.client_order_id = f.client_order_id
+                 OR (f.client_order_id IS NULL AND o.order_id = f.order_id)
+             WHERE {ORDER_FILTER}"
+        );
+        let orders_sql = self.backend.sql(&orders_stmt);
+        let markouts_sql = self.backend.sql(&markouts_stmt);
+        let (orders, markouts) = with_pool!(&self.pool, |pool| {
+            let orders = sqlx::query_as::<_, OrderQuality>(&orders_sql)
+                .bind(query.run_id.as_deref())
+                .bind(query.since_ms)
+                .bind(query.until_ms)
+                .fetch_all(pool)
+                .await?;
+            let markouts = sqlx::query_as::<_, FillMarkout>(&markouts_sql)
+                .bind(query.run_id.as_deref())
+                .bind(query.since_ms)
+                .bind(query.until_ms)
+                .bind(query.toxic_horizon_ms)
+                .fetch_all(pool)
+                .await?;
+            (orders, markouts)
+        });
+
+        let mut groups: BTreeMap<(String, i64), (ExecutionStats, Vec<i64>, f64)> = BTreeMap::new();
+        for order in orders {
+            let (stats, times, notional) = groups
+                .entry((order.strategy.clone(), order.market_id))
+                .or_default();
+            let qty_filled = order.qty_filled.unwrap_or(0.0);
+            stats.orders += 1;
+            if qty_filled > 0.0 && qty_filled >= order.qty {
+                stats.filled += 1;
+            } else if qty_filled > 0.0 {
+                stats.partially_filled += 1;
+            }
+            match order.status.as_str() {
+                "Canceled" => stats.canceled += 1,
+                "Rejected" | "Failed" => stats.rejected += 1,
+                _ => {}
+            }
+            stats.qty_submitted += order.qty;
+            stats.qty_filled += qty_filled;
+            *notional += order.vwap.unwrap_or(0.0) * qty_filled;
+            stats.fee_usd += order.fee_usd.unwrap_or(0.0);
+            if let Some(first) = order.ts_first_fill_ms {
+                times.push(first - order.ts_submitted_ms);
+            }
+        }
+        for markout in markouts {
+            let (stats, _, _) = groups
+                .entry((markout.strategy.clone(), markout.market_id))
+                .or_default();
+            stats.fills += 1;
+            if let Some(mid) = markout.mid_after {
+                stats.fills_checked += 1;
+                if is_adverse(&markout.side, markout.price, mid) {
+                    stats.toxic_fills += 1;
+                }
+            }
+        }
+
+        Ok(groups
+            .into_iter()
+            .map(
+                |((strategy, market_id), (mut stats, mut times, notional))| {
+                    stats.strategy = strategy;
+                    stats.market_id = market_id;
+                    let orders = stats.orders.max(1) as f64;
+                    stats.fill_rate = stats.filled as f64 / orders;
+                    stats.partial_fill_rate = stats.partially_filled as f64 / orders;
+                    stats.cancel_rate = stats.canceled as f64 / orders;
+                    stats.vwap = (stats.qty_filled > 0.0).then(|| notional / stats.qty_filled);
+                    times.sort_unstable();
+                    if !times.is_empty() {
+                        stats.time_to_fill_mean_ms =
+                            Some(times.iter().sum::<i64>() as f64 / times.len() as f64);
+                        stats.time_to_fill_p50_ms = Some(percentile(&times, 50));
+                        stats.time_to_fill_p90_ms = Some(percentile(&times, 90));
+                    }
+                    stats.toxic_fill_rate = (stats.fills_checked > 0)
+                        .then(|| stats.toxic_fills as f64 / stats.fills_checked as f64);
+                    stats
+                },
+            )
+            .collect())
+    }
+}
+
+/// Whether the YES mid moved against the fill: below the price for a YES
+/// buy, above it for a YES sell, and mirrored through `1 - mid` for NO.
+fn is_adverse(side: &str, price: f64, yes_mid: f64) -> bool {
+    match side {
+        "BuyYes" => yes_mid < price,
+        "SellYes" => yes_mid > price,
+        "BuyNo" => 1.0 - yes_mid < price,
+        "SellNo" => 1.0 - yes_mid > price,
+        _ => false,
+    }
+}
+
+/// Nearest-rank percentile of sorted, non-empty `values`.
+fn percentile(values: &[i64], pct: usize) -> i64 {
+    let rank = (values.len() * pct).div_ceil(100).max(1);
+    values[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{fresh_stores, unique};
+    use crate::{FillRow, OrderRow, SnapshotRow};
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        assert_eq!(percentile(&[7], 90), 7);
+        let values: Vec<i64> = (1..=10).collect();
+        assert_eq!(percentile(&values, 50), 5);
+        assert_eq!(percentile(&values, 90), 9);
+        assert!(is_adverse("BuyNo", 0.5, 0.625));
+        assert!(!is_adverse("SellNo", 0.5, 0.625));
+    }
+
+    #[tokio::test]
+    async fn reports_rates_fill_times_and_toxic_fills() -> Result<()> {
+        for store in fresh_stores().await? {
+            let run = unique("run-quality");
+            store.insert_run(&run, None).await?;
+            // (status, qty, filled qty, fill price)
+            let orders = [
+                ("Filled", 4.0, 4.0, 0.5),
+                ("Canceled", 4.0, 1.0, 0.5),
+                ("Canceled", 2.0, 0.0, 0.0),
+                ("Rejected", 2.0, 0.0, 0.0),
+            ];
+            for (i, (status, qty, filled, price)) in orders.into_iter().enumerate() {
+                let client_order_id = format!("{run}-{i}");
+                let submitted = 1_000 * (i as i64 + 1);
+                store
+                    .upsert_order(&OrderRow {
+                        run_id: run.clone(),
+                        client_order_id: client_order_id.clone(),
+                        order_id: None,
+                        approved_id: None,
+                        intent_id: None,
+                        strategy: "mm".into(),
+                        market_id: 3,
+                        venue: "polymarket".into(),
+                        status: status.into(),
+                        side: "BuyYes".into(),
+                        limit_price: 0.5,
+                        qty,
+                        ts_submitted_ms: submitted,
+                        ts_acked_ms: None,
+                        ts_final_ms: None,
+                        submit_latency_ms: None,
+                        notes: None,
+                    })
+                    .await?;
+                if filled > 0.0 {
+                    store
+                        .insert_fill(&FillRow {
+                            run_id: run.clone(),
+                            ts_ms: submitted + 100 * (i as i64 + 1),
+                            venue: "polymarket".into(),
+                            fill_id: None,
+                            order_id: None,
+                            client_order_id: Some(client_order_id),
+                            market_id: 3,
+                            strategy: "mm".into(),
+                            side: "BuyYes".into(),
+                            price,
+                            qty: filled,
+                            fee_usd: Some(0.25),
+                            liquidity: None,
+                            raw_json: None,
+                        })
+                        .await?;
+                }
+            }
+            // Mid drops below the first fill's price within the horizon and
+            // there is no snapshot late enough for the second.
+            store
+                .insert_snapshot(&SnapshotRow {
+                    run_id: run.clone(),
+                    ts_ms: 1_500,
+                    market_id: 3,
+                    best_bid_px: Some(0.375),
+                    best_bid_qty: Some(1.0),
+                    best_ask_px: Some(0.5),
+                    best_ask_qty: Some(1.0),
+                    spread: Some(0.125),
+                    yes_qty: 0.0,
+                    no_qty: 0.0,
+                    net_exposure_usd: 0.0,
+                    can_trade: true,
+                    drawdown_halt: false,
+                    crowding_score: 0.0,
+                    toxicity_score: 0.0,
+                    spread_compression: 0.0,
+                    feature_schema_version: 1,
+                    features_json: "{}".into(),
+                })
+                .await?;
+            store.roll_up_orders(100, 1).await?;
+
+            let query = ExecutionQuery {
+                run_id: Some(run.clone()),
+                toxic_horizon_ms: 200,
+                ..ExecutionQuery::default()
+            };
+            let stats = store.execution_quality(&query).await?;
+            assert_eq!(stats.len(), 1);
+            let mm = &stats[0];
+            assert_eq!((mm.strategy.as_str(), mm.market_id), ("mm", 3));
+            assert_eq!(
+                (
+                    mm.orders,
+                    mm.filled,
+                    mm.partially_filled,
+                    mm.canceled,
+                    mm.rejected
+                ),
+                (4, 1, 1, 2, 1)
+            );
+            assert_eq!(mm.fill_rate, 0.25);
+            assert_eq!(mm.cancel_rate, 0.5);
+            assert_eq!((mm.qty_submitted, mm.qty_filled), (12.0, 5.0));
+            assert_eq!(mm.vwap, Some(0.5));
+            assert_eq!(mm.fee_usd, 0.5);
+            assert_eq!(mm.time_to_fill_p50_ms, Some(100));
+            assert_eq!(mm.time_to_fill_p90_ms, Some(200));
+            assert_eq!(mm.time_to_fill_mean_ms, Some(150.0));
+            assert_eq!((mm.fills, mm.fills_checked, mm.toxic_fills), (2, 1, 1));
+            assert_eq!(mm.toxic_fill_rate, Some(1.0));
+
+            // The window keeps only orders submitted from 2s on.
+            let later = store
+                .execution_quality(&ExecutionQuery {
+                    since_ms: Some(2_000),
+                    ..query
+                })
+                .await?;
+            assert_eq!(later[0].orders, 3);
+            assert_eq!(later[0].fills, 1);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+//! Backend-agnostic statements.
+//!
+//! Every statement is written once, in the subset of SQL both backends
+//! accept: `?N` placeholders, `ON CONFLICT ... DO UPDATE SET x = excluded.x`
+//! upserts and `RETURNING`. Reads cast REAL columns `AS DOUBLE PRECISION`
+//! and INTEGER ids `AS BIGINT` so they decode as `f64`/`i64` on Postgres,
+//! where those are 4-byte types; on SQLite the casts are no-ops.
+//! [`DatabaseBackend::sql`] rewrites the placeholders for Postgres and
+//! [`with_pool!`] runs the same query body against whichever pool the store
+//! holds.
+
+use std::borrow::Cow;
+
+use crate::DatabaseBackend;
+
+/// Run `$body` with `$pool` bound to the store's concrete pool. The body is
+/// compiled once per enabled backend, so it must only use sqlx APIs (and
+/// bind types) both drivers support.
+macro_rules! with_pool {
+    ($store_pool:expr, |$pool:ident| $body:expr) => {
+        match $store_pool {
+            #[cfg(feature = "sqlite")]
+            $crate::StorePool::Sqlite($pool) => $body,
+            #[cfg(feature = "postgres")]
+            $crate::StorePool::Postgres($pool) => $body,
+        }
+    };
+}
+pub(crate) use with_pool;
+
+impl DatabaseBackend {
+    /// `stmt` with placeholders in this backend's syntax: `?N` for SQLite,
+    /// `$N` for Postgres. Only a `?` followed by a digit is rewritten.
+    pub(crate) fn sql(self, stmt: &str) -> Cow<'_, str> {
+        match self {
+            DatabaseBackend::Sqlite => Cow::Borrowed(stmt),
+            DatabaseBackend::Postgres => {
+                let mut out = String::with_capacity(stmt.len());
+                let mut chars = stmt.chars().peekable();
+                while let Some(c) = chars.next() {
+                    if c == '?' && chars.peek().is_some_and(|n| n.is_ascii_digit()) {
+                        out.push('$');
+                    } else {
+                        out.push(c);
+                    }
+                }
+                Cow::Owned(out)
+            }
+        }
+    }
+
+    /// Names of the columns of table `?1` in the current schema.
+    pub(crate) fn columns_sql(self) -> &'static str {
+        match self {
+            DatabaseBackend::Sqlite => "SELECT name FROM pragma_table_info(?1)",
+            DatabaseBackend::Postgres => {
+                "SELECT CAST(column_name AS TEXT) FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = ?1"
+            }
+        }
+    }
+
+    /// Name, declared type and NOT NULL (1 or 0) of every column of table
+    /// `?1`.
+    pub(crate) fn column_types_sql(self) -> &'static str {
+        match self {
+            DatabaseBackend::Sqlite => r#"SELECT name, type, CAST("notnull" AS BIGINT) FROM pragma_table_info(?1)"#,
+            DatabaseBackend::Postgres => {
+                "SELECT CAST(column_name AS TEXT), CAST(data_type AS TEXT), CAST(CASE WHEN is_nullable = 'NO' THEN 1 ELSE 0 END AS BIGINT) FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = ?1"
+            }
+        }
+    }
+
+    /// Count of user tables named `?1` in the current schema.
+    pub(crate) fn table_exists_sql(self) -> &'static str {
+        match self {
+            DatabaseBackend::Sqlite => {
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1"
+            }
+            DatabaseBackend::Postgres => {
+                "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = current_schema() AND table_name = ?1"
+            }
+        }
+    }
+}
+
+pub(crate) const INSERT_EVENT: &str =
+    "INSERT INTO raw_events (run_id, ts_ms, source, topic, payload_json) VALUES (?1, ?2, ?3, ?4, ?5)";
+
+pub(crate) const INSERT_INCIDENT: &str =
+    "INSERT INTO incidents (run_id, ts_ms, severity, kind, message) VALUES (?1, ?2, ?3, ?4, ?5)";
+
+pub(crate) const INSERT_FILL: &str =
+    "INSERT INTO fills (run_id, ts_ms, venue, fill_id, order_id, client_order_id, market_id, strategy, side, price, qty, fee_usd, liquidity, raw_json)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14) RETURNING CAST(id AS BIGINT)";
+
+pub(crate) const UPSERT_ORDER: &str =
+    "INSERT INTO orders (run_id, ts_submitted_ms, approved_id, intent_id, strategy, market_id, venue, order_id, client_order_id, status, side, limit_price, qty, ts_acked_ms, ts_final_ms, submit_latency_ms, notes)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
+     ON CONFLICT(client_order_id) DO UPDATE SET order_id = excluded.order_id, status = excluded.status, ts_acked_ms = excluded.ts_acked_ms, ts_final_ms = excluded.ts_final_ms, submit_latency_ms = excluded.submit_latency_ms, notes = excluded.notes
+     RETURNING CAST(id AS BIGINT)";
+
+pub(crate) const INSERT_PNL: &str =
+    "INSERT INTO pnl_ledger (run_id, ts_ms, market_id, strategy, kind, ref, pnl_usd, notes)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8) RETURNING CAST(id AS BIGINT)";
+
+/// Column list for reading an [`crate::OrderRow`] from `orders`.
+pub(crate) const ORDER_COLUMNS: &str =
+    "run_id, client_order_id, order_id, CAST(approved_id AS BIGINT) AS approved_id, CAST(intent_id AS BIGINT) AS intent_id, strategy, market_id, venue, status, side, CAST(limit_price AS DOUBLE PRECISION) AS limit_price, CAST(qty AS DOUBLE PRECISION) AS qty, ts_submitted_ms, ts_acked_ms, ts_final_ms, submit_latency_ms, notes";
+
+/// Bind a [`crate::FillRow`] to [`INSERT_FILL`].
+macro_rules! bind_fill {
+    ($query:expr, $fill:expr) => {
+        $query
+            .bind(&$fill.run_id)
+            .bind($fill.ts_ms)
+            .bind(&$fill.venue)
+            .bind(&$fill.fill_id)
+            .bind(&$fill.order_id)
+            .bind(&$fill.client_order_id)
+            .bind($fill.market_id)
+            .bind(&$fill.strategy)
+            .bind(&$fill.side)
+            .bind($fill.price)
+            .bind($fill.qty)
+            .bind($fill.fee_usd)
+            .bind(&$fill.liquidity)
+            .bind(&$fill.raw_json)
+    };
+}
+pub(crate) use bind_fill;
+
+/// Bind an [`crate::OrderRow`] to [`UPSERT_ORDER`].
+macro_rules! bind_order {
+    ($query:expr, $order:expr) => {
+        $query
+            .bind(&$order.run_id)
+            .bind($order.ts_submitted_ms)
+            .bind($order.approved_id)
+            .bind($order.intent_id)
+            .bind(&$order.strategy)
+            .bind($order.market_id)
+            .bind(&$order.venue)
+            .bind(&$order.order_id)
+            .bind(&$order.client_order_id)
+            .bind(&$order.status)
+            .bind(&$order.side)
+            .bind($order.limit_price)
+            .bind($order.qty)
+            .bind($order.ts_acked_ms)
+            .bind($order.ts_final_ms)
+            .bind($order.submit_latency_ms)
+            .bind(&$order.notes)
+    };
+}
+pub(crate) use bind_order;
+
+/// Bind a [`crate::PnlEntry`] to [`INSERT_PNL`].
+macro_rules! bind_pnl {
+    ($query:expr, $entry:expr) => {
+        $query
+            .bind(&$entry.run_id)
+            .bind($entry.ts_ms)
+            .bind($entry.market_id)
+            .bind(&$entry.strategy)
+            .bind(&$entry.kind)
+            .bind(&$entry.reference)
+            .bind($entry.pnl_usd)
+            .bind(&$entry.notes)
+    };
+}
+pub(crate) use bind_pnl;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_follow_the_backend() {
+        let stmt = "SELECT a FROM t WHERE b = ?1 AND c IN (?2, ?10) AND d = '?'";
+        assert_eq!(DatabaseBackend::Sqlite.sql(stmt), stmt);
+        assert_eq!(
+            DatabaseBackend::Postgres.sql(stmt),
+            "SELECT a FROM t WHERE b = $1 AND c IN ($2, $10) AND d = '?'"
+        );
+    }
+}
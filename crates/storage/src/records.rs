@@ -0,0 +1,562 @@
+use anyhow::{bail, Context, Result};
+
+use crate::query::{self, with_pool};
+use crate::{OrderRow, Store};
+
+/// One row of the `snapshots` table: the state a decision was made on.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct SnapshotRow {
+    pub run_id: String,
+    pub ts_ms: i64,
+    pub market_id: i64,
+    pub best_bid_px: Option<f64>,
+    pub best_bid_qty: Option<f64>,
+    pub best_ask_px: Option<f64>,
+    pub best_ask_qty: Option<f64>,
+    pub spread: Option<f64>,
+    pub yes_qty: f64,
+    pub no_qty: f64,
+    pub net_exposure_usd: f64,
+    pub can_trade: bool,
+    pub drawdown_halt: bool,
+    pub crowding_score: f64,
+    pub toxicity_score: f64,
+    pub spread_compression: f64,
+    pub feature_schema_version: i64,
+    pub features_json: String,
+}
+
+/// One row of the `strategy_intents` table.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct IntentRow {
+    pub run_id: String,
+    pub ts_ms: i64,
+    pub snapshot_id: i64,
+    pub strategy: String,
+    pub market_id: i64,
+    pub intent_kind: String,
+    pub side: Option<String>,
+    pub price: Option<f64>,
+    pub size: Option<f64>,
+    pub urgency: String,
+    pub ttl_ms: i64,
+    pub expected_value: f64,
+    pub confidence: f64,
+    pub risk_cost: f64,
+    pub tags_json: String,
+    pub rationale_json: Option<String>,
+    /// Strategy priority tier (0 = arb, 1 = MM, 2 = directional).
+    pub tier: Option<i64>,
+    /// Shared by the intents of one multi-leg trade.
+    pub leg_group: Option<String>,
+}
+
+/// One row of the `arbiter_approvals` table. Rejections are recorded too,
+/// with `approved = false`.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct ApprovalRow {
+    pub run_id: String,
+    pub ts_ms: i64,
+    pub intent_id: i64,
+    pub approved: bool,
+    pub reason: Option<String>,
+    pub owner_strategy: Option<String>,
+}
+
+/// One row of the `portfolio_snapshots` table.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct PortfolioSnapshotRow {
+    pub run_id: String,
+    pub ts_ms: i64,
+    pub equity_usd: f64,
+    pub realized_pnl_usd: f64,
+    pub unrealized_pnl_usd: f64,
+    pub gross_exposure_usd: f64,
+    pub net_exposure_usd: f64,
+    pub drawdown_usd: f64,
+    pub drawdown_pct: f64,
+    pub open_orders_count: i64,
+}
+
+/// One row of the `plugin_signals` table.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct PluginSignalRow {
+    pub run_id: String,
+    pub ts_ms: i64,
+    pub plugin: String,
+    pub scope: String,
+    pub market_id: Option<i64>,
+    pub payload_json: String,
+}
+
+impl Store {
+    /// Record the ordered feature names of `features_json` for `version`.
+    /// Registering a version again is a no-op with the same names and an
+    /// error with different ones: changed features need a new version.
+    pub async fn register_feature_schema(
+        &self,
+        version: i64,
+        description: &str,
+        features: &[String],
+        now_ms: i64,
+    ) -> Result<()> {
+        let names = serde_json::to_string(features)?;
+        let sql = self.backend.sql(
+            "INSERT INTO feature_schemas (feature_schema_version, created_at_ms, description, features_json)
+             VALUES (?1, ?2, ?3, ?4) ON CONFLICT(feature_schema_version) DO NOTHING",
+        );
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(&sql)
+                .bind(version)
+                .bind(now_ms)
+                .bind(description)
+                .bind(&names)
+                .execute(pool)
+                .await?;
+        });
+        match self.feature_schema(version).await? {
+            Some(existing) if existing == features => Ok(()),
+            Some(existing) => bail!(
+                "feature schema {version} is registered as {existing:?}, not {features:?}; bump the version"
+            ),
+            None => bail!("feature schema {version} was not recorded"),
+        }
+    }
+
+    /// Ordered feature names of `version`, if registered.
+    pub async fn feature_schema(&self, version: i64) -> Result<Option<Vec<String>>> {
+        let sql = self
+            .backend
+            .sql("SELECT features_json FROM feature_schemas WHERE feature_schema_version = ?1");
+        let names: Option<String> = with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar(&sql)
+                .bind(version)
+                .fetch_optional(pool)
+                .await?
+        });
+        names
+            .map(|names| {
+                serde_json::from_str(&names)
+                    .with_context(|| format!("feature_schemas.features_json of version {version}"))
+            })
+            .transpose()
+    }
+
+    /// Insert a snapshot; returns its `snapshot_id`.
+    pub async fn insert_snapshot(&self, snapshot: &SnapshotRow) -> Result<i64> {
+        let sql = self.backend.sql(
+            "INSERT INTO snapshots (run_id, ts_ms, market_id, best_bid_px, best_bid_qty, best_ask_px, best_ask_qty, spread, yes_qty, no_qty, net_exposure_usd, can_trade, drawdown_halt, crowding_score, toxicity_score, spread_compression, feature_schema_version, features_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18) RETURNING CAST(snapshot_id AS BIGINT)",
+        );
+        let id = with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar::<_, i64>(&sql)
+                .bind(&snapshot.run_id)
+                .bind(snapshot.ts_ms)
+                .bind(snapshot.market_id)
+                .bind(snapshot.best_bid_px)
+                .bind(snapshot.best_bid_qty)
+                .bind(snapshot.best_ask_px)
+                .bind(snapshot.best_ask_qty)
+                .bind(snapshot.spread)
+                .bind(snapshot.yes_qty)
+                .bind(snapshot.no_qty)
+                .bind(snapshot.net_exposure_usd)
+                .bind(i32::from(snapshot.can_trade))
+                .bind(i32::from(snapshot.drawdown_halt))
+                .bind(snapshot.crowding_score)
+                .bind(snapshot.toxicity_score)
+                .bind(snapshot.spread_compression)
+                .bind(snapshot.feature_schema_version)
+                .bind(&snapshot.features_json)
+                .fetch_one(pool)
+                .await?
+        });
+        Ok(id)
+    }
+
+    pub async fn fetch_snapshot(&self, snapshot_id: i64) -> Result<Option<SnapshotRow>> {
+        let sql = self.backend.sql(
+            "SELECT run_id, ts_ms, market_id, CAST(best_bid_px AS DOUBLE PRECISION) AS best_bid_px, CAST(best_bid_qty AS DOUBLE PRECISION) AS best_bid_qty, CAST(best_ask_px AS DOUBLE PRECISION) AS best_ask_px, CAST(best_ask_qty AS DOUBLE PRECISION) AS best_ask_qty, CAST(spread AS DOUBLE PRECISION) AS spread, CAST(yes_qty AS DOUBLE PRECISION) AS yes_qty, CAST(no_qty AS DOUBLE PRECISION) AS no_qty, CAST(net_exposure_usd AS DOUBLE PRECISION) AS net_exposure_usd, can_trade <> 0 AS can_trade, drawdown_halt <> 0 AS drawdown_halt, CAST(crowding_score AS DOUBLE PRECISION) AS crowding_score, CAST(toxicity_score AS DOUBLE PRECISION) AS toxicity_score, CAST(spread_compression AS DOUBLE PRECISION) AS spread_compression, CAST(feature_schema_version AS BIGINT) AS feature_schema_version, features_json
+             FROM snapshots WHERE snapshot_id = ?1",
+        );
+        let row = with_pool!(&self.pool, |pool| {
+            sqlx::query_as::<_, SnapshotRow>(&sql)
+                .bind(snapshot_id)
+                .fetch_optional(pool)
+                .await?
+        });
+        Ok(row)
+    }
+
+    /// Insert a strategy intent; returns its `intent_id`.
+    pub async fn insert_intent(&self, intent: &IntentRow) -> Result<i64> {
+        let sql = self.backend.sql(
+            "INSERT INTO strategy_intents (run_id, ts_ms, snapshot_id, strategy, market_id, intent_kind, side, price, size, urgency, ttl_ms, expected_value, confidence, risk_cost, tags_json, rationale_json, tier, leg_group)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18) RETURNING CAST(intent_id AS BIGINT)",
+        );
+        let id = with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar::<_, i64>(&sql)
+                .bind(&intent.run_id)
+                .bind(intent.ts_ms)
+                .bind(intent.snapshot_id)
+                .bind(&intent.strategy)
+                .bind(intent.market_id)
+                .bind(&intent.intent_kind)
+                .bind(&intent.side)
+                .bind(intent.price)
+                .bind(intent.size)
+                .bind(&intent.urgency)
+                .bind(intent.ttl_ms)
+                .bind(intent.expected_value)
+                .bind(intent.confidence)
+                .bind(intent.risk_cost)
+                .bind(&intent.tags_json)
+                .bind(&intent.rationale_json)
+                .bind(intent.tier)
+                .bind(&intent.leg_group)
+                .fetch_one(pool)
+                .await?
+        });
+        Ok(id)
+    }
+
+    pub async fn fetch_intent(&self, intent_id: i64) -> Result<Option<IntentRow>> {
+        let sql = self.backend.sql(
+            "SELECT run_id, ts_ms, CAST(snapshot_id AS BIGINT) AS snapshot_id, strategy, market_id, intent_kind, side, CAST(price AS DOUBLE PRECISION) AS price, CAST(size AS DOUBLE PRECISION) AS size, urgency, ttl_ms, CAST(expected_value AS DOUBLE PRECISION) AS expected_value, CAST(confidence AS DOUBLE PRECISION) AS confidence, CAST(risk_cost AS DOUBLE PRECISION) AS risk_cost, tags_json, rationale_json, CAST(tier AS BIGINT) AS tier, leg_group
+             FROM strategy_intents WHERE intent_id = ?1",
+        );
+        let row = with_pool!(&self.pool, |pool| {
+            sqlx::query_as::<_, IntentRow>(&sql)
+                .bind(intent_id)
+                .fetch_optional(pool)
+                .await?
+        });
+        Ok(row)
+    }
+
+    /// Record an arbiter decision; returns its `approved_id`.
+    pub async fn insert_approval(&self, approval: &ApprovalRow) -> Result<i64> {
+        let sql = self.backend.sql(
+            "INSERT INTO arbiter_approvals (run_id, ts_ms, intent_id, approved, reason, owner_strategy)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6) RETURNING CAST(approved_id AS BIGINT)",
+        );
+        let id = with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar::<_, i64>(&sql)
+                .bind(&approval.run_id)
+                .bind(approval.ts_ms)
+                .bind(approval.intent_id)
+                .bind(i32::from(approval.approved))
+                .bind(&approval.reason)
+                .bind(&approval.owner_strategy)
+                .fetch_one(pool)
+                .await?
+        });
+        Ok(id)
+    }
+
+    pub async fn fetch_approval(&self, approved_id: i64) -> Result<Option<ApprovalRow>> {
+        let sql = self.backend.sql(
+            "SELECT run_id, ts_ms, CAST(intent_id AS BIGINT) AS intent_id, approved <> 0 AS approved, reason, owner_strategy
+             FROM arbiter_approvals WHERE approved_id = ?1",
+        );
+        let row = with_pool!(&self.pool, |pool| {
+            sqlx::query_as::<_, ApprovalRow>(&sql)
+                .bind(approved_id)
+                .fetch_optional(pool)
+                .await?
+        });
+        Ok(row)
+    }
+
+    /// Every order of a run, in submission order.
+    pub async fn orders_for_run(&self, run_id: &str) -> Result<Vec<OrderRow>> {
+        let sql = self
+            .backend
+            .sql(&format!(
+                "SELECT {} FROM orders WHERE run_id = ?1 ORDER BY id",
+                query::ORDER_COLUMNS
+            ))
+            .into_owned();
+        let rows = with_pool!(&self.pool, |pool| {
+            sqlx::query_as::<_, OrderRow>(&sql)
+                .bind(run_id)
+                .fetch_all(pool)
+                .await?
+        });
+        Ok(rows)
+    }
+
+    /// Insert a portfolio snapshot; returns its id.
+    pub async fn insert_portfolio_snapshot(&self, snapshot: &PortfolioSnapshotRow) -> Result<i64> {
+        let sql = self.backend.sql(
+            "INSERT INTO portfolio_snapshots (run_id, ts_ms, equity_usd, realized_pnl_usd, unrealized_pnl_usd, gross_exposure_usd, net_exposure_usd, drawdown_usd, drawdown_pct, open_orders_count)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10) RETURNING CAST(id AS BIGINT)",
+        );
+        let id = with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar::<_, i64>(&sql)
+                .bind(&snapshot.run_id)
+                .bind(snapshot.ts_ms)
+                .bind(snapshot.equity_usd)
+                .bind(snapshot.realized_pnl_usd)
+                .bind(snapshot.unrealized_pnl_usd)
+                .bind(snapshot.gross_exposure_usd)
+                .bind(snapshot.net_exposure_usd)
+                .bind(snapshot.drawdown_usd)
+                .bind(snapshot.drawdown_pct)
+                .bind(snapshot.open_orders_count)
+                .fetch_one(pool)
+                .await?
+        });
+        Ok(id)
+    }
+
+    pub async fn portfolio_snapshots(&self, run_id: &str) -> Result<Vec<PortfolioSnapshotRow>> {
+        let sql = self.backend.sql(
+            "SELECT run_id, ts_ms, CAST(equity_usd AS DOUBLE PRECISION) AS equity_usd, CAST(realized_pnl_usd AS DOUBLE PRECISION) AS realized_pnl_usd, CAST(unrealized_pnl_usd AS DOUBLE PRECISION) AS unrealized_pnl_usd, CAST(gross_exposure_usd AS DOUBLE PRECISION) AS gross_exposure_usd, CAST(net_exposure_usd AS DOUBLE PRECISION) AS net_exposure_usd, CAST(drawdown_usd AS DOUBLE PRECISION) AS drawdown_usd, CAST(drawdown_pct AS DOUBLE PRECISION) AS drawdown_pct, CAST(open_orders_count AS BIGINT) AS open_orders_count
+             FROM portfolio_snapshots WHERE run_id = ?1 ORDER BY id",
+        );
+        let rows = with_pool!(&self.pool, |pool| {
+            sqlx::query_as::<_, PortfolioSnapshotRow>(&sql)
+                .bind(run_id)
+                .fetch_all(pool)
+                .await?
+        });
+        Ok(rows)
+    }
+
+    /// Insert a plugin signal; returns its id.
+    pub async fn insert_plugin_signal(&self, signal: &PluginSignalRow) -> Result<i64> {
+        let sql = self.backend.sql(
+            "INSERT INTO plugin_signals (run_id, ts_ms, plugin, scope, market_id, payload_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6) RETURNING CAST(id AS BIGINT)",
+        );
+        let id = with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar::<_, i64>(&sql)
+                .bind(&signal.run_id)
+                .bind(signal.ts_ms)
+                .bind(&signal.plugin)
+                .bind(&signal.scope)
+                .bind(signal.market_id)
+                .bind(&signal.payload_json)
+                .fetch_one(pool)
+                .await?
+        });
+        Ok(id)
+    }
+
+    pub async fn plugin_signals(&self, run_id: &str) -> Result<Vec<PluginSignalRow>> {
+        let sql = self.backend.sql(
+            "SELECT run_id, ts_ms, plugin, scope, market_id, payload_json
+             FROM plugin_signals WHERE run_id = ?1 ORDER BY id",
+        );
+        let rows = with_pool!(&self.pool, |pool| {
+            sqlx::query_as::<_, PluginSignalRow>(&sql)
+                .bind(run_id)
+                .fetch_all(pool)
+                .await?
+        });
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{test_stores, unique};
+    use crate::{FillRow, PnlEntry};
+
+    // Float fixtures are exact in f32: Postgres stores REAL columns as float4.
+    fn snapshot(run_id: &str) -> SnapshotRow {
+        SnapshotRow {
+            run_id: run_id.into(),
+            ts_ms: 1_000,
+            market_id: 7,
+            best_bid_px: Some(0.375),
+            best_bid_qty: Some(120.0),
+            best_ask_px: Some(0.4375),
+            best_ask_qty: Some(80.0),
+            spread: Some(0.0625),
+            yes_qty: 0.0,
+            no_qty: 0.0,
+            net_exposure_usd: 0.0,
+            can_trade: true,
+            drawdown_halt: false,
+            crowding_score: 0.125,
+            toxicity_score: 0.25,
+            spread_compression: 0.0,
+            feature_schema_version: 1,
+            features_json: r#"{"imbalance":0.6}"#.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn golden_chain_links_intent_to_pnl() -> Result<()> {
+        for store in test_stores().await? {
+            let run = unique("run-chain");
+            store.insert_run(&run, None).await?;
+
+            let snapshot_id = store.insert_snapshot(&snapshot(&run)).await?;
+            assert_eq!(
+                store.fetch_snapshot(snapshot_id).await?,
+                Some(snapshot(&run))
+            );
+
+            let intent = IntentRow {
+                run_id: run.clone(),
+                ts_ms: 1_001,
+                snapshot_id,
+                strategy: "mm".into(),
+                market_id: 7,
+                intent_kind: "PlaceOrder".into(),
+                side: Some("BuyYes".into()),
+                price: Some(0.40625),
+                size: Some(10.0),
+                urgency: "maker".into(),
+                ttl_ms: 30_000,
+                expected_value: 0.0625,
+                confidence: 0.75,
+                risk_cost: 0.015625,
+                tags_json: r#"["mm"]"#.into(),
+                rationale_json: None,
+                tier: None,
+                leg_group: None,
+            };
+            let intent_id = store.insert_intent(&intent).await?;
+            assert_eq!(store.fetch_intent(intent_id).await?, Some(intent));
+
+            let approval = ApprovalRow {
+                run_id: run.clone(),
+                ts_ms: 1_002,
+                intent_id,
+                approved: true,
+                reason: None,
+                owner_strategy: Some("mm".into()),
+            };
+            let approved_id = store.insert_approval(&approval).await?;
+            assert_eq!(store.fetch_approval(approved_id).await?, Some(approval));
+
+            let client_order_id = unique("c");
+            let order = OrderRow {
+                run_id: run.clone(),
+                client_order_id: client_order_id.clone(),
+                order_id: Some("0xabc".into()),
+                approved_id: Some(approved_id),
+                intent_id: Some(intent_id),
+                strategy: "mm".into(),
+                market_id: 7,
+                venue: "polymarket".into(),
+                status: "Filled".into(),
+                side: "BuyYes".into(),
+                limit_price: 0.40625,
+                qty: 10.0,
+                ts_submitted_ms: 1_003,
+                ts_acked_ms: Some(1_010),
+                ts_final_ms: Some(1_500),
+                submit_latency_ms: Some(7),
+                notes: None,
+            };
+            store.upsert_order(&order).await?;
+            assert_eq!(store.orders_for_run(&run).await?, vec![order]);
+
+            let fill_id = store
+                .insert_fill(&FillRow {
+                    run_id: run.clone(),
+                    ts_ms: 1_500,
+                    venue: "polymarket".into(),
+                    fill_id: Some(unique("f")),
+                    order_id: Some("0xabc".into()),
+                    client_order_id: Some(client_order_id),
+                    market_id: 7,
+                    strategy: "mm".into(),
+                    side: "BuyYes".into(),
+                    price: 0.40625,
+                    qty: 10.0,
+                    fee_usd: Some(0.0),
+                    liquidity: Some("maker".into()),
+                    raw_json: None,
+                })
+                .await?;
+            let pnl_id = store
+                .insert_pnl(&PnlEntry {
+                    run_id: run.clone(),
+                    ts_ms: 2_000,
+                    market_id: 7,
+                    strategy: Some("mm".into()),
+                    kind: "realized".into(),
+                    reference: Some(fill_id.to_string()),
+                    pnl_usd: 0.25,
+                    notes: None,
+                })
+                .await?;
+            assert!(fill_id > 0 && pnl_id > 0);
+            assert_eq!(
+                store.pnl_entries(&run).await?[0].reference,
+                Some(fill_id.to_string())
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn portfolio_snapshots_and_plugin_signals_round_trip() -> Result<()> {
+        for store in test_stores().await? {
+            let run = unique("run-portfolio");
+            store.insert_run(&run, None).await?;
+
+            let portfolio = PortfolioSnapshotRow {
+                run_id: run.clone(),
+                ts_ms: 5_000,
+                equity_usd: 1_000.0,
+                realized_pnl_usd: 12.5,
+                unrealized_pnl_usd: -2.0,
+                gross_exposure_usd: 300.0,
+                net_exposure_usd: 120.0,
+                drawdown_usd: 4.0,
+                drawdown_pct: 0.00390625,
+                open_orders_count: 3,
+            };
+            let first = store.insert_portfolio_snapshot(&portfolio).await?;
+            let second = store.insert_portfolio_snapshot(&portfolio).await?;
+            assert!(second > first);
+            assert_eq!(
+                store.portfolio_snapshots(&run).await?,
+                vec![portfolio.clone(), portfolio]
+            );
+
+            let signal = PluginSignalRow {
+                run_id: run.clone(),
+                ts_ms: 5_001,
+                plugin: "news".into(),
+                scope: "market".into(),
+                market_id: Some(7),
+                payload_json: r#"{"sentiment":-0.4}"#.into(),
+            };
+            store.insert_plugin_signal(&signal).await?;
+            assert_eq!(store.plugin_signals(&run).await?, vec![signal]);
+
+            // intents must point at a recorded snapshot
+            let dangling = IntentRow {
+                run_id: run.clone(),
+                ts_ms: 0,
+                snapshot_id: i32::MAX as i64,
+                strategy: "mm".into(),
+                market_id: 7,
+                intent_kind: "NoOp".into(),
+                side: None,
+                price: None,
+                size: None,
+                urgency: "neutral".into(),
+                ttl_ms: 0,
+                expected_value: 0.0,
+                confidence: 0.0,
+                risk_cost: 0.0,
+                tags_json: "[]".into(),
+                rationale_json: None,
+                tier: None,
+                leg_group: None,
+            };
+            assert!(store.insert_intent(&dangling).await.is_err());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,590 @@
+//! `raw_events` retention: whole days older than the retention window are
+//! exported per run and day to zstd-compressed JSONL under the archive
+//! directory, recorded in `manifest.jsonl` with a sha256 of each file, and
+//! only then deleted from the database in bounded batches.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::{info, warn};
+
+use crate::query::with_pool;
+use crate::{Store, StorePool};
+
+/// `incidents.kind` for a failed retention pass.
+pub const RETENTION_INCIDENT: &str = "RETENTION";
+
+pub const MANIFEST_FILE: &str = "manifest.jsonl";
+
+const DAY_MS: i64 = 86_400_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionConfig {
+    /// Whole days of `raw_events` kept in the database. 0 disables
+    /// retention.
+    pub keep_days: u32,
+    pub archive_dir: PathBuf,
+    /// Rows read per page while exporting, and deleted per statement.
+    pub batch_size: usize,
+    /// Pause between delete batches so the storage writer gets the lock.
+    pub batch_pause: Duration,
+    /// Pages handed back per `PRAGMA incremental_vacuum` (SQLite only).
+    pub vacuum_pages: u32,
+    pub zstd_level: i32,
+    pub interval: Duration,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            keep_days: 14,
+            archive_dir: PathBuf::from("archive"),
+            batch_size: 5_000,
+            batch_pause: Duration::from_millis(20),
+            vacuum_pages: 2_000,
+            zstd_level: 3,
+            interval: Duration::from_secs(3_600),
+        }
+    }
+}
+
+impl RetentionConfig {
+    /// Rows with `ts_ms` below this are due; always a UTC day boundary.
+    pub fn cutoff_ms(&self, now_ms: i64) -> Option<i64> {
+        (self.keep_days > 0).then(|| (now_ms.div_euclid(DAY_MS) - self.keep_days as i64) * DAY_MS)
+    }
+}
+
+/// One archived file, as recorded in the manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    pub table: String,
+    pub run_id: String,
+    /// UTC date, `YYYY-MM-DD`.
+    pub day: String,
+    /// Relative to the archive directory.
+    pub path: String,
+    pub rows: u64,
+    pub first_id: i64,
+    pub last_id: i64,
+    pub first_ts_ms: i64,
+    pub last_ts_ms: i64,
+    pub bytes: u64,
+    pub sha256: String,
+    pub created_at_ms: i64,
+}
+
+/// The `raw_events` rows of one run on one UTC day.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct RawEventPartition {
+    pub run_id: String,
+    /// Days since the Unix epoch.
+    pub day: i64,
+    pub row_count: i64,
+    pub first_id: i64,
+    pub last_id: i64,
+}
+
+impl RawEventPartition {
+    pub fn date(&self) -> String {
+        DateTime::<Utc>::from_timestamp_millis(self.day * DAY_MS)
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| self.day.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionReport {
+    pub archived: Vec<ArchiveEntry>,
+    pub rows_deleted: u64,
+    /// Whether SQLite free pages were released with `incremental_vacuum`.
+    pub vacuumed: bool,
+    /// Set when the pass stopped early; files archived before the failure
+    /// are still listed.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct RawEventRow {
+    id: i64,
+    run_id: String,
+    ts_ms: i64,
+    source: String,
+    topic: String,
+    market_id: Option<i64>,
+    payload_json: String,
+}
+
+/// Counts bytes and hashes them on the way to the file.
+pub(crate) struct HashingWriter<W> {
+    pub(crate) inner: W,
+    pub(crate) hasher: Sha256,
+    pub(crate) bytes: u64,
+}
+
+impl<W> HashingWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+            bytes: 0,
+        }
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.bytes += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub(crate) fn path_safe(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+pub fn read_manifest(archive_dir: &Path) -> Result<Vec<ArchiveEntry>> {
+    let path = archive_dir.join(MANIFEST_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("reading {}", path.display())),
+    };
+    text.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| serde_json::from_str(l).context("bad manifest line"))
+        .collect()
+}
+
+/// Re-hash every file in the manifest; returns one line per missing or
+/// mismatched file.
+pub fn verify_archive(archive_dir: &Path) -> Result<Vec<String>> {
+    let mut problems = Vec::new();
+    for entry in read_manifest(archive_dir)? {
+        let path = archive_dir.join(&entry.path);
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) => {
+                problems.push(format!("{}: {err}", entry.path));
+                continue;
+            }
+        };
+        let mut hashing = HashingWriter::new(io::sink());
+        io::copy(&mut file, &mut hashing)?;
+        let sha256 = hex::encode(hashing.hasher.finalize());
+        if hashing.bytes != entry.bytes || sha256 != entry.sha256 {
+            problems.push(format!(
+                "{}: checksum mismatch ({} bytes, sha256 {sha256})",
+                entry.path, hashing.bytes
+            ));
+        }
+    }
+    Ok(problems)
+}
+
+impl Store {
+    /// `raw_events` partitions entirely older than `cutoff_ms`.
+    pub async fn raw_event_partitions(&self, cutoff_ms: i64) -> Result<Vec<RawEventPartition>> {
+        let sql = self.backend.sql(
+            "SELECT run_id, CAST(ts_ms / 86400000 AS BIGINT) AS day, COUNT(*) AS row_count, CAST(MIN(id) AS BIGINT) AS first_id, CAST(MAX(id) AS BIGINT) AS last_id
+             FROM raw_events WHERE ts_ms < ?1
+             GROUP BY run_id, ts_ms / 86400000 ORDER BY run_id, day",
+        );
+        let rows = with_pool!(&self.pool, |pool| {
+            sqlx::query_as::<_, RawEventPartition>(&sql)
+                .bind(cutoff_ms)
+                .fetch_all(pool)
+                .await?
+        });
+        Ok(rows)
+    }
+
+    async fn raw_events_page(
+        &self,
+        part: &RawEventPartition,
+        (from_ms, to_ms): (i64, i64),
+        after_id: i64,
+        limit: usize,
+    ) -> Result<Vec<RawEventRow>> {
+        let sql = self.backend.sql(
+            "SELECT CAST(id AS BIGINT) AS id, run_id, ts_ms, source, topic, market_id, payload_json
+             FROM raw_events
+             WHERE run_id = ?1 AND ts_ms >= ?2 AND ts_ms < ?3 AND id > ?4 AND id <= ?5
+             ORDER BY id LIMIT ?6",
+        );
+        let rows = with_pool!(&self.pool, |pool| {
+            sqlx::query_as::<_, RawEventRow>(&sql)
+                .bind(&part.run_id)
+                .bind(from_ms)
+                .bind(to_ms)
+                .bind(after_id)
+                .bind(part.last_id)
+                .bind(limit as i64)
+                .fetch_all(pool)
+                .await?
+        });
+        Ok(rows)
+    }
+
+    async fn delete_raw_events(
+        &self,
+        run_id: &str,
+        (from_ms, to_ms): (i64, i64),
+        last_id: i64,
+        limit: usize,
+    ) -> Result<u64> {
+        let sql = self.backend.sql(
+            "DELETE FROM raw_events WHERE id IN (
+               SELECT id FROM raw_events
+               WHERE run_id = ?1 AND ts_ms >= ?2 AND ts_ms < ?3 AND id <= ?4
+               LIMIT ?5)",
+        );
+        let deleted = with_pool!(&self.pool, |pool| {
+            sqlx::query(&sql)
+                .bind(run_id)
+                .bind(from_ms)
+                .bind(to_ms)
+                .bind(last_id)
+                .bind(limit as i64)
+                .execute(pool)
+                .await?
+                .rows_affected()
+        });
+        Ok(deleted)
+    }
+
+    /// Hand up to `pages` free pages back to the filesystem. Only SQLite
+    /// databases in `auto_vacuum = INCREMENTAL` mode have any to give;
+    /// returns whether a vacuum step ran.
+    #[cfg_attr(not(feature = "sqlite"), allow(unused_variables))]
+    pub async fn incremental_vacuum(&self, pages: u32) -> Result<bool> {
+        match &self.pool {
+            #[cfg(feature = "sqlite")]
+            StorePool::Sqlite(pool) => {
+                let mode: i64 = sqlx::query_scalar("PRAGMA auto_vacuum")
+                    .fetch_one(pool)
+                    .await?;
+                if mode != 2 {
+                    return Ok(false);
+                }
+                sqlx::query(&format!("PRAGMA incremental_vacuum({pages})"))
+                    .execute(pool)
+                    .await?;
+                Ok(true)
+            }
+            // autovacuum reclaims dead tuples on Postgres
+            #[cfg(feature = "postgres")]
+            StorePool::Postgres(_) => Ok(false),
+        }
+    }
+}
+
+pub struct RetentionJob {
+    store: Store,
+    config: RetentionConfig,
+    reports: UnboundedSender<RetentionReport>,
+}
+
+impl RetentionJob {
+    pub fn new(
+        store: Store,
+        config: RetentionConfig,
+    ) -> (Self, UnboundedReceiver<RetentionReport>) {
+        let (reports, rx) = mpsc::unbounded_channel();
+        (
+            Self {
+                store,
+                config,
+                reports,
+            },
+            rx,
+        )
+    }
+
+    /// Partitions a pass at `now_ms` would archive.
+    pub async fn pending(&self, now_ms: i64) -> Result<Vec<RawEventPartition>> {
+        match self.config.cutoff_ms(now_ms) {
+            Some(cutoff) => self.store.raw_event_partitions(cutoff).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Archive and delete every due partition. A partition's rows are only
+    /// deleted once its file and manifest line are on disk, so a pass that
+    /// dies midway is simply repeated by the next one.
+    pub async fn run_once(&self, now_ms: i64) -> RetentionReport {
+        let mut report = RetentionReport::default();
+        if let Err(err) = self.archive_due(now_ms, &mut report).await {
+            report.error = Some(format!("{err:#}"));
+        }
+        report
+    }
+
+    async fn archive_due(&self, now_ms: i64, report: &mut RetentionReport) -> Result<()> {
+        let Some(cutoff) = self.config.cutoff_ms(now_ms) else {
+            return Ok(());
+        };
+        for part in self.store.raw_event_partitions(cutoff).await? {
+            let span = (part.day * DAY_MS, ((part.day + 1) * DAY_MS).min(cutoff));
+            let Some(entry) = self.export(&part, span, now_ms).await? else {
+                continue;
+            };
+            append_manifest(&self.config.archive_dir, &entry)?;
+            loop {
+                let deleted = self
+                    .store
+                    .delete_raw_events(
+                        &part.run_id,
+                        span,
+                        entry.last_id,
+                        self.config.batch_size.max(1),
+                    )
+                    .await?;
+                report.rows_deleted += deleted;
+                if deleted == 0 {
+                    break;
+                }
+                report.vacuumed |= self
+                    .store
+                    .incremental_vacuum(self.config.vacuum_pages)
+                    .await?;
+                tokio::time::sleep(self.config.batch_pause).await;
+            }
+            info!(run_id = %part.run_id, day = %entry.day, rows = entry.rows, path = %entry.path, "raw events archived");
+            report.archived.push(entry);
+        }
+        Ok(())
+    }
+
+    async fn export(
+        &self,
+        part: &RawEventPartition,
+        span: (i64, i64),
+        now_ms: i64,
+    ) -> Result<Option<ArchiveEntry>> {
+        let day = part.date();
+        let rel = PathBuf::from("raw_events")
+            .join(format!("run={}", path_safe(&part.run_id)))
+            .join(format!("day={day}"))
+            .join(format!(
+                "raw_events-{}-{}.jsonl.zst",
+                part.first_id, part.last_id
+            ));
+        let path = self.config.archive_dir.join(&rel);
+        let tmp = path.with_extension("zst.tmp");
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+
+        let file = File::create(&tmp).with_context(|| format!("creating {}", tmp.display()))?;
+        let mut encoder = zstd::Encoder::new(
+            HashingWriter::new(BufWriter::new(file)),
+            self.config.zstd_level,
+        )?;
+        let (mut rows, mut first, mut last) = (0u64, None, None);
+        let mut after_id = i64::MIN;
+        loop {
+            let page = self
+                .store
+                .raw_events_page(part, span, after_id, self.config.batch_size.max(1))
+                .await?;
+            let Some(tail) = page.last() else {
+                break;
+            };
+            after_id = tail.id;
+            for row in &page {
+                serde_json::to_writer(&mut encoder, row)?;
+                encoder.write_all(b"\n")?;
+                first.get_or_insert((row.id, row.ts_ms));
+                last = Some((row.id, row.ts_ms));
+                rows += 1;
+            }
+        }
+        let mut hashing = encoder.finish()?;
+        hashing.flush()?;
+        let (Some((first_id, first_ts_ms)), Some((last_id, last_ts_ms))) = (first, last) else {
+            let _ = fs::remove_file(&tmp);
+            return Ok(None);
+        };
+        let HashingWriter {
+            inner,
+            hasher,
+            bytes,
+        } = hashing;
+        inner.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp, &path).with_context(|| format!("renaming {}", tmp.display()))?;
+
+        Ok(Some(ArchiveEntry {
+            table: "raw_events".into(),
+            run_id: part.run_id.clone(),
+            day,
+            path: rel.to_string_lossy().replace('\\', "/"),
+            rows,
+            first_id,
+            last_id,
+            first_ts_ms,
+            last_ts_ms,
+            bytes,
+            sha256: hex::encode(hasher.finalize()),
+            created_at_ms: now_ms,
+        }))
+    }
+
+    pub async fn run(&self) {
+        let mut ticker = tokio::time::interval(self.config.interval.max(Duration::from_secs(1)));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let report = self.run_once(Utc::now().timestamp_millis()).await;
+            if let Some(err) = &report.error {
+                warn!(error = %err, "raw event retention failed");
+            }
+            if self.reports.send(report).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn append_manifest(archive_dir: &Path, entry: &ArchiveEntry) -> Result<()> {
+    let path = archive_dir.join(MANIFEST_FILE);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("opening {}", path.display()))?;
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+    file.write_all(&line)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{test_stores, unique};
+    use crate::WriteOp;
+    use std::io::BufRead;
+
+    const NOW: i64 = 20_000 * DAY_MS + 5 * 3_600_000;
+
+    fn event(run_id: &str, day: i64, topic: &str) -> WriteOp {
+        WriteOp::Event {
+            run_id: run_id.into(),
+            ts_ms: day * DAY_MS + 1_000,
+            source: "internal".into(),
+            topic: topic.into(),
+            payload_json: r#"{"tick":1}"#.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn archives_whole_old_days_then_deletes_them() -> Result<()> {
+        for store in test_stores().await? {
+            let dir = tempfile::tempdir()?;
+            let (a, b) = (unique("run-a"), unique("run-b"));
+            store.insert_run(&a, None).await?;
+            store.insert_run(&b, None).await?;
+            store
+                .write_batch(&[
+                    event(&a, 19_990, "tick"),
+                    event(&a, 19_990, "trade"),
+                    event(&b, 19_990, "tick"),
+                    event(&a, 19_997, "tick"),
+                    event(&a, 19_999, "tick"),
+                ])
+                .await?;
+
+            let config = RetentionConfig {
+                keep_days: 2,
+                archive_dir: dir.path().to_path_buf(),
+                batch_size: 1,
+                batch_pause: Duration::ZERO,
+                ..RetentionConfig::default()
+            };
+            let (job, _reports) = RetentionJob::new(store.clone(), config);
+            assert_eq!(job.pending(NOW).await?.len(), 3);
+
+            let report = job.run_once(NOW).await;
+            assert_eq!(report.error, None);
+            assert_eq!(report.rows_deleted, 4);
+            let days: Vec<_> = report
+                .archived
+                .iter()
+                .map(|e| (e.run_id.as_str(), e.day.as_str(), e.rows))
+                .collect();
+            assert_eq!(
+                days,
+                vec![
+                    (a.as_str(), "2024-09-24", 2),
+                    (a.as_str(), "2024-10-01", 1),
+                    (b.as_str(), "2024-09-24", 1)
+                ]
+            );
+            assert_eq!(read_manifest(dir.path())?, report.archived);
+            assert!(verify_archive(dir.path())?.is_empty());
+            assert!(job.pending(NOW).await?.is_empty());
+
+            let first = dir.path().join(&report.archived[0].path);
+            let lines: Vec<serde_json::Value> =
+                io::BufReader::new(zstd::Decoder::new(File::open(&first)?)?)
+                    .lines()
+                    .map(|l| serde_json::from_str(&l.unwrap()).unwrap())
+                    .collect();
+            assert_eq!(lines.len(), 2);
+            assert_eq!(lines[1]["topic"], "trade");
+
+            // the day inside the window stays
+            let kept: Vec<_> = store
+                .raw_event_partitions(i64::MAX)
+                .await?
+                .into_iter()
+                .filter(|p| p.run_id == a || p.run_id == b)
+                .collect();
+            assert_eq!(kept.len(), 1);
+            assert_eq!(kept[0].day, 19_999);
+
+            fs::write(&first, b"tampered")?;
+            assert_eq!(verify_archive(dir.path())?.len(), 1);
+
+            let idle = job.run_once(NOW).await;
+            assert!(idle.archived.is_empty() && idle.error.is_none());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn zero_keep_days_disables_retention() {
+        let off = RetentionConfig {
+            keep_days: 0,
+            ..RetentionConfig::default()
+        };
+        assert_eq!(off.cutoff_ms(NOW), None);
+        assert_eq!(
+            RetentionConfig::default().cutoff_ms(NOW),
+            Some((20_000 - 14) * DAY_MS)
+        );
+    }
+}
@@ -0,0 +1,467 @@
+//! Per-order execution rollups (data_schema.md §4). Fills are folded into
+//! `order_rollups` in fill id order; `rollup_watermarks` records the last
+//! fill id folded in, so each pass only reads new fills and a restart
+//! neither skips nor double counts any. Fills passed over because their
+//! order did not exist yet wait in `rollup_pending_fills` until it does.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::warn;
+
+use crate::query::with_pool;
+use crate::Store;
+
+pub const ROLLUP_INCIDENT: &str = "ROLLUP";
+
+const WATERMARK: &str = "order_rollups";
+
+/// A fill belongs to the order with its client order id or, for fills the
+/// venue reported without one, the order with its venue order id.
+const FILL_MATCHES_ORDER: &str =
+    "(o.client_order_id = f.client_order_id OR (f.client_order_id IS NULL AND o.order_id = f.order_id))";
+
+/// Claims the watermark row before reading it: the first statement is a
+/// write, so SQLite takes the write lock up front instead of failing to
+/// upgrade a read, and concurrent passes on Postgres queue on the row.
+const CLAIM_WATERMARK: &str =
+    "INSERT INTO rollup_watermarks (name, last_id, updated_at_ms) VALUES (?1, 0, ?2)
+     ON CONFLICT(name) DO UPDATE SET updated_at_ms = excluded.updated_at_ms";
+
+const SELECT_WATERMARK: &str =
+    "SELECT CAST(last_id AS BIGINT) FROM rollup_watermarks WHERE name = ?1";
+
+const ADVANCE_WATERMARK: &str = "UPDATE rollup_watermarks SET last_id = ?2 WHERE name = ?1";
+
+const PENDING_FILLS: &str = "f.id IN (SELECT fill_row_id FROM rollup_pending_fills)";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RollupConfig {
+    /// Zero disables the schedule.
+    pub interval: Duration,
+    /// Fill ids folded per transaction.
+    pub batch_size: i64,
+}
+
+impl Default for RollupConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            batch_size: 10_000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RollupReport {
+    /// Fills folded into a rollup.
+    pub fills: u64,
+    /// New fills no order matches yet, left pending for a later pass.
+    pub unmatched_fills: u64,
+    /// Pending fills folded in because their order has appeared.
+    pub rescanned_fills: u64,
+    /// Rollup rows inserted or updated.
+    pub orders: u64,
+    /// Watermark after the pass.
+    pub last_fill_id: i64,
+}
+
+/// One row of `order_rollups`.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct OrderRollup {
+    pub client_order_id: String,
+    pub order_id: Option<String>,
+    pub run_id: String,
+    pub market_id: i64,
+    pub strategy: String,
+    pub side: String,
+    pub qty_submitted: f64,
+    pub qty_filled: f64,
+    pub fill_count: i64,
+    pub vwap: Option<f64>,
+    pub fee_usd: Option<f64>,
+    pub ts_first_fill_ms: Option<i64>,
+    pub ts_last_fill_ms: Option<i64>,
+    pub updated_at_ms: i64,
+}
+
+impl Store {
+    /// Fold pending fills whose order now exists, then every fill after
+    /// the watermark, into `order_rollups`, in transactions of `batch_size`
+    /// fill ids. Relies on fill ids becoming visible in order, which the
+    /// store ensures by serializing fill inserts.
+    pub async fn roll_up_orders(&self, batch_size: i64, now_ms: i64) -> Result<RollupReport> {
+        let batch_size = batch_size.max(1);
+        let claim_sql = self.backend.sql(CLAIM_WATERMARK);
+        let select_sql = self.backend.sql(SELECT_WATERMARK);
+        let advance_sql = self.backend.sql(ADVANCE_WATERMARK);
+        let upsert_stmt = upsert_rollups_sql("f.id > ?2 AND f.id <= ?3");
+        let upsert_sql = self.backend.sql(&upsert_stmt);
+        let unmatched_stmt = format!(
+            "INSERT INTO rollup_pending_fills (fill_row_id)
+             SELECT f.id FROM fills f WHERE f.id > ?1 AND f.id <= ?2
+             AND NOT EXISTS (SELECT 1 FROM orders o WHERE {FILL_MATCHES_ORDER})
+             ON CONFLICT (fill_row_id) DO NOTHING"
+        );
+        let unmatched_sql = self.backend.sql(&unmatched_stmt);
+        let count_sql = self
+            .backend
+            .sql("SELECT COUNT(*) FROM fills WHERE id > ?1 AND id <= ?2");
+        let rescan_stmt = upsert_rollups_sql(PENDING_FILLS);
+        let rescan_sql = self.backend.sql(&rescan_stmt);
+        let matched_stmt = format!(
+            "DELETE FROM rollup_pending_fills WHERE EXISTS (
+                 SELECT 1 FROM fills f JOIN orders o ON {FILL_MATCHES_ORDER}
+                 WHERE f.id = rollup_pending_fills.fill_row_id)"
+        );
+        let matched_sql = self.backend.sql(&matched_stmt);
+
+        let mut report = RollupReport::default();
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.begin().await?;
+            sqlx::query(&claim_sql)
+                .bind(WATERMARK)
+                .bind(now_ms)
+                .execute(&mut *tx)
+                .await?;
+            report.orders += sqlx::query(&rescan_sql)
+                .bind(now_ms)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            report.rescanned_fills = sqlx::query(&matched_sql)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            tx.commit().await?;
+        });
+        loop {
+            let caught_up = with_pool!(&self.pool, |pool| {
+                let mut tx = pool.begin().await?;
+                sqlx::query(&claim_sql)
+                    .bind(WATERMARK)
+                    .bind(now_ms)
+                    .execute(&mut *tx)
+                    .await?;
+                let last: i64 = sqlx::query_scalar(&select_sql)
+                    .bind(WATERMARK)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                let top: Option<i64> =
+                    sqlx::query_scalar("SELECT CAST(MAX(id) AS BIGINT) FROM fills")
+                        .fetch_one(&mut *tx)
+                        .await?;
+                let top = top.unwrap_or(0);
+                report.last_fill_id = last;
+                if top <= last {
+                    tx.commit().await?;
+                    true
+                } else {
+                    let upto = top.min(last.saturating_add(batch_size));
+                    let fills: i64 = sqlx::query_scalar(&count_sql)
+                        .bind(last)
+                        .bind(upto)
+                        .fetch_one(&mut *tx)
+                        .await?;
+                    let unmatched = sqlx::query(&unmatched_sql)
+                        .bind(last)
+                        .bind(upto)
+                        .execute(&mut *tx)
+                        .await?
+                        .rows_affected() as i64;
+                    let orders = sqlx::query(&upsert_sql)
+                        .bind(now_ms)
+                        .bind(last)
+                        .bind(upto)
+                        .execute(&mut *tx)
+                        .await?
+                        .rows_affected();
+                    sqlx::query(&advance_sql)
+                        .bind(WATERMARK)
+                        .bind(upto)
+                        .execute(&mut *tx)
+                        .await?;
+                    tx.commit().await?;
+                    report.fills += (fills - unmatched) as u64;
+                    report.unmatched_fills += unmatched as u64;
+                    report.orders += orders;
+                    report.last_fill_id = upto;
+                    upto == top
+                }
+            });
+            if caught_up {
+                return Ok(report);
+            }
+        }
+    }
+
+    /// Drop every rollup and fold all fills again, e.g. after fills or
+    /// orders were edited by hand.
+    pub async fn rebuild_order_rollups(
+        &self,
+        batch_size: i64,
+        now_ms: i64,
+    ) -> Result<RollupReport> {
+        let reset_sql = self
+            .backend
+            .sql("DELETE FROM rollup_watermarks WHERE name = ?1");
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.begin().await?;
+            sqlx::query("DELETE FROM order_rollups")
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM rollup_pending_fills")
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(&reset_sql)
+                .bind(WATERMARK)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+        });
+        self.roll_up_orders(batch_size, now_ms).await
+    }
+
+    pub async fn order_rollup(&self, client_order_id: &str) -> Result<Option<OrderRollup>> {
+        let sql = self.backend.sql(
+            "SELECT client_order_id, order_id, run_id, CAST(market_id AS BIGINT) AS market_id,
+                    strategy, side,
+                    CAST(qty_submitted AS DOUBLE PRECISION) AS qty_submitted,
+                    CAST(qty_filled AS DOUBLE PRECISION) AS qty_filled,
+                    CAST(fill_count AS BIGINT) AS fill_count,
+                    CAST(vwap AS DOUBLE PRECISION) AS vwap,
+                    CAST(fee_usd AS DOUBLE PRECISION) AS fee_usd,
+                    CAST(ts_first_fill_ms AS BIGINT) AS ts_first_fill_ms,
+                    CAST(ts_last_fill_ms AS BIGINT) AS ts_last_fill_ms,
+                    CAST(updated_at_ms AS BIGINT) AS updated_at_ms
+             FROM order_rollups WHERE client_order_id = ?1",
+        );
+        let row = with_pool!(&self.pool, |pool| {
+            sqlx::query_as::<_, OrderRollup>(&sql)
+                .bind(client_order_id)
+                .fetch_optional(pool)
+                .await?
+        });
+        Ok(row)
+    }
+}
+
+/// Aggregate the fills matching `filter` per order, stamped `?1`, and merge
+/// them into the existing rollup: quantities, counts and fees add up, VWAP is re-weighted
+/// by filled quantity and the fill times widen.
+fn upsert_rollups_sql(filter: &str) -> String {
+    format!(
+        "INSERT INTO order_rollups (client_order_id, order_id, run_id, market_id, strategy, side,
+             qty_submitted, qty_filled, fill_count, vwap, fee_usd, ts_first_fill_ms, ts_last_fill_ms,
+             updated_at_ms)
+         SELECT o.client_order_id, MAX(COALESCE(f.order_id, o.order_id)), o.run_id, o.market_id,
+             o.strategy, o.side, o.qty, SUM(f.qty), COUNT(*),
+             SUM(f.price * f.qty) / NULLIF(SUM(f.qty), 0), SUM(COALESCE(f.fee_usd, 0)),
+             MIN(f.ts_ms), MAX(f.ts_ms), ?1
+         FROM fills f JOIN orders o ON {FILL_MATCHES_ORDER}
+         WHERE {filter}
+         GROUP BY o.client_order_id, o.run_id, o.market_id, o.strategy, o.side, o.qty
+         ON CONFLICT(client_order_id) DO UPDATE SET
+             order_id = COALESCE(excluded.order_id, order_rollups.order_id),
+             qty_submitted = excluded.qty_submitted,
+             qty_filled = order_rollups.qty_filled + excluded.qty_filled,
+             fill_count = order_rollups.fill_count + excluded.fill_count,
+             vwap = CASE WHEN order_rollups.qty_filled + excluded.qty_filled > 0 THEN
+                 (COALESCE(order_rollups.vwap, 0) * order_rollups.qty_filled
+                  + COALESCE(excluded.vwap, 0) * excluded.qty_filled)
+                 / (order_rollups.qty_filled + excluded.qty_filled) END,
+             fee_usd = COALESCE(order_rollups.fee_usd, 0) + COALESCE(excluded.fee_usd, 0),
+             ts_first_fill_ms = CASE WHEN order_rollups.ts_first_fill_ms IS NULL
+                 OR excluded.ts_first_fill_ms < order_rollups.ts_first_fill_ms
+                 THEN excluded.ts_first_fill_ms ELSE order_rollups.ts_first_fill_ms END,
+             ts_last_fill_ms = CASE WHEN order_rollups.ts_last_fill_ms IS NULL
+                 OR excluded.ts_last_fill_ms > order_rollups.ts_last_fill_ms
+                 THEN excluded.ts_last_fill_ms ELSE order_rollups.ts_last_fill_ms END,
+             updated_at_ms = excluded.updated_at_ms"
+    )
+}
+
+/// Keeps `order_rollups` current on a schedule.
+pub struct RollupJob {
+    store: Store,
+    config: RollupConfig,
+    reports: UnboundedSender<Result<RollupReport, String>>,
+}
+
+impl RollupJob {
+    pub fn new(
+        store: Store,
+        config: RollupConfig,
+    ) -> (Self, UnboundedReceiver<Result<RollupReport, String>>) {
+        let (reports, rx) = mpsc::unbounded_channel();
+        (
+            Self {
+                store,
+                config,
+                reports,
+            },
+            rx,
+        )
+    }
+
+    pub async fn run_once(&self) -> Result<RollupReport> {
+        self.store
+            .roll_up_orders(self.config.batch_size, Utc::now().timestamp_millis())
+            .await
+    }
+
+    /// Loops until the report receiver is dropped, starting with a pass at
+    /// boot so rollups catch up on fills written while the daemon was down.
+    pub async fn run(&self) {
+        if self.config.interval.is_zero() {
+            return;
+        }
+        let mut ticker = tokio::time::interval(self.config.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let result = self.run_once().await.map_err(|err| format!("{err:#}"));
+            match &result {
+                Err(err) => warn!(error = %err, "order rollup pass failed"),
+                Ok(report) if report.unmatched_fills > 0 => {
+                    warn!(
+                        fills = report.unmatched_fills,
+                        "fills without a matching order wait until their order is written"
+                    );
+                }
+                Ok(_) => {}
+            }
+            if self.reports.send(result).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{fresh_stores, unique};
+    use crate::{FillRow, OrderRow};
+
+    fn order(run: &str, client_order_id: &str, qty: f64) -> OrderRow {
+        OrderRow {
+            run_id: run.into(),
+            client_order_id: client_order_id.into(),
+            order_id: None,
+            approved_id: None,
+            intent_id: None,
+            strategy: "mm".into(),
+            market_id: 9,
+            venue: "polymarket".into(),
+            status: "Open".into(),
+            side: "BuyYes".into(),
+            limit_price: 0.5,
+            qty,
+            ts_submitted_ms: 1_000,
+            ts_acked_ms: None,
+            ts_final_ms: None,
+            submit_latency_ms: None,
+            notes: None,
+        }
+    }
+
+    fn fill(run: &str, client_order_id: Option<&str>, ts_ms: i64, price: f64, qty: f64) -> FillRow {
+        FillRow {
+            run_id: run.into(),
+            ts_ms,
+            venue: "polymarket".into(),
+            fill_id: Some(unique("f")),
+            order_id: Some(format!("0x{ts_ms}")),
+            client_order_id: client_order_id.map(Into::into),
+            market_id: 9,
+            strategy: "mm".into(),
+            side: "BuyYes".into(),
+            price,
+            qty,
+            fee_usd: Some(0.125),
+            liquidity: Some("maker".into()),
+            raw_json: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn folds_new_fills_incrementally() -> Result<()> {
+        for store in fresh_stores().await? {
+            let run = unique("run-rollup");
+            store.insert_run(&run, None).await?;
+            let coid = unique("c");
+            store.upsert_order(&order(&run, &coid, 10.0)).await?;
+
+            store
+                .insert_fill(&fill(&run, Some(&coid), 1_100, 0.5, 2.0))
+                .await?;
+            store
+                .insert_fill(&fill(&run, Some(&coid), 1_050, 0.25, 2.0))
+                .await?;
+            store
+                .insert_fill(&fill(&run, Some(&unique("orphan")), 1_060, 0.5, 1.0))
+                .await?;
+            let first = store.roll_up_orders(2, 2).await?;
+            assert_eq!(first.fills, 2);
+            assert_eq!(first.unmatched_fills, 1);
+
+            let rollup = store.order_rollup(&coid).await?.expect("rollup");
+            assert_eq!(rollup.qty_filled, 4.0);
+            assert_eq!(rollup.fill_count, 2);
+            assert_eq!(rollup.vwap, Some(0.375));
+            assert_eq!(rollup.fee_usd, Some(0.25));
+            assert_eq!(rollup.ts_first_fill_ms, Some(1_050));
+            assert_eq!(rollup.ts_last_fill_ms, Some(1_100));
+
+            // A second pass only sees the new fill; a third sees nothing.
+            store
+                .insert_fill(&fill(&run, Some(&coid), 1_200, 1.0, 4.0))
+                .await?;
+            let second = store.roll_up_orders(2, 3).await?;
+            assert_eq!((second.fills, second.orders), (1, 1));
+            assert_eq!(store.roll_up_orders(2, 4).await?.fills, 0);
+
+            let rollup = store.order_rollup(&coid).await?.expect("rollup");
+            assert_eq!(rollup.qty_filled, 8.0);
+            assert_eq!(rollup.vwap, Some(0.6875));
+            assert_eq!(rollup.ts_last_fill_ms, Some(1_200));
+            assert_eq!(rollup.updated_at_ms, 3);
+
+            let rebuilt = store.rebuild_order_rollups(1_000, 5).await?;
+            assert_eq!(rebuilt.last_fill_id, second.last_fill_id);
+            let again = store.order_rollup(&coid).await?.expect("rollup");
+            assert_eq!((again.qty_filled, again.vwap), (8.0, Some(0.6875)));
+        }
+        Ok(())
+    }
+    #[tokio::test]
+    async fn fills_wait_for_an_order_written_after_them() -> Result<()> {
+        for store in fresh_stores().await? {
+            let run = unique("run-rollup");
+            store.insert_run(&run, None).await?;
+            let coid = unique("c");
+
+            store
+                .insert_fill(&fill(&run, Some(&coid), 1_100, 0.5, 2.0))
+                .await?;
+            let early = store.roll_up_orders(10, 2).await?;
+            assert_eq!((early.fills, early.unmatched_fills), (0, 1));
+            assert!(store.order_rollup(&coid).await?.is_none());
+
+            store.upsert_order(&order(&run, &coid, 10.0)).await?;
+            let late = store.roll_up_orders(10, 3).await?;
+            assert_eq!((late.rescanned_fills, late.orders), (1, 1));
+            let rollup = store.order_rollup(&coid).await?.expect("rollup");
+            assert_eq!((rollup.qty_filled, rollup.fill_count), (2.0, 1));
+
+            // folded once only
+            assert_eq!(store.roll_up_orders(10, 4).await?.rescanned_fills, 0);
+            let rollup = store.order_rollup(&coid).await?.expect("rollup");
+            assert_eq!(rollup.qty_filled, 2.0);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+//! Reading whole tables as untyped rows, for code that walks every column
+//! of a table without a row struct: the backend copy and the dataset
+//! export.
+
+use anyhow::{Context, Result};
+use sqlx::Row;
+
+use crate::query::with_pool;
+use crate::Store;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Int,
+    Float,
+    Text,
+}
+
+impl Kind {
+    fn from_declared(declared: &str) -> Self {
+        let declared = declared.to_ascii_lowercase();
+        if declared.contains("int") {
+            Kind::Int
+        } else if ["real", "double", "float", "numeric"]
+            .iter()
+            .any(|t| declared.contains(t))
+        {
+            Kind::Float
+        } else {
+            Kind::Text
+        }
+    }
+
+    /// Select expression that decodes as this kind's Rust type on both
+    /// backends.
+    fn select(self, column: &str) -> String {
+        match self {
+            Kind::Int => format!("CAST({column} AS BIGINT)"),
+            Kind::Float => format!("CAST({column} AS DOUBLE PRECISION)"),
+            Kind::Text => column.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+    Int(Option<i64>),
+    Float(Option<f64>),
+    Text(Option<String>),
+}
+
+/// Bind a [`Value`] with its own type, so NULLs stay typed on Postgres.
+macro_rules! bind_value {
+    ($query:expr, $value:expr) => {
+        match $value {
+            Value::Int(v) => $query.bind(*v),
+            Value::Float(v) => $query.bind(*v),
+            Value::Text(v) => $query.bind(v.as_deref()),
+        }
+    };
+}
+pub(crate) use bind_value;
+
+/// One table's columns and how to read them.
+pub(crate) struct TablePlan {
+    pub(crate) table: &'static str,
+    pub(crate) columns: &'static [&'static str],
+    pub(crate) kinds: Vec<Kind>,
+    /// Whether the column is declared without NOT NULL. The primary key
+    /// never is, although SQLite reports an INTEGER PRIMARY KEY as
+    /// nullable.
+    #[cfg_attr(not(feature = "parquet"), allow(dead_code))]
+    pub(crate) nullable: Vec<bool>,
+}
+
+impl TablePlan {
+    pub(crate) fn primary_key(&self) -> &'static str {
+        self.columns[0]
+    }
+
+    #[cfg_attr(not(feature = "parquet"), allow(dead_code))]
+    pub(crate) fn position(&self, column: &str) -> Option<usize> {
+        self.columns.iter().position(|c| *c == column)
+    }
+}
+
+impl Store {
+    pub(crate) async fn table_plan(
+        &self,
+        table: &'static str,
+        columns: &'static [&'static str],
+    ) -> Result<TablePlan> {
+        let sql = self.backend.sql(self.backend.column_types_sql());
+        let declared: Vec<(String, String, i64)> = with_pool!(&self.pool, |pool| {
+            sqlx::query_as(&sql).bind(table).fetch_all(pool).await?
+        });
+        let mut kinds = Vec::with_capacity(columns.len());
+        let mut nullable = Vec::with_capacity(columns.len());
+        for (i, column) in columns.iter().enumerate() {
+            let (_, declared, not_null) = declared
+                .iter()
+                .find(|(name, _, _)| name == column)
+                .with_context(|| format!("source has no column {table}.{column}"))?;
+            kinds.push(Kind::from_declared(declared));
+            nullable.push(i > 0 && *not_null == 0);
+        }
+        Ok(TablePlan {
+            table,
+            columns,
+            kinds,
+            nullable,
+        })
+    }
+
+    /// Up to `limit` rows with a primary key after `after`, in key order,
+    /// optionally only those of one run.
+    pub(crate) async fn fetch_page(
+        &self,
+        plan: &TablePlan,
+        run_id: Option<&str>,
+        after: Option<&Value>,
+        limit: usize,
+    ) -> Result<Vec<Vec<Value>>> {
+        let select = plan
+            .columns
+            .iter()
+            .zip(&plan.kinds)
+            .map(|(column, kind)| kind.select(column))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let key = plan.primary_key();
+        let mut filters = Vec::new();
+        if run_id.is_some() {
+            filters.push(format!("run_id = ?{}", filters.len() + 1));
+        }
+        if after.is_some() {
+            filters.push(format!("{key} > ?{}", filters.len() + 1));
+        }
+        let filter = if filters.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {} ", filters.join(" AND "))
+        };
+        let stmt = format!(
+            "SELECT {select} FROM {} {filter}ORDER BY {key} LIMIT {limit}",
+            plan.table
+        );
+        let sql = self.backend.sql(&stmt);
+        let page = with_pool!(&self.pool, |pool| {
+            let mut query = sqlx::query(&sql);
+            if let Some(run_id) = run_id {
+                query = query.bind(run_id);
+            }
+            if let Some(after) = after {
+                query = bind_value!(query, after);
+            }
+            let rows = query.fetch_all(pool).await?;
+            let mut page = Vec::with_capacity(rows.len());
+            for row in &rows {
+                let mut values = Vec::with_capacity(plan.kinds.len());
+                for (i, kind) in plan.kinds.iter().enumerate() {
+                    values.push(match kind {
+                        Kind::Int => Value::Int(row.try_get(i)?),
+                        Kind::Float => Value::Float(row.try_get(i)?),
+                        Kind::Text => Value::Text(row.try_get(i)?),
+                    });
+                }
+                page.push(values);
+            }
+            page
+        });
+        Ok(page)
+    }
+}
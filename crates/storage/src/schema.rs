@@ -0,0 +1,509 @@
+//! Schema versioning. Migrations live in `migrations/<backend>/NNNN_*.sql`
+//! and are embedded at compile time; each one that changes the schema also
+//! bumps `schema_meta.schema_version`. On connect the store refuses a
+//! database that is ahead of this binary, applies any pending migrations
+//! and then checks that every table and column the code uses exists.
+
+use anyhow::{bail, Context, Result};
+use sqlx::migrate::Migrator;
+use tracing::info;
+
+use crate::query::with_pool;
+use crate::{DatabaseBackend, Store};
+
+/// `schema_meta.schema_version` this binary reads and writes.
+pub const SCHEMA_VERSION: i64 = 4;
+
+#[cfg(feature = "sqlite")]
+pub(crate) static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("../../migrations/sqlite");
+
+#[cfg(feature = "postgres")]
+pub(crate) static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("../../migrations/postgres");
+
+/// Every table, parents before the tables whose foreign keys point at
+/// them, with the columns the code reads or writes. The first column is
+/// the table's primary key.
+pub(crate) const COLUMNS: &[(&str, &[&str])] = &[
+    ("schema_meta", &["key", "value"]),
+    (
+        "feature_schemas",
+        &[
+            "feature_schema_version",
+            "created_at_ms",
+            "description",
+            "features_json",
+        ],
+    ),
+    (
+        "runs",
+        &[
+            "run_id",
+            "started_at_ms",
+            "git_sha",
+            "config_hash",
+            "host",
+            "notes",
+        ],
+    ),
+    (
+        "raw_events",
+        &[
+            "id",
+            "run_id",
+            "ts_ms",
+            "source",
+            "topic",
+            "market_id",
+            "payload_json",
+        ],
+    ),
+    (
+        "snapshots",
+        &[
+            "snapshot_id",
+            "run_id",
+            "ts_ms",
+            "market_id",
+            "best_bid_px",
+            "best_bid_qty",
+            "best_ask_px",
+            "best_ask_qty",
+            "spread",
+            "yes_qty",
+            "no_qty",
+            "net_exposure_usd",
+            "can_trade",
+            "drawdown_halt",
+            "crowding_score",
+            "toxicity_score",
+            "spread_compression",
+            "feature_schema_version",
+            "features_json",
+        ],
+    ),
+    (
+        "strategy_intents",
+        &[
+            "intent_id",
+            "run_id",
+            "ts_ms",
+            "snapshot_id",
+            "strategy",
+            "market_id",
+            "intent_kind",
+            "side",
+            "price",
+            "size",
+            "urgency",
+            "ttl_ms",
+            "expected_value",
+            "confidence",
+            "risk_cost",
+            "tags_json",
+            "rationale_json",
+            "tier",
+            "leg_group",
+        ],
+    ),
+    (
+        "arbiter_approvals",
+        &[
+            "approved_id",
+            "run_id",
+            "ts_ms",
+            "intent_id",
+            "approved",
+            "reason",
+            "owner_strategy",
+        ],
+    ),
+    (
+        "orders",
+        &[
+            "id",
+            "run_id",
+            "ts_submitted_ms",
+            "approved_id",
+            "intent_id",
+            "strategy",
+            "market_id",
+            "venue",
+            "order_id",
+            "client_order_id",
+            "status",
+            "side",
+            "limit_price",
+            "qty",
+            "ts_acked_ms",
+            "ts_final_ms",
+            "submit_latency_ms",
+            "notes",
+        ],
+    ),
+    (
+        "fills",
+        &[
+            "id",
+            "run_id",
+            "ts_ms",
+            "venue",
+            "fill_id",
+            "order_id",
+            "client_order_id",
+            "market_id",
+            "strategy",
+            "side",
+            "price",
+            "qty",
+            "fee_usd",
+            "liquidity",
+            "raw_json",
+        ],
+    ),
+    (
+        "order_rollups",
+        &[
+            "client_order_id",
+            "order_id",
+            "run_id",
+            "market_id",
+            "strategy",
+            "side",
+            "qty_submitted",
+            "qty_filled",
+            "fill_count",
+            "vwap",
+            "fee_usd",
+            "ts_first_fill_ms",
+            "ts_last_fill_ms",
+            "updated_at_ms",
+        ],
+    ),
+    (
+        "pnl_ledger",
+        &[
+            "id",
+            "run_id",
+            "ts_ms",
+            "market_id",
+            "strategy",
+            "kind",
+            "ref",
+            "pnl_usd",
+            "notes",
+        ],
+    ),
+    (
+        "portfolio_snapshots",
+        &[
+            "id",
+            "run_id",
+            "ts_ms",
+            "equity_usd",
+            "realized_pnl_usd",
+            "unrealized_pnl_usd",
+            "gross_exposure_usd",
+            "net_exposure_usd",
+            "drawdown_usd",
+            "drawdown_pct",
+            "open_orders_count",
+        ],
+    ),
+    (
+        "plugin_signals",
+        &[
+            "id",
+            "run_id",
+            "ts_ms",
+            "plugin",
+            "scope",
+            "market_id",
+            "payload_json",
+        ],
+    ),
+    (
+        "incidents",
+        &[
+            "id",
+            "run_id",
+            "ts_ms",
+            "severity",
+            "kind",
+            "message",
+            "payload_json",
+        ],
+    ),
+    ("rollup_watermarks", &["name", "last_id", "updated_at_ms"]),
+    ("rollup_pending_fills", &["fill_row_id"]),
+];
+
+/// Table names in foreign-key order.
+pub(crate) fn tables() -> impl Iterator<Item = &'static str> {
+    COLUMNS.iter().map(|(table, _)| *table)
+}
+
+impl DatabaseBackend {
+    fn migrator(self) -> &'static Migrator {
+        match self {
+            #[cfg(feature = "sqlite")]
+            DatabaseBackend::Sqlite => &SQLITE_MIGRATOR,
+            #[cfg(feature = "postgres")]
+            DatabaseBackend::Postgres => &POSTGRES_MIGRATOR,
+            #[allow(unreachable_patterns)]
+            _ => unreachable!("store for a disabled backend"),
+        }
+    }
+}
+
+impl Store {
+    /// Refuse a database ahead of this binary, apply pending migrations,
+    /// then check the result has every table and column in [`COLUMNS`].
+    pub(crate) async fn migrate(&self) -> Result<()> {
+        let migrator = self.backend.migrator();
+        let known = migrator.iter().map(|m| m.version).max().unwrap_or(0);
+        if let Some(applied) = self.applied_migration().await? {
+            if applied > known {
+                bail!(
+                    "database has migration {applied} but this binary only knows up to {known}; upgrade the binary before connecting"
+                );
+            }
+        }
+        if let Some(version) = self.schema_version().await? {
+            if version > SCHEMA_VERSION {
+                bail!(
+                    "database schema version {version} is newer than {SCHEMA_VERSION}; upgrade the binary before connecting"
+                );
+            }
+        }
+
+        with_pool!(&self.pool, |pool| migrator
+            .run(pool)
+            .await
+            .context("running migrations")?);
+
+        let version = self.schema_version().await?.unwrap_or(0);
+        if version != SCHEMA_VERSION {
+            bail!("schema version is {version} after migrating, expected {SCHEMA_VERSION}");
+        }
+        let missing = self.missing_columns().await?;
+        if !missing.is_empty() {
+            bail!("schema is missing {}", missing.join(", "));
+        }
+        info!(
+            schema_version = version,
+            migration = known,
+            "schema up to date"
+        );
+        Ok(())
+    }
+
+    /// Refuse a database that is not exactly at this binary's migrations,
+    /// for handles that must not migrate it themselves.
+    pub(crate) async fn check_readable(&self) -> Result<()> {
+        let known = self
+            .backend
+            .migrator()
+            .iter()
+            .map(|m| m.version)
+            .max()
+            .unwrap_or(0);
+        match self.applied_migration().await? {
+            Some(applied) if applied > known => bail!(
+                "database has migration {applied} but this binary only knows up to {known}; upgrade the binary before reading"
+            ),
+            Some(applied) if applied == known => Ok(()),
+            applied => bail!(
+                "database is at migration {} but this binary reads {known}; migrate it (start traderd against it) first",
+                applied.unwrap_or(0)
+            ),
+        }
+    }
+
+    /// Highest applied sqlx migration, or `None` on a fresh database.
+    async fn applied_migration(&self) -> Result<Option<i64>> {
+        if !self.table_exists("_sqlx_migrations").await? {
+            return Ok(None);
+        }
+        let version = with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar::<_, Option<i64>>(
+                "SELECT CAST(MAX(version) AS BIGINT) FROM _sqlx_migrations WHERE success",
+            )
+            .fetch_one(pool)
+            .await?
+        });
+        Ok(version)
+    }
+
+    /// `schema_meta.schema_version`, or `None` before the first migration.
+    pub async fn schema_version(&self) -> Result<Option<i64>> {
+        if !self.table_exists("schema_meta").await? {
+            return Ok(None);
+        }
+        let value = with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar::<_, String>(
+                "SELECT value FROM schema_meta WHERE key = 'schema_version'",
+            )
+            .fetch_optional(pool)
+            .await?
+        });
+        value
+            .map(|v| {
+                v.parse()
+                    .context("schema_meta.schema_version is not a number")
+            })
+            .transpose()
+    }
+
+    pub(crate) async fn table_exists(&self, table: &str) -> Result<bool> {
+        let sql = self.backend.sql(self.backend.table_exists_sql());
+        let found = with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar::<_, i64>(&sql)
+                .bind(table)
+                .fetch_one(pool)
+                .await?
+        });
+        Ok(found > 0)
+    }
+
+    /// `table.column` for every expected column that does not exist, and
+    /// `table` for every missing table.
+    pub async fn missing_columns(&self) -> Result<Vec<String>> {
+        let sql = self.backend.sql(self.backend.columns_sql());
+        let mut missing = Vec::new();
+        for (table, columns) in COLUMNS {
+            let found: Vec<String> = with_pool!(&self.pool, |pool| {
+                sqlx::query_scalar(&sql).bind(table).fetch_all(pool).await?
+            });
+            if found.is_empty() {
+                missing.push((*table).to_string());
+                continue;
+            }
+            missing.extend(
+                columns
+                    .iter()
+                    .filter(|c| !found.iter().any(|f| f == *c))
+                    .map(|c| format!("{table}.{c}")),
+            );
+        }
+        Ok(missing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::fresh_postgres_url;
+    use crate::IntentRow;
+
+    /// Database URLs to run upgrade tests on: a new SQLite file, plus an
+    /// empty Postgres schema when `TEST_POSTGRES_URL` is set.
+    async fn empty_databases(dir: &std::path::Path) -> Result<Vec<String>> {
+        let mut urls = vec![format!(
+            "sqlite://{}?mode=rwc",
+            dir.join("upgrade.db").display()
+        )];
+        urls.extend(fresh_postgres_url().await?);
+        Ok(urls)
+    }
+
+    /// A store on `url` migrated to version 1 only, the way a binary from
+    /// before the second migration left it.
+    async fn at_version_1(url: &str) -> Result<Store> {
+        let store = Store::open(url, &crate::StoreOptions::default()).await?;
+        let backend = match store.backend {
+            DatabaseBackend::Sqlite => "sqlite",
+            DatabaseBackend::Postgres => "postgres",
+        };
+        let source = tempfile::tempdir()?;
+        std::fs::copy(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../migrations")
+                .join(backend)
+                .join("0001_init.sql"),
+            source.path().join("0001_init.sql"),
+        )?;
+        let first = Migrator::new(source.path()).await?;
+        with_pool!(&store.pool, |pool| first.run(pool).await?);
+        Ok(store)
+    }
+
+    #[tokio::test]
+    async fn upgrades_a_version_1_database_in_place() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for url in empty_databases(dir.path()).await? {
+            let old = at_version_1(&url).await?;
+            assert_eq!(old.schema_version().await?, Some(1));
+            assert!(old
+                .missing_columns()
+                .await?
+                .contains(&"strategy_intents.tier".to_string()));
+            old.insert_run("run-v1", None).await?;
+            let snapshot_id: i64 = with_pool!(&old.pool, |pool| {
+                sqlx::query_scalar(&old.backend.sql(
+                    "INSERT INTO snapshots (run_id, ts_ms, market_id, yes_qty, no_qty, net_exposure_usd, can_trade, drawdown_halt, crowding_score, toxicity_score, spread_compression, feature_schema_version, features_json)
+                     VALUES ('run-v1', 1, 2, 0, 0, 0, 1, 0, 0, 0, 0, 1, '{}') RETURNING CAST(snapshot_id AS BIGINT)",
+                ))
+                .fetch_one(pool)
+                .await?
+            });
+            let intent_id: i64 = with_pool!(&old.pool, |pool| {
+                sqlx::query_scalar(&old.backend.sql(
+                    "INSERT INTO strategy_intents (run_id, ts_ms, snapshot_id, strategy, market_id, intent_kind, urgency, ttl_ms, expected_value, confidence, risk_cost, tags_json)
+                     VALUES ('run-v1', 2, ?1, 'mm', 2, 'NoOp', 'maker', 0, 0, 0, 0, '[]') RETURNING CAST(intent_id AS BIGINT)",
+                ))
+                .bind(snapshot_id)
+                .fetch_one(pool)
+                .await?
+            });
+            drop(old);
+
+            let store = Store::connect(&url).await?;
+            assert_eq!(store.schema_version().await?, Some(SCHEMA_VERSION));
+            assert!(store.missing_columns().await?.is_empty());
+            let intent = store.fetch_intent(intent_id).await?.expect("kept");
+            assert_eq!((intent.tier, intent.leg_group), (None, None));
+
+            let tiered = IntentRow {
+                tier: Some(0),
+                leg_group: Some("box-1".into()),
+                ..intent
+            };
+            let id = store.insert_intent(&tiered).await?;
+            assert_eq!(store.fetch_intent(id).await?, Some(tiered));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn refuses_a_database_from_a_newer_binary() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for url in empty_databases(dir.path()).await? {
+            let store = Store::connect(&url).await?;
+            with_pool!(&store.pool, |pool| {
+                sqlx::query("UPDATE schema_meta SET value = '99' WHERE key = 'schema_version'")
+                    .execute(pool)
+                    .await?;
+            });
+            let err = Store::connect(&url).await.err().expect("refused");
+            assert!(format!("{err:#}").contains("newer"), "{err:#}");
+
+            with_pool!(&store.pool, |pool| {
+                sqlx::query(&format!(
+                    "UPDATE schema_meta SET value = '{SCHEMA_VERSION}' WHERE key = 'schema_version'"
+                ))
+                .execute(pool)
+                .await?;
+                sqlx::query(&store.backend.sql(
+                    "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+                     VALUES (9999, 'from the future', TRUE, ?1, 0)",
+                ))
+                .bind(vec![0u8])
+                .execute(pool)
+                .await?;
+            });
+            let err = Store::connect(&url).await.err().expect("refused");
+            assert!(format!("{err:#}").contains("9999"), "{err:#}");
+        }
+        Ok(())
+    }
+}
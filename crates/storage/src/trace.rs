@@ -0,0 +1,275 @@
+use anyhow::Result;
+
+use crate::query::{self, with_pool};
+use crate::{ApprovalRow, FillRow, IntentRow, OrderRow, PnlEntry, SnapshotRow, Store};
+
+/// One order of a trace with the fills it got and the PnL booked on them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderTrace {
+    pub order: OrderRow,
+    pub fills: Vec<FillRow>,
+    pub pnl: Vec<PnlEntry>,
+}
+
+/// The attribution chain for one decision: snapshot → intent → arbiter
+/// decision → orders → fills → PnL (data_schema.md §5.2).
+///
+/// An order placed outside the intent pipeline (flatten, adopted orphan)
+/// traces with no intent, snapshot or approvals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeTrace {
+    pub snapshot_id: Option<i64>,
+    pub snapshot: Option<SnapshotRow>,
+    pub intent_id: Option<i64>,
+    pub intent: Option<IntentRow>,
+    pub approvals: Vec<ApprovalRow>,
+    pub orders: Vec<OrderTrace>,
+}
+
+impl Store {
+    /// The chain around `intent_id`, or `None` if no such intent exists.
+    pub async fn trace_intent(&self, intent_id: i64) -> Result<Option<TradeTrace>> {
+        let Some(intent) = self.fetch_intent(intent_id).await? else {
+            return Ok(None);
+        };
+        let snapshot = self.fetch_snapshot(intent.snapshot_id).await?;
+
+        let approvals_sql = self.backend.sql(
+            "SELECT run_id, ts_ms, CAST(intent_id AS BIGINT) AS intent_id, approved <> 0 AS approved, reason, owner_strategy
+             FROM arbiter_approvals WHERE intent_id = ?1 ORDER BY approved_id",
+        );
+        let approvals = with_pool!(&self.pool, |pool| {
+            sqlx::query_as::<_, ApprovalRow>(&approvals_sql)
+                .bind(intent_id)
+                .fetch_all(pool)
+                .await?
+        });
+
+        let orders_sql = self
+            .backend
+            .sql(&format!(
+                "SELECT {} FROM orders WHERE intent_id = ?1 ORDER BY id",
+                query::ORDER_COLUMNS
+            ))
+            .into_owned();
+        let orders = with_pool!(&self.pool, |pool| {
+            sqlx::query_as::<_, OrderRow>(&orders_sql)
+                .bind(intent_id)
+                .fetch_all(pool)
+                .await?
+        });
+
+        let mut traced = Vec::with_capacity(orders.len());
+        for order in orders {
+            traced.push(self.trace_order_row(order).await?);
+        }
+        Ok(Some(TradeTrace {
+            snapshot_id: Some(intent.snapshot_id),
+            snapshot,
+            intent_id: Some(intent_id),
+            intent: Some(intent),
+            approvals,
+            orders: traced,
+        }))
+    }
+
+    /// The chain around an order. If the order came from an intent this is
+    /// the intent's full trace, sibling orders included.
+    pub async fn trace_order(&self, client_order_id: &str) -> Result<Option<TradeTrace>> {
+        let Some(order) = self.fetch_order(client_order_id).await? else {
+            return Ok(None);
+        };
+        if let Some(intent_id) = order.intent_id {
+            if let Some(trace) = self.trace_intent(intent_id).await? {
+                return Ok(Some(trace));
+            }
+        }
+        Ok(Some(TradeTrace {
+            snapshot_id: None,
+            snapshot: None,
+            intent_id: None,
+            intent: None,
+            approvals: Vec::new(),
+            orders: vec![self.trace_order_row(order).await?],
+        }))
+    }
+
+    /// Fills for `order`, and the PnL rows whose `ref` points at one of
+    /// those fills (by row id or venue fill id) or at the order itself.
+    async fn trace_order_row(&self, order: OrderRow) -> Result<OrderTrace> {
+        let fills_sql = self.backend.sql(
+            "SELECT run_id, ts_ms, venue, fill_id, order_id, client_order_id, market_id, strategy, side, CAST(price AS DOUBLE PRECISION) AS price, CAST(qty AS DOUBLE PRECISION) AS qty, CAST(fee_usd AS DOUBLE PRECISION) AS fee_usd, liquidity, raw_json
+             FROM fills WHERE client_order_id = ?1 ORDER BY id",
+        );
+        let pnl_sql = self.backend.sql(
+            "SELECT run_id, ts_ms, market_id, strategy, kind, ref AS reference, CAST(pnl_usd AS DOUBLE PRECISION) AS pnl_usd, notes
+             FROM pnl_ledger
+             WHERE run_id = ?1
+               AND (ref = ?2
+                    OR ref IN (SELECT CAST(id AS TEXT) FROM fills WHERE client_order_id = ?2)
+                    OR ref IN (SELECT fill_id FROM fills WHERE client_order_id = ?2 AND fill_id IS NOT NULL))
+             ORDER BY id",
+        );
+        let (fills, pnl) = with_pool!(&self.pool, |pool| {
+            let fills = sqlx::query_as::<_, FillRow>(&fills_sql)
+                .bind(&order.client_order_id)
+                .fetch_all(pool)
+                .await?;
+            let pnl = sqlx::query_as::<_, PnlEntry>(&pnl_sql)
+                .bind(&order.run_id)
+                .bind(&order.client_order_id)
+                .fetch_all(pool)
+                .await?;
+            (fills, pnl)
+        });
+        Ok(OrderTrace { order, fills, pnl })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{test_stores, unique};
+
+    async fn seed(store: &Store, run: &str) -> Result<(i64, String)> {
+        let snapshot_id = store
+            .insert_snapshot(&SnapshotRow {
+                run_id: run.into(),
+                ts_ms: 1_000,
+                market_id: 4,
+                best_bid_px: Some(0.5),
+                best_bid_qty: Some(10.0),
+                best_ask_px: Some(0.5625),
+                best_ask_qty: Some(10.0),
+                spread: Some(0.0625),
+                yes_qty: 0.0,
+                no_qty: 0.0,
+                net_exposure_usd: 0.0,
+                can_trade: true,
+                drawdown_halt: false,
+                crowding_score: 0.0,
+                toxicity_score: 0.0,
+                spread_compression: 0.0,
+                feature_schema_version: 1,
+                features_json: "{}".into(),
+            })
+            .await?;
+        let intent_id = store
+            .insert_intent(&IntentRow {
+                run_id: run.into(),
+                ts_ms: 1_001,
+                snapshot_id,
+                strategy: "arb".into(),
+                market_id: 4,
+                intent_kind: "PlaceOrder".into(),
+                side: Some("BuyYes".into()),
+                price: Some(0.5625),
+                size: Some(8.0),
+                urgency: "taker".into(),
+                ttl_ms: 0,
+                expected_value: 0.125,
+                confidence: 0.5,
+                risk_cost: 0.0,
+                tags_json: "[]".into(),
+                rationale_json: Some(r#"{"edge":0.02}"#.into()),
+                tier: Some(0),
+                leg_group: None,
+            })
+            .await?;
+        let approved_id = store
+            .insert_approval(&ApprovalRow {
+                run_id: run.into(),
+                ts_ms: 1_002,
+                intent_id,
+                approved: true,
+                reason: None,
+                owner_strategy: Some("arb".into()),
+            })
+            .await?;
+        let client_order_id = unique("c");
+        store
+            .upsert_order(&OrderRow {
+                run_id: run.into(),
+                client_order_id: client_order_id.clone(),
+                order_id: Some("0x1".into()),
+                approved_id: Some(approved_id),
+                intent_id: Some(intent_id),
+                strategy: "arb".into(),
+                market_id: 4,
+                venue: "polymarket".into(),
+                status: "Filled".into(),
+                side: "BuyYes".into(),
+                limit_price: 0.5625,
+                qty: 8.0,
+                ts_submitted_ms: 1_003,
+                ts_acked_ms: Some(1_004),
+                ts_final_ms: Some(1_100),
+                submit_latency_ms: Some(1),
+                notes: None,
+            })
+            .await?;
+        let venue_fill_id = unique("f");
+        for (fill_id, qty) in [(Some(venue_fill_id.clone()), 5.0), (None, 3.0)] {
+            store
+                .insert_fill(&FillRow {
+                    run_id: run.into(),
+                    ts_ms: 1_050,
+                    venue: "polymarket".into(),
+                    fill_id,
+                    order_id: Some("0x1".into()),
+                    client_order_id: Some(client_order_id.clone()),
+                    market_id: 4,
+                    strategy: "arb".into(),
+                    side: "BuyYes".into(),
+                    price: 0.5625,
+                    qty,
+                    fee_usd: Some(0.0),
+                    liquidity: Some("taker".into()),
+                    raw_json: None,
+                })
+                .await?;
+        }
+        for reference in [Some(venue_fill_id), Some("unrelated".to_string()), None] {
+            store
+                .insert_pnl(&PnlEntry {
+                    run_id: run.into(),
+                    ts_ms: 2_000,
+                    market_id: 4,
+                    strategy: Some("arb".into()),
+                    kind: "realized".into(),
+                    reference,
+                    pnl_usd: 0.5,
+                    notes: None,
+                })
+                .await?;
+        }
+        Ok((intent_id, client_order_id))
+    }
+
+    #[tokio::test]
+    async fn traces_intent_and_order_to_the_same_chain() -> Result<()> {
+        for store in test_stores().await? {
+            let run = unique("run-trace");
+            store.insert_run(&run, None).await?;
+            let (intent_id, client_order_id) = seed(&store, &run).await?;
+
+            let trace = store.trace_intent(intent_id).await?.expect("intent");
+            assert_eq!(trace.intent_id, Some(intent_id));
+            assert_eq!(trace.snapshot.as_ref().map(|s| s.market_id), Some(4));
+            assert_eq!(trace.approvals.len(), 1);
+            assert_eq!(trace.orders.len(), 1);
+            let order = &trace.orders[0];
+            assert_eq!(order.order.client_order_id, client_order_id);
+            assert_eq!(order.fills.len(), 2);
+            assert_eq!(order.pnl.len(), 1);
+
+            assert_eq!(
+                store.trace_order(&client_order_id).await?,
+                Some(trace.clone())
+            );
+            assert_eq!(store.trace_intent(i32::MAX as i64).await?, None);
+            assert_eq!(store.trace_order(&unique("missing")).await?, None);
+        }
+        Ok(())
+    }
+}
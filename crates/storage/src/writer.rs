@@ -0,0 +1,603 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use tokio::sync::mpsc::{self, error::TrySendError, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::query::{self, bind_fill, bind_order, bind_pnl, with_pool};
+use crate::{FillRow, OrderRow, PnlEntry, Store};
+
+/// A row queued for the background writer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WriteOp {
+    Event {
+        run_id: String,
+        ts_ms: i64,
+        source: String,
+        topic: String,
+        payload_json: String,
+    },
+    Incident {
+        run_id: String,
+        ts_ms: i64,
+        severity: String,
+        kind: String,
+        message: String,
+    },
+    Fill(FillRow),
+    Order(OrderRow),
+    Pnl(PnlEntry),
+}
+
+impl WriteOp {
+    /// Fills, orders, incidents and PnL are never dropped.
+    pub fn is_critical(&self) -> bool {
+        !matches!(self, WriteOp::Event { .. })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WriterConfig {
+    /// Queue capacity; producers wait when it is full.
+    pub capacity: usize,
+    /// Flush once this many rows are buffered.
+    pub batch_size: usize,
+    /// Flush buffered rows at least this often.
+    pub flush_interval: Duration,
+    /// Event topic prefixes that are dropped rather than queued under load.
+    pub low_priority_topics: Vec<String>,
+    /// Queue fill fraction above which low-priority events are dropped, so
+    /// critical rows always find room.
+    pub low_priority_high_water: f64,
+}
+
+impl Default for WriterConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            batch_size: 500,
+            flush_interval: Duration::from_millis(100),
+            low_priority_topics: vec!["l2".into()],
+            low_priority_high_water: 0.5,
+        }
+    }
+}
+
+/// Outcome of one batch, for the flush-latency histogram.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlushReport {
+    pub rows: usize,
+    pub latency: Duration,
+    /// Rows that could not be written even one at a time.
+    pub failed: usize,
+    /// Low-priority events dropped at enqueue since the previous report.
+    pub dropped: u64,
+}
+
+enum Msg {
+    Op(Box<WriteOp>),
+    Flush(oneshot::Sender<()>),
+    Shutdown(oneshot::Sender<()>),
+}
+
+/// Batches inserts into transactions on a background task so producers
+/// don't wait on the database.
+///
+/// Critical rows apply backpressure when the queue is full; low-priority
+/// events (L2 book updates and the like) are dropped instead once the queue
+/// passes its high-water mark.
+#[derive(Clone)]
+pub struct StoreWriter {
+    tx: mpsc::Sender<Msg>,
+    config: Arc<WriterConfig>,
+    dropped: Arc<AtomicU64>,
+    task: Arc<std::sync::Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl StoreWriter {
+    pub fn spawn(store: Store, config: WriterConfig) -> (Self, UnboundedReceiver<FlushReport>) {
+        let (tx, rx) = mpsc::channel(config.capacity.max(1));
+        let (reports_tx, reports_rx) = mpsc::unbounded_channel();
+        let dropped = Arc::new(AtomicU64::new(0));
+        let worker = Worker {
+            store,
+            rx,
+            batch: Vec::with_capacity(config.batch_size),
+            batch_size: config.batch_size.max(1),
+            reports: reports_tx,
+            dropped: dropped.clone(),
+        };
+        let task = tokio::spawn(worker.run(config.flush_interval));
+        let writer = Self {
+            tx,
+            config: Arc::new(config),
+            dropped,
+            task: Arc::new(std::sync::Mutex::new(Some(task))),
+        };
+        (writer, reports_rx)
+    }
+
+    /// Rows waiting in the queue (not counting the batch being built).
+    pub fn queue_depth(&self) -> usize {
+        self.tx.max_capacity() - self.tx.capacity()
+    }
+
+    pub async fn log_event(&self, run_id: &str, source: &str, topic: &str, payload_json: &str) {
+        self.enqueue(WriteOp::Event {
+            run_id: run_id.to_string(),
+            ts_ms: Utc::now().timestamp_millis(),
+            source: source.to_string(),
+            topic: topic.to_string(),
+            payload_json: payload_json.to_string(),
+        })
+        .await;
+    }
+
+    pub async fn log_incident(&self, run_id: &str, severity: &str, kind: &str, message: &str) {
+        self.enqueue(WriteOp::Incident {
+            run_id: run_id.to_string(),
+            ts_ms: Utc::now().timestamp_millis(),
+            severity: severity.to_string(),
+            kind: kind.to_string(),
+            message: message.to_string(),
+        })
+        .await;
+    }
+
+    /// Queue a row. Returns false if it was dropped (low priority under
+    /// load, or the writer has shut down).
+    pub async fn enqueue(&self, op: WriteOp) -> bool {
+        if self.is_low_priority(&op) {
+            let high_water = self.tx.max_capacity() as f64 * self.config.low_priority_high_water;
+            if self.queue_depth() as f64 >= high_water {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                return false;
+            }
+            return match self.tx.try_send(Msg::Op(Box::new(op))) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    false
+                }
+                Err(TrySendError::Closed(_)) => false,
+            };
+        }
+        let critical = op.is_critical();
+        if self.tx.send(Msg::Op(Box::new(op))).await.is_err() {
+            if critical {
+                warn!("storage writer is shut down; critical row lost");
+            }
+            return false;
+        }
+        true
+    }
+
+    /// Write everything queued so far.
+    pub async fn flush(&self) -> Result<()> {
+        let (ack, done) = oneshot::channel();
+        self.tx
+            .send(Msg::Flush(ack))
+            .await
+            .map_err(|_| anyhow!("storage writer is shut down"))?;
+        done.await.map_err(|_| anyhow!("storage writer stopped"))
+    }
+
+    /// Drain and write everything queued, then stop the writer task. Rows
+    /// enqueued after this are dropped.
+    pub async fn shutdown(&self) -> Result<()> {
+        let (ack, done) = oneshot::channel();
+        if self.tx.send(Msg::Shutdown(ack)).await.is_ok() {
+            let _ = done.await;
+        }
+        let task = self.task.lock().ok().and_then(|mut t| t.take());
+        if let Some(task) = task {
+            task.await
+                .map_err(|err| anyhow!("storage writer task failed: {err}"))?;
+        }
+        Ok(())
+    }
+
+    fn is_low_priority(&self, op: &WriteOp) -> bool {
+        match op {
+            WriteOp::Event { topic, .. } => self
+                .config
+                .low_priority_topics
+                .iter()
+                .any(|prefix| topic.starts_with(prefix.as_str())),
+            _ => false,
+        }
+    }
+}
+
+struct Worker {
+    store: Store,
+    rx: mpsc::Receiver<Msg>,
+    batch: Vec<WriteOp>,
+    batch_size: usize,
+    reports: UnboundedSender<FlushReport>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Worker {
+    async fn run(mut self, flush_interval: Duration) {
+        let mut ticker = tokio::time::interval(flush_interval.max(Duration::from_millis(1)));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                msg = self.rx.recv() => match msg {
+                    Some(Msg::Op(op)) => {
+                        self.batch.push(*op);
+                        if self.batch.len() >= self.batch_size {
+                            self.flush().await;
+                        }
+                    }
+                    Some(Msg::Flush(ack)) => {
+                        self.flush().await;
+                        let _ = ack.send(());
+                    }
+                    Some(Msg::Shutdown(ack)) => {
+                        self.rx.close();
+                        while let Some(msg) = self.rx.recv().await {
+                            match msg {
+                                Msg::Op(op) => self.batch.push(*op),
+                                Msg::Flush(ack) | Msg::Shutdown(ack) => {
+                                    let _ = ack.send(());
+                                }
+                            }
+                            if self.batch.len() >= self.batch_size {
+                                self.flush().await;
+                            }
+                        }
+                        self.flush().await;
+                        let _ = ack.send(());
+                        return;
+                    }
+                    None => {
+                        self.flush().await;
+                        return;
+                    }
+                },
+                _ = ticker.tick() => {
+                    if !self.batch.is_empty() {
+                        self.flush().await;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn flush(&mut self) {
+        if self.batch.is_empty() {
+            return;
+        }
+        let started = Instant::now();
+        let batch = std::mem::take(&mut self.batch);
+        let mut failed = 0;
+        if let Err(err) = self.store.write_batch(&batch).await {
+            // One bad row shouldn't take the rest of the batch with it.
+            warn!(error = ?err, rows = batch.len(), "batch write failed; retrying row by row");
+            for op in &batch {
+                if let Err(err) = self.store.write_batch(std::slice::from_ref(op)).await {
+                    failed += 1;
+                    warn!(error = ?err, critical = op.is_critical(), "storage write failed");
+                }
+            }
+        }
+        let _ = self.reports.send(FlushReport {
+            rows: batch.len(),
+            latency: started.elapsed(),
+            failed,
+            dropped: self.dropped.swap(0, Ordering::Relaxed),
+        });
+    }
+}
+
+impl Store {
+    /// Insert `ops` in one transaction.
+    pub async fn write_batch(&self, ops: &[WriteOp]) -> Result<()> {
+        let event_sql = self.backend.sql(query::INSERT_EVENT);
+        let incident_sql = self.backend.sql(query::INSERT_INCIDENT);
+        let fill_sql = self.backend.sql(query::INSERT_FILL);
+        let order_sql = self.backend.sql(query::UPSERT_ORDER);
+        let pnl_sql = self.backend.sql(query::INSERT_PNL);
+        let _ordered = if ops.iter().any(|op| matches!(op, WriteOp::Fill(_))) {
+            Some(self.fill_writes.lock().await)
+        } else {
+            None
+        };
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.begin().await?;
+            for op in ops {
+                match op {
+                    WriteOp::Event {
+                        run_id,
+                        ts_ms,
+                        source,
+                        topic,
+                        payload_json,
+                    } => {
+                        sqlx::query(&event_sql)
+                            .bind(run_id)
+                            .bind(ts_ms)
+                            .bind(source)
+                            .bind(topic)
+                            .bind(payload_json)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                    WriteOp::Incident {
+                        run_id,
+                        ts_ms,
+                        severity,
+                        kind,
+                        message,
+                    } => {
+                        sqlx::query(&incident_sql)
+                            .bind(run_id)
+                            .bind(ts_ms)
+                            .bind(severity)
+                            .bind(kind)
+                            .bind(message)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                    WriteOp::Fill(fill) => {
+                        bind_fill!(sqlx::query(&fill_sql), fill)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                    WriteOp::Order(order) => {
+                        bind_order!(sqlx::query(&order_sql), order)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                    WriteOp::Pnl(entry) => {
+                        bind_pnl!(sqlx::query(&pnl_sql), entry)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                }
+            }
+            tx.commit().await?
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::init_sqlite;
+
+    async fn count(store: &Store, table: &str) -> i64 {
+        with_pool!(&store.pool, |pool| {
+            sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table}"))
+                .fetch_one(pool)
+                .await
+                .unwrap()
+        })
+    }
+
+    fn fill(n: i64) -> FillRow {
+        FillRow {
+            run_id: "run-w".into(),
+            ts_ms: n,
+            venue: "polymarket".into(),
+            fill_id: Some(format!("f-{n}")),
+            order_id: None,
+            client_order_id: None,
+            market_id: 1,
+            strategy: "mm".into(),
+            side: "BuyYes".into(),
+            price: 0.5,
+            qty: 1.0,
+            fee_usd: None,
+            liquidity: None,
+            raw_json: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn batches_flush_by_size_and_time() {
+        let store = init_sqlite("sqlite::memory:").await.unwrap();
+        store.insert_run("run-w", None).await.unwrap();
+        let (writer, mut reports) = StoreWriter::spawn(
+            store.clone(),
+            WriterConfig {
+                batch_size: 3,
+                flush_interval: Duration::from_millis(20),
+                ..WriterConfig::default()
+            },
+        );
+        for i in 0..4 {
+            writer
+                .log_event("run-w", "internal", "tick", &i.to_string())
+                .await;
+        }
+        let first = reports.recv().await.unwrap();
+        assert_eq!(first.rows, 3);
+        // the fourth row goes out on the timer
+        let second = reports.recv().await.unwrap();
+        assert_eq!((second.rows, second.failed), (1, 0));
+        assert_eq!(count(&store, "raw_events").await, 4);
+        writer.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn drops_low_priority_topics_but_never_critical_rows() {
+        let store = init_sqlite("sqlite::memory:").await.unwrap();
+        store.insert_run("run-w", None).await.unwrap();
+        let (writer, mut reports) = StoreWriter::spawn(
+            store.clone(),
+            WriterConfig {
+                capacity: 8,
+                batch_size: 1_000,
+                flush_interval: Duration::from_secs(3_600),
+                low_priority_high_water: 0.5,
+                ..WriterConfig::default()
+            },
+        );
+        // The test runtime is single threaded, so the worker doesn't drain
+        // the queue until we yield.
+        let mut accepted = 0;
+        for _ in 0..20 {
+            if writer
+                .enqueue(WriteOp::Event {
+                    run_id: "run-w".into(),
+                    ts_ms: 0,
+                    source: "ws".into(),
+                    topic: "l2.book".into(),
+                    payload_json: "{}".into(),
+                })
+                .await
+            {
+                accepted += 1;
+            }
+        }
+        assert_eq!(accepted, 4);
+        for i in 0..3 {
+            assert!(writer.enqueue(WriteOp::Fill(fill(i))).await);
+        }
+        writer
+            .log_incident("run-w", "warning", "TEST", "kept")
+            .await;
+
+        writer.shutdown().await.unwrap();
+        assert_eq!(count(&store, "fills").await, 3);
+        assert_eq!(count(&store, "incidents").await, 1);
+        let mut dropped = 0;
+        while let Ok(report) = reports.try_recv() {
+            dropped += report.dropped;
+        }
+        assert_eq!(dropped, 20 - accepted);
+        assert_eq!(count(&store, "raw_events").await, accepted as i64);
+    }
+
+    #[tokio::test]
+    async fn shutdown_flushes_everything_queued() {
+        let store = init_sqlite("sqlite::memory:").await.unwrap();
+        store.insert_run("run-w", None).await.unwrap();
+        let (writer, _reports) = StoreWriter::spawn(
+            store.clone(),
+            WriterConfig {
+                flush_interval: Duration::from_secs(3_600),
+                ..WriterConfig::default()
+            },
+        );
+        for i in 0..50 {
+            writer.enqueue(WriteOp::Fill(fill(i))).await;
+        }
+        writer.shutdown().await.unwrap();
+        assert_eq!(count(&store, "fills").await, 50);
+        assert!(!writer.enqueue(WriteOp::Fill(fill(99))).await);
+        assert!(writer.flush().await.is_err());
+    }
+
+    /// Set in the child process of `killed_writer_leaves_whole_batches`.
+    const CRASH_CHILD_DB: &str = "STORAGE_CRASH_CHILD_DB";
+    const CRASH_CHILD_WAL: &str = "STORAGE_CRASH_CHILD_WAL";
+    const CRASH_BATCH: i64 = 250;
+
+    /// Child half of `killed_writer_leaves_whole_batches`: commits batches
+    /// until it is killed, printing each one. A no-op in a normal run.
+    #[tokio::test]
+    async fn crash_child_writes_until_killed() -> Result<()> {
+        let Ok(url) = std::env::var(CRASH_CHILD_DB) else {
+            return Ok(());
+        };
+        let options = crate::StoreOptions {
+            sqlite_wal: std::env::var(CRASH_CHILD_WAL).as_deref() == Ok("1"),
+            ..crate::StoreOptions::default()
+        };
+        let store = Store::connect_with(&url, &options).await?;
+        for batch in 0u64.. {
+            let ops: Vec<_> = (0..CRASH_BATCH)
+                .map(|i| WriteOp::Event {
+                    run_id: "run-crash".into(),
+                    ts_ms: i,
+                    source: "internal".into(),
+                    topic: format!("batch-{batch}"),
+                    payload_json: r#"{"pad":"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx"}"#.into(),
+                })
+                .collect();
+            store.write_batch(&ops).await?;
+            println!("committed {batch}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn killed_writer_leaves_whole_batches() -> Result<()> {
+        use std::io::{BufRead, BufReader};
+        use std::process::{Command, Stdio};
+
+        for wal in [true, false] {
+            let dir = tempfile::tempdir()?;
+            let url = format!(
+                "sqlite://{}?mode=rwc",
+                dir.path().join("crash.db").display()
+            );
+            let options = crate::StoreOptions {
+                sqlite_wal: wal,
+                ..crate::StoreOptions::default()
+            };
+            Store::connect_with(&url, &options)
+                .await?
+                .insert_run("run-crash", None)
+                .await?;
+
+            let mut child = Command::new(std::env::current_exe()?)
+                .args([
+                    "writer::tests::crash_child_writes_until_killed",
+                    "--exact",
+                    "--nocapture",
+                ])
+                .env(CRASH_CHILD_DB, &url)
+                .env(CRASH_CHILD_WAL, if wal { "1" } else { "0" })
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()?;
+            let mut committed = 0;
+            for line in BufReader::new(child.stdout.take().unwrap()).lines() {
+                if let Some(batch) = line?.strip_prefix("committed ") {
+                    committed = batch.parse::<usize>()? + 1;
+                    if committed >= 20 {
+                        break;
+                    }
+                }
+            }
+            // SIGKILL: no destructors, no rollback, most likely mid-batch
+            child.kill()?;
+            child.wait()?;
+            assert!(committed >= 20, "child stopped after {committed} batches");
+
+            let store = Store::connect_with(&url, &options).await?;
+            let (integrity, dangling, batches) = with_pool!(&store.pool, |pool| {
+                let integrity: String = sqlx::query_scalar("PRAGMA integrity_check")
+                    .fetch_one(pool)
+                    .await?;
+                let dangling = sqlx::query("PRAGMA foreign_key_check")
+                    .fetch_all(pool)
+                    .await?
+                    .len();
+                let batches: Vec<(String, i64)> =
+                    sqlx::query_as("SELECT topic, COUNT(*) FROM raw_events GROUP BY topic")
+                        .fetch_all(pool)
+                        .await?;
+                (integrity, dangling, batches)
+            });
+            assert_eq!(integrity, "ok");
+            assert_eq!(dangling, 0);
+            assert!(batches.len() >= committed, "lost committed batches");
+            for (topic, rows) in batches {
+                assert_eq!(rows, CRASH_BATCH, "{topic} is torn (wal={wal})");
+            }
+        }
+        Ok(())
+    }
+}
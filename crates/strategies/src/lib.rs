@@ -9,11 +9,116 @@ pub enum IntentKind {
     NoOp,
 }
 
+/// Outcome token of a binary market.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Token {
+    Yes,
+    No,
+}
+
+impl Token {
+    pub fn complement(self) -> Token {
+        match self {
+            Token::Yes => Token::No,
+            Token::No => Token::Yes,
+        }
+    }
+}
+
+/// Order side, matching the `side` column values in storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Side {
+    BuyYes,
+    BuyNo,
+    SellYes,
+    SellNo,
+}
+
+impl Side {
+    pub fn token(self) -> Token {
+        match self {
+            Side::BuyYes | Side::SellYes => Token::Yes,
+            Side::BuyNo | Side::SellNo => Token::No,
+        }
+    }
+
+    pub fn is_buy(self) -> bool {
+        matches!(self, Side::BuyYes | Side::BuyNo)
+    }
+
+    pub fn buy(token: Token) -> Side {
+        match token {
+            Token::Yes => Side::BuyYes,
+            Token::No => Side::BuyNo,
+        }
+    }
+
+    pub fn sell(token: Token) -> Side {
+        match token {
+            Token::Yes => Side::SellYes,
+            Token::No => Side::SellNo,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Side::BuyYes => "BuyYes",
+            Side::BuyNo => "BuyNo",
+            Side::SellYes => "SellYes",
+            Side::SellNo => "SellNo",
+        }
+    }
+}
+
+/// How aggressively an intent should be worked, matching the
+/// `strategy_intents.urgency` values in storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Urgency {
+    /// Rest passively; never take liquidity.
+    Maker,
+    #[default]
+    Neutral,
+    /// Cross now or not at all.
+    Taker,
+}
+
+impl Urgency {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Urgency::Maker => "Maker",
+            Urgency::Neutral => "Neutral",
+            Urgency::Taker => "Taker",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "Maker" => Some(Urgency::Maker),
+            "Neutral" => Some(Urgency::Neutral),
+            "Taker" => Some(Urgency::Taker),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Intent {
     pub intent_id: String,
+    #[serde(default)]
+    pub strategy: String,
     pub market_id: i64,
     pub kind: IntentKind,
+    #[serde(default)]
+    pub side: Option<Side>,
+    #[serde(default)]
+    pub price: Option<f64>,
+    #[serde(default)]
+    pub size: Option<f64>,
+    #[serde(default)]
+    pub urgency: Urgency,
+    /// How long a resulting order may rest; 0 means until canceled.
+    #[serde(default)]
+    pub ttl_ms: i64,
     pub expected_value: f64,
 }
 
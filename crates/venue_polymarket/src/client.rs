@@ -0,0 +1,519 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::Method;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::auth::{l2_headers, ApiCredentials};
+use crate::signing::{ClobSide, OrderSigner};
+
+pub const DEFAULT_CLOB_URL: &str = "https://clob.polymarket.com";
+
+/// Cursor value the CLOB returns on the last page.
+const END_CURSOR: &str = "LTE=";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ClobOrderType {
+    #[serde(rename = "GTC")]
+    Gtc,
+    #[serde(rename = "GTD")]
+    Gtd,
+    #[serde(rename = "FOK")]
+    Fok,
+    #[serde(rename = "FAK")]
+    Fak,
+}
+
+/// How a posted order works on the book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderOptions {
+    pub order_type: ClobOrderType,
+    /// Signed expiration (unix seconds) for GTD; 0 otherwise.
+    pub expiration_s: u64,
+    pub post_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClobOrder {
+    pub id: String,
+    pub token_id: String,
+    pub side: ClobSide,
+    pub price: f64,
+    pub original_size: f64,
+    pub size_matched: f64,
+}
+
+/// One of our fills, from our side of the trade.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClobTrade {
+    pub fill_id: String,
+    pub order_id: String,
+    pub token_id: String,
+    pub side: ClobSide,
+    pub price: f64,
+    pub size: f64,
+    /// Fee charged on the fill, from the order's `fee_rate_bps`.
+    pub fee_usd: f64,
+    pub ts_ms: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClobLevel {
+    pub price: f64,
+    pub size: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClobBook {
+    /// Best (highest) bid first.
+    pub bids: Vec<ClobLevel>,
+    /// Best (lowest) ask first.
+    pub asks: Vec<ClobLevel>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedOrder {
+    pub order_id: String,
+    /// "live", "matched", "delayed" or "unmatched".
+    pub status: String,
+}
+
+/// Client for the CLOB's public market data. It holds no key, so paper
+/// runs can read the live book without being able to trade.
+#[derive(Clone)]
+pub struct BookClient {
+    http: reqwest::Client,
+    host: String,
+}
+
+impl BookClient {
+    pub fn new(host: &str) -> Result<Self> {
+        Ok(Self {
+            http: http_client()?,
+            host: host.trim_end_matches('/').to_string(),
+        })
+    }
+
+    pub async fn book(&self, token_id: &str) -> Result<ClobBook> {
+        let resp = self
+            .http
+            .get(format!("{}/book", self.host))
+            .query(&[("token_id", token_id)])
+            .send()
+            .await?;
+        let status = resp.status();
+        let value: Value = resp.json().await.context("book response is not json")?;
+        if !status.is_success() {
+            bail!("clob /book returned {status}: {value}");
+        }
+        parse_book(&value)
+    }
+}
+
+/// Minimal authenticated client for the Polymarket CLOB REST API.
+pub struct ClobClient {
+    http: reqwest::Client,
+    host: String,
+    creds: ApiCredentials,
+    signer: OrderSigner,
+    books: BookClient,
+}
+
+impl ClobClient {
+    pub fn new(host: &str, creds: ApiCredentials, signer: OrderSigner) -> Result<Self> {
+        let http = http_client()?;
+        let host = host.trim_end_matches('/').to_string();
+        Ok(Self {
+            books: BookClient {
+                http: http.clone(),
+                host: host.clone(),
+            },
+            http,
+            host,
+            creds,
+            signer,
+        })
+    }
+
+    pub fn address(&self) -> &str {
+        self.signer.address()
+    }
+
+    pub async fn post_order(
+        &self,
+        token_id: &str,
+        side: ClobSide,
+        price: f64,
+        size: f64,
+        options: OrderOptions,
+    ) -> Result<PlacedOrder> {
+        let order = self
+            .signer
+            .sign_order(token_id, side, price, size, options.expiration_s)?;
+        let body = json!({
+            "order": order,
+            "owner": self.creds.api_key,
+            "orderType": options.order_type,
+            "postOnly": options.post_only,
+        });
+        let resp = self
+            .request(Method::POST, "/order", &[], Some(body))
+            .await?;
+        parse_placed(&resp)
+    }
+
+    pub async fn cancel(&self, order_id: &str) -> Result<()> {
+        let resp = self
+            .request(
+                Method::DELETE,
+                "/order",
+                &[],
+                Some(json!({ "orderID": order_id })),
+            )
+            .await?;
+        check_canceled(&resp, order_id)
+    }
+
+    pub async fn cancel_all(&self) -> Result<()> {
+        self.request(Method::DELETE, "/cancel-all", &[], None)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn open_orders(&self) -> Result<Vec<ClobOrder>> {
+        let mut orders = Vec::new();
+        for page in self.pages("/data/orders", &[]).await? {
+            orders.extend(parse_orders(&page)?);
+        }
+        Ok(orders)
+    }
+
+    /// Our trades matched at or after `after_s` (unix seconds).
+    pub async fn trades(&self, after_s: i64) -> Result<Vec<ClobTrade>> {
+        let after = after_s.to_string();
+        let mut trades = Vec::new();
+        for page in self.pages("/data/trades", &[("after", &after)]).await? {
+            trades.extend(parse_trades(&page, &self.creds.api_key)?);
+        }
+        Ok(trades)
+    }
+
+    /// Conditional token balance in shares.
+    pub async fn balance(&self, token_id: &str) -> Result<f64> {
+        let resp = self
+            .request(
+                Method::GET,
+                "/balance-allowance",
+                &[
+                    ("asset_type", "CONDITIONAL"),
+                    ("token_id", token_id),
+                    ("signature_type", "0"),
+                ],
+                None,
+            )
+            .await?;
+        Ok(number(&resp["balance"]).unwrap_or(0.0) / 1_000_000.0)
+    }
+
+    pub async fn book(&self, token_id: &str) -> Result<ClobBook> {
+        self.books.book(token_id).await
+    }
+
+    async fn pages(&self, path: &str, query: &[(&str, &str)]) -> Result<Vec<Value>> {
+        let mut cursor = "MA==".to_string();
+        let mut pages = Vec::new();
+        loop {
+            let mut q = query.to_vec();
+            q.push(("next_cursor", &cursor));
+            let page = self.request(Method::GET, path, &q, None).await?;
+            let next = page["next_cursor"]
+                .as_str()
+                .unwrap_or(END_CURSOR)
+                .to_string();
+            pages.push(page);
+            if next == END_CURSOR || next.is_empty() || next == cursor {
+                return Ok(pages);
+            }
+            cursor = next;
+        }
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<Value>,
+    ) -> Result<Value> {
+        let body = body.map(|b| b.to_string()).unwrap_or_default();
+        let ts = now_s();
+        let headers = l2_headers(
+            &self.creds,
+            self.signer.address(),
+            ts,
+            method.as_str(),
+            path,
+            &body,
+        )?;
+
+        let mut req = self
+            .http
+            .request(method.clone(), format!("{}{path}", self.host))
+            .query(query);
+        for (name, value) in headers {
+            req = req.header(name, value);
+        }
+        if !body.is_empty() {
+            req = req
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body);
+        }
+        let resp = req.send().await?;
+        let status = resp.status();
+        let text = resp.text().await?;
+        if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(
+                ClobRejection(format!("clob {method} {path} returned {status}: {text}")).into(),
+            );
+        }
+        if !status.is_success() {
+            bail!("clob {method} {path} returned {status}: {text}");
+        }
+        if text.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_str(&text).with_context(|| format!("clob {path} response is not json"))
+    }
+}
+
+/// The CLOB refused a request on its merits (4xx, order or cancel
+/// rejected). Retrying the same request will not help.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClobRejection(pub String);
+
+impl std::fmt::Display for ClobRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ClobRejection {}
+
+fn http_client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?)
+}
+
+fn now_s() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// CLOB numbers arrive as strings or numbers.
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::String(s) => s.parse().ok(),
+        Value::Number(n) => n.as_f64(),
+        _ => None,
+    }
+}
+
+fn string(value: &Value, field: &str) -> Result<String> {
+    value[field]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("missing {field} in {value}"))
+}
+
+fn side(value: &Value) -> Result<ClobSide> {
+    value["side"]
+        .as_str()
+        .and_then(ClobSide::parse)
+        .ok_or_else(|| anyhow!("missing side in {value}"))
+}
+
+fn parse_placed(resp: &Value) -> Result<PlacedOrder> {
+    let error = resp["errorMsg"].as_str().unwrap_or_default();
+    if resp["success"].as_bool() == Some(false) || !error.is_empty() {
+        return Err(ClobRejection(format!(
+            "order rejected: {}",
+            if error.is_empty() { "unknown" } else { error }
+        ))
+        .into());
+    }
+    Ok(PlacedOrder {
+        order_id: string(resp, "orderID")?,
+        status: resp["status"].as_str().unwrap_or_default().to_string(),
+    })
+}
+
+fn check_canceled(resp: &Value, order_id: &str) -> Result<()> {
+    if let Some(reason) = resp["not_canceled"].get(order_id) {
+        return Err(ClobRejection(format!("cancel of {order_id} refused: {reason}")).into());
+    }
+    Ok(())
+}
+
+fn items(page: &Value) -> &[Value] {
+    page["data"]
+        .as_array()
+        .or_else(|| page.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn parse_orders(page: &Value) -> Result<Vec<ClobOrder>> {
+    items(page)
+        .iter()
+        .map(|o| {
+            Ok(ClobOrder {
+                id: string(o, "id")?,
+                token_id: string(o, "asset_id")?,
+                side: side(o)?,
+                price: number(&o["price"]).unwrap_or_default(),
+                original_size: number(&o["original_size"]).unwrap_or_default(),
+                size_matched: number(&o["size_matched"]).unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Our fills in a trades page. As taker we own the trade itself; as maker
+/// only the entries of `maker_orders` owned by our API key are ours.
+fn parse_trades(page: &Value, api_key: &str) -> Result<Vec<ClobTrade>> {
+    let mut trades = Vec::new();
+    for t in items(page) {
+        let id = string(t, "id")?;
+        let ts_ms = number(&t["match_time"]).unwrap_or_default() as i64 * 1000;
+        let taker_side = side(t)?;
+        if t["trader_side"].as_str() == Some("MAKER") {
+            for m in t["maker_orders"].as_array().into_iter().flatten() {
+                if m["owner"].as_str() != Some(api_key) {
+                    continue;
+                }
+                let order_id = string(m, "order_id")?;
+                let maker_side = side(m).unwrap_or(match taker_side {
+                    ClobSide::Buy => ClobSide::Sell,
+                    ClobSide::Sell => ClobSide::Buy,
+                });
+                trades.push(ClobTrade {
+                    fill_id: format!("{id}:{order_id}"),
+                    order_id,
+                    token_id: string(m, "asset_id")?,
+                    side: maker_side,
+                    price: number(&m["price"]).unwrap_or_default(),
+                    size: number(&m["matched_amount"]).unwrap_or_default(),
+                    fee_usd: fee_usd(m, "price", "matched_amount"),
+                    ts_ms,
+                });
+            }
+        } else {
+            trades.push(ClobTrade {
+                fill_id: id,
+                order_id: string(t, "taker_order_id")?,
+                token_id: string(t, "asset_id")?,
+                side: taker_side,
+                price: number(&t["price"]).unwrap_or_default(),
+                size: number(&t["size"]).unwrap_or_default(),
+                fee_usd: fee_usd(t, "price", "size"),
+                ts_ms,
+            });
+        }
+    }
+    Ok(trades)
+}
+
+/// The CLOB charges `fee_rate_bps` on the cheaper side of the outcome
+/// pair: `rate * min(price, 1 - price) * size`.
+fn fee_usd(value: &Value, price: &str, size: &str) -> f64 {
+    let rate = number(&value["fee_rate_bps"]).unwrap_or_default() / 10_000.0;
+    let price = number(&value[price]).unwrap_or_default();
+    let size = number(&value[size]).unwrap_or_default();
+    rate * price.min(1.0 - price) * size
+}
+
+fn parse_book(value: &Value) -> Result<ClobBook> {
+    let levels = |key: &str| -> Vec<ClobLevel> {
+        value[key]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|l| {
+                Some(ClobLevel {
+                    price: number(&l["price"])?,
+                    size: number(&l["size"])?,
+                })
+            })
+            .collect()
+    };
+    let mut book = ClobBook {
+        bids: levels("bids"),
+        asks: levels("asks"),
+    };
+    book.bids.sort_by(|a, b| b.price.total_cmp(&a.price));
+    book.asks.sort_by(|a, b| a.price.total_cmp(&b.price));
+    Ok(book)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_order_placement_results() {
+        let ok = json!({"success": true, "errorMsg": "", "orderID": "0xabc", "status": "live"});
+        assert_eq!(parse_placed(&ok).unwrap().order_id, "0xabc");
+        let rejected = json!({"success": false, "errorMsg": "not enough balance"});
+        let err = parse_placed(&rejected).unwrap_err().to_string();
+        assert!(err.contains("not enough balance"));
+    }
+
+    #[test]
+    fn parses_taker_and_maker_fills() {
+        let page = json!({
+            "data": [
+                {"id": "t1", "taker_order_id": "o1", "asset_id": "111", "side": "BUY",
+                 "size": "5", "price": "0.41", "fee_rate_bps": "200", "match_time": "1700000000",
+                 "trader_side": "TAKER"},
+                {"id": "t2", "taker_order_id": "x", "asset_id": "111", "side": "BUY",
+                 "size": "9", "price": "0.45", "match_time": "1700000001", "trader_side": "MAKER",
+                 "maker_orders": [
+                    {"order_id": "o2", "owner": "me", "matched_amount": "3", "price": "0.45", "asset_id": "111"},
+                    {"order_id": "o3", "owner": "other", "matched_amount": "6", "price": "0.45", "asset_id": "111"}
+                 ]}
+            ],
+            "next_cursor": "LTE="
+        });
+        let trades = parse_trades(&page, "me").unwrap();
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0].side, ClobSide::Buy);
+        assert_eq!(trades[0].ts_ms, 1_700_000_000_000);
+        assert!((trades[0].fee_usd - 0.02 * 0.41 * 5.0).abs() < 1e-12);
+        assert_eq!(trades[1].fee_usd, 0.0);
+        assert_eq!(trades[1].order_id, "o2");
+        assert_eq!(trades[1].side, ClobSide::Sell);
+        assert_eq!(trades[1].size, 3.0);
+    }
+
+    #[test]
+    fn sorts_book_levels_best_first() {
+        let book = parse_book(&json!({
+            "bids": [{"price": "0.40", "size": "10"}, {"price": "0.42", "size": "5"}],
+            "asks": [{"price": "0.47", "size": "1"}, {"price": "0.45", "size": "2"}]
+        }))
+        .unwrap();
+        assert_eq!(book.bids[0].price, 0.42);
+        assert_eq!(book.asks[0].price, 0.45);
+
+        let orders = parse_orders(&json!({"data": [
+            {"id": "o1", "asset_id": "111", "side": "SELL", "price": "0.5",
+             "original_size": "10", "size_matched": "4"}
+        ]}))
+        .unwrap();
+        assert_eq!(orders[0].size_matched, 4.0);
+    }
+}
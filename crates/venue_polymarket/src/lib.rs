@@ -1 +1,14 @@
-pub fn placeholder() {}
+pub mod auth;
+pub mod client;
+pub mod signing;
+pub mod tokens;
+
+pub use auth::ApiCredentials;
+pub use client::{
+    BookClient, ClobBook, ClobClient, ClobLevel, ClobOrder, ClobOrderType, ClobRejection,
+    ClobTrade, OrderOptions, PlacedOrder, DEFAULT_CLOB_URL,
+};
+pub use signing::{
+    ClobSide, OrderSigner, SignedOrder, CTF_EXCHANGE, NEG_RISK_CTF_EXCHANGE, POLYGON_CHAIN_ID,
+};
+pub use tokens::{MarketTokens, TokenMap};
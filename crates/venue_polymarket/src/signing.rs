@@ -0,0 +1,316 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context, Result};
+use k256::ecdsa::{SigningKey, VerifyingKey};
+use serde::Serialize;
+use sha3::{Digest, Keccak256};
+
+/// CTF exchange on Polygon mainnet.
+pub const CTF_EXCHANGE: &str = "0x4bFb41d5B3570DeFd03C39a9A4D8dE6Bd8B8982E";
+/// Exchange used by neg-risk markets.
+pub const NEG_RISK_CTF_EXCHANGE: &str = "0xC5d563A36AE78145C45a50134d48A1215220f80a";
+pub const POLYGON_CHAIN_ID: u64 = 137;
+
+const ORDER_TYPE: &str = "Order(uint256 salt,address maker,address signer,address taker,uint256 tokenId,uint256 makerAmount,uint256 takerAmount,uint256 expiration,uint256 nonce,uint256 feeRateBps,uint8 side,uint8 signatureType)";
+const DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+const DOMAIN_NAME: &str = "Polymarket CTF Exchange";
+const DOMAIN_VERSION: &str = "1";
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+/// USDC and outcome tokens both use 6 decimals on the exchange.
+const AMOUNT_SCALE: f64 = 1_000_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ClobSide {
+    Buy,
+    Sell,
+}
+
+impl ClobSide {
+    fn as_u8(self) -> u8 {
+        match self {
+            ClobSide::Buy => 0,
+            ClobSide::Sell => 1,
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_uppercase().as_str() {
+            "BUY" => Some(ClobSide::Buy),
+            "SELL" => Some(ClobSide::Sell),
+            _ => None,
+        }
+    }
+}
+
+/// Order fields covered by the EIP-712 signature, in wire format.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedOrder {
+    pub salt: u64,
+    pub maker: String,
+    pub signer: String,
+    pub taker: String,
+    pub token_id: String,
+    pub maker_amount: String,
+    pub taker_amount: String,
+    pub expiration: String,
+    pub nonce: String,
+    pub fee_rate_bps: String,
+    pub side: ClobSide,
+    pub signature_type: u8,
+    pub signature: String,
+}
+
+/// Signs CLOB orders with an EOA key (signature type 0).
+///
+/// This is the only type in the workspace that holds key material; paper
+/// trading never constructs one.
+pub struct OrderSigner {
+    key: SigningKey,
+    address: String,
+    chain_id: u64,
+    exchange: [u8; 20],
+    salt: AtomicU64,
+}
+
+impl std::fmt::Debug for OrderSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OrderSigner")
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OrderSigner {
+    pub fn from_hex(private_key: &str, chain_id: u64, exchange: &str) -> Result<Self> {
+        let bytes = hex::decode(private_key.trim().trim_start_matches("0x"))
+            .context("private key is not hex")?;
+        let key = SigningKey::from_slice(&bytes).map_err(|_| anyhow!("invalid private key"))?;
+        let address = to_checksum(&address_of(key.verifying_key()));
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        Ok(Self {
+            key,
+            address,
+            chain_id,
+            exchange: parse_address(exchange)?,
+            salt: AtomicU64::new(seed),
+        })
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Build and sign a limit order for `size` shares at `price`.
+    pub fn sign_order(
+        &self,
+        token_id: &str,
+        side: ClobSide,
+        price: f64,
+        size: f64,
+        expiration_s: u64,
+    ) -> Result<SignedOrder> {
+        if !(price > 0.0 && price < 1.0) || size <= 0.0 {
+            bail!("invalid order price {price} size {size}");
+        }
+        let shares = (size * AMOUNT_SCALE).round() as u64;
+        let notional = (price * size * AMOUNT_SCALE).round() as u64;
+        let (maker_amount, taker_amount) = match side {
+            ClobSide::Buy => (notional, shares),
+            ClobSide::Sell => (shares, notional),
+        };
+        let salt = self.salt.fetch_add(1, Ordering::Relaxed);
+
+        let mut order = SignedOrder {
+            salt,
+            maker: self.address.clone(),
+            signer: self.address.clone(),
+            taker: ZERO_ADDRESS.to_string(),
+            token_id: token_id.to_string(),
+            maker_amount: maker_amount.to_string(),
+            taker_amount: taker_amount.to_string(),
+            expiration: expiration_s.to_string(),
+            nonce: "0".to_string(),
+            fee_rate_bps: "0".to_string(),
+            side,
+            signature_type: 0,
+            signature: String::new(),
+        };
+        let digest = self.digest(&order)?;
+        let (signature, recovery) = self
+            .key
+            .sign_prehash_recoverable(&digest)
+            .map_err(|e| anyhow!("signing failed: {e}"))?;
+        let mut bytes = signature.to_bytes().to_vec();
+        bytes.push(recovery.to_byte() + 27);
+        order.signature = format!("0x{}", hex::encode(bytes));
+        Ok(order)
+    }
+
+    /// EIP-712 digest of `order`.
+    pub fn digest(&self, order: &SignedOrder) -> Result<[u8; 32]> {
+        let mut domain = Vec::with_capacity(32 * 5);
+        domain.extend(keccak(DOMAIN_TYPE.as_bytes()));
+        domain.extend(keccak(DOMAIN_NAME.as_bytes()));
+        domain.extend(keccak(DOMAIN_VERSION.as_bytes()));
+        domain.extend(uint_word(self.chain_id));
+        domain.extend(address_word(&self.exchange));
+
+        let mut data = Vec::with_capacity(32 * 13);
+        data.extend(keccak(ORDER_TYPE.as_bytes()));
+        data.extend(uint_word(order.salt));
+        data.extend(address_word(&parse_address(&order.maker)?));
+        data.extend(address_word(&parse_address(&order.signer)?));
+        data.extend(address_word(&parse_address(&order.taker)?));
+        data.extend(decimal_word(&order.token_id)?);
+        data.extend(decimal_word(&order.maker_amount)?);
+        data.extend(decimal_word(&order.taker_amount)?);
+        data.extend(decimal_word(&order.expiration)?);
+        data.extend(decimal_word(&order.nonce)?);
+        data.extend(decimal_word(&order.fee_rate_bps)?);
+        data.extend(uint_word(order.side.as_u8() as u64));
+        data.extend(uint_word(order.signature_type as u64));
+
+        let mut payload = vec![0x19, 0x01];
+        payload.extend(keccak(&domain));
+        payload.extend(keccak(&data));
+        Ok(keccak(&payload))
+    }
+}
+
+fn keccak(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+fn uint_word(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+fn address_word(address: &[u8; 20]) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address);
+    word
+}
+
+/// Big-endian uint256 from a decimal string (token ids exceed u128).
+fn decimal_word(value: &str) -> Result<[u8; 32]> {
+    if value.is_empty() {
+        bail!("empty uint256");
+    }
+    let mut word = [0u8; 32];
+    for c in value.chars() {
+        let digit = c
+            .to_digit(10)
+            .ok_or_else(|| anyhow!("invalid uint256 {value}"))?;
+        let mut carry = digit;
+        for byte in word.iter_mut().rev() {
+            let v = *byte as u32 * 10 + carry;
+            *byte = (v & 0xff) as u8;
+            carry = v >> 8;
+        }
+        if carry != 0 {
+            bail!("uint256 overflow {value}");
+        }
+    }
+    Ok(word)
+}
+
+fn parse_address(address: &str) -> Result<[u8; 20]> {
+    let bytes = hex::decode(address.trim_start_matches("0x"))
+        .with_context(|| format!("invalid address {address}"))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("invalid address length {address}"))
+}
+
+fn address_of(key: &VerifyingKey) -> [u8; 20] {
+    let point = key.to_encoded_point(false);
+    let hash = keccak(&point.as_bytes()[1..]);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    address
+}
+
+/// EIP-55 mixed-case address.
+fn to_checksum(address: &[u8; 20]) -> String {
+    let lower = hex::encode(address);
+    let hash = keccak(lower.as_bytes());
+    let mut out = String::from("0x");
+    for (i, c) in lower.chars().enumerate() {
+        let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+        if c.is_ascii_alphabetic() && nibble >= 8 {
+            out.push(c.to_ascii_uppercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::{RecoveryId, Signature};
+
+    const KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    fn signer() -> OrderSigner {
+        OrderSigner::from_hex(KEY, POLYGON_CHAIN_ID, CTF_EXCHANGE).unwrap()
+    }
+
+    #[test]
+    fn derives_checksummed_address() {
+        assert_eq!(
+            signer().address(),
+            "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23"
+        );
+    }
+
+    #[test]
+    fn buy_and_sell_amounts_use_six_decimals() {
+        let s = signer();
+        let buy = s.sign_order("123", ClobSide::Buy, 0.42, 10.0, 0).unwrap();
+        assert_eq!(buy.maker_amount, "4200000");
+        assert_eq!(buy.taker_amount, "10000000");
+        let sell = s.sign_order("123", ClobSide::Sell, 0.42, 10.0, 0).unwrap();
+        assert_eq!(sell.maker_amount, "10000000");
+        assert_eq!(sell.taker_amount, "4200000");
+        assert!(s.sign_order("123", ClobSide::Buy, 1.2, 10.0, 0).is_err());
+    }
+
+    #[test]
+    fn signature_recovers_to_signer() {
+        let s = signer();
+        let token = "71321045679252212594626385532706912750332728571942532289631379312455583992563";
+        let order = s.sign_order(token, ClobSide::Buy, 0.5, 2.0, 0).unwrap();
+        let bytes = hex::decode(order.signature.trim_start_matches("0x")).unwrap();
+        assert_eq!(bytes.len(), 65);
+
+        let signature = Signature::from_slice(&bytes[..64]).unwrap();
+        let recovery = RecoveryId::from_byte(bytes[64] - 27).unwrap();
+        let digest = s.digest(&order).unwrap();
+        let key = VerifyingKey::recover_from_prehash(&digest, &signature, recovery).unwrap();
+        assert_eq!(to_checksum(&address_of(&key)), s.address());
+    }
+
+    #[test]
+    fn decimal_words_handle_token_ids() {
+        assert_eq!(decimal_word("258").unwrap()[30..], [1, 2]);
+        assert!(decimal_word("12a").is_err());
+        let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+        assert_eq!(decimal_word(max).unwrap(), [0xff; 32]);
+        assert!(decimal_word(
+            "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// CLOB token ids of one binary market.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarketTokens {
+    pub yes: String,
+    pub no: String,
+}
+
+/// Maps our integer market ids to CLOB token ids and back.
+///
+/// Loaded from a JSON object keyed by market id:
+/// `{"12": {"yes": "7132…", "no": "4810…"}}`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TokenMap {
+    markets: HashMap<i64, MarketTokens>,
+    /// token id -> (market id, is_yes)
+    tokens: HashMap<String, (i64, bool)>,
+}
+
+impl TokenMap {
+    pub fn new(markets: HashMap<i64, MarketTokens>) -> Self {
+        let mut tokens = HashMap::new();
+        for (&market_id, t) in &markets {
+            tokens.insert(t.yes.clone(), (market_id, true));
+            tokens.insert(t.no.clone(), (market_id, false));
+        }
+        Self { markets, tokens }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let raw: HashMap<String, MarketTokens> =
+            serde_json::from_str(json).context("invalid market map")?;
+        let mut markets = HashMap::new();
+        for (key, tokens) in raw {
+            let market_id: i64 = key
+                .parse()
+                .with_context(|| format!("market map key {key} is not an integer"))?;
+            if tokens.yes == tokens.no {
+                bail!("market {market_id} maps YES and NO to the same token");
+            }
+            markets.insert(market_id, tokens);
+        }
+        Ok(Self::new(markets))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("reading market map {}", path.display()))?;
+        Self::from_json(&json)
+    }
+
+    pub fn token_id(&self, market_id: i64, yes: bool) -> Option<&str> {
+        self.markets
+            .get(&market_id)
+            .map(|t| if yes { t.yes.as_str() } else { t.no.as_str() })
+    }
+
+    /// `(market_id, is_yes)` for a CLOB token id.
+    pub fn market_of(&self, token_id: &str) -> Option<(i64, bool)> {
+        self.tokens.get(token_id).copied()
+    }
+
+    /// Every mapped token as `(market_id, is_yes, token_id)`, sorted.
+    pub fn all(&self) -> Vec<(i64, bool, &str)> {
+        let mut all: Vec<_> = self
+            .markets
+            .iter()
+            .flat_map(|(&m, t)| [(m, true, t.yes.as_str()), (m, false, t.no.as_str())])
+            .collect();
+        all.sort_by_key(|(m, yes, _)| (*m, !*yes));
+        all
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_both_directions() {
+        let map = TokenMap::from_json(r#"{"12": {"yes": "111", "no": "222"}}"#).unwrap();
+        assert_eq!(map.token_id(12, true), Some("111"));
+        assert_eq!(map.token_id(12, false), Some("222"));
+        assert_eq!(map.market_of("222"), Some((12, false)));
+        assert_eq!(map.market_of("333"), None);
+        assert_eq!(map.all().len(), 2);
+
+        assert!(TokenMap::from_json(r#"{"x": {"yes": "1", "no": "2"}}"#).is_err());
+        assert!(TokenMap::from_json(r#"{"1": {"yes": "1", "no": "1"}}"#).is_err());
+    }
+}
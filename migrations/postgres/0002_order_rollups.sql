@@ -0,0 +1,30 @@
+-- Schema version 2: strategy tier and leg group on intents, per-order
+-- execution rollups (data_schema.md §4).
+
+ALTER TABLE strategy_intents ADD COLUMN tier INTEGER;
+ALTER TABLE strategy_intents ADD COLUMN leg_group TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_intents_leg_group ON strategy_intents(leg_group);
+
+CREATE TABLE IF NOT EXISTS order_rollups (
+  client_order_id TEXT PRIMARY KEY,
+  order_id TEXT,
+  run_id TEXT NOT NULL,
+  market_id BIGINT NOT NULL,
+  strategy TEXT NOT NULL,
+  side TEXT NOT NULL,
+  qty_submitted REAL NOT NULL,
+  qty_filled REAL NOT NULL,
+  fill_count INTEGER NOT NULL,
+  vwap REAL,
+  fee_usd REAL,
+  ts_first_fill_ms BIGINT,
+  ts_last_fill_ms BIGINT,
+  updated_at_ms BIGINT NOT NULL,
+  FOREIGN KEY(run_id) REFERENCES runs(run_id),
+  FOREIGN KEY(client_order_id) REFERENCES orders(client_order_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_order_rollups_run_strategy ON order_rollups(run_id, strategy, market_id);
+
+UPDATE schema_meta SET value = '2' WHERE key = 'schema_version';
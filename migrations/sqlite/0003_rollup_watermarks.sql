@@ -0,0 +1,10 @@
+-- Schema version 3: progress of incremental rollup jobs, so a restart
+-- picks up after the last fill already folded into order_rollups.
+
+CREATE TABLE IF NOT EXISTS rollup_watermarks (
+  name TEXT PRIMARY KEY,
+  last_id INTEGER NOT NULL,
+  updated_at_ms INTEGER NOT NULL
+);
+
+UPDATE schema_meta SET value = '3' WHERE key = 'schema_version';
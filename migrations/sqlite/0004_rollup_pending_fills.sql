@@ -0,0 +1,9 @@
+-- Schema version 4: fills the order rollup passed over because no order
+-- matched them yet; each pass folds the ones whose order has appeared.
+
+CREATE TABLE IF NOT EXISTS rollup_pending_fills (
+  fill_row_id INTEGER PRIMARY KEY,
+  FOREIGN KEY(fill_row_id) REFERENCES fills(id)
+);
+
+UPDATE schema_meta SET value = '4' WHERE key = 'schema_version';
@@ -1,6 +1,16 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use admin_ipc::{send_request, AdminRequest, DEFAULT_SOCKET_PATH};
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::{Parser, Subcommand};
+use storage::{
+    verify_archive, verify_dataset, CopyConfig, DatasetConfig, PnlQuery, RetentionConfig,
+    RetentionJob, Store,
+};
+
+mod report;
+mod trace;
 
 #[derive(Parser, Debug)]
 struct Cli {
@@ -16,6 +26,103 @@ enum Command {
     Status,
     Pause,
     Resume,
+    /// Block new orders, cancel everything and optionally flatten positions.
+    Kill {
+        #[arg(long)]
+        flatten: bool,
+    },
+    /// Release the kill switch into Paused.
+    ResetKill,
+    /// Have traderd take a verified database backup now.
+    Backup,
+    /// Print the snapshot → intent → approval → order → fill → PnL chain
+    /// for an intent or order. Reads the database directly, so it works
+    /// while the daemon is down.
+    Trace {
+        #[arg(long, env = "DB_URL", default_value = "sqlite://bot.db")]
+        db_url: String,
+        #[arg(long, conflicts_with = "order", required_unless_present = "order")]
+        intent: Option<i64>,
+        /// Client order id.
+        #[arg(long)]
+        order: Option<String>,
+    },
+    /// Archive raw_events older than --keep-days to compressed files and
+    /// delete them from the database.
+    Retention {
+        #[arg(long, env = "DB_URL", default_value = "sqlite://bot.db")]
+        db_url: String,
+        #[arg(long, env = "RAW_EVENTS_KEEP_DAYS", default_value_t = 14)]
+        keep_days: u32,
+        #[arg(long, env = "ARCHIVE_DIR", default_value = "archive")]
+        archive_dir: PathBuf,
+        /// List what is due without archiving or deleting anything.
+        #[arg(long)]
+        dry_run: bool,
+        /// Re-hash the archived files against the manifest instead.
+        #[arg(long, conflicts_with = "dry_run")]
+        verify: bool,
+    },
+    /// Reports read from the database (read-only; works with traderd down).
+    Report {
+        #[arg(long, env = "DB_URL", default_value = "sqlite://bot.db")]
+        db_url: String,
+        #[command(subcommand)]
+        command: ReportCommand,
+    },
+    /// Write a run's snapshots (features as columns), intents, fills and
+    /// PnL ledger to Parquet for the research repo, with a schema
+    /// manifest. Replaces an earlier export of the run.
+    Export {
+        #[arg(long, env = "DB_URL", default_value = "sqlite://bot.db")]
+        db_url: String,
+        #[arg(long)]
+        run: String,
+        #[arg(long, env = "DATASET_DIR", default_value = "dataset")]
+        dir: PathBuf,
+        /// Re-hash the run's exported files against its manifest instead.
+        #[arg(long)]
+        verify: bool,
+    },
+    /// Database maintenance that does not go through traderd.
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ReportCommand {
+    /// PnL by day and strategy, and attribution by strategy, market, tier
+    /// and tag with hit rate, fee drag and drawdown contribution.
+    Pnl {
+        /// Limit to one run; all runs otherwise.
+        #[arg(long)]
+        run: Option<String>,
+        /// From this time on: YYYY-MM-DD (UTC), RFC 3339 or epoch ms.
+        #[arg(long, value_parser = report::parse_time)]
+        since: Option<i64>,
+        /// Before this time, same formats as --since.
+        #[arg(long, value_parser = report::parse_time)]
+        until: Option<i64>,
+        #[arg(long, value_enum, default_value_t = report::Format::Table)]
+        format: report::Format,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DbCommand {
+    /// Copy every table into a new, empty database (e.g. SQLite →
+    /// Postgres), keeping ids, then check row counts and checksums. Stop
+    /// traderd first.
+    MigrateTo {
+        #[arg(long, env = "DB_URL", default_value = "sqlite://bot.db")]
+        from: String,
+        #[arg(long)]
+        to: String,
+        #[arg(long, default_value_t = CopyConfig::default().batch_size)]
+        batch_size: usize,
+    },
 }
 
 #[tokio::main]
@@ -25,6 +132,154 @@ async fn main() -> Result<()> {
         Command::Status => AdminRequest::Status,
         Command::Pause => AdminRequest::Pause,
         Command::Resume => AdminRequest::Resume,
+        Command::Kill { flatten } => AdminRequest::KillSwitch { flatten },
+        Command::ResetKill => AdminRequest::ResetKillSwitch,
+        Command::Backup => AdminRequest::Backup,
+        Command::Trace {
+            db_url,
+            intent,
+            order,
+        } => {
+            let store = Store::open_reader(&db_url).await?;
+            let found = match (intent, order) {
+                (Some(intent_id), _) => store.trace_intent(intent_id).await?,
+                (None, Some(client_order_id)) => store.trace_order(&client_order_id).await?,
+                (None, None) => unreachable!("clap requires --intent or --order"),
+            };
+            let Some(found) = found else {
+                bail!("nothing to trace: no such intent or order");
+            };
+            print!("{}", trace::render(&found));
+            return Ok(());
+        }
+        Command::Retention {
+            db_url,
+            keep_days,
+            archive_dir,
+            dry_run,
+            verify,
+        } => {
+            if verify {
+                let problems = verify_archive(&archive_dir)?;
+                for problem in &problems {
+                    println!("{problem}");
+                }
+                if !problems.is_empty() {
+                    bail!("{} archived files failed verification", problems.len());
+                }
+                println!("archive ok");
+                return Ok(());
+            }
+            let store = Store::connect(&db_url).await?;
+            let config = RetentionConfig {
+                keep_days,
+                archive_dir,
+                ..RetentionConfig::default()
+            };
+            let (job, _reports) = RetentionJob::new(store, config);
+            let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+            if dry_run {
+                for part in job.pending(now_ms).await? {
+                    println!("{} {} rows={}", part.run_id, part.date(), part.row_count);
+                }
+                return Ok(());
+            }
+            let report = job.run_once(now_ms).await;
+            for entry in &report.archived {
+                println!("{} rows={} sha256={}", entry.path, entry.rows, entry.sha256);
+            }
+            println!(
+                "archived {} files, deleted {} rows",
+                report.archived.len(),
+                report.rows_deleted
+            );
+            if let Some(err) = report.error {
+                bail!(err);
+            }
+            return Ok(());
+        }
+        Command::Report {
+            db_url,
+            command:
+                ReportCommand::Pnl {
+                    run,
+                    since,
+                    until,
+                    format,
+                },
+        } => {
+            let store = Store::open_reader(&db_url).await?;
+            let pnl = store
+                .pnl_report(&PnlQuery {
+                    run_id: run,
+                    since_ms: since,
+                    until_ms: until,
+                })
+                .await?;
+            print!("{}", report::render(&pnl, format)?);
+            return Ok(());
+        }
+        Command::Export {
+            db_url,
+            run,
+            dir,
+            verify,
+        } => {
+            if verify {
+                let problems = verify_dataset(&dir, &run)?;
+                for problem in &problems {
+                    println!("{problem}");
+                }
+                if !problems.is_empty() {
+                    bail!("{} exported files failed verification", problems.len());
+                }
+                println!("dataset ok");
+                return Ok(());
+            }
+            let store = Store::open_reader(&db_url).await?;
+            let config = DatasetConfig {
+                dir,
+                ..DatasetConfig::default()
+            };
+            let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+            let manifest = store.export_dataset(&run, &config, now_ms).await?;
+            for file in manifest.tables.iter().flat_map(|t| &t.files) {
+                println!("{} rows={} sha256={}", file.path, file.rows, file.sha256);
+            }
+            println!(
+                "exported {} rows in {} files",
+                manifest.tables.iter().map(|t| t.rows).sum::<u64>(),
+                manifest.tables.iter().map(|t| t.files.len()).sum::<usize>()
+            );
+            return Ok(());
+        }
+        Command::Db {
+            command:
+                DbCommand::MigrateTo {
+                    from,
+                    to,
+                    batch_size,
+                },
+        } => {
+            if from == to {
+                bail!("--from and --to are the same database");
+            }
+            let source = Store::connect(&from).await?;
+            let target = Store::connect(&to).await?;
+            let report = source.copy_to(&target, &CopyConfig { batch_size }).await?;
+            for table in &report.tables {
+                println!(
+                    "{} rows={} checksum={}",
+                    table.table, table.rows, table.checksum
+                );
+            }
+            println!(
+                "copied and verified {} tables in {:.1}s",
+                report.tables.len(),
+                report.elapsed.as_secs_f64()
+            );
+            return Ok(());
+        }
     };
 
     let resp = send_request(&cli.socket, &req).await?;
@@ -0,0 +1,184 @@
+use std::fmt::Write;
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, NaiveDate};
+use clap::ValueEnum;
+use storage::{PnlBreakdown, PnlReport};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Table,
+    Csv,
+    Json,
+}
+
+/// `--since`/`--until`: a UTC date, an RFC 3339 timestamp or epoch ms.
+pub fn parse_time(s: &str) -> Result<i64> {
+    if let Ok(ms) = s.parse::<i64>() {
+        return Ok(ms);
+    }
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Ok(t.timestamp_millis());
+    }
+    if let Ok(day) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(day
+            .and_hms_opt(0, 0, 0)
+            .unwrap_or_default()
+            .and_utc()
+            .timestamp_millis());
+    }
+    bail!("expected YYYY-MM-DD, an RFC 3339 timestamp or epoch ms, got {s}")
+}
+
+pub fn render(report: &PnlReport, format: Format) -> Result<String> {
+    Ok(match format {
+        Format::Table => table(report),
+        Format::Csv => csv(report),
+        Format::Json => serde_json::to_string_pretty(report)? + "\n",
+    })
+}
+
+fn opt(v: Option<f64>) -> String {
+    v.map(|v| format!("{v:.4}")).unwrap_or_else(|| "-".into())
+}
+
+/// Left-aligned columns padded to their widest cell.
+fn columns(out: &mut String, header: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.len());
+        }
+    }
+    let header: Vec<String> = header.iter().map(|h| h.to_string()).collect();
+    for row in std::iter::once(&header).chain(rows) {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, w)| format!("{cell:<w$}"))
+            .collect();
+        let _ = writeln!(out, "{}", line.join("  ").trim_end());
+    }
+}
+
+const BREAKDOWN_HEADER: [&str; 10] = [
+    "key",
+    "pnl",
+    "realized",
+    "mtm",
+    "fees",
+    "fee_drag",
+    "entries",
+    "wins/losses",
+    "hit_rate",
+    "dd_contrib",
+];
+
+fn breakdown_rows(groups: &[PnlBreakdown]) -> Vec<Vec<String>> {
+    groups
+        .iter()
+        .map(|g| {
+            vec![
+                g.key.clone(),
+                format!("{:+.4}", g.pnl_usd),
+                format!("{:+.4}", g.realized_usd),
+                format!("{:+.4}", g.mtm_usd),
+                format!("{:.4}", g.fees_usd),
+                opt(g.fee_drag),
+                g.entries.to_string(),
+                format!("{}/{}", g.wins, g.losses),
+                opt(g.hit_rate),
+                format!("{:+.4}", g.drawdown_contribution_usd),
+            ]
+        })
+        .collect()
+}
+
+fn sections(report: &PnlReport) -> [(&'static str, &[PnlBreakdown]); 4] {
+    [
+        ("strategy", &report.by_strategy),
+        ("market", &report.by_market),
+        ("tier", &report.by_tier),
+        ("tag", &report.by_tag),
+    ]
+}
+
+fn table(report: &PnlReport) -> String {
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "pnl={:+.4} fees={:.4} max_drawdown={:.4}",
+        report.pnl_usd, report.fees_usd, report.max_drawdown_usd
+    );
+    if let (Some(start), Some(end)) = (report.drawdown_start_ms, report.drawdown_end_ms) {
+        let _ = write!(out, " ({start}..{end})");
+    }
+    let _ = writeln!(out);
+
+    let _ = writeln!(out, "\nby day");
+    let daily: Vec<Vec<String>> = report
+        .daily
+        .iter()
+        .map(|d| {
+            vec![
+                d.day.clone(),
+                d.strategy.clone(),
+                format!("{:+.4}", d.pnl_usd),
+                format!("{:.4}", d.fees_usd),
+            ]
+        })
+        .collect();
+    columns(&mut out, &["day", "strategy", "pnl", "fees"], &daily);
+    for (name, groups) in sections(report) {
+        let _ = writeln!(out, "\nby {name}");
+        columns(&mut out, &BREAKDOWN_HEADER, &breakdown_rows(groups));
+    }
+    out
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// One row per day/strategy and per breakdown group, told apart by the
+/// `section` column.
+fn csv(report: &PnlReport) -> String {
+    let num = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+    let mut out = String::from(
+        "section,day,key,pnl_usd,realized_usd,mtm_usd,fees_usd,fee_drag,entries,wins,losses,hit_rate,drawdown_contribution_usd\n",
+    );
+    for d in &report.daily {
+        let _ = writeln!(
+            out,
+            "daily,{},{},{},,,{},,,,,,",
+            d.day,
+            csv_field(&d.strategy),
+            d.pnl_usd,
+            d.fees_usd
+        );
+    }
+    for (name, groups) in sections(report) {
+        for g in groups {
+            let _ = writeln!(
+                out,
+                "{name},,{},{},{},{},{},{},{},{},{},{},{}",
+                csv_field(&g.key),
+                g.pnl_usd,
+                g.realized_usd,
+                g.mtm_usd,
+                g.fees_usd,
+                num(g.fee_drag),
+                g.entries,
+                g.wins,
+                g.losses,
+                num(g.hit_rate),
+                g.drawdown_contribution_usd
+            );
+        }
+    }
+    out
+}
@@ -0,0 +1,133 @@
+use std::fmt::Write;
+
+use storage::{OrderTrace, TradeTrace};
+
+fn px(v: Option<f64>) -> String {
+    v.map(|v| format!("{v:.4}")).unwrap_or_else(|| "-".into())
+}
+
+fn opt(v: &Option<String>) -> &str {
+    v.as_deref().unwrap_or("-")
+}
+
+/// Offset of `ts` from `base`, e.g. `+12ms`.
+fn after(base: i64, ts: Option<i64>) -> String {
+    ts.map(|ts| format!("+{}ms", ts - base))
+        .unwrap_or_else(|| "-".into())
+}
+
+/// Human-readable rendering of a trace, one line per row, children
+/// indented under their parent.
+pub fn render(trace: &TradeTrace) -> String {
+    let mut out = String::new();
+    match (&trace.snapshot, trace.snapshot_id) {
+        (Some(s), Some(id)) => {
+            let _ = writeln!(
+                out,
+                "snapshot {id}  ts={} market={} bid={}x{} ask={}x{} can_trade={} halt={} toxicity={:.3} crowding={:.3}",
+                s.ts_ms,
+                s.market_id,
+                px(s.best_bid_px),
+                px(s.best_bid_qty),
+                px(s.best_ask_px),
+                px(s.best_ask_qty),
+                s.can_trade,
+                s.drawdown_halt,
+                s.toxicity_score,
+                s.crowding_score,
+            );
+            let _ = writeln!(out, "  features {}", s.features_json);
+        }
+        (None, Some(id)) => {
+            let _ = writeln!(out, "snapshot {id}  (missing)");
+        }
+        _ => {}
+    }
+    if let (Some(i), Some(id)) = (&trace.intent, trace.intent_id) {
+        let _ = writeln!(
+            out,
+            "intent {id}  ts={} strategy={} {} {} size={} px={} urgency={} ttl={}ms ev={:.4} conf={:.2} risk={:.4} tags={}",
+            i.ts_ms,
+            i.strategy,
+            i.intent_kind,
+            opt(&i.side),
+            px(i.size),
+            px(i.price),
+            i.urgency,
+            i.ttl_ms,
+            i.expected_value,
+            i.confidence,
+            i.risk_cost,
+            i.tags_json,
+        );
+        if let Some(rationale) = &i.rationale_json {
+            let _ = writeln!(out, "  rationale {rationale}");
+        }
+    } else {
+        let _ = writeln!(out, "intent -  (order placed outside the intent pipeline)");
+    }
+    for a in &trace.approvals {
+        let _ = writeln!(
+            out,
+            "  {}  ts={} owner={} reason={}",
+            if a.approved { "approved" } else { "rejected" },
+            a.ts_ms,
+            opt(&a.owner_strategy),
+            opt(&a.reason),
+        );
+    }
+    if trace.orders.is_empty() {
+        let _ = writeln!(out, "  no orders");
+    }
+    for order in &trace.orders {
+        render_order(&mut out, order);
+    }
+    out
+}
+
+fn render_order(out: &mut String, t: &OrderTrace) {
+    let o = &t.order;
+    let _ = writeln!(
+        out,
+        "  order {}  venue_id={} ts={} {} {}@{:.4} status={} ack={} final={}",
+        o.client_order_id,
+        opt(&o.order_id),
+        o.ts_submitted_ms,
+        o.side,
+        o.qty,
+        o.limit_price,
+        o.status,
+        after(o.ts_submitted_ms, o.ts_acked_ms),
+        after(o.ts_submitted_ms, o.ts_final_ms),
+    );
+    if let Some(notes) = &o.notes {
+        let _ = writeln!(out, "    notes {notes}");
+    }
+    let mut filled = 0.0;
+    for f in &t.fills {
+        filled += f.qty;
+        let _ = writeln!(
+            out,
+            "    fill {}  ts={} {}@{:.4} fee={} {}",
+            opt(&f.fill_id),
+            f.ts_ms,
+            f.qty,
+            f.price,
+            px(f.fee_usd),
+            opt(&f.liquidity),
+        );
+    }
+    if !t.fills.is_empty() {
+        let _ = writeln!(out, "    filled {filled} of {}", o.qty);
+    }
+    for p in &t.pnl {
+        let _ = writeln!(
+            out,
+            "    pnl {}  ts={} {:+.4} ref={}",
+            p.kind,
+            p.ts_ms,
+            p.pnl_usd,
+            opt(&p.reference),
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use std::sync::Arc;
+
+use admin_ipc::{AdminRequest, AdminResponse, AdminStatus, BackupOutcome, KillSwitchOutcome};
+use execution::{
+    kill_switch, KillSwitchConfig, KillSwitchReport, OrderManager, ResilientVenue, Venue,
+    KILL_SWITCH_INCIDENT,
+};
+use risk::RiskGate;
+use storage::{BackupConfig, BackupReport, Store, BACKUP_INCIDENT};
+use tracing::warn;
+
+/// Shared state behind the admin socket.
+#[derive(Clone)]
+pub(crate) struct AdminContext<V> {
+    pub run_id: String,
+    pub gate: RiskGate,
+    pub store: Store,
+    pub orders: Arc<OrderManager<ResilientVenue<V>>>,
+    pub kill_switch: KillSwitchConfig,
+    pub backup: BackupConfig,
+}
+
+impl<V: Venue + Clone + 'static> AdminContext<V> {
+    pub async fn handle(&self, req: AdminRequest) -> anyhow::Result<AdminResponse> {
+        match req {
+            AdminRequest::Status => Ok(AdminResponse::Status(AdminStatus {
+                run_id: self.run_id.clone(),
+                risk_state: format!("{:?}", self.gate.status()),
+            })),
+            AdminRequest::Pause => {
+                self.gate.pause();
+                Ok(AdminResponse::Ack)
+            }
+            AdminRequest::Resume => {
+                self.gate.resume();
+                Ok(AdminResponse::Ack)
+            }
+            AdminRequest::KillSwitch { flatten } => {
+                let config = KillSwitchConfig {
+                    flatten,
+                    ..self.kill_switch.clone()
+                };
+                // straight to the venue: an open breaker must not stop it
+                let report = kill_switch::engage(
+                    &self.orders,
+                    self.orders.venue().inner(),
+                    &self.gate,
+                    &config,
+                    "operator kill switch",
+                )
+                .await;
+                self.record_kill_switch(&report).await;
+                Ok(AdminResponse::KillSwitch(kill_switch_outcome(&report)))
+            }
+            AdminRequest::ResetKillSwitch => {
+                self.gate.reset_kill_switch();
+                Ok(AdminResponse::Ack)
+            }
+            AdminRequest::Backup => {
+                match self.store.backup(&self.backup, crate::epoch_ms()).await {
+                    Ok(report) => Ok(AdminResponse::Backup(backup_outcome(&report))),
+                    Err(err) => {
+                        let message = format!("{err:#}");
+                        if let Err(err) = self
+                            .store
+                            .log_incident(&self.run_id, "warning", BACKUP_INCIDENT, &message)
+                            .await
+                        {
+                            warn!(error = ?err, "failed to record backup incident");
+                        }
+                        Ok(AdminResponse::Error(message))
+                    }
+                }
+            }
+        }
+    }
+
+    async fn record_kill_switch(&self, report: &KillSwitchReport) {
+        let severity = if report.confirmed {
+            "warning"
+        } else {
+            "critical"
+        };
+        if let Err(err) = self
+            .store
+            .log_incident(
+                &self.run_id,
+                severity,
+                KILL_SWITCH_INCIDENT,
+                &report.summary(),
+            )
+            .await
+        {
+            warn!(error = ?err, "failed to record kill switch incident");
+        }
+    }
+}
+
+fn kill_switch_outcome(report: &KillSwitchReport) -> KillSwitchOutcome {
+    KillSwitchOutcome {
+        confirmed: report.confirmed,
+        attempts: report.attempts,
+        flatten_orders: report.flatten_orders,
+        residual_open_orders: report.residual_open_orders,
+        residual_positions: report.residual_positions.len(),
+        errors: report.errors.clone(),
+    }
+}
+
+fn backup_outcome(report: &BackupReport) -> BackupOutcome {
+    BackupOutcome {
+        path: report.path.display().to_string(),
+        bytes: report.bytes,
+        elapsed_ms: report.elapsed.as_millis() as u64,
+        rotated: report
+            .rotated
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use execution::{BookLevel, BreakerConfig, MockVenue, RetryPolicy};
+    use risk::RiskState;
+    use strategies::Token;
+
+    #[tokio::test]
+    async fn kill_switch_cancels_flattens_and_reports() -> anyhow::Result<()> {
+        let store = storage::init_sqlite("sqlite::memory:").await?;
+        store.insert_run("run-ks", None).await?;
+
+        let venue = MockVenue::new();
+        venue.set_position(7, Token::Yes, 10.0);
+        venue.set_book(
+            7,
+            Token::Yes,
+            Some(BookLevel {
+                price: 0.42,
+                qty: 100.0,
+            }),
+            None,
+        );
+        venue.fail_next("cancel_all", 1);
+
+        let (resilient, _events) = ResilientVenue::new(
+            venue.clone(),
+            RetryPolicy::default(),
+            BreakerConfig::default(),
+        );
+        let gate = RiskGate::new();
+        let orders = OrderManager::new(resilient, "polymarket", "run-ks", store.clone())
+            .with_risk_gate(gate.clone());
+        let ctx = AdminContext {
+            run_id: "run-ks".into(),
+            gate,
+            store,
+            orders: Arc::new(orders),
+            kill_switch: KillSwitchConfig {
+                retry_delay: std::time::Duration::from_millis(1),
+                ..KillSwitchConfig::default()
+            },
+            backup: BackupConfig::default(),
+        };
+
+        let resp = ctx
+            .handle(AdminRequest::KillSwitch { flatten: true })
+            .await?;
+        let AdminResponse::KillSwitch(outcome) = resp else {
+            panic!("expected kill switch outcome");
+        };
+        assert!(outcome.confirmed);
+        assert_eq!(outcome.residual_positions, 0);
+        assert_eq!(ctx.gate.status(), RiskState::KillSwitched);
+
+        let placed = venue.placed();
+        assert_eq!(placed.len(), 1);
+        assert!(placed[0].price >= 0.37 - 1e-9);
+        let row = ctx.store.fetch_order(&placed[0].client_order_id).await?;
+        assert_eq!(row.unwrap().strategy, "kill_switch");
+
+        ctx.handle(AdminRequest::ResetKillSwitch).await?;
+        assert_eq!(ctx.gate.status(), RiskState::Paused);
+        Ok(())
+    }
+}
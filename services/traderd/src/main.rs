@@ -1,17 +1,40 @@
 #[cfg(test)]
 use std::sync::{Mutex, OnceLock};
-use std::{env, fs, future, net::SocketAddr, path::PathBuf, time::Duration};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, future, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 
-use admin_ipc::{run_server, AdminRequest, AdminResponse, DEFAULT_SOCKET_PATH};
-use anyhow::bail;
-use clap::Parser;
+use admin_ipc::{run_server_async, DEFAULT_SOCKET_PATH};
+use anyhow::{bail, Context};
+use clap::{ArgAction, Parser};
+use execution::{
+    BookFeed, BookFeedConfig, BreakerConfig, BreakerEvent, BreakerState, ExecutionBackend,
+    ExecutionMode, ExpiryConfig, ExpiryTimer, KillSwitchConfig, OrderManager, OrphanPolicy,
+    PolymarketVenue, ReconcileConfig, Reconciler, ResilientVenue, RetryPolicy, SelfTradePolicy,
+    UserUpdate, VENUE_BREAKER_INCIDENT,
+};
 use metrics::MetricsHandle;
-use risk::RiskGate;
-use storage::{DatabaseBackend, Store};
+use risk::{
+    DrawdownLimits, PriceBandLimits, PriceBands, QuoteGovernor, QuoteRateEpisode, QuoteRateLimits,
+    RiskGate, RiskScope, RiskTransition, StaleEpisode, StalenessGuard, StalenessLimits,
+    QUOTE_RATE_INCIDENT, RISK_HALT_INCIDENT, STALE_STATE_INCIDENT,
+};
+use storage::{
+    BackupConfig, BackupJob, DatabaseBackend, RetentionConfig, RetentionJob, RollupConfig,
+    RollupJob, Store, StoreOptions, StoreWriter, Synchronous, WriterConfig, BACKUP_INCIDENT,
+    RETENTION_INCIDENT, ROLLUP_INCIDENT,
+};
 use tokio::task;
 use tokio::time;
 use tracing::{info, warn, Level};
 use uuid::Uuid;
+use venue_polymarket::{
+    ApiCredentials, BookClient, ClobClient, OrderSigner, TokenMap, CTF_EXCHANGE, DEFAULT_CLOB_URL,
+    POLYGON_CHAIN_ID,
+};
+
+mod admin;
+
+use admin::AdminContext;
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -23,12 +46,403 @@ struct Args {
     )]
     db_url: String,
 
+    /// Read-write database connections.
+    #[arg(long, env = "DB_MAX_CONNECTIONS", default_value_t = 5)]
+    db_max_connections: u32,
+
+    /// Read-only connections for analytics queries (SQLite; 0 shares the
+    /// read-write pool).
+    #[arg(long, env = "DB_READ_CONNECTIONS", default_value_t = 4)]
+    db_read_connections: u32,
+
+    /// SQLite write-ahead logging.
+    #[arg(long, env = "SQLITE_WAL", default_value_t = true, action = ArgAction::Set)]
+    sqlite_wal: bool,
+
+    /// SQLite `PRAGMA synchronous`: off, normal, full or extra.
+    #[arg(long, env = "SQLITE_SYNCHRONOUS", default_value_t = Synchronous::Normal)]
+    sqlite_synchronous: Synchronous,
+
+    /// How long a SQLite connection waits on a lock before failing.
+    #[arg(long, env = "SQLITE_BUSY_TIMEOUT_MS", default_value_t = 5_000)]
+    sqlite_busy_timeout_ms: u64,
+
+    /// Enforce the schema's foreign keys on SQLite.
+    #[arg(long, env = "SQLITE_FOREIGN_KEYS", default_value_t = true, action = ArgAction::Set)]
+    sqlite_foreign_keys: bool,
+
     #[arg(long, env = "ADMIN_SOCKET", default_value = DEFAULT_SOCKET_PATH)]
     admin_socket: String,
 
     #[arg(long, env = "METRICS_ADDR", default_value = "127.0.0.1:9109")]
     metrics_addr: SocketAddr,
+
+    #[arg(long, env = "MAX_DRAWDOWN_USD", default_value_t = 0.0)]
+    max_drawdown_usd: f64,
+
+    #[arg(long, env = "MAX_DRAWDOWN_PCT", default_value_t = 0.0)]
+    max_drawdown_pct: f64,
+
+    #[arg(long, env = "MAX_DAILY_LOSS_USD", default_value_t = 0.0)]
+    max_daily_loss_usd: f64,
+
+    #[arg(long, env = "MAX_STRATEGY_LOSS_USD", default_value_t = 0.0)]
+    max_strategy_loss_usd: f64,
+
+    /// Account equity at startup; the run's PnL is added to it for the
+    /// drawdown limits.
+    #[arg(long, env = "STARTING_EQUITY_USD", default_value_t = 0.0)]
+    starting_equity_usd: f64,
+
+    /// Seconds between PnL samples fed to the risk gate; 0 disables the
+    /// drawdown and loss limits.
+    #[arg(long, env = "PNL_SAMPLE_SECS", default_value_t = 5)]
+    pnl_sample_secs: u64,
+
+    #[arg(long, env = "MAX_BOOK_AGE_MS", default_value_t = 2_000)]
+    max_book_age_ms: i64,
+
+    #[arg(long, env = "MAX_FEED_AGE_MS", default_value_t = 5_000)]
+    max_feed_age_ms: i64,
+
+    #[arg(long, env = "MAX_SNAPSHOT_AGE_MS", default_value_t = 500)]
+    max_snapshot_age_ms: i64,
+
+    #[arg(long, env = "MAX_CLOCK_SKEW_MS", default_value_t = 1_000)]
+    max_clock_skew_ms: i64,
+
+    /// Milliseconds between polls of the CLOB book of every MARKET_MAP
+    /// market; 0 disables the poller (and with it every placement while
+    /// MAX_BOOK_AGE_MS is set).
+    #[arg(long, env = "BOOK_POLL_MS", default_value_t = 500)]
+    book_poll_ms: u64,
+
+    /// Check every placement against the top of book before it is sent.
+    #[arg(long, env = "PRICE_BANDS", default_value_t = true, action = ArgAction::Set)]
+    price_bands: bool,
+
+    /// Max distance of a limit price from the mid, in ticks; 0 disables.
+    #[arg(long, env = "MAX_TICKS_FROM_REFERENCE", default_value_t = 10.0)]
+    max_ticks_from_reference: f64,
+
+    /// Max ticks a marketable order may price through the touch; 0 disables.
+    #[arg(long, env = "MAX_TAKER_SLIPPAGE_TICKS", default_value_t = 2.0)]
+    max_taker_slippage_ticks: f64,
+
+    /// Max order size as a multiple of the visible touch size; 0 disables.
+    #[arg(long, env = "MAX_SIZE_TO_DEPTH", default_value_t = 1.0)]
+    max_size_to_depth: f64,
+
+    /// JSON file of per-strategy and per-market band overrides
+    /// ({"strategies": {"mm": {...}}, "markets": {"12": {...}}}).
+    #[arg(long, env = "PRICE_BAND_OVERRIDES")]
+    price_band_overrides: Option<PathBuf>,
+
+    /// Order messages per second allowed per market; 0 disables.
+    #[arg(long, env = "MAX_MARKET_MSGS_PER_SEC", default_value_t = 5.0)]
+    max_market_msgs_per_sec: f64,
+
+    /// Order messages per second allowed per strategy; 0 disables.
+    #[arg(long, env = "MAX_STRATEGY_MSGS_PER_SEC", default_value_t = 20.0)]
+    max_strategy_msgs_per_sec: f64,
+
+    /// Order messages per fill over the ratio window; 0 disables.
+    #[arg(long, env = "MAX_ORDER_TO_TRADE", default_value_t = 50.0)]
+    max_order_to_trade: f64,
+
+    /// Rows the storage writer buffers before producers wait.
+    #[arg(long, env = "STORAGE_QUEUE_CAPACITY", default_value_t = 10_000)]
+    storage_queue_capacity: usize,
+
+    /// Rows per storage transaction.
+    #[arg(long, env = "STORAGE_BATCH_SIZE", default_value_t = 500)]
+    storage_batch_size: usize,
+
+    /// Longest a row waits in the storage writer before it is flushed.
+    #[arg(long, env = "STORAGE_FLUSH_MS", default_value_t = 100)]
+    storage_flush_ms: u64,
+
+    /// Days of raw_events kept in the database before they are archived
+    /// and deleted (0 keeps everything).
+    #[arg(long, env = "RAW_EVENTS_KEEP_DAYS", default_value_t = 14)]
+    raw_events_keep_days: u32,
+
+    /// Where archived raw_events and their manifest are written.
+    #[arg(long, env = "ARCHIVE_DIR", default_value = "archive")]
+    archive_dir: PathBuf,
+
+    #[arg(long, env = "RETENTION_INTERVAL_SECS", default_value_t = 3_600)]
+    retention_interval_secs: u64,
+
+    /// Where database backups are written.
+    #[arg(long, env = "BACKUP_DIR", default_value = "backups")]
+    backup_dir: PathBuf,
+
+    /// Backups kept after rotation (0 keeps all).
+    #[arg(long, env = "BACKUP_KEEP", default_value_t = 7)]
+    backup_keep: usize,
+
+    /// Seconds between scheduled backups; 0 only backs up on request.
+    #[arg(long, env = "BACKUP_INTERVAL_SECS", default_value_t = 86_400)]
+    backup_interval_secs: u64,
+
+    /// Seconds between passes folding new fills into order_rollups; 0
+    /// disables them.
+    #[arg(long, env = "ROLLUP_INTERVAL_SECS", default_value_t = 60)]
+    rollup_interval_secs: u64,
+
+    /// Worst price past the touch accepted when the kill switch flattens.
+    #[arg(long, env = "KILL_SWITCH_MAX_SLIPPAGE", default_value_t = 0.05)]
+    kill_switch_max_slippage: f64,
+
+    /// Seconds between venue reconciliation passes; 0 only reconciles on boot.
+    #[arg(long, env = "RECONCILE_INTERVAL_SECS", default_value_t = 30)]
+    reconcile_interval_secs: u64,
+
+    /// Adopt unknown open venue orders instead of canceling them.
+    #[arg(long, env = "RECONCILE_ADOPT_ORPHANS", default_value_t = false)]
+    reconcile_adopt_orphans: bool,
+
+    /// Reconcile passes in a row a position must drift on before it forces
+    /// ReduceOnly; 0 or 1 acts on the first.
+    #[arg(long, env = "RECONCILE_DRIFT_PASSES", default_value_t = 2)]
+    reconcile_drift_passes: u32,
+
+    /// Cancel our resting orders that an outgoing order would cross instead
+    /// of rejecting the outgoing order.
+    #[arg(long, env = "SELF_TRADE_CANCEL_RESTING", default_value_t = false)]
+    self_trade_cancel_resting: bool,
+
+    /// Attempts per venue call, including the first. Placements are never
+    /// retried.
+    #[arg(long, env = "VENUE_RETRY_ATTEMPTS", default_value_t = 3)]
+    venue_retry_attempts: u32,
+
+    /// Open a venue endpoint's breaker after this many errors in a row.
+    #[arg(long, env = "BREAKER_CONSECUTIVE_ERRORS", default_value_t = 5)]
+    breaker_consecutive_errors: u32,
+
+    /// Open a breaker when this fraction of calls in the last minute failed.
+    #[arg(long, env = "BREAKER_ERROR_RATE", default_value_t = 0.5)]
+    breaker_error_rate: f64,
+
+    /// Seconds an open breaker waits before letting a probe call through.
+    #[arg(long, env = "BREAKER_OPEN_SECS", default_value_t = 30)]
+    breaker_open_secs: u64,
+
+    /// `paper` simulates fills against the live book; `live` signs and
+    /// submits real orders.
+    #[arg(long, env = "EXECUTION_MODE", default_value_t = ExecutionMode::Paper)]
+    execution_mode: ExecutionMode,
+
+    #[arg(long, env = "CLOB_URL", default_value = DEFAULT_CLOB_URL)]
+    clob_url: String,
+
+    /// Only read in live mode.
+    #[arg(long, env = "POLY_PRIVATE_KEY", hide_env_values = true)]
+    poly_private_key: Option<String>,
+
+    #[arg(long, env = "POLY_API_KEY", hide_env_values = true)]
+    poly_api_key: Option<String>,
+
+    #[arg(long, env = "POLY_API_SECRET", hide_env_values = true)]
+    poly_api_secret: Option<String>,
+
+    #[arg(long, env = "POLY_API_PASSPHRASE", hide_env_values = true)]
+    poly_api_passphrase: Option<String>,
+
+    /// JSON map of market id to CLOB token ids ({"12": {"yes": "...", "no": "..."}}).
+    #[arg(long, env = "MARKET_MAP")]
+    market_map: Option<PathBuf>,
+}
+
+impl Args {
+    fn drawdown_limits(&self) -> DrawdownLimits {
+        DrawdownLimits {
+            max_drawdown_usd: self.max_drawdown_usd,
+            max_drawdown_pct: self.max_drawdown_pct,
+            max_daily_loss_usd: self.max_daily_loss_usd,
+            max_strategy_loss_usd: self.max_strategy_loss_usd,
+            ..DrawdownLimits::default()
+        }
+    }
+
+    fn staleness_limits(&self) -> StalenessLimits {
+        StalenessLimits {
+            max_book_age_ms: self.max_book_age_ms,
+            max_feed_age_ms: self.max_feed_age_ms,
+            max_snapshot_age_ms: self.max_snapshot_age_ms,
+            max_clock_skew_ms: self.max_clock_skew_ms,
+            ..StalenessLimits::default()
+        }
+    }
+
+    fn book_feed_config(&self) -> BookFeedConfig {
+        BookFeedConfig {
+            interval: Duration::from_millis(self.book_poll_ms),
+        }
+    }
+
+    fn price_bands(&self) -> anyhow::Result<PriceBands> {
+        let bands = PriceBands::new(PriceBandLimits {
+            max_ticks_from_reference: self.max_ticks_from_reference,
+            max_taker_slippage_ticks: self.max_taker_slippage_ticks,
+            max_size_to_depth: self.max_size_to_depth,
+            ..PriceBandLimits::default()
+        });
+        match &self.price_band_overrides {
+            Some(path) => {
+                let json = std::fs::read_to_string(path)
+                    .with_context(|| format!("reading {}", path.display()))?;
+                bands
+                    .with_overrides_json(&json)
+                    .with_context(|| format!("parsing {}", path.display()))
+            }
+            None => Ok(bands),
+        }
+    }
+
+    fn quote_rate_limits(&self) -> QuoteRateLimits {
+        QuoteRateLimits {
+            max_market_msgs_per_sec: self.max_market_msgs_per_sec,
+            max_strategy_msgs_per_sec: self.max_strategy_msgs_per_sec,
+            max_order_to_trade: self.max_order_to_trade,
+            ..QuoteRateLimits::default()
+        }
+    }
+
+    fn store_options(&self) -> StoreOptions {
+        StoreOptions {
+            max_connections: self.db_max_connections,
+            read_connections: self.db_read_connections,
+            sqlite_wal: self.sqlite_wal,
+            sqlite_synchronous: self.sqlite_synchronous,
+            sqlite_busy_timeout: Duration::from_millis(self.sqlite_busy_timeout_ms),
+            sqlite_foreign_keys: self.sqlite_foreign_keys,
+        }
+    }
+
+    fn writer_config(&self) -> WriterConfig {
+        WriterConfig {
+            capacity: self.storage_queue_capacity,
+            batch_size: self.storage_batch_size,
+            flush_interval: Duration::from_millis(self.storage_flush_ms),
+            ..WriterConfig::default()
+        }
+    }
+
+    fn retention_config(&self) -> RetentionConfig {
+        RetentionConfig {
+            keep_days: self.raw_events_keep_days,
+            archive_dir: self.archive_dir.clone(),
+            interval: Duration::from_secs(self.retention_interval_secs),
+            ..RetentionConfig::default()
+        }
+    }
+
+    fn backup_config(&self) -> BackupConfig {
+        BackupConfig {
+            dir: self.backup_dir.clone(),
+            keep: self.backup_keep,
+            interval: Duration::from_secs(self.backup_interval_secs),
+            ..BackupConfig::default()
+        }
+    }
+
+    fn rollup_config(&self) -> RollupConfig {
+        RollupConfig {
+            interval: Duration::from_secs(self.rollup_interval_secs),
+            ..RollupConfig::default()
+        }
+    }
+
+    fn kill_switch_config(&self) -> KillSwitchConfig {
+        KillSwitchConfig {
+            max_slippage: self.kill_switch_max_slippage,
+            ..KillSwitchConfig::default()
+        }
+    }
+
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.venue_retry_attempts.max(1),
+            ..RetryPolicy::default()
+        }
+    }
+
+    fn breaker_config(&self) -> BreakerConfig {
+        BreakerConfig {
+            consecutive_errors: self.breaker_consecutive_errors,
+            error_rate: self.breaker_error_rate,
+            open_for: Duration::from_secs(self.breaker_open_secs),
+            ..BreakerConfig::default()
+        }
+    }
+
+    fn self_trade_policy(&self) -> SelfTradePolicy {
+        if self.self_trade_cancel_resting {
+            SelfTradePolicy::CancelResting
+        } else {
+            SelfTradePolicy::Block
+        }
+    }
+
+    fn reconcile_config(&self) -> ReconcileConfig {
+        ReconcileConfig {
+            interval: Duration::from_secs(self.reconcile_interval_secs),
+            orphan_policy: if self.reconcile_adopt_orphans {
+                OrphanPolicy::Adopt
+            } else {
+                OrphanPolicy::Cancel
+            },
+            drift_passes: self.reconcile_drift_passes,
+            ..ReconcileConfig::default()
+        }
+    }
+}
+
+type UserUpdates = tokio::sync::mpsc::UnboundedReceiver<UserUpdate>;
+
+/// Build the execution backend for `args.execution_mode`. Credentials are
+/// only read in live mode; a paper backend never sees the key.
+fn build_backend(args: &Args) -> anyhow::Result<(ExecutionBackend, Option<UserUpdates>)> {
+    match args.execution_mode {
+        ExecutionMode::Paper => {
+            if args.poly_private_key.is_some() {
+                warn!("POLY_PRIVATE_KEY is set but ignored in paper mode");
+            }
+            let (backend, updates) = ExecutionBackend::paper();
+            Ok((backend, Some(updates)))
+        }
+        ExecutionMode::Live => {
+            let (Some(key), Some(api_key), Some(secret), Some(passphrase)) = (
+                args.poly_private_key.as_deref(),
+                args.poly_api_key.clone(),
+                args.poly_api_secret.clone(),
+                args.poly_api_passphrase.clone(),
+            ) else {
+                bail!("live mode requires POLY_PRIVATE_KEY, POLY_API_KEY, POLY_API_SECRET and POLY_API_PASSPHRASE");
+            };
+            let Some(market_map) = &args.market_map else {
+                bail!("live mode requires MARKET_MAP");
+            };
+            let signer = OrderSigner::from_hex(key, POLYGON_CHAIN_ID, CTF_EXCHANGE)?;
+            info!(address = %signer.address(), "live order signer loaded");
+            let creds = ApiCredentials {
+                api_key,
+                secret,
+                passphrase,
+            };
+            let client = ClobClient::new(&args.clob_url, creds, signer)?;
+            let tokens = TokenMap::load(market_map)?;
+            Ok((
+                ExecutionBackend::live(PolymarketVenue::new(client, tokens)),
+                None,
+            ))
+        }
+    }
 }
+
 fn log_startup(args: &Args, backend: DatabaseBackend, run_id: &str) {
     info!(
         backend = ?backend,
@@ -40,6 +454,59 @@ fn log_startup(args: &Args, backend: DatabaseBackend, run_id: &str) {
     info!(%run_id, "run initialized");
 }
 
+fn risk_transition_message(t: &RiskTransition) -> String {
+    let scope = match &t.scope {
+        RiskScope::Global => "global".to_string(),
+        RiskScope::Strategy(name) => format!("strategy:{name}"),
+    };
+    format!(
+        "{scope} {} -> {}: {}",
+        t.from.as_str(),
+        t.to.as_str(),
+        t.reason
+    )
+}
+
+fn breaker_message(e: &BreakerEvent) -> String {
+    format!(
+        "venue {} breaker {} -> {}: {}",
+        e.endpoint.as_str(),
+        e.from.as_str(),
+        e.to.as_str(),
+        e.reason
+    )
+}
+
+fn stale_episode_message(e: &StaleEpisode) -> String {
+    match (&e.reason, e.duration_ms) {
+        (Some(reason), _) => format!("market {} stale: {reason}", e.market_id),
+        (None, Some(duration_ms)) => {
+            format!("market {} fresh again after {duration_ms}ms", e.market_id)
+        }
+        (None, None) => format!("market {} fresh again", e.market_id),
+    }
+}
+
+fn epoch_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+fn quote_rate_message(e: &QuoteRateEpisode) -> String {
+    match (&e.violation, e.duration_ms) {
+        (Some(violation), _) => format!("quote rate limit breached: {violation}"),
+        (None, Some(duration_ms)) => {
+            format!(
+                "{} back under quote rate limits after {duration_ms}ms",
+                e.scope
+            )
+        }
+        (None, None) => format!("{} back under quote rate limits", e.scope),
+    }
+}
+
 fn parse_sqlite_file_path(db_url: &str) -> anyhow::Result<Option<PathBuf>> {
     const MEMORY_PREFIX: &str = "sqlite::memory:";
     const URL_PREFIX: &str = "sqlite://";
@@ -62,17 +529,17 @@ fn parse_sqlite_file_path(db_url: &str) -> anyhow::Result<Option<PathBuf>> {
     }
 
     // Windows: sqlite:///C:/... becomes "/C:/..." after stripping "sqlite://"
-    // We want "C:/..." (a real absolute path).
+    // We want "C:/..." (a real absolute path). The URL means the same drive
+    // path wherever it is parsed, so this is not Windows-only.
     let mut p = path_part.to_string();
+    let b = p.as_bytes();
+    if b.len() >= 4 && b[0] == b'/' && b[1].is_ascii_alphabetic() && b[2] == b':' {
+        // "/C:/..." -> "C:/..."
+        p.remove(0);
+    }
 
     #[cfg(windows)]
     {
-        let b = p.as_bytes();
-        if b.len() >= 4 && b[0] == b'/' && b[2] == b':' {
-            // "/C:/..." -> "C:/..."
-            p.remove(0);
-        }
-
         // Reject drive-relative "C:foo" because it’s ambiguous and causes pain.
         let b = p.as_bytes();
         if b.len() >= 3 && b[1] == b':' && b[2] != b'\\' && b[2] != b'/' {
@@ -129,7 +596,7 @@ async fn main() -> anyhow::Result<()> {
     );
 
     let run_id = Uuid::new_v4().to_string();
-    let store = Store::connect(&args.db_url).await?;
+    let store = Store::connect_with(&args.db_url, &args.store_options()).await?;
     store.insert_run(&run_id, None).await?;
     log_startup(&args, backend, &run_id);
 
@@ -156,40 +623,256 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
-    let risk_gate = RiskGate::new();
-    let run_id_clone = run_id.clone();
-    let gate_clone = risk_gate.clone();
-    let socket_path = args.admin_socket.clone();
+    let metrics = MetricsHandle::new();
+    let heartbeat_counter = metrics.heartbeat_counter();
+    let metrics_addr = args.metrics_addr;
+    let metrics_task = metrics.clone();
+    task::spawn(async move {
+        if let Err(err) = metrics_task.serve(metrics_addr).await {
+            tracing::error!(error = ?err, "metrics server error");
+        }
+    });
+
+    let (risk_gate, mut risk_transitions) = RiskGate::with_limits(args.drawdown_limits());
+    let risk_state_gauge = metrics.risk_state();
+    let risk_transition_counter = metrics.risk_transitions();
+    let store_risk = store.clone();
+    let run_id_risk = run_id.clone();
+    task::spawn(async move {
+        while let Some(t) = risk_transitions.recv().await {
+            if t.scope == RiskScope::Global {
+                risk_state_gauge.set(t.to.level());
+            }
+            let scope = match &t.scope {
+                RiskScope::Global => "global",
+                RiskScope::Strategy(_) => "strategy",
+            };
+            risk_transition_counter
+                .with_label_values(&[scope, t.to.as_str()])
+                .inc();
+            let message = risk_transition_message(&t);
+            warn!(%message, "risk state transition");
+            if let Err(err) = store_risk
+                .log_incident(&run_id_risk, t.severity(), RISK_HALT_INCIDENT, &message)
+                .await
+            {
+                warn!(error = ?err, "failed to record risk transition incident");
+            }
+        }
+    });
+
+    // Fed by the book poller below; consulted by the order manager.
+    let (staleness_guard, mut stale_episodes) = StalenessGuard::new(args.staleness_limits());
+    let store_stale = store.clone();
+    let run_id_stale = run_id.clone();
+    task::spawn(async move {
+        while let Some(episode) = stale_episodes.recv().await {
+            let severity = if episode.reason.is_some() {
+                "warning"
+            } else {
+                "info"
+            };
+            let message = stale_episode_message(&episode);
+            warn!(%message, "stale state episode");
+            if let Err(err) = store_stale
+                .log_incident(&run_id_stale, severity, STALE_STATE_INCIDENT, &message)
+                .await
+            {
+                warn!(error = ?err, "failed to record stale state incident");
+            }
+        }
+    });
 
+    let (quote_governor, mut quote_episodes) = QuoteGovernor::new(args.quote_rate_limits());
+    let quote_breaches = metrics.quote_rate_breaches();
+    let store_quote = store.clone();
+    let run_id_quote = run_id.clone();
     task::spawn(async move {
-        let handler = move |req: AdminRequest| -> anyhow::Result<AdminResponse> {
-            match req {
-                AdminRequest::Status => Ok(AdminResponse::Status(admin_ipc::AdminStatus {
-                    run_id: run_id_clone.clone(),
-                    risk_state: format!("{:?}", gate_clone.status()),
-                })),
-                AdminRequest::Pause => {
-                    gate_clone.pause();
-                    Ok(AdminResponse::Ack)
+        while let Some(episode) = quote_episodes.recv().await {
+            let severity = match &episode.violation {
+                Some(violation) => {
+                    quote_breaches
+                        .with_label_values(&[episode.scope.kind(), violation.limit_name()])
+                        .inc();
+                    "warning"
                 }
-                AdminRequest::Resume => {
-                    gate_clone.resume();
-                    Ok(AdminResponse::Ack)
+                None => "info",
+            };
+            let message = quote_rate_message(&episode);
+            warn!(%message, "quote rate episode");
+            if let Err(err) = store_quote
+                .log_incident(&run_id_quote, severity, QUOTE_RATE_INCIDENT, &message)
+                .await
+            {
+                warn!(error = ?err, "failed to record quote rate incident");
+            }
+        }
+    });
+    let quote_rate_gauge = metrics.quote_msgs_per_sec();
+    let order_to_trade_gauge = metrics.quote_order_to_trade();
+    let governor_stats = quote_governor.clone();
+    task::spawn(async move {
+        let mut ticker = time::interval(Duration::from_secs(1));
+        loop {
+            ticker.tick().await;
+            // Scopes that went idle drop out of the stats; reset so their
+            // gauges don't hold the last value.
+            quote_rate_gauge.reset();
+            order_to_trade_gauge.reset();
+            for stats in governor_stats.stats(epoch_ms()) {
+                let labels = [stats.scope.kind(), &stats.scope.id()];
+                quote_rate_gauge
+                    .with_label_values(&labels)
+                    .set(stats.msgs_per_sec);
+                order_to_trade_gauge
+                    .with_label_values(&labels)
+                    .set(stats.order_to_trade);
+            }
+        }
+    });
+
+    let (execution_backend, paper_updates) = build_backend(&args)?;
+    info!(
+        mode = %execution_backend.mode(),
+        venue = execution_backend.venue_name(),
+        "execution backend ready"
+    );
+    let venue_name = execution_backend.venue_name();
+    let paper_venue = match &execution_backend {
+        ExecutionBackend::Paper(paper) => Some(paper.clone()),
+        ExecutionBackend::Live(_) => None,
+    };
+    let (venue, mut breaker_events) = ResilientVenue::new(
+        execution_backend,
+        args.retry_policy(),
+        args.breaker_config(),
+    );
+    let breaker_gauge = metrics.venue_breaker_state();
+    let breaker_trips = metrics.venue_breaker_trips();
+    let gate_breaker = risk_gate.clone();
+    let store_breaker = store.clone();
+    let run_id_breaker = run_id.clone();
+    task::spawn(async move {
+        while let Some(event) = breaker_events.recv().await {
+            let endpoint = event.endpoint.as_str();
+            breaker_gauge
+                .with_label_values(&[endpoint])
+                .set(event.to.level());
+            // half-open stays blocked until the probe succeeds
+            let source = format!("breaker:{endpoint}");
+            match event.to {
+                BreakerState::Open => {
+                    breaker_trips.with_label_values(&[endpoint]).inc();
+                    gate_breaker.block_placements(&source);
                 }
+                BreakerState::Closed => gate_breaker.unblock_placements(&source),
+                BreakerState::HalfOpen => {}
+            }
+            let message = breaker_message(&event);
+            warn!(%message, "venue circuit breaker transition");
+            if let Err(err) = store_breaker
+                .log_incident(
+                    &run_id_breaker,
+                    event.severity(),
+                    VENUE_BREAKER_INCIDENT,
+                    &message,
+                )
+                .await
+            {
+                warn!(error = ?err, "failed to record breaker incident");
             }
-        };
-        if let Err(err) = run_server(&socket_path, handler).await {
-            tracing::error!(error = ?err, "admin ipc server failed");
         }
     });
 
-    let metrics = MetricsHandle::new();
-    let heartbeat_counter = metrics.heartbeat_counter();
-    let metrics_addr = args.metrics_addr;
-    let metrics_task = metrics.clone();
+    let mut order_manager = OrderManager::new(venue.clone(), venue_name, &run_id, store.clone())
+        .with_self_trade_policy(args.self_trade_policy())
+        .with_risk_gate(risk_gate.clone())
+        .with_staleness_guard(staleness_guard.clone())
+        .with_quote_governor(quote_governor);
+    if args.price_bands {
+        order_manager = order_manager.with_price_bands(args.price_bands()?);
+    }
+    let order_manager = Arc::new(order_manager);
+    if args.pnl_sample_secs > 0 {
+        let manager = order_manager.clone();
+        let gate_pnl = risk_gate.clone();
+        let starting_equity_usd = args.starting_equity_usd;
+        let interval = Duration::from_secs(args.pnl_sample_secs);
+        task::spawn(async move {
+            let mut ticker = time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let sample = manager.pnl_sample(starting_equity_usd).await;
+                gate_pnl.observe_pnl(&sample);
+            }
+        });
+    }
+    // The live user websocket is not wired yet; live fills reach the order
+    // manager through the reconciler's trade backfill.
+    if let Some(mut updates) = paper_updates {
+        let manager = order_manager.clone();
+        task::spawn(async move {
+            while let Some(update) = updates.recv().await {
+                manager.apply_update(update).await;
+            }
+        });
+    }
+
+    match &args.market_map {
+        Some(market_map) if args.book_poll_ms > 0 => {
+            let mut feed = BookFeed::new(
+                BookClient::new(&args.clob_url)?,
+                TokenMap::load(market_map)?,
+                args.book_feed_config(),
+            )
+            .with_staleness_guard(staleness_guard.clone());
+            if let Some(paper) = paper_venue {
+                feed = feed.with_paper_venue(paper);
+            }
+            task::spawn(async move { feed.run().await });
+        }
+        _ if args.max_book_age_ms > 0 => {
+            warn!("no book feed (MARKET_MAP unset or BOOK_POLL_MS=0); placements will be rejected as stale and paper orders never fill")
+        }
+        _ => {}
+    }
+
+    let expiry_timer = ExpiryTimer::new(order_manager.clone(), ExpiryConfig::default());
+    task::spawn(async move { expiry_timer.run().await });
+
+    let reconcile_config = args.reconcile_config();
+    let reconciler = Reconciler::new(
+        order_manager.clone(),
+        store.clone(),
+        risk_gate.clone(),
+        &run_id,
+        venue_name,
+        reconcile_config.clone(),
+    );
+    if let Err(err) = reconciler.reconcile().await {
+        warn!(error = ?err, "startup reconcile failed");
+    }
+    if !reconcile_config.interval.is_zero() {
+        task::spawn(async move { reconciler.run().await });
+    }
+
+    let admin_ctx = AdminContext {
+        run_id: run_id.clone(),
+        gate: risk_gate.clone(),
+        store: store.clone(),
+        orders: order_manager.clone(),
+        kill_switch: args.kill_switch_config(),
+        backup: args.backup_config(),
+    };
+    let socket_path = args.admin_socket.clone();
+
     task::spawn(async move {
-        if let Err(err) = metrics_task.serve(metrics_addr).await {
-            tracing::error!(error = ?err, "metrics server error");
+        let handler = move |req| {
+            let ctx = admin_ctx.clone();
+            async move { ctx.handle(req).await }
+        };
+        if let Err(err) = run_server_async(&socket_path, handler).await {
+            tracing::error!(error = ?err, "admin ipc server failed");
         }
     });
 
@@ -210,7 +893,73 @@ async fn main() -> anyhow::Result<()> {
 
     info!(run_id = %run_id, "started");
 
-    let store_clone = store.clone();
+    let (writer, mut flush_reports) = StoreWriter::spawn(store.clone(), args.writer_config());
+    let flush_seconds = metrics.storage_flush_seconds();
+    let storage_dropped = metrics.storage_dropped();
+    let storage_failures = metrics.storage_write_failures();
+    task::spawn(async move {
+        while let Some(report) = flush_reports.recv().await {
+            flush_seconds.observe(report.latency.as_secs_f64());
+            storage_dropped.inc_by(report.dropped);
+            storage_failures.inc_by(report.failed as u64);
+        }
+    });
+
+    if args.raw_events_keep_days > 0 {
+        let (retention, mut retention_reports) =
+            RetentionJob::new(store.clone(), args.retention_config());
+        task::spawn(async move { retention.run().await });
+        let archived_rows = metrics.retention_archived_rows();
+        let deleted_rows = metrics.retention_deleted_rows();
+        let retention_writer = writer.clone();
+        let run_id_retention = run_id.clone();
+        task::spawn(async move {
+            while let Some(report) = retention_reports.recv().await {
+                archived_rows.inc_by(report.archived.iter().map(|e| e.rows).sum());
+                deleted_rows.inc_by(report.rows_deleted);
+                if let Some(err) = report.error {
+                    retention_writer
+                        .log_incident(&run_id_retention, "warning", RETENTION_INCIDENT, &err)
+                        .await;
+                }
+            }
+        });
+    }
+
+    if args.backup_interval_secs > 0 {
+        let (backups, mut backup_reports) = BackupJob::new(store.clone(), args.backup_config());
+        task::spawn(async move { backups.run().await });
+        let backup_writer = writer.clone();
+        let run_id_backup = run_id.clone();
+        task::spawn(async move {
+            while let Some(result) = backup_reports.recv().await {
+                if let Err(err) = result {
+                    backup_writer
+                        .log_incident(&run_id_backup, "warning", BACKUP_INCIDENT, &err)
+                        .await;
+                }
+            }
+        });
+    }
+
+    if args.rollup_interval_secs > 0 {
+        let (rollups, mut rollup_reports) = RollupJob::new(store.clone(), args.rollup_config());
+        task::spawn(async move { rollups.run().await });
+        let rollup_writer = writer.clone();
+        let run_id_rollup = run_id.clone();
+        task::spawn(async move {
+            while let Some(result) = rollup_reports.recv().await {
+                if let Err(err) = result {
+                    rollup_writer
+                        .log_incident(&run_id_rollup, "warning", ROLLUP_INCIDENT, &err)
+                        .await;
+                }
+            }
+        });
+    }
+
+    let heartbeat_writer = writer.clone();
+    let queue_depth = metrics.storage_queue_depth();
     let run_id_clone2 = run_id.clone();
     task::spawn(async move {
         let mut ticker = time::interval(Duration::from_secs(1));
@@ -218,22 +967,49 @@ async fn main() -> anyhow::Result<()> {
         loop {
             ticker.tick().await;
             heartbeat_counter.inc();
+            queue_depth.set(heartbeat_writer.queue_depth() as i64);
             tick += 1;
             let payload = serde_json::json!({"tick": tick});
-            if let Err(err) = store_clone
+            heartbeat_writer
                 .log_event(&run_id_clone2, "internal", "tick", &payload.to_string())
-                .await
-            {
-                tracing::warn!(error = ?err, "failed to log tick");
-            }
+                .await;
         }
     });
 
-    // keep running
-    future::pending::<()>().await;
+    shutdown_signal().await;
+    info!(run_id = %run_id, "shutting down; flushing storage writer");
+    writer.shutdown().await?;
     Ok(())
 }
 
+/// Resolves on SIGINT or SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            warn!(error = ?err, "failed to listen for ctrl-c");
+            future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(err) => {
+                warn!(error = ?err, "failed to listen for SIGTERM");
+                future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
 #[cfg(test)]
 pub(crate) fn cwd_guard() -> &'static Mutex<()> {
     static GUARD: OnceLock<Mutex<()>> = OnceLock::new();
@@ -269,6 +1045,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn paper_mode_never_reads_the_signing_key() {
+        let args = Args::parse_from(["traderd", "--poly-private-key", "not-a-key"]);
+        assert_eq!(args.execution_mode, ExecutionMode::Paper);
+        let (backend, updates) = build_backend(&args).unwrap();
+        assert_eq!(backend.mode(), ExecutionMode::Paper);
+        assert_eq!(backend.venue_name(), execution::PAPER_VENUE);
+        assert!(updates.is_some());
+
+        let live = Args::parse_from([
+            "traderd",
+            "--execution-mode",
+            "live",
+            "--poly-private-key",
+            "not-a-key",
+        ]);
+        assert!(build_backend(&live).is_err());
+    }
+
     #[test]
     fn startup_logs_include_configuration() {
         let args = Args::parse_from([
@@ -315,7 +1110,7 @@ mod tests {
             "sqlite:///{}",
             db_path.display().to_string().replace('\\', "/")
         );
-        
+
         ensure_sqlite_parent_dir(&url).expect("should be able to create parent directories");
 
         let expected_parent = db_path.parent().unwrap();
@@ -340,7 +1135,6 @@ mod tests {
         validate_sqlite_path("sqlite://bot.db").expect("relative file url should validate");
         validate_sqlite_path("sqlite:///C:/poly/data/bot.db")
             .expect("absolute windows file url should validate");
-    
     }
     #[test]
     fn rejects_missing_or_invalid_urls() {